@@ -0,0 +1,150 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, DateTime, ZipWriter};
+
+use crate::error::GitResult;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+// 归档条目的公共属性；mtime 统一使用提交时间，和 git archive 一致
+pub struct ArchiveEntry<'a> {
+    pub path: &'a str,
+    pub mode: u32,
+    // 提交时间（Unix 时间戳，秒）与提交者时区偏移（分钟）
+    pub mtime: i64,
+    pub offset_minutes: i32,
+}
+
+pub enum ArchiveWriter {
+    Zip(ZipWriter<BufWriter<File>>),
+    Tar(tar::Builder<BufWriter<File>>),
+    TarGz(tar::Builder<GzEncoder<BufWriter<File>>>),
+}
+
+// zip 只能保存不带时区的本地时间，按提交者的时区换算；超出 zip 支持的年份范围时用默认时间
+fn zip_time(mtime: i64, offset_minutes: i32) -> DateTime {
+    let local = mtime + offset_minutes as i64 * 60;
+    let (days, seconds) = (local.div_euclid(86400), local.rem_euclid(86400));
+    // 参见 Howard Hinnant 的 civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    DateTime::from_date_and_time(
+        year.clamp(0, u16::MAX as i64) as u16,
+        month as u8,
+        day as u8,
+        (seconds / 3600) as u8,
+        (seconds % 3600 / 60) as u8,
+        (seconds % 60) as u8,
+    )
+    .unwrap_or_default()
+}
+
+fn zip_options(entry: &ArchiveEntry, size: u64) -> SimpleFileOptions {
+    SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .last_modified_time(zip_time(entry.mtime, entry.offset_minutes))
+        .unix_permissions(entry.mode)
+        .large_file(size >= u32::MAX as u64)
+}
+
+fn tar_header(entry: &ArchiveEntry, kind: tar::EntryType, size: u64) -> tar::Header {
+    let mut header = tar::Header::new_gnu();
+    header.set_entry_type(kind);
+    header.set_mode(entry.mode);
+    header.set_mtime(entry.mtime.max(0) as u64);
+    header.set_size(size);
+    header
+}
+
+fn append_tar_file<W: Write>(builder: &mut tar::Builder<W>, entry: &ArchiveEntry, content: &[u8]) -> GitResult<()> {
+    let mut header = tar_header(entry, tar::EntryType::Regular, content.len() as u64);
+    builder.append_data(&mut header, entry.path, content)?;
+    Ok(())
+}
+
+fn append_tar_directory<W: Write>(builder: &mut tar::Builder<W>, entry: &ArchiveEntry) -> GitResult<()> {
+    let mut header = tar_header(entry, tar::EntryType::Directory, 0);
+    builder.append_data(&mut header, format!("{}/", entry.path), std::io::empty())?;
+    Ok(())
+}
+
+fn append_tar_symlink<W: Write>(builder: &mut tar::Builder<W>, entry: &ArchiveEntry, target: &str) -> GitResult<()> {
+    let mut header = tar_header(entry, tar::EntryType::Symlink, 0);
+    builder.append_link(&mut header, entry.path, target)?;
+    Ok(())
+}
+
+impl ArchiveWriter {
+    pub fn create(file: File, format: ArchiveFormat) -> Self {
+        let file = BufWriter::new(file);
+        match format {
+            ArchiveFormat::Zip => ArchiveWriter::Zip(ZipWriter::new(file)),
+            ArchiveFormat::Tar => ArchiveWriter::Tar(tar::Builder::new(file)),
+            ArchiveFormat::TarGz => ArchiveWriter::TarGz(tar::Builder::new(GzEncoder::new(file, Compression::default()))),
+        }
+    }
+
+    pub fn add_file(&mut self, entry: &ArchiveEntry, content: &[u8]) -> GitResult<()> {
+        match self {
+            ArchiveWriter::Zip(zip) => {
+                zip.start_file(entry.path, zip_options(entry, content.len() as u64))?;
+                zip.write_all(content)?;
+                Ok(())
+            }
+            ArchiveWriter::Tar(builder) => append_tar_file(builder, entry, content),
+            ArchiveWriter::TarGz(builder) => append_tar_file(builder, entry, content),
+        }
+    }
+
+    pub fn add_directory(&mut self, entry: &ArchiveEntry) -> GitResult<()> {
+        match self {
+            ArchiveWriter::Zip(zip) => {
+                zip.add_directory(entry.path, zip_options(entry, 0))?;
+                Ok(())
+            }
+            ArchiveWriter::Tar(builder) => append_tar_directory(builder, entry),
+            ArchiveWriter::TarGz(builder) => append_tar_directory(builder, entry),
+        }
+    }
+
+    pub fn add_symlink(&mut self, entry: &ArchiveEntry, target: &str) -> GitResult<()> {
+        match self {
+            ArchiveWriter::Zip(zip) => {
+                zip.add_symlink(entry.path, target, zip_options(entry, 0))?;
+                Ok(())
+            }
+            ArchiveWriter::Tar(builder) => append_tar_symlink(builder, entry, target),
+            ArchiveWriter::TarGz(builder) => append_tar_symlink(builder, entry, target),
+        }
+    }
+
+    // 写入归档的结尾（zip 的中央目录、tar 的结束块、gzip 的校验）并刷新到磁盘
+    pub fn finish(self) -> GitResult<()> {
+        let mut file = match self {
+            ArchiveWriter::Zip(zip) => zip.finish()?,
+            ArchiveWriter::Tar(builder) => builder.into_inner()?,
+            ArchiveWriter::TarGz(builder) => builder.into_inner()?.finish()?,
+        };
+        file.flush()?;
+        file.get_ref().sync_all()?;
+        Ok(())
+    }
+}
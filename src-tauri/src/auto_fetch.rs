@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use git2::{Oid, Repository};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+
+use crate::cancel::CancellationToken;
+use crate::error::{GitError, GitResult};
+use crate::git_cli;
+use crate::repo_cache::common_dir;
+use crate::sessions::RepoSessions;
+
+pub const AUTO_FETCH_EVENT: &str = "auto-fetch";
+pub const DEFAULT_AUTO_FETCH_INTERVAL_SECS: u64 = 300;
+const MIN_AUTO_FETCH_INTERVAL_SECS: u64 = 30;
+// 一轮抓取的最长时间，超时后结束 git 进程，下一个间隔再试
+const FETCH_TIMEOUT: Duration = Duration::from_secs(120);
+const WATCHDOG_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoFetchSettings {
+    pub enabled: bool,
+    pub interval_secs: u64,
+}
+
+// 一次抓取中变化的远程跟踪分支；新出现的分支 old_target 为空，被修剪的分支 new_target 为空
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FetchedRef {
+    pub name: String,
+    pub old_target: Option<String>,
+    pub new_target: Option<String>,
+}
+
+// 只在远程跟踪分支有变化时发出，前端据此刷新分支列表的领先/落后数
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoFetchEvent {
+    pub repo_id: String,
+    pub refs: Vec<FetchedRef>,
+}
+
+struct Schedule {
+    interval_secs: u64,
+    // 发送端被丢弃后通道关闭，后台线程随之退出
+    _stop: mpsc::Sender<()>,
+    // 停止或被新的设置替换时取消正在进行的抓取
+    cancel: CancellationToken,
+}
+
+impl Drop for Schedule {
+    fn drop(&mut self) {
+        self.cancel.cancel();
+    }
+}
+
+// 每个开启了自动抓取的仓库一个后台线程，以仓库句柄为键，由 Tauri 托管为全局状态
+#[derive(Clone, Default)]
+pub struct AutoFetchers {
+    schedules: Arc<Mutex<HashMap<String, Schedule>>>,
+}
+
+fn remote_tracking_refs(repo: &Repository) -> GitResult<HashMap<String, Oid>> {
+    let mut refs = HashMap::new();
+    for reference in repo.references_glob("refs/remotes/*")? {
+        let reference = reference?;
+        // refs/remotes/origin/HEAD 是符号引用，没有直接的目标
+        if let (Some(name), Some(target)) = (reference.name(), reference.target()) {
+            refs.insert(name.to_string(), target);
+        }
+    }
+    Ok(refs)
+}
+
+// 比较抓取前后的远程跟踪分支，返回变化的分支
+fn changed_refs(before: &HashMap<String, Oid>, after: &HashMap<String, Oid>) -> Vec<FetchedRef> {
+    let mut refs: Vec<FetchedRef> = after
+        .iter()
+        .filter(|(name, target)| before.get(*name) != Some(target))
+        .map(|(name, target)| FetchedRef {
+            name: name.clone(),
+            old_target: before.get(name).map(|oid| oid.to_string()),
+            new_target: Some(target.to_string()),
+        })
+        .collect();
+    refs.extend(before.iter().filter(|(name, _)| !after.contains_key(*name)).map(|(name, target)| FetchedRef {
+        name: name.clone(),
+        old_target: Some(target.to_string()),
+        new_target: None,
+    }));
+    refs.sort_by(|a, b| a.name.cmp(&b.name));
+    refs
+}
+
+// 抓取所有远程。超过 FETCH_TIMEOUT 或 stop 被取消时结束 git 进程，返回 GitError::Cancelled
+fn fetch_all(git_dir: &Path, stop: &CancellationToken) -> GitResult<()> {
+    let cancel = CancellationToken::default();
+    let (done, finished) = mpsc::channel::<()>();
+    let watchdog = {
+        let (cancel, stop) = (cancel.clone(), stop.clone());
+        let deadline = Instant::now() + FETCH_TIMEOUT;
+        thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = finished.recv_timeout(WATCHDOG_POLL_INTERVAL) {
+                if stop.is_cancelled() || Instant::now() >= deadline {
+                    cancel.cancel();
+                    return;
+                }
+            }
+        })
+    };
+    let result = git_cli::run_git(git_dir, &["fetch", "--all", "--quiet"], &cancel);
+    drop(done);
+    let _ = watchdog.join();
+    result.map(|_| ())
+}
+
+// 执行一轮抓取。抓取只改动远程跟踪分支，不拿仓库的写锁，也不占用缓存的仓库句柄，用户的操作不会被挡住
+fn fetch_once(app: &AppHandle, sessions: &RepoSessions, repo_id: &str, stop: &CancellationToken) -> GitResult<()> {
+    let (git_dir, before) = sessions.with_repo(repo_id, |repo| Ok((common_dir(repo.path()), remote_tracking_refs(repo)?)))?;
+    let result = fetch_all(&git_dir, stop);
+    sessions.invalidate(repo_id);
+    result?;
+    let after = sessions.with_repo(repo_id, remote_tracking_refs)?;
+    let refs = changed_refs(&before, &after);
+    if !refs.is_empty() {
+        let payload = AutoFetchEvent {
+            repo_id: repo_id.to_string(),
+            refs,
+        };
+        if let Err(e) = app.emit(AUTO_FETCH_EVENT, payload) {
+            log::warn!("failed to emit {}: {}", AUTO_FETCH_EVENT, e);
+        }
+    }
+    Ok(())
+}
+
+impl AutoFetchers {
+    pub fn settings(&self, repo_id: &str) -> AutoFetchSettings {
+        match self.schedules.lock().unwrap().get(repo_id) {
+            Some(schedule) => AutoFetchSettings {
+                enabled: true,
+                interval_secs: schedule.interval_secs,
+            },
+            None => AutoFetchSettings {
+                enabled: false,
+                interval_secs: DEFAULT_AUTO_FETCH_INTERVAL_SECS,
+            },
+        }
+    }
+
+    // 重新开启时替换原来的线程；第一次抓取在一个间隔之后进行
+    pub fn start(&self, app: AppHandle, sessions: RepoSessions, repo_id: &str, interval_secs: u64) -> GitResult<AutoFetchSettings> {
+        sessions.get(repo_id)?;
+        if interval_secs < MIN_AUTO_FETCH_INTERVAL_SECS {
+            return Err(GitError::invalid(format!(
+                "auto-fetch interval must be at least {} seconds",
+                MIN_AUTO_FETCH_INTERVAL_SECS
+            )));
+        }
+        let (stop, stopped) = mpsc::channel::<()>();
+        let cancel = CancellationToken::default();
+        let interval = Duration::from_secs(interval_secs);
+        let id = repo_id.to_string();
+        let thread_cancel = cancel.clone();
+        thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                match fetch_once(&app, &sessions, &id, &thread_cancel) {
+                    Ok(()) => {}
+                    // 自动抓取已停止
+                    Err(GitError::Cancelled) if thread_cancel.is_cancelled() => break,
+                    // 仓库已关闭
+                    Err(GitError::NotFound { .. }) if sessions.get(&id).is_err() => break,
+                    Err(e) => log::warn!("auto-fetch failed for {}: {}", id, e),
+                }
+            }
+        });
+        self.schedules.lock().unwrap().insert(
+            repo_id.to_string(),
+            Schedule {
+                interval_secs,
+                _stop: stop,
+                cancel,
+            },
+        );
+        Ok(self.settings(repo_id))
+    }
+
+    // 返回该仓库之前是否开启了自动抓取；正在进行的抓取会被取消
+    pub fn stop(&self, repo_id: &str) -> bool {
+        self.schedules.lock().unwrap().remove(repo_id).is_some()
+    }
+}
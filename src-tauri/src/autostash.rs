@@ -0,0 +1,94 @@
+// 自动暂存：会改写工作目录的操作（目前是切换分支和检出提交）开始前把未提交的改动（包括未跟踪文件）
+// 用 git stash 存起来，操作结束后再恢复，和 git rebase --autostash 的做法相同。
+// 操作本身失败时照常返回错误（改动会先恢复）；恢复改动时的冲突不算操作失败，记在 GitAutostash 中，
+// 这时 stash 保留在列表里，解决冲突后由用户删除
+use git2::{Repository, StatusOptions};
+use serde::{Deserialize, Serialize};
+
+use crate::cancel::CancellationToken;
+use crate::error::{GitError, GitResult};
+use crate::git;
+use crate::git_cli;
+
+const STASH_REF: &str = "refs/stash";
+const STASH_MESSAGE: &str = "autostash";
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitAutostash {
+    // 暂存改动的 stash 提交
+    pub stash_id: String,
+    // 改动已恢复，stash 已删除
+    pub restored: bool,
+    // 恢复时产生冲突的文件
+    pub conflicts: Vec<String>,
+    // 恢复失败时 git stash pop 的输出
+    pub message: Option<String>,
+}
+
+fn has_local_changes(repo: &Repository) -> GitResult<bool> {
+    let mut options = StatusOptions::new();
+    options.include_untracked(true).include_ignored(false).exclude_submodules(true);
+    Ok(!repo.statuses(Some(&mut options))?.is_empty())
+}
+
+fn stash_tip(repo: &Repository) -> Option<git2::Oid> {
+    repo.refname_to_id(STASH_REF).ok()
+}
+
+fn restore(repo: &Repository, stash_id: git2::Oid) -> GitResult<GitAutostash> {
+    let workdir = repo.workdir().ok_or(GitError::BareRepository)?;
+    let mut autostash = GitAutostash {
+        stash_id: stash_id.to_string(),
+        restored: false,
+        conflicts: Vec::new(),
+        message: None,
+    };
+    // 持有仓库的写锁，stash@{0} 应当还是刚才存的那一个；不是时不去动别人的 stash
+    if stash_tip(repo) != Some(stash_id) {
+        autostash.message = Some("the autostash is no longer the latest stash".to_string());
+        return Ok(autostash);
+    }
+    match git_cli::run_git_with_progress(repo.path(), Some(workdir), &["stash", "pop"], &CancellationToken::default(), |_| {}) {
+        Ok(_) => autostash.restored = true,
+        // 冲突时 git stash pop 写入冲突并以非零退出，stash 不会被删除
+        Err(GitError::CommandFailed { message, .. }) => {
+            let mut index = repo.index()?;
+            index.read(true)?;
+            autostash.conflicts = git::conflict_paths(&index)?;
+            autostash.message = Some(message).filter(|message| !message.is_empty());
+        }
+        Err(e) => return Err(e),
+    }
+    Ok(autostash)
+}
+
+// enabled 且有未提交的改动时，先存起来再执行 operation，结束后恢复；否则直接执行 operation
+pub fn with_autostash<T>(repo: &Repository, enabled: bool, operation: impl FnOnce(&Repository) -> GitResult<T>) -> GitResult<(T, Option<GitAutostash>)> {
+    if !enabled || repo.is_bare() || !has_local_changes(repo)? {
+        return operation(repo).map(|result| (result, None));
+    }
+    let workdir = repo.workdir().ok_or(GitError::BareRepository)?;
+    let before = stash_tip(repo);
+    git_cli::run_git_with_progress(
+        repo.path(),
+        Some(workdir),
+        &["stash", "push", "--include-untracked", "--message", STASH_MESSAGE],
+        &CancellationToken::default(),
+        |_| {},
+    )?;
+    let stash_id = match stash_tip(repo) {
+        Some(stash_id) if Some(stash_id) != before => stash_id,
+        // 改动只有 git stash 不会存的内容（例如子模块），没有生成 stash
+        _ => return operation(repo).map(|result| (result, None)),
+    };
+
+    match operation(repo) {
+        Ok(result) => Ok((result, Some(restore(repo, stash_id)?))),
+        Err(e) => {
+            // 操作没有完成，工作目录通常没有变化，恢复改动后返回原来的错误；恢复不了时 stash 留在列表中
+            let _ = restore(repo, stash_id);
+            Err(e)
+        }
+    }
+}
@@ -0,0 +1,194 @@
+// 二分查找引入问题的提交。状态保存在 git 自己的 refs/bisect/* 和 BISECT_* 文件中，
+// 和命令行中的 git bisect 可以混用
+use std::fs;
+
+use git2::{Oid, Repository};
+use serde::{Deserialize, Serialize};
+
+use crate::cancel::CancellationToken;
+use crate::error::{GitError, GitResult};
+use crate::git_cli;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum GitBisectMark {
+    Good,
+    Bad,
+    Skip,
+}
+
+impl GitBisectMark {
+    fn as_str(self) -> &'static str {
+        match self {
+            GitBisectMark::Good => "good",
+            GitBisectMark::Bad => "bad",
+            GitBisectMark::Skip => "skip",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GitBisectCommit {
+    pub hash: String,
+    pub summary: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitBisectStatus {
+    pub active: bool,
+    // 开始二分前所在的分支（或提交），bisect_reset 时回到这里
+    pub original_head: Option<String>,
+    pub bad: Option<GitBisectCommit>,
+    pub good: Vec<GitBisectCommit>,
+    pub skipped: Vec<GitBisectCommit>,
+    // 下一个要测试的提交，即 git 检出的 HEAD
+    pub current: Option<GitBisectCommit>,
+    // 除 bad 之外仍可能是第一个坏提交的数量（包括跳过的提交），以及大约还要测试几次
+    pub remaining: usize,
+    pub steps: u32,
+    // 范围缩小到只剩 bad 一个提交时就是要找的提交
+    pub first_bad: Option<GitBisectCommit>,
+    // 剩下的都是跳过的提交，无法继续缩小范围，第一个坏提交是它们和 bad 中的某一个
+    pub only_skipped_left: bool,
+}
+
+fn bisect_git(repo: &Repository, args: &[&str]) -> GitResult<()> {
+    let workdir = repo.workdir().ok_or(GitError::BareRepository)?;
+    let mut command_args = vec!["bisect"];
+    command_args.extend_from_slice(args);
+    git_cli::run_git_with_progress(repo.path(), Some(workdir), &command_args, &CancellationToken::default(), |_| {})?;
+    Ok(())
+}
+
+fn resolve_commit(repo: &Repository, rev: &str) -> GitResult<Oid> {
+    Ok(repo.revparse_single(rev)?.peel_to_commit()?.id())
+}
+
+fn bisect_commit(repo: &Repository, oid: Oid) -> GitResult<GitBisectCommit> {
+    let commit = repo.find_commit(oid)?;
+    Ok(GitBisectCommit {
+        hash: oid.to_string(),
+        summary: commit.summary().unwrap_or("").to_string(),
+    })
+}
+
+fn bisect_refs(repo: &Repository, glob: &str) -> GitResult<Vec<Oid>> {
+    let mut oids = Vec::new();
+    for reference in repo.references_glob(glob)? {
+        if let Some(oid) = reference?.target() {
+            oids.push(oid);
+        }
+    }
+    Ok(oids)
+}
+
+pub fn is_bisecting(repo: &Repository) -> bool {
+    repo.path().join("BISECT_START").is_file()
+}
+
+pub fn get_bisect_status(repo: &Repository) -> GitResult<GitBisectStatus> {
+    if !is_bisecting(repo) {
+        return Ok(GitBisectStatus {
+            active: false,
+            original_head: None,
+            bad: None,
+            good: Vec::new(),
+            skipped: Vec::new(),
+            current: None,
+            remaining: 0,
+            steps: 0,
+            first_bad: None,
+            only_skipped_left: false,
+        });
+    }
+    let original_head = fs::read_to_string(repo.path().join("BISECT_START"))
+        .ok()
+        .map(|content| content.trim().to_string())
+        .filter(|content| !content.is_empty());
+    let bad = match repo.find_reference("refs/bisect/bad") {
+        Ok(reference) => reference.target(),
+        Err(_) => None,
+    };
+    let good = bisect_refs(repo, "refs/bisect/good-*")?;
+    let skipped = bisect_refs(repo, "refs/bisect/skip-*")?;
+
+    // 和 git 一样，候选范围是 bad 可达而任何 good 都不可达的提交；还没有好坏两端时无法计算
+    let mut remaining = 0;
+    let mut untested = 0;
+    let mut first_bad = None;
+    if let Some(bad) = bad.filter(|_| !good.is_empty()) {
+        let mut walk = repo.revwalk()?;
+        walk.push(bad)?;
+        for oid in &good {
+            walk.hide(*oid)?;
+        }
+        for oid in walk {
+            let oid = oid?;
+            if oid != bad {
+                remaining += 1;
+                if !skipped.contains(&oid) {
+                    untested += 1;
+                }
+            }
+        }
+        if remaining == 0 {
+            first_bad = Some(bisect_commit(repo, bad)?);
+        }
+    }
+    let current = match repo.head().ok().and_then(|head| head.target()) {
+        Some(oid) if first_bad.is_none() => Some(bisect_commit(repo, oid)?),
+        _ => None,
+    };
+
+    Ok(GitBisectStatus {
+        active: true,
+        original_head,
+        bad: bad.map(|oid| bisect_commit(repo, oid)).transpose()?,
+        good: good.into_iter().map(|oid| bisect_commit(repo, oid)).collect::<GitResult<_>>()?,
+        skipped: skipped.into_iter().map(|oid| bisect_commit(repo, oid)).collect::<GitResult<_>>()?,
+        current,
+        remaining,
+        // 每测试一次范围大约减半
+        steps: (remaining + 1).next_power_of_two().trailing_zeros(),
+        first_bad,
+        only_skipped_left: remaining > 0 && untested == 0,
+    })
+}
+
+// 从已知的好提交和坏提交开始二分，git 会检出两者中间的提交
+pub fn bisect_start(repo: &Repository, good: &str, bad: &str) -> GitResult<GitBisectStatus> {
+    if is_bisecting(repo) {
+        return Err(GitError::invalid("a bisect is already in progress"));
+    }
+    let good = resolve_commit(repo, good)?;
+    let bad = resolve_commit(repo, bad)?;
+    if good == bad {
+        return Err(GitError::invalid("the good and bad commits must be different"));
+    }
+    bisect_git(repo, &["start", &bad.to_string(), &good.to_string(), "--"])?;
+    get_bisect_status(repo)
+}
+
+// 标记提交（默认为当前 HEAD）的测试结果，git 会检出下一个要测试的提交
+pub fn bisect_mark(repo: &Repository, commit: Option<&str>, mark: GitBisectMark) -> GitResult<GitBisectStatus> {
+    if !is_bisecting(repo) {
+        return Err(GitError::invalid("no bisect is in progress"));
+    }
+    let oid = resolve_commit(repo, commit.unwrap_or("HEAD"))?;
+    match bisect_git(repo, &[mark.as_str(), &oid.to_string()]) {
+        // 只剩跳过的提交时 git 以 2 退出，标记已经记录，由 only_skipped_left 告知前端
+        Ok(()) | Err(GitError::CommandFailed { exit_code: Some(2), .. }) => {}
+        Err(e) => return Err(e),
+    }
+    get_bisect_status(repo)
+}
+
+// 结束二分，回到开始前所在的分支
+pub fn bisect_reset(repo: &Repository) -> GitResult<GitBisectStatus> {
+    if is_bisecting(repo) {
+        bisect_git(repo, &["reset"])?;
+    }
+    get_bisect_status(repo)
+}
@@ -0,0 +1,75 @@
+use std::io::{BufRead, Write};
+
+use git2::Oid;
+
+use crate::error::{GitError, GitResult};
+
+const BUNDLE_V2_SIGNATURE: &str = "# v2 git bundle";
+const BUNDLE_V3_SIGNATURE: &str = "# v3 git bundle";
+
+// bundle 文件头，之后紧跟一个 packfile；格式参见 git 的 Documentation/gitformat-bundle.txt
+#[derive(Default)]
+pub struct BundleHeader {
+    // 接收方必须已有的提交及其说明（通常是提交标题）
+    pub prerequisites: Vec<(Oid, String)>,
+    // 引用的完整名称及其指向的对象
+    pub refs: Vec<(Oid, String)>,
+}
+
+fn parse_oid(hex: &str) -> GitResult<Oid> {
+    Oid::from_str(hex).map_err(|_| GitError::invalid(format!("invalid object id in bundle: {}", hex)))
+}
+
+impl BundleHeader {
+    pub fn write(&self, out: &mut impl Write) -> GitResult<()> {
+        writeln!(out, "{}", BUNDLE_V2_SIGNATURE)?;
+        for (oid, comment) in &self.prerequisites {
+            writeln!(out, "-{} {}", oid, comment)?;
+        }
+        for (oid, name) in &self.refs {
+            writeln!(out, "{} {}", oid, name)?;
+        }
+        writeln!(out)?;
+        Ok(())
+    }
+
+    // 读到空行为止，reader 随后停在 packfile 的开头
+    pub fn read(reader: &mut impl BufRead) -> GitResult<BundleHeader> {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let signature = line.trim_end();
+        if signature != BUNDLE_V2_SIGNATURE && signature != BUNDLE_V3_SIGNATURE {
+            return Err(GitError::invalid("not a git bundle file"));
+        }
+
+        let mut header = BundleHeader::default();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                return Err(GitError::invalid("bundle header is truncated"));
+            }
+            let line = line.trim_end_matches('\n');
+            if line.is_empty() {
+                return Ok(header);
+            }
+            // v3 的能力声明，只支持默认的 sha1
+            if let Some(capability) = line.strip_prefix('@') {
+                if capability.starts_with("object-format=") && capability != "object-format=sha1" {
+                    return Err(GitError::Unsupported {
+                        message: format!("unsupported bundle {}", capability),
+                    });
+                }
+                continue;
+            }
+            if let Some(prerequisite) = line.strip_prefix('-') {
+                let (hex, comment) = prerequisite.split_once(' ').unwrap_or((prerequisite, ""));
+                header.prerequisites.push((parse_oid(hex)?, comment.to_string()));
+                continue;
+            }
+            let (hex, name) = line
+                .split_once(' ')
+                .ok_or_else(|| GitError::invalid(format!("invalid bundle header line: {}", line)))?;
+            header.refs.push((parse_oid(hex)?, name.to_string()));
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::error::{GitError, GitResult};
+
+// 长时间运行的操作在循环中定期检查令牌，被取消后尽快返回 GitError::Cancelled
+#[derive(Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub fn check(&self) -> GitResult<()> {
+        if self.is_cancelled() {
+            Err(GitError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+// 以前端生成的操作 id 为键登记正在运行的操作，由 Tauri 托管为全局状态
+#[derive(Default)]
+pub struct OperationRegistry {
+    tokens: Arc<Mutex<HashMap<String, CancellationToken>>>,
+}
+
+impl OperationRegistry {
+    // 没有 id 的操作无法被取消，只拿到一个不登记的令牌
+    pub fn register(&self, operation_id: Option<&str>) -> OperationGuard {
+        let token = CancellationToken::default();
+        if let Some(id) = operation_id {
+            self.tokens.lock().unwrap().insert(id.to_string(), token.clone());
+        }
+        OperationGuard {
+            id: operation_id.map(|id| id.to_string()),
+            token,
+            tokens: self.tokens.clone(),
+        }
+    }
+
+    pub fn cancel(&self, operation_id: &str) -> bool {
+        match self.tokens.lock().unwrap().get(operation_id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+// 操作结束（guard 被丢弃）时自动注销
+pub struct OperationGuard {
+    id: Option<String>,
+    token: CancellationToken,
+    tokens: Arc<Mutex<HashMap<String, CancellationToken>>>,
+}
+
+impl OperationGuard {
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+}
+
+impl Drop for OperationGuard {
+    fn drop(&mut self) {
+        if let Some(id) = &self.id {
+            let mut tokens = self.tokens.lock().unwrap();
+            // 同一个 id 可能已被新的操作复用，只移除自己登记的令牌
+            if tokens.get(id).is_some_and(|token| Arc::ptr_eq(&token.0, &self.token.0)) {
+                tokens.remove(id);
+            }
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use git2::Oid;
+
+use crate::error::{GitError, GitResult};
+
+// commit-graph 文件的格式参见 git 的 Documentation/gitformat-commit-graph.txt。
+// libgit2 遍历历史、计算 merge-base 和领先/落后数时会自动读取 objects/info/commit-graph，
+// 但不支持 --split 生成的 commit-graphs/ 链，这里只读取查找提交所需的部分
+const SIGNATURE: &[u8; 4] = b"CGPH";
+const HEADER_SIZE: usize = 8;
+const CHUNK_ENTRY_SIZE: usize = 12;
+const CHUNK_OID_FANOUT: &[u8; 4] = b"OIDF";
+const CHUNK_OID_LOOKUP: &[u8; 4] = b"OIDL";
+const FANOUT_SIZE: usize = 256 * 4;
+const OID_SIZE: usize = 20;
+
+fn be_u32(data: &[u8], at: usize) -> u32 {
+    u32::from_be_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]])
+}
+
+fn be_u64(data: &[u8], at: usize) -> u64 {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&data[at..at + 8]);
+    u64::from_be_bytes(bytes)
+}
+
+pub fn graph_file_path(objects_dir: &Path) -> PathBuf {
+    objects_dir.join("info").join("commit-graph")
+}
+
+pub fn graph_chain_path(objects_dir: &Path) -> PathBuf {
+    objects_dir.join("info").join("commit-graphs").join("commit-graph-chain")
+}
+
+// 整个文件读入内存，按 fanout 表二分查找
+pub struct CommitGraphFile {
+    data: Vec<u8>,
+    fanout: usize,
+    lookup: usize,
+    count: usize,
+}
+
+impl CommitGraphFile {
+    pub fn open(path: &Path) -> GitResult<CommitGraphFile> {
+        let data = fs::read(path)?;
+        let invalid = || GitError::invalid(format!("invalid commit-graph file: {}", path.display()));
+        if data.len() < HEADER_SIZE || &data[..4] != SIGNATURE || data[4] != 1 || data[5] != 1 {
+            return Err(invalid());
+        }
+        let chunk_count = data[6] as usize;
+        if data.len() < HEADER_SIZE + (chunk_count + 1) * CHUNK_ENTRY_SIZE {
+            return Err(invalid());
+        }
+
+        let mut fanout = None;
+        let mut lookup = None;
+        for i in 0..chunk_count {
+            let at = HEADER_SIZE + i * CHUNK_ENTRY_SIZE;
+            let offset = usize::try_from(be_u64(&data, at + 4)).map_err(|_| invalid())?;
+            match &data[at..at + 4] {
+                id if id == CHUNK_OID_FANOUT => fanout = Some(offset),
+                id if id == CHUNK_OID_LOOKUP => lookup = Some(offset),
+                _ => {}
+            }
+        }
+        // 偏移量和数量都来自文件本身，损坏或截断的文件要报告为无效而不是越界
+        let (fanout, lookup) = fanout.zip(lookup).ok_or_else(invalid)?;
+        if fanout.checked_add(FANOUT_SIZE).map_or(true, |end| data.len() < end) {
+            return Err(invalid());
+        }
+        let count = be_u32(&data, fanout + FANOUT_SIZE - 4) as usize;
+        // fanout 表中第 i 项是首字节不大于 i 的提交数，必须单调不减
+        let mut previous = 0;
+        for i in 0..256 {
+            let entry = be_u32(&data, fanout + i * 4) as usize;
+            if entry < previous || entry > count {
+                return Err(invalid());
+            }
+            previous = entry;
+        }
+        let lookup_end = count.checked_mul(OID_SIZE).and_then(|size| lookup.checked_add(size));
+        if lookup_end.map_or(true, |end| data.len() < end) {
+            return Err(invalid());
+        }
+        Ok(CommitGraphFile { data, fanout, lookup, count })
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn size_bytes(&self) -> u64 {
+        self.data.len() as u64
+    }
+
+    pub fn contains(&self, oid: Oid) -> bool {
+        let bytes = oid.as_bytes();
+        let first = bytes[0] as usize;
+        let mut low = if first == 0 { 0 } else { be_u32(&self.data, self.fanout + (first - 1) * 4) as usize };
+        let mut high = be_u32(&self.data, self.fanout + first * 4) as usize;
+        while low < high {
+            let mid = (low + high) / 2;
+            let at = self.lookup + mid * OID_SIZE;
+            match self.data[at..at + OID_SIZE].cmp(bytes) {
+                std::cmp::Ordering::Equal => return true,
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+            }
+        }
+        false
+    }
+}
@@ -0,0 +1,510 @@
+// 提交说明的模板（commit.template）与 Conventional Commits 规范检查，参见 https://www.conventionalcommits.org/
+use std::fs;
+use std::ops::Range;
+use std::sync::OnceLock;
+
+use git2::Repository;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{GitError, GitResult};
+
+const DEFAULT_TYPES: &[&str] = &["build", "chore", "ci", "docs", "feat", "fix", "perf", "refactor", "revert", "style", "test"];
+// git 自动生成的标题不按规范检查
+const GENERATED_HEADER_PREFIXES: &[&str] = &["Merge ", "Revert \"", "fixup! ", "squash! ", "amend! "];
+
+// 读取 commit.template 指向的文件；未配置时返回 None。
+// 相对路径和 git 一样相对于工作目录
+pub fn get_commit_template(repo: &Repository) -> GitResult<Option<String>> {
+    let path = match repo.config()?.get_path("commit.template") {
+        Ok(path) => path,
+        Err(_) => return Ok(None),
+    };
+    let path = match repo.workdir() {
+        Some(workdir) if path.is_relative() => workdir.join(path),
+        _ => path,
+    };
+    match fs::read_to_string(&path) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            Err(GitError::not_found(format!("commit template not found: {}", path.display())))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+// 未传的字段使用默认值，默认值与 @commitlint/config-conventional 接近
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CommitMessageRules {
+    // 按 Conventional Commits 检查标题格式、type、scope 和 BREAKING CHANGE；关闭时 types、scopes、require_scope 不起作用
+    pub conventional: bool,
+    pub types: Vec<String>,
+    // 为空时不限制 scope 的取值
+    pub scopes: Vec<String>,
+    pub require_scope: bool,
+    pub max_header_length: usize,
+    // 0 表示不限制正文每行的长度
+    pub max_body_line_length: usize,
+    // 说明以 "Added"、"Fixes" 这类非祈使语气的英文动词开头时提示
+    pub imperative_mood: bool,
+    pub trailing_whitespace: bool,
+    pub patterns: Vec<CommitMessagePattern>,
+}
+
+impl Default for CommitMessageRules {
+    fn default() -> Self {
+        CommitMessageRules {
+            conventional: true,
+            types: DEFAULT_TYPES.iter().map(|t| t.to_string()).collect(),
+            scopes: Vec::new(),
+            require_scope: false,
+            max_header_length: 72,
+            max_body_line_length: 100,
+            imperative_mood: false,
+            trailing_whitespace: true,
+            patterns: Vec::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum CommitMessagePatternTarget {
+    #[default]
+    Header,
+    Body,
+    // 所有非注释行
+    Message,
+}
+
+// 自定义的正则表达式规则，例如禁止 "WIP" 或要求标题中带有工单号
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitMessagePattern {
+    pub pattern: String,
+    // 报告给用户的说明
+    pub message: String,
+    #[serde(default)]
+    pub target: CommitMessagePatternTarget,
+    // true 时匹配到即报告，false 时 target 中没有任何一行匹配才报告
+    #[serde(default)]
+    pub forbid: bool,
+    #[serde(default = "default_pattern_severity")]
+    pub severity: CommitMessageSeverity,
+}
+
+fn default_pattern_severity() -> CommitMessageSeverity {
+    CommitMessageSeverity::Warning
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum CommitMessageRule {
+    HeaderFormat,
+    HeaderMaxLength,
+    TypeCase,
+    TypeEnum,
+    ScopeEmpty,
+    ScopeEnum,
+    SubjectEmpty,
+    SubjectFullStop,
+    SubjectMood,
+    BodyLeadingBlank,
+    BodyMaxLineLength,
+    BreakingChangeFormat,
+    TrailingWhitespace,
+    Pattern,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum CommitMessageSeverity {
+    Error,
+    Warning,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitMessageViolation {
+    pub rule: CommitMessageRule,
+    pub severity: CommitMessageSeverity,
+    pub message: String,
+    // 行号从 0 开始（去掉注释行之前的行号），列为 UTF-16 偏移的左闭右开区间，前端可以直接标注到编辑器中
+    pub line: usize,
+    pub start_column: usize,
+    pub end_column: usize,
+    // rule 为 Pattern 时是 rules.patterns 中的序号
+    pub pattern_index: Option<usize>,
+}
+
+fn header_regex() -> &'static Regex {
+    static HEADER: OnceLock<Regex> = OnceLock::new();
+    HEADER.get_or_init(|| Regex::new(r"^(?P<type>[^\s():!]+)(?:\((?P<scope>[^()]*)\))?(?P<breaking>!)?: (?P<subject>.*)$").unwrap())
+}
+
+fn utf16_len(text: &str) -> usize {
+    text.encode_utf16().count()
+}
+
+struct Violations(Vec<CommitMessageViolation>);
+
+impl Violations {
+    // range 为该行中的字节偏移
+    fn push(&mut self, rule: CommitMessageRule, severity: CommitMessageSeverity, message: String, line: usize, text: &str, range: Range<usize>) {
+        self.0.push(CommitMessageViolation {
+            rule,
+            severity,
+            message,
+            line,
+            start_column: utf16_len(&text[..range.start]),
+            end_column: utf16_len(&text[..range.end]),
+            pattern_index: None,
+        });
+    }
+}
+
+// 一次检查共用的内容，行号都是原始说明中的行号
+struct CommitMessageContext<'a> {
+    rules: &'a CommitMessageRules,
+    header_line: usize,
+    header: &'a str,
+    // 标题之后的非注释行
+    body: Vec<(usize, &'a str)>,
+    // 按 Conventional Commits 解析的标题，格式不符时为空
+    conventional: Option<Captures<'a>>,
+    patterns: Vec<Regex>,
+}
+
+impl CommitMessageContext<'_> {
+    // 标题中说明部分的字节范围：Conventional Commits 格式时为冒号后的部分，否则为整个标题
+    fn subject(&self) -> Range<usize> {
+        match &self.conventional {
+            Some(captures) => captures.name("subject").unwrap().range(),
+            None => 0..self.header.len(),
+        }
+    }
+
+    fn lines(&self) -> impl Iterator<Item = (usize, &str)> {
+        std::iter::once((self.header_line, self.header)).chain(self.body.iter().copied())
+    }
+}
+
+type CommitMessageCheck = fn(&CommitMessageContext, &mut Violations);
+
+// 按顺序执行的检查，新的检查加在这里；每个检查自己根据 rules 决定是否启用
+const CHECKS: &[CommitMessageCheck] = &[
+    check_header_length,
+    check_conventional_header,
+    check_subject_mood,
+    check_body,
+    check_trailing_whitespace,
+    check_patterns,
+];
+
+fn check_header_length(context: &CommitMessageContext, violations: &mut Violations) {
+    let (header, max) = (context.header, context.rules.max_header_length);
+    let header_length = header.chars().count();
+    if header_length > max {
+        let start = header.char_indices().nth(max).map_or(header.len(), |(i, _)| i);
+        let message = format!("header is {} characters, longer than {}", header_length, max);
+        violations.push(CommitMessageRule::HeaderMaxLength, CommitMessageSeverity::Error, message, context.header_line, header, start..header.len());
+    }
+}
+
+fn check_conventional_header(context: &CommitMessageContext, violations: &mut Violations) {
+    use CommitMessageRule::*;
+    use CommitMessageSeverity::*;
+
+    let rules = context.rules;
+    if !rules.conventional {
+        return;
+    }
+    let (header_line, header) = (context.header_line, context.header);
+    let Some(captures) = &context.conventional else {
+        let message = "header must look like \"type(scope): subject\"".to_string();
+        violations.push(HeaderFormat, Error, message, header_line, header, 0..header.len());
+        return;
+    };
+
+    let kind = captures.name("type").unwrap();
+    if kind.as_str() != kind.as_str().to_lowercase() {
+        let message = format!("type \"{}\" must be lower case", kind.as_str());
+        violations.push(TypeCase, Error, message, header_line, header, kind.start()..kind.end());
+    }
+    if !rules.types.is_empty() && !rules.types.iter().any(|t| t.eq_ignore_ascii_case(kind.as_str())) {
+        let message = format!("type \"{}\" is not one of: {}", kind.as_str(), rules.types.join(", "));
+        violations.push(TypeEnum, Error, message, header_line, header, kind.start()..kind.end());
+    }
+
+    match captures.name("scope").filter(|scope| !scope.as_str().trim().is_empty()) {
+        None if rules.require_scope => {
+            violations.push(ScopeEmpty, Error, "scope is required".to_string(), header_line, header, kind.start()..kind.end());
+        }
+        Some(scope) if !rules.scopes.is_empty() => {
+            // 多个 scope 可以用逗号或斜杠分隔
+            let unknown: Vec<&str> = scope
+                .as_str()
+                .split([',', '/'])
+                .map(str::trim)
+                .filter(|part| !rules.scopes.iter().any(|s| s == part))
+                .collect();
+            if !unknown.is_empty() {
+                let message = format!("scope \"{}\" is not one of: {}", unknown.join(", "), rules.scopes.join(", "));
+                violations.push(ScopeEnum, Error, message, header_line, header, scope.start()..scope.end());
+            }
+        }
+        _ => {}
+    }
+
+    let subject = captures.name("subject").unwrap();
+    if subject.as_str().trim().is_empty() {
+        violations.push(SubjectEmpty, Error, "subject is empty".to_string(), header_line, header, subject.start()..header.len());
+    } else if subject.as_str().ends_with('.') {
+        let message = "subject should not end with a full stop".to_string();
+        violations.push(SubjectFullStop, Warning, message, header_line, header, header.len() - 1..header.len());
+    }
+}
+
+// 提交说明中常见的动词，只对这些词判断语气，避免把普通的名词误报
+const COMMON_VERBS: &[&str] = &[
+    "add", "adjust", "allow", "avoid", "bump", "change", "clean", "correct", "create", "delete", "deprecate", "disable", "drop", "enable",
+    "ensure", "extract", "fix", "handle", "implement", "improve", "introduce", "make", "merge", "migrate", "move", "optimize", "prevent",
+    "refactor", "release", "remove", "rename", "reorder", "replace", "restore", "revert", "rewrite", "set", "show", "simplify", "skip",
+    "split", "support", "switch", "update", "upgrade", "use", "validate", "wrap",
+];
+
+// "Fixed" -> fix、"Adds" -> add、"Removing" -> remove；已经是祈使语气或不认识的词返回 None
+fn imperative_form(word: &str) -> Option<&'static str> {
+    let word = word.to_lowercase();
+    let mut candidates = Vec::new();
+    for suffix in ["ing", "ed", "es", "s", "d"] {
+        if let Some(stem) = word.strip_suffix(suffix) {
+            candidates.push(stem.to_string());
+            // remov+e、dropp-p
+            candidates.push(format!("{}e", stem));
+            let mut chars = stem.chars().rev();
+            if let (Some(last), Some(previous)) = (chars.next(), chars.next()) {
+                if last == previous {
+                    candidates.push(stem[..stem.len() - last.len_utf8()].to_string());
+                }
+            }
+        }
+    }
+    if COMMON_VERBS.contains(&word.as_str()) {
+        return None;
+    }
+    candidates.iter().find_map(|candidate| COMMON_VERBS.iter().find(|verb| **verb == candidate).copied())
+}
+
+fn check_subject_mood(context: &CommitMessageContext, violations: &mut Violations) {
+    if !context.rules.imperative_mood {
+        return;
+    }
+    let subject = context.subject();
+    let text = &context.header[subject.clone()];
+    let offset = subject.start + (text.len() - text.trim_start().len());
+    let Some(word) = context.header[offset..].split(|c: char| !c.is_alphabetic()).next().filter(|word| !word.is_empty()) else {
+        return;
+    };
+    if let Some(verb) = imperative_form(word) {
+        let message = format!("use the imperative mood: \"{}\" instead of \"{}\"", verb, word);
+        violations.push(CommitMessageRule::SubjectMood, CommitMessageSeverity::Warning, message, context.header_line, context.header, offset..offset + word.len());
+    }
+}
+
+fn check_body(context: &CommitMessageContext, violations: &mut Violations) {
+    use CommitMessageRule::*;
+    use CommitMessageSeverity::*;
+
+    let rules = context.rules;
+    if let Some(&(index, line)) = context.body.first().filter(|(_, line)| !line.trim().is_empty()) {
+        let message = "body must be separated from the header by a blank line".to_string();
+        violations.push(BodyLeadingBlank, Warning, message, index, line, 0..line.len());
+    }
+    for &(index, line) in &context.body {
+        let length = line.chars().count();
+        // 没有空格的长行（链接等）无法换行，不检查
+        if rules.max_body_line_length > 0 && length > rules.max_body_line_length && line.trim().contains(' ') {
+            let start = line.char_indices().nth(rules.max_body_line_length).map_or(line.len(), |(i, _)| i);
+            let message = format!("line is {} characters, longer than {}", length, rules.max_body_line_length);
+            violations.push(BodyMaxLineLength, Warning, message, index, line, start..line.len());
+        }
+        // 脚注中的 BREAKING CHANGE 必须全部大写并紧跟冒号
+        let lower = line.to_lowercase();
+        if rules.conventional
+            && (lower.starts_with("breaking change") || lower.starts_with("breaking-change"))
+            && !(line.starts_with("BREAKING CHANGE: ") || line.starts_with("BREAKING-CHANGE: "))
+        {
+            let end = line.find(':').unwrap_or(line.len());
+            let message = "breaking changes must be written as \"BREAKING CHANGE: description\"".to_string();
+            violations.push(BreakingChangeFormat, Error, message, index, line, 0..end);
+        }
+    }
+}
+
+fn check_trailing_whitespace(context: &CommitMessageContext, violations: &mut Violations) {
+    if !context.rules.trailing_whitespace {
+        return;
+    }
+    for (index, line) in context.lines() {
+        let trimmed = line.trim_end();
+        // 空行中只有空白也算
+        if trimmed.len() < line.len() {
+            let message = "line has trailing whitespace".to_string();
+            violations.push(CommitMessageRule::TrailingWhitespace, CommitMessageSeverity::Warning, message, index, line, trimmed.len()..line.len());
+        }
+    }
+}
+
+fn check_patterns(context: &CommitMessageContext, violations: &mut Violations) {
+    for (pattern_index, (pattern, regex)) in context.rules.patterns.iter().zip(&context.patterns).enumerate() {
+        let lines: Vec<(usize, &str)> = match pattern.target {
+            CommitMessagePatternTarget::Header => vec![(context.header_line, context.header)],
+            CommitMessagePatternTarget::Body => context.body.clone(),
+            CommitMessagePatternTarget::Message => context.lines().collect(),
+        };
+        let start = violations.0.len();
+        if pattern.forbid {
+            for (index, line) in lines {
+                for found in regex.find_iter(line).filter(|found| !found.is_empty()) {
+                    violations.push(CommitMessageRule::Pattern, pattern.severity, pattern.message.clone(), index, line, found.range());
+                }
+            }
+        } else if !lines.iter().any(|(_, line)| regex.is_match(line)) {
+            // 要求的内容缺失时标注在标题上
+            let header = context.header;
+            violations.push(CommitMessageRule::Pattern, pattern.severity, pattern.message.clone(), context.header_line, header, 0..header.len());
+        }
+        for violation in &mut violations.0[start..] {
+            violation.pattern_index = Some(pattern_index);
+        }
+    }
+}
+
+// 依次执行 CHECKS，返回的问题按行排列；以 # 开头的注释行不参与检查，自定义规则的正则表达式无效时返回错误
+pub fn validate_commit_message(message: &str, rules: &CommitMessageRules) -> GitResult<Vec<CommitMessageViolation>> {
+    let mut patterns = Vec::new();
+    for pattern in &rules.patterns {
+        patterns.push(Regex::new(&pattern.pattern)?);
+    }
+    let lines: Vec<(usize, &str)> = message
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.starts_with('#'))
+        .collect();
+    let mut violations = Violations(Vec::new());
+    let (header_line, header) = match lines.iter().find(|(_, line)| !line.trim().is_empty()) {
+        Some(&(index, line)) => (index, line),
+        None => return Ok(violations.0),
+    };
+    if GENERATED_HEADER_PREFIXES.iter().any(|prefix| header.starts_with(prefix)) {
+        return Ok(violations.0);
+    }
+
+    let context = CommitMessageContext {
+        rules,
+        header_line,
+        header,
+        body: lines.into_iter().filter(|(index, _)| *index > header_line).collect(),
+        conventional: header_regex().captures(header),
+        patterns,
+    };
+    for check in CHECKS {
+        check(&context, &mut violations);
+    }
+    violations.0.sort_by_key(|violation| violation.line);
+    Ok(violations.0)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CoAuthor {
+    pub name: String,
+    pub email: String,
+}
+
+// 创建提交时追加到说明末尾的 trailer
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CommitTrailers {
+    // 追加 Signed-off-by，使用仓库配置的 user.name / user.email
+    pub signoff: bool,
+    pub co_authors: Vec<CoAuthor>,
+}
+
+fn trailer_regex() -> &'static Regex {
+    static TRAILER: OnceLock<Regex> = OnceLock::new();
+    TRAILER.get_or_init(|| Regex::new(r"^(?P<key>[A-Za-z0-9][A-Za-z0-9-]*):\s*(?P<value>.*)$").unwrap())
+}
+
+fn format_identity(name: &str, email: &str) -> GitResult<String> {
+    let (name, email) = (name.trim(), email.trim());
+    if email.is_empty() || [name, email].iter().any(|part| part.contains(['<', '>', '\n', '\r'])) {
+        return Err(GitError::invalid(format!("invalid identity: {} <{}>", name, email)));
+    }
+    Ok(if name.is_empty() { format!("<{}>", email) } else { format!("{} <{}>", name, email) })
+}
+
+// 同一 trailer 只比较键（不区分大小写）和值中的邮箱（不区分大小写），名字写法不同也视为重复
+fn same_trailer(key: &str, value: &str, other_key: &str, other_value: &str) -> bool {
+    let email = |value: &str| value.rsplit_once('<').map(|(_, email)| email.trim_end_matches('>').trim().to_lowercase());
+    key.eq_ignore_ascii_case(other_key)
+        && match (email(value), email(other_value)) {
+            (Some(email), Some(other_email)) => email == other_email,
+            _ => value.trim() == other_value.trim(),
+        }
+}
+
+// 和 git interpret-trailers 一样：说明的最后一段全部是 trailer（允许以空白开头的续行）时追加到这一段，
+// 否则空一行另起一段；已有相同的 trailer 时不再重复添加。标题行本身不算 trailer 段
+pub fn append_trailers(message: &str, trailers: &[(&str, String)]) -> String {
+    let body = message.trim_end();
+    let paragraphs: Vec<&str> = body.split("\n\n").collect();
+    let last = paragraphs.last().copied().unwrap_or("");
+    let is_trailer_block = paragraphs.len() > 1
+        && !last.trim().is_empty()
+        && last
+            .lines()
+            .all(|line| line.starts_with(char::is_whitespace) || trailer_regex().is_match(line));
+    let existing: Vec<(String, String)> = if is_trailer_block {
+        last.lines()
+            .filter_map(|line| trailer_regex().captures(line))
+            .map(|captures| (captures["key"].to_string(), captures["value"].to_string()))
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let mut added: Vec<(&str, &str)> = Vec::new();
+    for (key, value) in trailers {
+        let duplicate = existing.iter().any(|(k, v)| same_trailer(k, v, key, value))
+            || added.iter().any(|(k, v)| same_trailer(k, v, key, value));
+        if !duplicate {
+            added.push((key, value));
+        }
+    }
+    if added.is_empty() {
+        return message.to_string();
+    }
+
+    let mut result = body.to_string();
+    result.push_str(if is_trailer_block { "\n" } else { "\n\n" });
+    for (key, value) in added {
+        result.push_str(&format!("{}: {}\n", key, value));
+    }
+    result
+}
+
+// signer 为提交者身份（名字, 邮箱），用于 Signed-off-by
+pub fn apply_commit_trailers(message: &str, trailers: &CommitTrailers, signer: (&str, &str)) -> GitResult<String> {
+    let mut lines = Vec::new();
+    for co_author in &trailers.co_authors {
+        lines.push(("Co-authored-by", format_identity(&co_author.name, &co_author.email)?));
+    }
+    // 和 git commit -s 一样，Signed-off-by 放在最后
+    if trailers.signoff {
+        lines.push(("Signed-off-by", format_identity(signer.0, signer.1)?));
+    }
+    Ok(append_trailers(message, &lines))
+}
@@ -0,0 +1,180 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use rayon::prelude::*;
+
+use crate::cancel::CancellationToken;
+use crate::error::GitResult;
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+// 一个目录的占用情况；不跟随符号链接
+pub struct DirUsage {
+    // 扫描时目录自身的修改时间，目录中增删、重命名条目时才会变化
+    mtime: Option<SystemTime>,
+    // 直接位于该目录下的文件名
+    file_names: Vec<String>,
+    pub total_bytes: u64,
+    pub total_files: u64,
+    pub subdirs: BTreeMap<String, DirUsage>,
+}
+
+impl DirUsage {
+    // 按 / 分隔的相对路径查找子目录
+    pub fn descendant(&self, rel_path: &str) -> Option<&DirUsage> {
+        rel_path
+            .split('/')
+            .filter(|part| !part.is_empty())
+            .try_fold(self, |dir, part| dir.subdirs.get(part))
+    }
+}
+
+// 扫描进度，多个工作线程共同累加，按固定间隔回调
+struct ScanProgress<'a> {
+    bytes: AtomicU64,
+    files: AtomicU64,
+    last_report: Mutex<Instant>,
+    on_progress: &'a (dyn Fn(u64, u64) + Sync),
+}
+
+impl ScanProgress<'_> {
+    fn add(&self, bytes: u64, files: u64) {
+        let bytes = self.bytes.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        let files = self.files.fetch_add(files, Ordering::Relaxed) + files;
+        // 其它线程正在回调时直接跳过
+        if let Ok(mut last_report) = self.last_report.try_lock() {
+            if last_report.elapsed() >= PROGRESS_INTERVAL {
+                (self.on_progress)(files, bytes);
+                *last_report = Instant::now();
+            }
+        }
+    }
+}
+
+// 直接位于目录下的文件大小之和、文件名与子目录名
+struct DirEntries {
+    bytes: u64,
+    file_names: Vec<String>,
+    subdirs: Vec<String>,
+}
+
+// 读取目录下的条目；读取失败的目录按空目录处理
+fn read_entries(path: &Path, skip_name: Option<&str>) -> DirEntries {
+    let mut entries = DirEntries {
+        bytes: 0,
+        file_names: Vec::new(),
+        subdirs: Vec::new(),
+    };
+    let dir = match fs::read_dir(path) {
+        Ok(dir) => dir,
+        Err(_) => return entries,
+    };
+    for entry in dir.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if skip_name == Some(name.as_str()) {
+            continue;
+        }
+        // DirEntry::metadata 不跟随符号链接
+        let metadata = match entry.metadata() {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+        if metadata.is_file() {
+            entries.bytes = entries.bytes.saturating_add(metadata.len());
+            entries.file_names.push(name);
+        } else if metadata.is_dir() {
+            entries.subdirs.push(name);
+        }
+    }
+    entries
+}
+
+// 目录的修改时间没变说明没有增删条目，沿用上次的条目名，但每个文件都重新读取大小，
+// 原地修改或追加写入的文件也能反映出来；有文件读取失败时重新列出目录
+fn reread_entries(path: &Path, cached: &DirUsage) -> Option<DirEntries> {
+    let mut bytes = 0_u64;
+    for name in &cached.file_names {
+        let metadata = fs::symlink_metadata(path.join(name)).ok().filter(|metadata| metadata.is_file())?;
+        bytes = bytes.saturating_add(metadata.len());
+    }
+    Some(DirEntries {
+        bytes,
+        file_names: cached.file_names.clone(),
+        subdirs: cached.subdirs.keys().cloned().collect(),
+    })
+}
+
+// 子目录并行扫描
+fn scan_dir(
+    path: &Path,
+    skip_name: Option<&str>,
+    cached: Option<&DirUsage>,
+    cancel: &CancellationToken,
+    progress: &ScanProgress,
+) -> GitResult<DirUsage> {
+    cancel.check()?;
+    let mtime = fs::symlink_metadata(path).and_then(|metadata| metadata.modified()).ok();
+    let entries = cached
+        .filter(|cached| mtime.is_some() && cached.mtime == mtime)
+        .and_then(|cached| reread_entries(path, cached))
+        .unwrap_or_else(|| read_entries(path, skip_name));
+    let DirEntries { bytes: own_bytes, file_names, subdirs: subdir_names } = entries;
+    let own_files = file_names.len() as u64;
+    progress.add(own_bytes, own_files);
+
+    let subdirs: BTreeMap<String, DirUsage> = subdir_names
+        .into_par_iter()
+        .map(|name| {
+            let cached_child = cached.and_then(|cached| cached.subdirs.get(&name));
+            let usage = scan_dir(&path.join(&name), None, cached_child, cancel, progress)?;
+            Ok((name, usage))
+        })
+        .collect::<GitResult<_>>()?;
+
+    let total_bytes = subdirs.values().fold(own_bytes, |total, dir| total.saturating_add(dir.total_bytes));
+    let total_files = subdirs.values().fold(own_files, |total, dir| total + dir.total_files);
+    Ok(DirUsage {
+        mtime,
+        file_names,
+        total_bytes,
+        total_files,
+        subdirs,
+    })
+}
+
+// 根目录和根目录下跳过的条目名
+type ScanKey = (PathBuf, Option<String>);
+
+// 按根目录缓存上次的扫描结果，由 Tauri 托管为全局状态；refresh 时不使用缓存，重新列出所有目录
+#[derive(Clone, Default)]
+pub struct SizeCache {
+    scans: Arc<Mutex<HashMap<ScanKey, Arc<DirUsage>>>>,
+}
+
+impl SizeCache {
+    // skip_name 只作用于根目录下的条目（例如工作目录中的 .git）
+    pub fn scan(
+        &self,
+        root: &Path,
+        skip_name: Option<&str>,
+        refresh: bool,
+        cancel: &CancellationToken,
+        on_progress: &(dyn Fn(u64, u64) + Sync),
+    ) -> GitResult<Arc<DirUsage>> {
+        let key = (root.to_path_buf(), skip_name.map(str::to_string));
+        let cached = if refresh { None } else { self.scans.lock().unwrap().get(&key).cloned() };
+        let progress = ScanProgress {
+            bytes: AtomicU64::new(0),
+            files: AtomicU64::new(0),
+            last_report: Mutex::new(Instant::now()),
+            on_progress,
+        };
+        let usage = Arc::new(scan_dir(root, skip_name, cached.as_deref(), cancel, &progress)?);
+        self.scans.lock().unwrap().insert(key, usage.clone());
+        Ok(usage)
+    }
+}
@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use git2::Repository;
+
+use crate::error::{GitError, GitResult};
+use crate::git;
+
+const COMMIT_DRAFTS_FILE: &str = "commit_drafts.json";
+// 分离 HEAD 时草稿记在这个名称下
+const DETACHED_HEAD_KEY: &str = "HEAD";
+
+// 未完成的提交说明，按仓库和分支分别保存
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitDraft {
+    pub repo_path: String,
+    pub branch: String,
+    pub message: String,
+    // Unix 时间戳（秒）
+    pub updated_at: i64,
+}
+
+// 提交说明草稿，保存在应用数据目录下，由 Tauri 托管为全局状态。
+// 每次保存都写入文件，应用崩溃或误切换分支后草稿仍在
+#[derive(Clone)]
+pub struct CommitDrafts {
+    file: PathBuf,
+    drafts: Arc<Mutex<Vec<CommitDraft>>>,
+}
+
+fn now_seconds() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+// 草稿的键：仓库的工作目录（裸仓库为 git 目录）和分支名，branch 为空时取当前分支
+pub fn draft_key(repo: &Repository, branch: Option<&str>) -> GitResult<(String, String)> {
+    let root = repo.workdir().unwrap_or_else(|| repo.path());
+    let repo_path = root.to_string_lossy().trim_end_matches(['/', '\\']).to_string();
+    let branch = match branch {
+        Some(branch) => branch.to_string(),
+        None => git::get_head_state(repo)?.branch.unwrap_or_else(|| DETACHED_HEAD_KEY.to_string()),
+    };
+    Ok((repo_path, branch))
+}
+
+impl CommitDrafts {
+    // 文件不存在或内容损坏时从空列表开始，不影响应用启动
+    pub fn load(data_dir: &Path) -> Self {
+        let file = data_dir.join(COMMIT_DRAFTS_FILE);
+        let drafts = match fs::read_to_string(&file) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                log::warn!("ignoring corrupt {}: {}", file.display(), e);
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+        CommitDrafts {
+            file,
+            drafts: Arc::new(Mutex::new(drafts)),
+        }
+    }
+
+    pub fn get(&self, repo_path: &str, branch: &str) -> Option<CommitDraft> {
+        self.drafts
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|draft| draft.repo_path == repo_path && draft.branch == branch)
+            .cloned()
+    }
+
+    // 只有空白的说明视为清空草稿（例如提交成功后），返回 None
+    pub fn save(&self, repo_path: &str, branch: &str, message: &str) -> GitResult<Option<CommitDraft>> {
+        let mut drafts = self.drafts.lock().unwrap();
+        drafts.retain(|draft| !(draft.repo_path == repo_path && draft.branch == branch));
+        let draft = if message.trim().is_empty() {
+            None
+        } else {
+            let draft = CommitDraft {
+                repo_path: repo_path.to_string(),
+                branch: branch.to_string(),
+                message: message.to_string(),
+                updated_at: now_seconds(),
+            };
+            drafts.push(draft.clone());
+            Some(draft)
+        };
+        self.write(&drafts)?;
+        Ok(draft)
+    }
+
+    // 先写临时文件再改名，避免写到一半退出时留下损坏的文件
+    fn write(&self, drafts: &[CommitDraft]) -> GitResult<()> {
+        if let Some(dir) = self.file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let content = serde_json::to_string_pretty(drafts).map_err(|e| GitError::Internal { message: e.to_string() })?;
+        let temp_file = self.file.with_extension("json.tmp");
+        fs::write(&temp_file, content)?;
+        fs::rename(&temp_file, &self.file)?;
+        Ok(())
+    }
+}
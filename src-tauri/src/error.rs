@@ -0,0 +1,199 @@
+use git2::{ErrorClass, ErrorCode};
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use serde_json::{json, Value};
+use std::fmt;
+
+// 返回给前端的结构化错误，序列化为 { code, message, context }，前端按 code 分支处理
+#[derive(Debug)]
+pub enum GitError {
+    NotARepository { path: String },
+    BareRepository,
+    NotFound { message: String },
+    InvalidArgument { message: String },
+    DirtyWorkdir { files: Vec<String> },
+    MergeConflict { paths: Vec<String> },
+    AuthFailed { message: String },
+    // ssh 连接的主机密钥未知或与 known_hosts 中的不一致，需要用户核对指纹后 accept_host_key
+    HostKeyUnverified { host: String, port: u16, key_type: String, fingerprint: String, changed: bool },
+    NetworkError { message: String },
+    DetachedHead,
+    UnbornBranch,
+    Locked { message: String },
+    AlreadyExists { message: String },
+    NotFastForward { message: String },
+    // force-with-lease 推送时远程分支已经不是预期的提交（别人推送过），actual 为远程当前的提交，分支已被删除时为空
+    RemoteChanged { remote: String, branch: String, expected: Option<String>, actual: Option<String> },
+    // 同一仓库上已有写操作在执行，operation 为该操作的名称
+    OperationInProgress { operation: String },
+    // gpg / ssh-keygen 签名失败（密钥不存在、口令取消等）
+    SigningFailed { message: String },
+    // 外部 git 命令以非零状态退出，message 为其 stderr
+    CommandFailed { command: String, exit_code: Option<i32>, message: String },
+    // pre-commit、commit-msg 等钩子以非零状态退出，中止了操作；output 为钩子的输出
+    HookRejected { hook: String, exit_code: Option<i32>, output: String },
+    Unsupported { message: String },
+    Io { message: String },
+    Cancelled,
+    Internal { message: String },
+    // 无法归类的 libgit2 错误，保留原始的 class 与 code
+    Git { class: String, code: String, message: String },
+}
+
+pub type GitResult<T> = Result<T, GitError>;
+
+impl GitError {
+    pub fn not_found(message: impl Into<String>) -> Self {
+        GitError::NotFound { message: message.into() }
+    }
+
+    pub fn invalid(message: impl Into<String>) -> Self {
+        GitError::InvalidArgument { message: message.into() }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            GitError::NotARepository { .. } => "notARepository",
+            GitError::BareRepository => "bareRepository",
+            GitError::NotFound { .. } => "notFound",
+            GitError::InvalidArgument { .. } => "invalidArgument",
+            GitError::DirtyWorkdir { .. } => "dirtyWorkdir",
+            GitError::MergeConflict { .. } => "mergeConflict",
+            GitError::AuthFailed { .. } => "authFailed",
+            GitError::HostKeyUnverified { .. } => "hostKeyUnverified",
+            GitError::NetworkError { .. } => "networkError",
+            GitError::DetachedHead => "detachedHead",
+            GitError::UnbornBranch => "unbornBranch",
+            GitError::Locked { .. } => "locked",
+            GitError::AlreadyExists { .. } => "alreadyExists",
+            GitError::NotFastForward { .. } => "notFastForward",
+            GitError::RemoteChanged { .. } => "remoteChanged",
+            GitError::OperationInProgress { .. } => "operationInProgress",
+            GitError::SigningFailed { .. } => "signingFailed",
+            GitError::CommandFailed { .. } => "commandFailed",
+            GitError::HookRejected { .. } => "hookRejected",
+            GitError::Unsupported { .. } => "unsupported",
+            GitError::Io { .. } => "io",
+            GitError::Cancelled => "cancelled",
+            GitError::Internal { .. } => "internal",
+            GitError::Git { .. } => "git",
+        }
+    }
+
+    fn context(&self) -> Value {
+        match self {
+            GitError::NotARepository { path } => json!({ "path": path }),
+            GitError::DirtyWorkdir { files } => json!({ "files": files }),
+            GitError::MergeConflict { paths } => json!({ "paths": paths }),
+            GitError::OperationInProgress { operation } => json!({ "operation": operation }),
+            GitError::RemoteChanged { remote, branch, expected, actual } => {
+                json!({ "remote": remote, "branch": branch, "expected": expected, "actual": actual })
+            }
+            GitError::HostKeyUnverified { host, port, key_type, fingerprint, changed } => {
+                json!({ "host": host, "port": port, "keyType": key_type, "fingerprint": fingerprint, "changed": changed })
+            }
+            GitError::CommandFailed { command, exit_code, .. } => json!({ "command": command, "exitCode": exit_code }),
+            GitError::HookRejected { hook, exit_code, output } => json!({ "hook": hook, "exitCode": exit_code, "output": output }),
+            GitError::Git { class, code, .. } => json!({ "class": class, "code": code }),
+            _ => Value::Null,
+        }
+    }
+}
+
+impl fmt::Display for GitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GitError::NotARepository { path } => write!(f, "not a git repository: {}", path),
+            GitError::BareRepository => write!(f, "repository has no working directory"),
+            GitError::DirtyWorkdir { files } if files.is_empty() => write!(f, "working directory has uncommitted changes"),
+            GitError::DirtyWorkdir { files } => write!(f, "working directory has uncommitted changes: {}", files.join(", ")),
+            GitError::MergeConflict { paths } if paths.is_empty() => write!(f, "merge conflict"),
+            GitError::MergeConflict { paths } => write!(f, "merge conflict in: {}", paths.join(", ")),
+            GitError::DetachedHead => write!(f, "HEAD is detached"),
+            GitError::UnbornBranch => write!(f, "current branch has no commits yet"),
+            GitError::OperationInProgress { operation } => write!(f, "another operation is in progress: {}", operation),
+            GitError::RemoteChanged { remote, branch, .. } => write!(f, "{}/{} has changed since it was last fetched", remote, branch),
+            GitError::HostKeyUnverified { host, changed: true, .. } => write!(f, "the host key of {} has changed", host),
+            GitError::HostKeyUnverified { host, .. } => write!(f, "the host key of {} is not known", host),
+            GitError::CommandFailed { command, message, .. } if message.is_empty() => write!(f, "{} failed", command),
+            GitError::CommandFailed { command, message, .. } => write!(f, "{} failed: {}", command, message),
+            GitError::HookRejected { hook, output, .. } if output.trim().is_empty() => write!(f, "{} hook failed", hook),
+            GitError::HookRejected { hook, output, .. } => write!(f, "{} hook failed: {}", hook, output.trim()),
+            GitError::Cancelled => write!(f, "operation was cancelled"),
+            GitError::NotFound { message }
+            | GitError::InvalidArgument { message }
+            | GitError::AuthFailed { message }
+            | GitError::NetworkError { message }
+            | GitError::Locked { message }
+            | GitError::AlreadyExists { message }
+            | GitError::NotFastForward { message }
+            | GitError::SigningFailed { message }
+            | GitError::Unsupported { message }
+            | GitError::Io { message }
+            | GitError::Internal { message }
+            | GitError::Git { message, .. } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for GitError {}
+
+impl Serialize for GitError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("GitError", 3)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("context", &self.context())?;
+        state.end()
+    }
+}
+
+impl From<git2::Error> for GitError {
+    fn from(e: git2::Error) -> Self {
+        let message = e.message().to_string();
+        match (e.class(), e.code()) {
+            (_, ErrorCode::Auth) => GitError::AuthFailed { message },
+            (ErrorClass::Net | ErrorClass::Http | ErrorClass::Ssh | ErrorClass::Ssl, _) => GitError::NetworkError { message },
+            (ErrorClass::Checkout, ErrorCode::Conflict) | (_, ErrorCode::Uncommitted) => GitError::DirtyWorkdir { files: Vec::new() },
+            (_, ErrorCode::Conflict) | (_, ErrorCode::MergeConflict) => GitError::MergeConflict { paths: Vec::new() },
+            (_, ErrorCode::UnbornBranch) => GitError::UnbornBranch,
+            (_, ErrorCode::Locked) => GitError::Locked { message },
+            (_, ErrorCode::Exists) => GitError::AlreadyExists { message },
+            (_, ErrorCode::NotFastForward) => GitError::NotFastForward { message },
+            (_, ErrorCode::BareRepo) => GitError::BareRepository,
+            (_, ErrorCode::NotFound) => GitError::NotFound { message },
+            (_, ErrorCode::InvalidSpec) | (_, ErrorCode::Invalid) | (_, ErrorCode::Ambiguous) => GitError::InvalidArgument { message },
+            (class, code) => GitError::Git {
+                class: format!("{:?}", class),
+                code: format!("{:?}", code),
+                message,
+            },
+        }
+    }
+}
+
+impl From<std::io::Error> for GitError {
+    fn from(e: std::io::Error) -> Self {
+        GitError::Io { message: e.to_string() }
+    }
+}
+
+impl From<notify::Error> for GitError {
+    fn from(e: notify::Error) -> Self {
+        GitError::Io { message: e.to_string() }
+    }
+}
+
+impl From<zip::result::ZipError> for GitError {
+    fn from(e: zip::result::ZipError) -> Self {
+        match e {
+            zip::result::ZipError::Io(e) => e.into(),
+            e => GitError::Internal { message: e.to_string() },
+        }
+    }
+}
+
+impl From<regex::Error> for GitError {
+    fn from(e: regex::Error) -> Self {
+        GitError::InvalidArgument { message: e.to_string() }
+    }
+}
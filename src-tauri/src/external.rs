@@ -0,0 +1,268 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::error::{GitError, GitResult};
+
+const EXTERNAL_TOOLS_FILE: &str = "external_tools.json";
+
+// 命令模板按空白拆分参数（支持引号），参数中的 {path} / {line} 替换为实际值；
+// 模板中没有 {path} 时把路径追加为最后一个参数。为空时使用系统默认的程序
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalToolSettings {
+    // 例如 "code -g {path}:{line}"、"subl {path}:{line}"
+    pub editor: Option<String>,
+    // 例如 "wezterm start --cwd {path}"
+    pub terminal: Option<String>,
+}
+
+// 外部编辑器和终端的设置，保存在应用数据目录下，由 Tauri 托管为全局状态
+#[derive(Clone)]
+pub struct ExternalTools {
+    file: PathBuf,
+    settings: Arc<Mutex<ExternalToolSettings>>,
+}
+
+impl ExternalTools {
+    pub fn load(data_dir: &Path) -> Self {
+        let file = data_dir.join(EXTERNAL_TOOLS_FILE);
+        let settings = match fs::read_to_string(&file) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                log::warn!("ignoring corrupt {}: {}", file.display(), e);
+                ExternalToolSettings::default()
+            }),
+            Err(_) => ExternalToolSettings::default(),
+        };
+        ExternalTools {
+            file,
+            settings: Arc::new(Mutex::new(settings)),
+        }
+    }
+
+    pub fn get(&self) -> ExternalToolSettings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    pub fn set(&self, settings: ExternalToolSettings) -> GitResult<()> {
+        for template in [&settings.editor, &settings.terminal].into_iter().flatten() {
+            if split_command(template).is_empty() {
+                return Err(GitError::invalid("command must not be empty"));
+            }
+        }
+        let mut current = self.settings.lock().unwrap();
+        if let Some(dir) = self.file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let content = serde_json::to_string_pretty(&settings).map_err(|e| GitError::Internal { message: e.to_string() })?;
+        let temp_file = self.file.with_extension("json.tmp");
+        fs::write(&temp_file, content)?;
+        fs::rename(&temp_file, &self.file)?;
+        *current = settings;
+        Ok(())
+    }
+
+    pub fn open_in_editor(&self, path: &str, line: Option<u32>) -> GitResult<()> {
+        let path = existing_path(path)?;
+        match self.get().editor.filter(|editor| !editor.trim().is_empty()) {
+            Some(template) => spawn(&mut template_command(&template, &path, line)),
+            None => open_with_default_app(&path),
+        }
+    }
+
+    pub fn open_in_terminal(&self, path: &str) -> GitResult<()> {
+        let path = existing_path(path)?;
+        // 传入文件时在它所在的目录打开
+        let dir = if path.is_dir() { path } else { path.parent().map(Path::to_path_buf).unwrap_or(path) };
+        match self.get().terminal.filter(|terminal| !terminal.trim().is_empty()) {
+            Some(template) => {
+                let mut command = template_command(&template, &dir, None);
+                command.current_dir(&dir);
+                spawn(&mut command)
+            }
+            None => open_default_terminal(&dir),
+        }
+    }
+}
+
+fn existing_path(path: &str) -> GitResult<PathBuf> {
+    let path = PathBuf::from(path);
+    if !path.exists() {
+        return Err(GitError::not_found(format!("path does not exist: {}", path.display())));
+    }
+    Ok(path)
+}
+
+// 按空白拆分，单引号和双引号内的空白不拆分
+fn split_command(template: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut quote = None;
+    let mut in_arg = false;
+    for c in template.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => current.push(c),
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                in_arg = true;
+            }
+            None if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            None => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if in_arg {
+        args.push(current);
+    }
+    args
+}
+
+fn template_command(template: &str, path: &Path, line: Option<u32>) -> Command {
+    let path = path.to_string_lossy();
+    let line = line.unwrap_or(1).to_string();
+    let args = split_command(template);
+    let has_path = args.iter().any(|arg| arg.contains("{path}"));
+    let mut args = args.into_iter().map(|arg| arg.replace("{path}", &path).replace("{line}", &line));
+    let mut command = Command::new(args.next().unwrap_or_default());
+    command.args(args);
+    if !has_path {
+        command.arg(path.as_ref());
+    }
+    command
+}
+
+// 不等待外部程序退出；在后台线程回收子进程，避免留下僵尸进程
+fn spawn(command: &mut Command) -> GitResult<()> {
+    let mut child = command.spawn().map_err(|e| GitError::Io {
+        message: format!("failed to run {}: {}", command.get_program().to_string_lossy(), e),
+    })?;
+    thread::spawn(move || child.wait());
+    Ok(())
+}
+
+// 依次尝试候选程序，返回第一个能启动的
+#[cfg(not(target_os = "macos"))]
+fn spawn_first(candidates: Vec<Command>) -> GitResult<()> {
+    let mut last_error = GitError::Unsupported {
+        message: "no suitable program found".to_string(),
+    };
+    for mut command in candidates {
+        match spawn(&mut command) {
+            Ok(()) => return Ok(()),
+            Err(e) => last_error = e,
+        }
+    }
+    Err(last_error)
+}
+
+fn command_with(program: &str, args: &[&str]) -> Command {
+    let mut command = Command::new(program);
+    command.args(args);
+    command
+}
+
+fn open_with_default_app(path: &Path) -> GitResult<()> {
+    let path = path.to_string_lossy();
+    #[cfg(target_os = "macos")]
+    {
+        spawn(&mut command_with("open", &[&path]))
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        spawn(&mut command_with("cmd", &["/C", "start", "", &path]))
+    }
+
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    {
+        spawn(&mut command_with("xdg-open", &[&path]))
+    }
+}
+
+fn open_default_terminal(dir: &Path) -> GitResult<()> {
+    #[cfg(target_os = "macos")]
+    {
+        spawn(&mut command_with("open", &["-a", "Terminal", &dir.to_string_lossy()]))
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        let dir = dir.to_string_lossy();
+        spawn_first(vec![
+            command_with("wt", &["-d", &dir]),
+            command_with("cmd", &["/C", "start", "", "cmd", "/K", "cd", "/d", &dir]),
+        ])
+    }
+
+    // 大多数终端模拟器都在当前目录打开，统一通过 current_dir 传递
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    {
+        let mut programs: Vec<String> = std::env::var("TERMINAL").into_iter().filter(|t| !t.is_empty()).collect();
+        programs.extend(
+            ["x-terminal-emulator", "gnome-terminal", "konsole", "xfce4-terminal", "alacritty", "kitty", "xterm"]
+                .iter()
+                .map(|program| program.to_string()),
+        );
+        spawn_first(
+            programs
+                .iter()
+                .map(|program| {
+                    let mut command = Command::new(program);
+                    command.current_dir(dir);
+                    command
+                })
+                .collect(),
+        )
+    }
+}
+
+// 在文件管理器中显示并选中该文件；Linux 上文件管理器不支持选中时退而打开所在目录
+pub fn reveal_in_file_manager(path: &str) -> GitResult<()> {
+    let path = existing_path(path)?;
+    let path_arg = path.to_string_lossy();
+    #[cfg(target_os = "macos")]
+    {
+        spawn(&mut command_with("open", &["-R", &path_arg]))
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        spawn(&mut command_with("explorer", &[&format!("/select,{}", path_arg)]))
+    }
+
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    {
+        let uri = format!("array:string:file://{}", path_arg);
+        let parent = path.parent().map(Path::to_path_buf).unwrap_or_else(|| path.clone());
+        let mut show_items = command_with(
+            "dbus-send",
+            &[
+                "--session",
+                "--print-reply",
+                "--dest=org.freedesktop.FileManager1",
+                "--type=method_call",
+                "/org/freedesktop/FileManager1",
+                "org.freedesktop.FileManager1.ShowItems",
+                &uri,
+                "string:",
+            ],
+        );
+        // dbus-send 能启动不代表调用成功，需要等它返回
+        let shown = show_items.output().map(|output| output.status.success()).unwrap_or(false);
+        if shown {
+            return Ok(());
+        }
+        spawn(&mut command_with("xdg-open", &[&parent.to_string_lossy()]))
+    }
+}
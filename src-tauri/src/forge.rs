@@ -0,0 +1,345 @@
+// 代码托管平台的信息：当前分支上打开的 pull request / merge request 和 CI 状态，供分支标题旁的指示器使用。
+// 只支持 GitHub（含 Enterprise）和 GitLab 的 REST API；远程不是这两者时不查询。
+// 请求由 curl 发出，使用和 git 相同的代理与证书配置；令牌从 git 的凭据助手（系统钥匙串、
+// Git Credential Manager 等）读取，没有时使用 GITHUB_TOKEN / GITLAB_TOKEN 环境变量，都没有时匿名访问
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use git2::{ErrorCode, Oid, Repository};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::{GitError, GitResult};
+use crate::git;
+use crate::remote_url::{self, RemoteProvider, RemoteWebRepo};
+use crate::signing;
+
+const REQUEST_TIMEOUT_SECS: &str = "20";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ForgeKind {
+    GitHub,
+    GitLab,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ForgeCiState {
+    Success,
+    Failure,
+    // 还在排队或运行
+    Pending,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitForgePullRequest {
+    // GitHub 的 PR 编号或 GitLab 的 MR iid
+    pub number: u64,
+    pub title: String,
+    pub url: String,
+    pub author: Option<String>,
+    pub draft: bool,
+    pub source_branch: String,
+    pub target_branch: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitForgeStatus {
+    pub forge: ForgeKind,
+    pub remote: String,
+    // 仓库网页地址，例如 https://github.com/owner/repo
+    pub web_url: String,
+    // 远程上的分支名
+    pub branch: String,
+    // 查询 CI 的提交：已推送的远程跟踪分支，没有时为 HEAD
+    pub commit: String,
+    pub pull_requests: Vec<GitForgePullRequest>,
+    // 该提交没有任何检查时为空
+    pub ci_state: Option<ForgeCiState>,
+    pub ci_url: Option<String>,
+    pub authenticated: bool,
+}
+
+struct ForgeApi {
+    kind: ForgeKind,
+    base_url: String,
+    // owner/repo，GitLab 可以有多级 group
+    project: String,
+    // 远程网页地址的协议（http 或 https），向凭据助手查询时使用
+    scheme: String,
+    host: String,
+    web_url: String,
+    token: Option<String>,
+}
+
+fn forge_api(web_repo: &RemoteWebRepo) -> Option<ForgeApi> {
+    let kind = match web_repo.provider {
+        RemoteProvider::GitHub => ForgeKind::GitHub,
+        RemoteProvider::GitLab => ForgeKind::GitLab,
+        _ => return None,
+    };
+    let (scheme, rest) = web_repo.base_url.split_once("://")?;
+    let (host, project) = rest.split_once('/')?;
+    let base_url = match kind {
+        ForgeKind::GitHub if host.eq_ignore_ascii_case("github.com") => "https://api.github.com".to_string(),
+        ForgeKind::GitHub => format!("{}://{}/api/v3", scheme, host),
+        ForgeKind::GitLab => format!("{}://{}/api/v4", scheme, host),
+    };
+    Some(ForgeApi {
+        kind,
+        base_url,
+        project: project.to_string(),
+        scheme: scheme.to_ascii_lowercase(),
+        host: host.to_string(),
+        web_url: web_repo.base_url.clone(),
+        token: None,
+    })
+}
+
+// 和 git push 时一样通过 git credential fill 向凭据助手要密码（即令牌）；不弹出任何提示。
+// 凭据助手没有时 git 会依次尝试 GIT_ASKPASS、core.askPass、SSH_ASKPASS 和终端，
+// GIT_ASKPASS 设为空值会跳过前三者，终端提示由 GIT_TERMINAL_PROMPT 关闭
+fn credential_token(git_dir: &Path, scheme: &str, host: &str) -> Option<String> {
+    let mut child = Command::new("git")
+        .arg("--git-dir")
+        .arg(git_dir)
+        .args(["credential", "fill"])
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .env("GIT_ASKPASS", "")
+        .env("SSH_ASKPASS", "")
+        .env("GCM_INTERACTIVE", "never")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    child.stdin.take()?.write_all(format!("protocol={}\nhost={}\n\n", scheme, host).as_bytes()).ok()?;
+    let output = child.wait_with_output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.strip_prefix("password="))
+        .map(str::to_string)
+        .filter(|token| !token.is_empty())
+}
+
+fn environment_token(kind: ForgeKind) -> Option<String> {
+    let names: &[&str] = match kind {
+        ForgeKind::GitHub => &["GITHUB_TOKEN", "GH_TOKEN"],
+        ForgeKind::GitLab => &["GITLAB_TOKEN"],
+    };
+    names.iter().find_map(|name| std::env::var(name).ok().filter(|token| !token.trim().is_empty()))
+}
+
+impl ForgeApi {
+    // 令牌通过 stdin 传给 curl，不出现在进程列表中
+    fn get(&self, git_dir: &Path, path: &str) -> GitResult<Value> {
+        let url = format!("{}{}", self.base_url, path);
+        let proxy = git::git_url_config(Some(git_dir), "http.proxy", &url)?;
+        let ca_info = git::git_url_config(Some(git_dir), "http.sslCAInfo", &url)?;
+        let ssl_verify = git::git_url_config(Some(git_dir), "http.sslVerify", &url)?;
+        let accept = match self.kind {
+            ForgeKind::GitHub => "Accept: application/vnd.github+json",
+            ForgeKind::GitLab => "Accept: application/json",
+        };
+        let mut args = vec!["-sS", "--max-time", REQUEST_TIMEOUT_SECS, "-H", accept, "-H", "User-Agent: GitGui", "-H", "@-", "-w", "\n%{http_code}"];
+        if let Some(proxy) = &proxy {
+            args.extend(["--proxy", proxy.as_str()]);
+        }
+        if let Some(ca_info) = &ca_info {
+            args.extend(["--cacert", ca_info.as_str()]);
+        }
+        if ssl_verify.is_some_and(|verify| git2::Config::parse_bool(verify).is_ok_and(|verify| !verify)) {
+            args.push("--insecure");
+        }
+        args.push(&url);
+        let headers = self.token.as_ref().map(|token| format!("Authorization: Bearer {}\n", token)).unwrap_or_default();
+        let output = signing::run_with_input("curl", &args, headers.as_bytes())?;
+        if !output.status.success() {
+            return Err(GitError::NetworkError {
+                message: format!("request to {} failed: {}", self.host, String::from_utf8_lossy(&output.stderr).trim()),
+            });
+        }
+
+        // -w 把状态码追加在响应体后面的单独一行
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        let (body, status) = stdout.rsplit_once('\n').unwrap_or(("", &stdout));
+        let status: u16 = status.trim().parse().unwrap_or(0);
+        let body: Value = serde_json::from_str(body).unwrap_or(Value::Null);
+        let message = body.get("message").and_then(Value::as_str).unwrap_or("").to_string();
+        match status {
+            200..=299 => Ok(body),
+            401 | 403 => Err(GitError::AuthFailed {
+                message: format!("{} rejected the request: {}", self.host, message),
+            }),
+            // 没有权限访问私有仓库时 GitHub 也返回 404
+            404 if self.token.is_none() => Err(GitError::AuthFailed {
+                message: format!("{} was not found; a token may be required for private repositories", self.project),
+            }),
+            404 => Err(GitError::not_found(format!("{} was not found on {}", self.project, self.host))),
+            _ => Err(GitError::NetworkError {
+                message: format!("{} returned HTTP {}: {}", self.host, status, message),
+            }),
+        }
+    }
+
+    fn pull_requests(&self, git_dir: &Path, branch: &str) -> GitResult<Vec<GitForgePullRequest>> {
+        let string = |value: &Value, key: &str| value.get(key).and_then(Value::as_str).unwrap_or("").to_string();
+        let items = match self.kind {
+            // head 要写成 "owner:branch"，所以只能查到以该远程为目标仓库的 PR
+            ForgeKind::GitHub => {
+                let owner = self.project.split('/').next().unwrap_or("");
+                let path = format!("/repos/{}/pulls?state=open&head={}", self.project, remote_url::encode_component(&format!("{}:{}", owner, branch)));
+                self.get(git_dir, &path)?
+            }
+            ForgeKind::GitLab => {
+                let path = format!("/projects/{}/merge_requests?state=opened&source_branch={}", remote_url::encode_component(&self.project), remote_url::encode_component(branch));
+                self.get(git_dir, &path)?
+            }
+        };
+        let items = items.as_array().cloned().unwrap_or_default();
+        Ok(items
+            .iter()
+            .map(|item| match self.kind {
+                ForgeKind::GitHub => GitForgePullRequest {
+                    number: item.get("number").and_then(Value::as_u64).unwrap_or(0),
+                    title: string(item, "title"),
+                    url: string(item, "html_url"),
+                    author: item.pointer("/user/login").and_then(Value::as_str).map(str::to_string),
+                    draft: item.get("draft").and_then(Value::as_bool).unwrap_or(false),
+                    source_branch: item.pointer("/head/ref").and_then(Value::as_str).unwrap_or("").to_string(),
+                    target_branch: item.pointer("/base/ref").and_then(Value::as_str).unwrap_or("").to_string(),
+                },
+                ForgeKind::GitLab => GitForgePullRequest {
+                    number: item.get("iid").and_then(Value::as_u64).unwrap_or(0),
+                    title: string(item, "title"),
+                    url: string(item, "web_url"),
+                    author: item.pointer("/author/username").and_then(Value::as_str).map(str::to_string),
+                    draft: item.get("draft").or_else(|| item.get("work_in_progress")).and_then(Value::as_bool).unwrap_or(false),
+                    source_branch: string(item, "source_branch"),
+                    target_branch: string(item, "target_branch"),
+                },
+            })
+            .collect())
+    }
+
+    // GitHub 的检查分为旧的 commit status 和 GitHub Actions 等使用的 check run，两者合并：
+    // 任何一项失败即为失败，否则有未完成的即为进行中
+    fn ci_status(&self, git_dir: &Path, commit: &str) -> GitResult<(Option<ForgeCiState>, Option<String>)> {
+        match self.kind {
+            ForgeKind::GitHub => {
+                let mut states = Vec::new();
+                let combined = self.get(git_dir, &format!("/repos/{}/commits/{}/status", self.project, commit))?;
+                if combined.get("total_count").and_then(Value::as_u64).unwrap_or(0) > 0 {
+                    states.push(match combined.get("state").and_then(Value::as_str) {
+                        Some("success") => ForgeCiState::Success,
+                        Some("pending") => ForgeCiState::Pending,
+                        _ => ForgeCiState::Failure,
+                    });
+                }
+                let check_runs = self.get(git_dir, &format!("/repos/{}/commits/{}/check-runs", self.project, commit))?;
+                for run in check_runs.get("check_runs").and_then(Value::as_array).into_iter().flatten() {
+                    states.push(match (run.get("status").and_then(Value::as_str), run.get("conclusion").and_then(Value::as_str)) {
+                        (Some("completed"), Some("success" | "neutral" | "skipped")) => ForgeCiState::Success,
+                        (Some("completed"), _) => ForgeCiState::Failure,
+                        _ => ForgeCiState::Pending,
+                    });
+                }
+                let state = if states.contains(&ForgeCiState::Failure) {
+                    Some(ForgeCiState::Failure)
+                } else if states.contains(&ForgeCiState::Pending) {
+                    Some(ForgeCiState::Pending)
+                } else {
+                    states.first().copied()
+                };
+                let url = state.map(|_| format!("{}/commit/{}/checks", self.web_url, commit));
+                Ok((state, url))
+            }
+            // 只看该提交最新的一条流水线
+            ForgeKind::GitLab => {
+                let pipelines = self.get(git_dir, &format!("/projects/{}/pipelines?sha={}&per_page=1", remote_url::encode_component(&self.project), commit))?;
+                let Some(pipeline) = pipelines.as_array().and_then(|pipelines| pipelines.first()) else {
+                    return Ok((None, None));
+                };
+                let state = match pipeline.get("status").and_then(Value::as_str) {
+                    Some("success") => Some(ForgeCiState::Success),
+                    Some("failed" | "canceled") => Some(ForgeCiState::Failure),
+                    Some("skipped") => None,
+                    _ => Some(ForgeCiState::Pending),
+                };
+                Ok((state, pipeline.get("web_url").and_then(Value::as_str).map(str::to_string)))
+            }
+        }
+    }
+}
+
+// 当前分支的上游（远程名称、远程上的分支名、远程跟踪分支指向的提交）；没有上游时用 origin 上的同名分支
+fn branch_upstream(repo: &Repository, ref_name: &str, branch: &str) -> GitResult<(String, String, Option<Oid>)> {
+    let upstream = match repo.branch_upstream_name(ref_name) {
+        Ok(upstream) => upstream.as_str().map(str::to_string),
+        Err(e) if e.code() == ErrorCode::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+    let remote = match repo.branch_upstream_remote(ref_name) {
+        Ok(remote) => remote.as_str().unwrap_or("origin").to_string(),
+        Err(e) if e.code() == ErrorCode::NotFound => "origin".to_string(),
+        Err(e) => return Err(e.into()),
+    };
+    let tracking = upstream.unwrap_or_else(|| format!("refs/remotes/{}/{}", remote, branch));
+    let remote_branch = tracking.strip_prefix(&format!("refs/remotes/{}/", remote)).unwrap_or(branch).to_string();
+    let target = repo.find_reference(&tracking).ok().and_then(|reference| reference.target());
+    Ok((remote, remote_branch, target))
+}
+
+// 远程不是 GitHub / GitLab 时返回 None
+pub fn get_forge_status(repo: &Repository) -> GitResult<Option<GitForgeStatus>> {
+    let head = match repo.head() {
+        Ok(head) => head,
+        Err(e) if e.code() == ErrorCode::UnbornBranch => return Err(GitError::UnbornBranch),
+        Err(e) => return Err(e.into()),
+    };
+    if !head.is_branch() {
+        return Err(GitError::DetachedHead);
+    }
+    let (Some(ref_name), Some(branch)) = (head.name(), head.shorthand()) else {
+        return Err(GitError::DetachedHead);
+    };
+    let (remote_name, remote_branch, pushed) = branch_upstream(repo, ref_name, branch)?;
+    let remote = match repo.find_remote(&remote_name) {
+        Ok(remote) => remote,
+        Err(e) if e.code() == ErrorCode::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let Some(web_repo) = remote.url().and_then(remote_url::parse) else {
+        return Ok(None);
+    };
+    let Some(mut api) = forge_api(&web_repo) else {
+        return Ok(None);
+    };
+
+    let git_dir = repo.path();
+    api.token = credential_token(git_dir, &api.scheme, &api.host).or_else(|| environment_token(api.kind));
+    let commit = match pushed.or_else(|| head.target()) {
+        Some(commit) => commit.to_string(),
+        None => return Err(GitError::UnbornBranch),
+    };
+    let pull_requests = api.pull_requests(git_dir, &remote_branch)?;
+    let (ci_state, ci_url) = api.ci_status(git_dir, &commit)?;
+    Ok(Some(GitForgeStatus {
+        forge: api.kind,
+        remote: remote_name,
+        web_url: api.web_url.clone(),
+        branch: remote_branch,
+        commit,
+        pull_requests,
+        ci_state,
+        ci_url,
+        authenticated: api.token.is_some(),
+    }))
+}
@@ -1,8 +1,36 @@
-use git2::{BranchType, Delta, DiffOptions, Oid, Repository, StatusOptions, StatusShow, Tree};
+use git2::{
+    ApplyLocation, ApplyOptions, AttrCheckFlags, AttrValue, BlameOptions, BranchType, Commit, Config, ConfigLevel, Delta, DiffFindOptions, DiffOptions,
+    Email, EmailCreateOptions, ErrorCode, FileMode, ObjectType, Oid, Patch, Repository, ResetType, Signature, Sort, StatusOptions, StatusShow,
+    SubmoduleIgnore, SubmoduleStatus, Tree, WorktreeAddOptions, WorktreeLockStatus, WorktreePruneOptions,
+};
+use regex::{Regex, RegexBuilder};
 use serde::{Deserialize, Serialize}; 
-use std::error::Error; 
+use sha2::{Digest, Sha256};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::archive::{ArchiveEntry, ArchiveFormat, ArchiveWriter};
+use crate::autostash;
+use crate::bisect;
+use crate::bundle::BundleHeader;
+use crate::cancel::CancellationToken;
+use crate::commit_message::{self, CommitTrailers};
+use crate::disk_usage::{DirUsage, SizeCache};
+use crate::error::{GitError, GitResult};
+use crate::git_cli;
+use crate::hooks;
+use crate::known_hosts;
+use crate::mbox;
+use crate::remote_url::{self, RemoteTransport, RemoteWebTarget};
+use crate::repo_cache::common_dir;
+use crate::rerere;
+use crate::signing::{self, SignatureStatus, Signer, SigningFormat};
+use crate::word_diff;
 
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
@@ -17,16 +45,130 @@ pub struct GitBranch {
 pub struct GitRemote {
     pub name: String,
     pub url: String,
+    // 未单独配置 pushurl 时为空，推送使用 url
+    pub push_url: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct GitCommit {
     pub hash: String,
-    pub author: String,
-    pub date: String,
+    pub author: GitIdentity,
+    pub committer: GitIdentity,
+    // 仅在 MailmapMode::Both 时填充提交中记录的原始身份
+    pub raw_author: Option<GitIdentity>,
+    pub raw_committer: Option<GitIdentity>,
     pub message: String,
     pub parents: Vec<String>,
+    // 指向该提交的分支、远程分支、标签和 HEAD
+    pub refs: Vec<GitRefDecoration>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitCommitPage {
+    pub commits: Vec<GitCommit>,
+    // 不透明的分页游标，原样传回即可继续遍历；为空表示历史已经到底
+    pub next_cursor: Option<String>,
+    // 本页遍历到了浅克隆的边界，更早的历史没有下载，前端可以提示加深
+    pub shallow_boundary: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum GitSearchField {
+    Hash,
+    Message,
+    Author,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GitCommitSearchHit {
+    pub commit: GitCommit,
+    pub matched: GitSearchField,
+}
+
+// commit-search-results 事件的内容，search_id 用来区分前端连续发起的多次搜索
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GitCommitSearchBatch {
+    pub search_id: String,
+    pub hits: Vec<GitCommitSearchHit>,
+}
+
+// commit-stream 事件的内容，每批提交按遍历顺序排列，前端直接追加到列表末尾
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GitCommitStreamBatch {
+    pub stream_id: String,
+    pub commits: Vec<GitCommit>,
+}
+
+// commit-stream-end 事件的内容，每次流式加载最后一定会发送一次；
+// 被 cancel_operation 取消时 cancelled 为 true，出错时 error 为错误信息，已发送的批次仍然有效
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GitCommitStreamEnd {
+    pub stream_id: String,
+    pub total: usize,
+    pub shallow_boundary: bool,
+    pub cancelled: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct GitCommitStreamSummary {
+    pub total: usize,
+    pub shallow_boundary: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct GitCommitFilter {
+    // 作者名或邮箱的子串，不区分大小写
+    pub author: Option<String>,
+    pub path: Option<String>,
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+    // 提交信息的正则表达式，不区分大小写
+    pub message: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum GitRefKind {
+    Head,
+    LocalBranch,
+    RemoteBranch,
+    Tag,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GitRefDecoration {
+    pub name: String,
+    pub kind: GitRefKind,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitGraphEdge {
+    pub parent_hash: String,
+    pub from_lane: usize,
+    pub to_lane: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitGraphNode {
+    pub hash: String,
+    pub lane: usize,
+    pub parent_edges: Vec<GitGraphEdge>,
+    // 从本行穿过、与当前提交无关的泳道
+    pub pass_through_lanes: Vec<usize>,
+    pub lane_count: usize,
+    pub refs: Vec<GitRefDecoration>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -34,6 +176,48 @@ pub struct GitCommit {
 pub struct GitCommitChange {
     pub path: String,
     pub status: String,
+    // 子模块（gitlink）的变化，普通文件为空
+    pub submodule: Option<GitSubmoduleChange>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GitIdentity {
+    pub name: String,
+    pub email: String,
+    pub time: i64,
+    // 相对 UTC 的时区偏移，单位分钟
+    pub offset_minutes: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitCommitFileStat {
+    pub path: String,
+    pub old_path: Option<String>,
+    pub status: String,
+    pub insertions: usize,
+    pub deletions: usize,
+    pub is_binary: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitCommitDetails {
+    pub hash: String,
+    pub summary: String,
+    pub message: String,
+    pub author: GitIdentity,
+    pub committer: GitIdentity,
+    pub raw_author: Option<GitIdentity>,
+    pub raw_committer: Option<GitIdentity>,
+    pub parents: Vec<String>,
+    pub is_signed: bool,
+    // gpg / ssh / x509，未签名时为空
+    pub signature_format: Option<String>,
+    pub insertions: usize,
+    pub deletions: usize,
+    pub files: Vec<GitCommitFileStat>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -41,6 +225,85 @@ pub struct GitCommitChange {
 pub struct GitCommitFileDiff {
     pub original: String,
     pub modified: String,
+    // 二进制文件不返回内容，只返回大小
+    pub is_binary: bool,
+    pub original_size: u64,
+    pub modified_size: u64,
+    // 内容超过大小上限时只返回前面一部分
+    pub truncated: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitBlameHunk {
+    pub commit_hash: String,
+    pub author: String,
+    pub author_email: String,
+    // 仅在 MailmapMode::Both 时填充
+    pub raw_author: Option<String>,
+    pub raw_author_email: Option<String>,
+    pub date: i64,
+    // 行号均从 1 开始
+    pub start_line: usize,
+    pub line_count: usize,
+    pub original_start_line: usize,
+    pub original_path: String,
+    pub is_boundary: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitBlame {
+    pub commit_hash: String,
+    pub lines: Vec<String>,
+    pub hunks: Vec<GitBlameHunk>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitDiffLine {
+    // '+' 新增，'-' 删除，' ' 上下文
+    pub origin: String,
+    pub content: String,
+    pub old_line_no: Option<u32>,
+    pub new_line_no: Option<u32>,
+    // 行内变化的区间，仅在请求单词级 diff 时填充
+    pub highlights: Vec<GitDiffRange>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitDiffRange {
+    // UTF-16 码元偏移，左闭右开
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitDiffHunk {
+    pub header: String,
+    pub old_start: u32,
+    pub old_lines: u32,
+    pub new_start: u32,
+    pub new_lines: u32,
+    pub lines: Vec<GitDiffLine>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitFilePatch {
+    pub path: String,
+    pub old_path: Option<String>,
+    pub status: String,
+    pub is_binary: bool,
+    pub insertions: usize,
+    pub deletions: usize,
+    // libgit2 生成的统一 diff 文本
+    pub patch: String,
+    pub hunks: Vec<GitDiffHunk>,
+    // 文件超过 max_bytes，按二进制处理而没有生成补丁，可以调大上限后重新获取
+    pub truncated: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -48,13 +311,156 @@ pub struct GitCommitFileDiff {
 pub struct GitStatus {
     pub file_path: String,
     pub status: String,
+    pub submodule: Option<GitSubmoduleChange>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitSubmoduleCommit {
+    pub hash: String,
+    pub summary: String,
+    // 只在旧提交一侧的提交（子模块被回退时），对应 git diff --submodule=log 中的 "<"
+    pub removed: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitSubmoduleChange {
+    // 新增或删除子模块时对应的一侧为空
+    pub old_commit: Option<String>,
+    pub new_commit: Option<String>,
+    // 两个提交之间的提交，只在需要时读取；子模块没有检出或缺少对象时为空
+    pub commits: Vec<GitSubmoduleCommit>,
+    pub commits_truncated: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct Worktree {
+    // 主工作树没有名称，为空字符串
+    pub name: String,
     pub path: String,
+    // 当前分支的短名称，分离 HEAD 时为空字符串
     pub branch: String,
+    // 工作树目录已不存在等无法打开时为空
+    pub head: Option<GitHeadState>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum GitHeadKind {
+    Branch,
+    Detached,
+    // 分支还没有任何提交（例如刚初始化的仓库）
+    Unborn,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GitHeadState {
+    pub kind: GitHeadKind,
+    // HEAD 指向的分支短名称，分离 HEAD 时为空
+    pub branch: Option<String>,
+    // HEAD 当前的提交，分支未诞生时为空
+    pub target: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitCheckoutResult {
+    pub head: GitHeadState,
+    // 使用了自动暂存且确实有改动被暂存时不为空
+    pub autostash: Option<autostash::GitAutostash>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitSubmodule {
+    pub name: String,
+    pub path: String,
+    pub url: Option<String>,
+    pub branch: Option<String>,
+    // 父仓库 HEAD 中记录的提交
+    pub recorded_commit: Option<String>,
+    // 子模块工作区当前检出的提交
+    pub checked_out_commit: Option<String>,
+    pub is_initialized: bool,
+    pub is_commit_mismatch: bool,
+    pub has_local_changes: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitReflogEntry {
+    // 对应 ref@{index}
+    pub index: usize,
+    pub old_hash: String,
+    pub new_hash: String,
+    pub message: String,
+    pub committer: GitIdentity,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ConfigScope {
+    Repo,
+    Global,
+    // 只读
+    System,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct GitConfigSettings {
+    pub user_name: Option<String>,
+    pub user_email: Option<String>,
+    // true / false / input
+    pub core_autocrlf: Option<String>,
+    // true / false / merges
+    pub pull_rebase: Option<String>,
+    // 签名相关：gpg.format 为 openpgp / x509 / ssh，commit.gpgSign、tag.gpgSign 为 true 时总是签名
+    pub user_signing_key: Option<String>,
+    pub gpg_format: Option<String>,
+    pub commit_gpg_sign: Option<String>,
+    pub tag_gpg_sign: Option<String>,
+    // 网络相关，fetch、push、克隆和 ls-remote 都由 git 命令行执行，直接使用这些配置。
+    // http.proxy 为空时 git 使用 https_proxy / http_proxy / all_proxy 环境变量
+    pub http_proxy: Option<String>,
+    // 企业内部证书颁发机构的 PEM 文件
+    pub http_ssl_ca_info: Option<String>,
+    pub http_ssl_verify: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitIgnoredStatus {
+    pub path: String,
+    pub ignored: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum IgnoreTarget {
+    File,
+    Extension,
+    Folder,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum ResetMode {
+    Soft,
+    Mixed,
+    Hard,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitResetResult {
+    pub target_hash: String,
+    pub performed: bool,
+    // 硬重置会丢弃的未提交改动，performed 为 false 时需要前端确认后带 force 重试
+    pub files_at_risk: Vec<GitStatus>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -64,6 +470,9 @@ pub struct GitRepoInfo {
     pub git_dir_path: String,
     pub worktree_path: String,
     pub is_bare: bool,
+    // 在链接工作树中打开时为 true，main_repo_path 为主仓库的根目录
+    pub is_worktree: bool,
+    pub main_repo_path: Option<String>,
     pub total_size_bytes: u64,
     pub worktree_size_bytes: u64,
     pub git_metadata_size_bytes: u64,
@@ -72,49 +481,121 @@ pub struct GitRepoInfo {
     pub git_refs_size_bytes: u64,
     pub lfs_enabled: bool,
     pub lfs_objects_size_bytes: u64,
+    // 没有配置 user.name 或 user.email 时不为空
+    pub identity_warning: Option<GitIdentityWarning>,
 }
 
-fn open_repo(repo_path: &str) -> Result<Repository, Box<dyn Error>> {
-    let path = Path::new(repo_path);
-    let discover_path = if path.is_file() {
-        path.parent().unwrap_or(path)
-    } else {
-        path
-    };
-    let repo = Repository::discover(discover_path)?;
-    Ok(repo)
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitLargeBlob {
+    pub oid: String,
+    // 未压缩的大小
+    pub size_bytes: u64,
+    // 历史中出现过的路径（最多 MAX_LARGE_BLOB_PATHS 个），不可达的对象为空
+    pub paths: Vec<String>,
+    // 最早包含该对象的提交
+    pub introduced_by: Option<String>,
+    pub introduced_at: Option<i64>,
 }
 
-fn dir_size(path: &Path, skip_name: Option<&str>) -> u64 {
-    let mut total = 0_u64;
-    let entries = match fs::read_dir(path) {
-        Ok(entries) => entries,
-        Err(_) => return total,
-    };
+// 目录树中的一个目录；children 只包含子目录，size_bytes 与子目录大小之和的差值是该目录下文件的大小
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitSizeNode {
+    pub name: String,
+    // 相对于工作目录或 .git 目录的路径，根节点为空
+    pub path: String,
+    pub size_bytes: u64,
+    pub file_count: u64,
+    // 按大小从大到小排序；超过请求的深度后不再展开
+    pub children: Vec<GitSizeNode>,
+}
 
-    for entry_result in entries {
-        let entry = match entry_result {
-            Ok(entry) => entry,
-            Err(_) => continue,
-        };
-        let entry_path = entry.path();
-        if let Some(skip) = skip_name {
-            if entry_path.file_name().and_then(|n| n.to_str()) == Some(skip) {
-                continue;
-            }
-        }
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitSizeBreakdown {
+    // 裸仓库没有工作目录
+    pub worktree: Option<GitSizeNode>,
+    pub git_dir: GitSizeNode,
+}
 
-        let metadata = match fs::symlink_metadata(&entry_path) {
-            Ok(metadata) => metadata,
-            Err(_) => continue,
-        };
-        if metadata.is_file() {
-            total = total.saturating_add(metadata.len());
-        } else if metadata.is_dir() {
-            total = total.saturating_add(dir_size(&entry_path, None));
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum GitSizeScanScope {
+    Worktree,
+    GitDir,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GitSizeScanProgress {
+    pub operation_id: Option<String>,
+    pub scope: GitSizeScanScope,
+    // 当前范围内已统计的文件数和字节数
+    pub scanned_files: u64,
+    pub scanned_bytes: u64,
+}
+
+pub const REPO_SIZE_PROGRESS_EVENT: &str = "repo-size-progress";
+pub const DEFAULT_SIZE_BREAKDOWN_DEPTH: usize = 3;
+
+// 扫描工作目录（跳过 .git）和 .git 目录；裸仓库没有工作目录
+fn scan_repo_sizes(
+    repo: &Repository,
+    sizes: &SizeCache,
+    refresh: bool,
+    cancel: &CancellationToken,
+    on_progress: &(impl Fn(GitSizeScanScope, u64, u64) + Sync),
+) -> GitResult<(Option<Arc<DirUsage>>, Arc<DirUsage>)> {
+    let worktree = match repo.workdir().filter(|_| !repo.is_bare()) {
+        Some(workdir) => Some(sizes.scan(workdir, Some(".git"), refresh, cancel, &|files, bytes| {
+            on_progress(GitSizeScanScope::Worktree, files, bytes)
+        })?),
+        None => None,
+    };
+    // 链接工作树自己的 git 目录里只有 HEAD、index 等，对象和引用在共享的主 .git 目录中
+    let git_dir = sizes.scan(&common_dir(repo.path()), None, refresh, cancel, &|files, bytes| {
+        on_progress(GitSizeScanScope::GitDir, files, bytes)
+    })?;
+    Ok((worktree, git_dir))
+}
+
+fn size_node(usage: &DirUsage, name: &str, rel_path: &str, depth: usize) -> GitSizeNode {
+    let mut children = Vec::new();
+    if depth > 0 {
+        for (child_name, child) in &usage.subdirs {
+            let child_path = if rel_path.is_empty() { child_name.clone() } else { format!("{}/{}", rel_path, child_name) };
+            children.push(size_node(child, child_name, &child_path, depth - 1));
         }
+        children.sort_by(|a, b| b.size_bytes.cmp(&a.size_bytes).then_with(|| a.name.cmp(&b.name)));
     }
-    total
+    GitSizeNode {
+        name: name.to_string(),
+        path: rel_path.to_string(),
+        size_bytes: usage.total_bytes,
+        file_count: usage.total_files,
+        children,
+    }
+}
+
+fn dir_name(path: &Path) -> String {
+    path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()
+}
+
+// 工作目录和 .git 目录按目录展开的大小，供前端绘制矩形树图
+pub fn get_size_breakdown(
+    repo: &Repository,
+    depth: usize,
+    sizes: &SizeCache,
+    refresh: bool,
+    cancel: &CancellationToken,
+    on_progress: impl Fn(GitSizeScanScope, u64, u64) + Sync,
+) -> GitResult<GitSizeBreakdown> {
+    let (worktree, git_dir) = scan_repo_sizes(repo, sizes, refresh, cancel, &on_progress)?;
+    Ok(GitSizeBreakdown {
+        worktree: worktree.map(|usage| size_node(&usage, &repo.workdir().map(dir_name).unwrap_or_default(), "", depth)),
+        git_dir: size_node(&git_dir, &dir_name(&common_dir(repo.path())), "", depth),
+    })
 }
 
 fn contains_lfs_filter(path: &Path) -> bool {
@@ -140,10 +621,234 @@ fn detect_lfs_enabled(repo: &Repository, worktree_path: &Path, git_dir: &Path) -
     false
 }
 
-pub fn get_branches(repo_path: &str) -> Result<Vec<GitBranch>, Box<dyn Error>> {
-    println!("Opening repository at: {}", repo_path);
-    let repo = open_repo(repo_path)?;
-    println!("Successfully opened repository");
+fn global_config_path() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(".gitconfig"))
+}
+
+// scope 为空时读取合并后的有效配置（本地 > 全局 > 系统）
+fn open_config(repo: &Repository, scope: Option<ConfigScope>) -> GitResult<Config> {
+    let config = repo.config()?;
+    let level = match scope {
+        None => return Ok(config),
+        Some(ConfigScope::Repo) => ConfigLevel::Local,
+        Some(ConfigScope::Global) => ConfigLevel::Global,
+        Some(ConfigScope::System) => ConfigLevel::System,
+    };
+    match config.open_level(level) {
+        Ok(config) => Ok(config),
+        // 用户还没有 ~/.gitconfig 时直接打开（写入时会自动创建）
+        Err(e) if e.code() == ErrorCode::NotFound && level == ConfigLevel::Global => {
+            let path = global_config_path().ok_or_else(|| GitError::not_found("cannot locate home directory for global config"))?;
+            Ok(Config::open(&path)?)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn open_writable_config(repo: &Repository, scope: ConfigScope) -> GitResult<Config> {
+    if scope == ConfigScope::System {
+        return Err(GitError::Unsupported {
+            message: "system config is read-only".to_string(),
+        });
+    }
+    open_config(repo, Some(scope))
+}
+
+fn config_string(config: &Config, key: &str) -> GitResult<Option<String>> {
+    match config.get_string(key) {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if e.code() == ErrorCode::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// value 为空时删除该键
+fn write_config_value(config: &mut Config, key: &str, value: Option<&str>) -> GitResult<()> {
+    match value {
+        Some(value) if !value.is_empty() => config.set_str(key, value)?,
+        _ => match config.remove(key) {
+            Ok(()) => {}
+            Err(e) if e.code() == ErrorCode::NotFound => {}
+            Err(e) => return Err(e.into()),
+        },
+    }
+    Ok(())
+}
+
+pub fn get_config(repo: &Repository, scope: Option<ConfigScope>) -> GitResult<GitConfigSettings> {
+    let config = open_config(repo, scope)?;
+
+    Ok(GitConfigSettings {
+        user_name: config_string(&config, "user.name")?,
+        user_email: config_string(&config, "user.email")?,
+        core_autocrlf: config_string(&config, "core.autocrlf")?,
+        pull_rebase: config_string(&config, "pull.rebase")?,
+        user_signing_key: config_string(&config, "user.signingkey")?,
+        gpg_format: config_string(&config, "gpg.format")?,
+        commit_gpg_sign: config_string(&config, "commit.gpgsign")?,
+        tag_gpg_sign: config_string(&config, "tag.gpgsign")?,
+        http_proxy: config_string(&config, "http.proxy")?,
+        http_ssl_ca_info: config_string(&config, "http.sslcainfo")?,
+        http_ssl_verify: config_string(&config, "http.sslverify")?,
+    })
+}
+
+// 和 curl 一样接受 host:port 或 协议://[user:password@]host[:port]
+fn validate_proxy(proxy: &str) -> GitResult<()> {
+    let invalid = || GitError::invalid(format!("invalid proxy: {}", proxy));
+    let rest = match proxy.split_once("://") {
+        Some((scheme, rest)) => {
+            if !matches!(scheme.to_ascii_lowercase().as_str(), "http" | "https" | "socks4" | "socks4a" | "socks5" | "socks5h") {
+                return Err(GitError::invalid(format!("unsupported proxy protocol: {}", scheme)));
+            }
+            rest
+        }
+        None => proxy,
+    };
+    let authority = rest.split('/').next().unwrap_or("");
+    let host_port = authority.rsplit('@').next().unwrap_or("");
+    // [::1] 形式的 IPv6 地址本身带冒号
+    let host = match host_port.rsplit_once(':') {
+        Some((host, port)) if !host_port.ends_with(']') => {
+            port.parse::<u16>().map_err(|_| invalid())?;
+            host
+        }
+        _ => host_port,
+    };
+    if host.is_empty() || host.contains(char::is_whitespace) {
+        return Err(invalid());
+    }
+    Ok(())
+}
+
+fn validate_network_settings(settings: &GitConfigSettings) -> GitResult<()> {
+    if let Some(proxy) = settings.http_proxy.as_deref().filter(|proxy| !proxy.is_empty()) {
+        validate_proxy(proxy)?;
+    }
+    if let Some(path) = settings.http_ssl_ca_info.as_deref().filter(|path| !path.is_empty()) {
+        if !signing::expand_home(path).is_file() {
+            return Err(GitError::not_found(format!("CA certificate file not found: {}", path)));
+        }
+    }
+    if let Some(verify) = settings.http_ssl_verify.as_deref().filter(|verify| !verify.is_empty()) {
+        if git2::Config::parse_bool(verify).is_err() {
+            return Err(GitError::invalid(format!("invalid boolean value for http.sslVerify: {}", verify)));
+        }
+    }
+    Ok(())
+}
+
+// 只写入提供了的字段；字段为空字符串表示删除
+pub fn set_config(repo: &Repository, scope: ConfigScope, settings: &GitConfigSettings) -> GitResult<()> {
+    validate_network_settings(settings)?;
+    let mut config = open_writable_config(repo, scope)?;
+    let values = [
+        ("user.name", &settings.user_name),
+        ("user.email", &settings.user_email),
+        ("core.autocrlf", &settings.core_autocrlf),
+        ("pull.rebase", &settings.pull_rebase),
+        ("user.signingkey", &settings.user_signing_key),
+        ("gpg.format", &settings.gpg_format),
+        ("commit.gpgsign", &settings.commit_gpg_sign),
+        ("tag.gpgsign", &settings.tag_gpg_sign),
+        ("http.proxy", &settings.http_proxy),
+        ("http.sslCAInfo", &settings.http_ssl_ca_info),
+        ("http.sslVerify", &settings.http_ssl_verify),
+    ];
+
+    for (key, value) in values {
+        if let Some(value) = value {
+            write_config_value(&mut config, key, Some(value))?;
+        }
+    }
+    Ok(())
+}
+
+pub fn get_config_value(repo: &Repository, key: &str, scope: Option<ConfigScope>) -> GitResult<Option<String>> {
+    let config = open_config(repo, scope)?;
+    config_string(&config, key)
+}
+
+pub fn set_config_value(repo: &Repository, key: &str, value: Option<&str>, scope: ConfigScope) -> GitResult<()> {
+    let mut config = open_writable_config(repo, scope)?;
+    write_config_value(&mut config, key, value)
+}
+
+// 提交时使用的身份：仓库本地配置优先于全局配置
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitRepoIdentity {
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub local_name: Option<String>,
+    pub local_email: Option<String>,
+    pub global_name: Option<String>,
+    pub global_email: Option<String>,
+}
+
+// 没有配置完整的身份时创建提交会失败，get_repo_info 提前告知前端缺少哪一项
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitIdentityWarning {
+    pub missing_name: bool,
+    pub missing_email: bool,
+}
+
+pub fn get_repo_identity(repo: &Repository) -> GitResult<GitRepoIdentity> {
+    let effective = open_config(repo, None)?;
+    let local = open_config(repo, Some(ConfigScope::Repo))?;
+    // 读取全局配置失败（例如找不到主目录）时按未配置处理
+    let global = open_config(repo, Some(ConfigScope::Global)).ok();
+    let global_string = |key: &str| -> GitResult<Option<String>> {
+        match &global {
+            Some(config) => config_string(config, key),
+            None => Ok(None),
+        }
+    };
+    Ok(GitRepoIdentity {
+        name: config_string(&effective, "user.name")?.filter(|name| !name.trim().is_empty()),
+        email: config_string(&effective, "user.email")?.filter(|email| !email.trim().is_empty()),
+        local_name: config_string(&local, "user.name")?,
+        local_email: config_string(&local, "user.email")?,
+        global_name: global_string("user.name")?,
+        global_email: global_string("user.email")?,
+    })
+}
+
+// 设置仓库本地的 user.name / user.email；为空时删除本地的值，回到全局身份
+pub fn set_repo_identity(repo: &Repository, name: Option<&str>, email: Option<&str>) -> GitResult<GitRepoIdentity> {
+    let name = name.map(str::trim).filter(|name| !name.is_empty());
+    let email = email.map(str::trim).filter(|email| !email.is_empty());
+    // 和提交签名的规则一致，名字和邮箱中不能包含尖括号和换行
+    for value in name.iter().chain(email.iter()) {
+        if value.contains(['<', '>', '\n']) {
+            return Err(GitError::invalid(format!("invalid identity value: {}", value)));
+        }
+    }
+    let mut config = open_writable_config(repo, ConfigScope::Repo)?;
+    write_config_value(&mut config, "user.name", name)?;
+    write_config_value(&mut config, "user.email", email)?;
+    get_repo_identity(repo)
+}
+
+fn identity_warning(repo: &Repository) -> Option<GitIdentityWarning> {
+    let identity = match get_repo_identity(repo) {
+        Ok(identity) => identity,
+        Err(e) => {
+            log::warn!("failed to read user identity: {}", e);
+            return None;
+        }
+    };
+    let warning = GitIdentityWarning {
+        missing_name: identity.name.is_none(),
+        missing_email: identity.email.is_none(),
+    };
+    (warning.missing_name || warning.missing_email).then_some(warning)
+}
+
+pub fn get_branches(repo: &Repository) -> GitResult<Vec<GitBranch>> {
     let mut branches = Vec::new();
     
     // 获取本地分支
@@ -178,263 +883,4993 @@ pub fn get_branches(repo_path: &str) -> Result<Vec<GitBranch>, Box<dyn Error>> {
     Ok(branches)
 }
 
-pub fn get_remotes(repo_path: &str) -> Result<Vec<GitRemote>, Box<dyn Error>> {
-    let repo = open_repo(repo_path)?;
-    let mut remotes = Vec::new();
-    
-    for remote in &repo.remotes()? {
-        if let Some(name) = remote {
-            let remote = repo.find_remote(name)?;
-            if let Some(url) = remote.url() {
-                remotes.push(GitRemote {
-                    name: name.to_string(),
-                    url: url.to_string(),
-                });
-            }
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitBranchTracking {
+    pub name: String,
+    pub is_current: bool,
+    // 上游分支的短名称，例如 origin/main；没有配置上游时为空
+    pub upstream: Option<String>,
+    // 配置了上游，但对应的远程跟踪分支已经不存在（远程分支被删除并修剪）
+    pub upstream_gone: bool,
+    // 相对上游领先/落后的提交数，没有上游或上游不存在时为 0
+    pub ahead: usize,
+    pub behind: usize,
+    pub last_commit_hash: String,
+    pub last_commit_summary: String,
+    pub last_commit_author: GitIdentity,
+}
+
+// 一次返回所有本地分支的上游与领先/落后情况，供清理分支的界面使用
+pub fn get_branch_tracking_overview(repo: &Repository) -> GitResult<Vec<GitBranchTracking>> {
+    let mut branches = Vec::new();
+    for branch in repo.branches(Some(BranchType::Local))? {
+        let (branch, _) = branch?;
+        let (name, ref_name) = match (branch.name()?, branch.get().name()) {
+            (Some(name), Some(ref_name)) => (name.to_string(), ref_name.to_string()),
+            _ => continue,
+        };
+        let commit = branch.get().peel_to_commit()?;
+
+        // 上游的名称只由 branch.<name>.remote / merge 配置决定，不要求引用存在
+        let upstream_ref = match repo.branch_upstream_name(&ref_name) {
+            Ok(upstream) => upstream.as_str().map(str::to_string),
+            Err(e) if e.code() == ErrorCode::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        let upstream_target = match &upstream_ref {
+            Some(upstream) => match repo.find_reference(upstream) {
+                Ok(reference) => Some(reference.peel_to_commit()?.id()),
+                Err(e) if e.code() == ErrorCode::NotFound => None,
+                Err(e) => return Err(e.into()),
+            },
+            None => None,
+        };
+        let (ahead, behind) = match upstream_target {
+            Some(target) => repo.graph_ahead_behind(commit.id(), target)?,
+            None => (0, 0),
+        };
+
+        branches.push(GitBranchTracking {
+            is_current: branch.is_head(),
+            upstream: upstream_ref.as_deref().map(|upstream| {
+                upstream
+                    .strip_prefix("refs/remotes/")
+                    .or_else(|| upstream.strip_prefix("refs/heads/"))
+                    .unwrap_or(upstream)
+                    .to_string()
+            }),
+            upstream_gone: upstream_ref.is_some() && upstream_target.is_none(),
+            ahead,
+            behind,
+            last_commit_hash: commit.id().to_string(),
+            last_commit_summary: commit.summary().unwrap_or("").to_string(),
+            last_commit_author: identity_from_signature(&commit.author()),
+            name,
+        });
+    }
+    branches.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(branches)
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitDeletedBranch {
+    pub name: String,
+    // 删除前指向的提交，可用来恢复分支
+    pub commit: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitSkippedBranch {
+    pub name: String,
+    pub reason: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitMergedBranchCleanup {
+    pub target: String,
+    pub dry_run: bool,
+    // 已删除（dry_run 时为将要删除）的分支
+    pub deleted: Vec<GitDeletedBranch>,
+    // 已合并但不能删除的分支，例如在某个工作树中检出的分支
+    pub skipped: Vec<GitSkippedBranch>,
+}
+
+// 删除已经完全合并到 target_ref 的本地分支（相当于 git branch -d），target_ref 本身指向的分支不删除
+pub fn delete_merged_branches(repo: &Repository, target_ref: &str, dry_run: bool) -> GitResult<GitMergedBranchCleanup> {
+    let target = repo.revparse_single(target_ref)?.peel_to_commit()?.id();
+    let target_branch = repo
+        .resolve_reference_from_short_name(target_ref)
+        .ok()
+        .filter(|reference| reference.is_branch())
+        .and_then(|reference| reference.name().map(str::to_string));
+    let checked_out = checked_out_branches(repo)?;
+
+    let mut deleted = Vec::new();
+    let mut skipped = Vec::new();
+    for branch in repo.branches(Some(BranchType::Local))? {
+        let (mut branch, _) = branch?;
+        let (name, ref_name) = match (branch.name()?, branch.get().name()) {
+            (Some(name), Some(ref_name)) => (name.to_string(), ref_name.to_string()),
+            _ => continue,
+        };
+        let commit = branch.get().peel_to_commit()?.id();
+        if target_branch.as_deref() == Some(ref_name.as_str()) {
+            continue;
+        }
+        if commit != target && !repo.graph_descendant_of(target, commit)? {
+            continue;
+        }
+        if let Some((_, path)) = checked_out.iter().find(|(checked_out_ref, _)| *checked_out_ref == ref_name) {
+            skipped.push(GitSkippedBranch {
+                name,
+                reason: format!("checked out at {}", path),
+            });
+            continue;
+        }
+        if !dry_run {
+            branch.delete()?;
+        }
+        deleted.push(GitDeletedBranch {
+            name,
+            commit: commit.to_string(),
+        });
+    }
+
+    Ok(GitMergedBranchCleanup {
+        target: target.to_string(),
+        dry_run,
+        deleted,
+        skipped,
+    })
+}
+
+pub fn get_remotes(repo: &Repository) -> GitResult<Vec<GitRemote>> {
+    let mut remotes = Vec::new();
+    
+    for remote in &repo.remotes()? {
+        if let Some(name) = remote {
+            let remote = repo.find_remote(name)?;
+            if let Some(url) = remote.url() {
+                remotes.push(GitRemote {
+                    name: name.to_string(),
+                    url: url.to_string(),
+                    push_url: remote.pushurl().map(|u| u.to_string()),
+                });
+            }
         }
     }
     
     Ok(remotes)
 }
 
-pub fn get_commits(repo_path: &str) -> Result<Vec<GitCommit>, Box<dyn Error>> {
-    let repo = open_repo(repo_path)?;
-    let mut commits = Vec::new();
-    
-    let head = repo.head()?;
-    let commit = head.peel_to_commit()?;
+// 识别远程地址使用的传输协议，无法识别时返回 None
+fn remote_url_transport(url: &str) -> Option<&'static str> {
+    let url = url.trim();
+    if url.is_empty() || url.chars().any(char::is_whitespace) {
+        return None;
+    }
+
+    if let Some((scheme, rest)) = url.split_once("://") {
+        if rest.is_empty() {
+            return None;
+        }
+        return match scheme.to_lowercase().as_str() {
+            "https" => Some("https"),
+            "http" => Some("http"),
+            "ssh" | "git+ssh" | "ssh+git" => Some("ssh"),
+            "git" => Some("git"),
+            "file" => Some("file"),
+            _ => None,
+        };
+    }
+
+    // scp 风格：[user@]host:path，需要排除 Windows 盘符 C:\
+    if let Some((host, path)) = url.split_once(':') {
+        let host = host.rsplit('@').next().unwrap_or(host);
+        if host.len() > 1 && !host.contains('/') && !path.is_empty() {
+            return Some("ssh");
+        }
+    }
+
+    if Path::new(url).is_absolute() {
+        return Some("local");
+    }
+    None
+}
+
+fn validate_remote(name: &str, url: &str) -> GitResult<()> {
+    if !git2::Remote::is_valid_name(name) {
+        return Err(GitError::invalid(format!("invalid remote name: {}", name)));
+    }
+    if remote_url_transport(url).is_none() {
+        return Err(GitError::invalid(format!("invalid remote url: {}", url)));
+    }
+    Ok(())
+}
+
+pub fn add_remote(repo: &Repository, name: &str, url: &str) -> GitResult<GitRemote> {
+    validate_remote(name, url)?;
+    let remote = repo.remote(name, url.trim())?;
+
+    Ok(GitRemote {
+        name: name.to_string(),
+        url: remote.url().unwrap_or("").to_string(),
+        push_url: None,
+    })
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitRemoteUrlCheck {
+    pub url: String,
+    // 地址格式是否有效；无效时 error 是原因，其余字段为空
+    pub valid: bool,
+    pub transport: Option<RemoteTransport>,
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub path: Option<String>,
+    // 只有 probe 时才有值
+    pub reachable: Option<bool>,
+    // 远程 HEAD 指向的分支，空仓库为空
+    pub default_branch: Option<String>,
+    pub branch_count: Option<usize>,
+    // 格式错误，或 probe 时连接失败的原因（git 输出的 fatal 信息）
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitRemoteRefEntry {
+    // 去掉 refs/heads/ 或 refs/tags/ 的短名称
+    pub name: String,
+    pub hash: String,
+    // 附注标签指向的提交；轻量标签和分支为空
+    pub target_hash: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct GitRemoteRefs {
+    // 远程 HEAD 指向的分支，即克隆后默认检出的分支；空仓库为空
+    pub default_branch: Option<String>,
+    pub branches: Vec<GitRemoteRefEntry>,
+    pub tags: Vec<GitRemoteRefEntry>,
+}
+
+// 连接阶段没有进展时放弃的秒数，避免地址不通时检查一直挂着
+const REMOTE_PROBE_TIMEOUT_SECS: &str = "20";
+
+fn remote_probe_error(message: &str) -> String {
+    message
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("fatal: "))
+        .unwrap_or_else(|| message.lines().next().unwrap_or("").trim())
+        .to_string()
+}
+
+// git ls-remote --symref 的输出："<hash>\t<ref>"，HEAD 指向的分支为 "ref: <ref>\tHEAD"，
+// 附注标签另有一行 "<提交>\t<tag>^{}"
+fn parse_ls_remote(output: &str) -> GitRemoteRefs {
+    let mut refs = GitRemoteRefs::default();
+    let mut peeled = HashMap::new();
+    for line in output.lines() {
+        let Some((value, name)) = line.split_once('\t') else {
+            continue;
+        };
+        if let Some(target) = value.strip_prefix("ref: ") {
+            if name == "HEAD" {
+                refs.default_branch = target.strip_prefix("refs/heads/").map(str::to_string);
+            }
+        } else if let Some(branch) = name.strip_prefix("refs/heads/") {
+            refs.branches.push(GitRemoteRefEntry {
+                name: branch.to_string(),
+                hash: value.to_string(),
+                target_hash: None,
+            });
+        } else if let Some(tag) = name.strip_prefix("refs/tags/") {
+            match tag.strip_suffix("^{}") {
+                Some(tag) => {
+                    peeled.insert(tag.to_string(), value.to_string());
+                }
+                None => refs.tags.push(GitRemoteRefEntry {
+                    name: tag.to_string(),
+                    hash: value.to_string(),
+                    target_hash: None,
+                }),
+            }
+        }
+    }
+    for tag in &mut refs.tags {
+        tag.target_hash = peeled.remove(&tag.name);
+    }
+    refs
+}
+
+// 列出远程的引用。git_dir 为空时不在任何仓库中执行（克隆之前），否则使用该仓库的远程名称和配置。
+// 连接时没有进展会超时放弃；没有自行配置 SSH 命令时禁止交互式提示，需要凭据或确认主机密钥时直接失败
+fn ls_remote(git_dir: Option<&Path>, remote: &str, patterns: &[&str], cancel: &CancellationToken) -> GitResult<GitRemoteRefs> {
+    // 以 - 开头会被 git 当作选项解析（如 --upload-pack=<cmd>）
+    if remote.starts_with('-') {
+        return Err(GitError::invalid(format!("invalid remote: {}", remote)));
+    }
+    let git_dir_arg = git_dir.map(|dir| dir.to_string_lossy().to_string());
+    let low_speed_time = format!("http.lowSpeedTime={}", REMOTE_PROBE_TIMEOUT_SECS);
+    let mut args = Vec::new();
+    if let Some(git_dir) = &git_dir_arg {
+        args.extend(["--git-dir", git_dir.as_str()]);
+    }
+    args.extend(["-c", "http.lowSpeedLimit=1", "-c", &low_speed_time, "ls-remote", "--symref", remote]);
+    args.extend_from_slice(patterns);
+    let connect_timeout = format!("ConnectTimeout={}", REMOTE_PROBE_TIMEOUT_SECS);
+    let ssh_command = known_hosts::ssh_command(git_dir, &["-o", "BatchMode=yes", "-o", &connect_timeout]);
+    let env: Vec<(&str, &str)> = ssh_command.iter().map(|command| ("GIT_SSH_COMMAND", command.as_str())).collect();
+    let output = git_cli::run_git_outside_repo(&args, &env, cancel)?;
+    Ok(parse_ls_remote(&output.stdout))
+}
+
+// 读取对 url 生效的配置（git config --get-urlmatch，包括 http.<url>.proxy 这样按地址的设置）
+pub fn git_url_config(git_dir: Option<&Path>, key: &str, url: &str) -> GitResult<Option<String>> {
+    let git_dir_arg = git_dir.map(|dir| dir.to_string_lossy().to_string());
+    let mut args = Vec::new();
+    if let Some(git_dir) = &git_dir_arg {
+        args.extend(["--git-dir", git_dir.as_str()]);
+    }
+    args.extend(["config", "--get-urlmatch", key, url]);
+    match git_cli::run_git_outside_repo(&args, &[], &CancellationToken::default()) {
+        Ok(output) => Ok(Some(output.stdout.trim().to_string()).filter(|value| !value.is_empty())),
+        // 没有该配置时以 1 退出
+        Err(GitError::CommandFailed { exit_code: Some(1), .. }) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+// 检查克隆地址的格式并识别传输协议；probe 时再用 git ls-remote 只取 HEAD 和分支列表，
+// 确认地址可以访问。不会弹出凭据或主机密钥的提示，需要它们时报告为无法访问
+pub fn validate_remote_url(url: &str, probe: bool, cancel: &CancellationToken) -> GitResult<GitRemoteUrlCheck> {
+    let mut check = GitRemoteUrlCheck {
+        url: url.trim().to_string(),
+        valid: false,
+        transport: None,
+        host: None,
+        port: None,
+        path: None,
+        reachable: None,
+        default_branch: None,
+        branch_count: None,
+        error: None,
+    };
+    let parts = match remote_url::parse_remote_url(url) {
+        Ok(parts) => parts,
+        Err(GitError::InvalidArgument { message }) => {
+            check.error = Some(message);
+            return Ok(check);
+        }
+        Err(e) => return Err(e),
+    };
+    check.valid = true;
+    check.transport = Some(parts.transport);
+    check.host = parts.host;
+    check.port = parts.port;
+    check.path = Some(parts.path);
+    if !probe {
+        return Ok(check);
+    }
+
+    match ls_remote(None, &check.url, &["HEAD", "refs/heads/*"], cancel) {
+        Ok(refs) => {
+            check.reachable = Some(true);
+            check.default_branch = refs.default_branch;
+            check.branch_count = Some(refs.branches.len());
+        }
+        Err(GitError::CommandFailed { message, .. }) => {
+            check.reachable = Some(false);
+            check.error = Some(remote_probe_error(&message));
+        }
+        Err(e) => return Err(e),
+    }
+    Ok(check)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum GitConnectivityErrorKind {
+    // 无法解析主机名
+    Dns,
+    Proxy,
+    // 证书校验失败，通常需要配置 http.sslCAInfo
+    Certificate,
+    // 连接成功但需要（或拒绝了）凭据
+    Auth,
+    Timeout,
+    Other,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitConnectivityReport {
+    pub url: String,
+    pub transport: RemoteTransport,
+    // 对该地址生效的代理（密码已隐去），来自配置或环境变量；SSH 和本地地址不使用代理
+    pub proxy: Option<String>,
+    pub proxy_from_environment: bool,
+    pub ssl_ca_info: Option<String>,
+    pub ssl_verify: bool,
+    pub reachable: bool,
+    pub elapsed_ms: u64,
+    pub error_kind: Option<GitConnectivityErrorKind>,
+    pub error: Option<String>,
+}
+
+fn mask_proxy_password(proxy: &str) -> String {
+    let (scheme, rest) = proxy.split_once("://").map_or(("", proxy), |(scheme, rest)| (scheme, rest));
+    let masked = match rest.split_once('@') {
+        Some((credentials, host)) if credentials.contains(':') => {
+            format!("{}:***@{}", credentials.split(':').next().unwrap_or(""), host)
+        }
+        _ => rest.to_string(),
+    };
+    if scheme.is_empty() {
+        masked
+    } else {
+        format!("{}://{}", scheme, masked)
+    }
+}
+
+// 和 curl 一样，先找协议对应的小写变量，再找大写的，最后是 all_proxy
+fn environment_proxy(transport: RemoteTransport) -> Option<String> {
+    let names: &[&str] = match transport {
+        RemoteTransport::Https => &["https_proxy", "HTTPS_PROXY", "all_proxy", "ALL_PROXY"],
+        RemoteTransport::Http => &["http_proxy", "all_proxy", "ALL_PROXY"],
+        _ => &[],
+    };
+    names.iter().find_map(|name| std::env::var(name).ok().filter(|value| !value.trim().is_empty()))
+}
+
+// 按 git / curl 的错误信息归类，便于界面给出下一步建议。使用代理时连不上的是代理本身
+fn connectivity_error_kind(message: &str, uses_proxy: bool) -> GitConnectivityErrorKind {
+    let message = message.to_lowercase();
+    let contains_any = |patterns: &[&str]| patterns.iter().any(|pattern| message.contains(pattern));
+    if contains_any(&["could not resolve proxy", "proxy connect", "received http code 407", "proxy authentication", "unable to connect to proxy"])
+        || (uses_proxy && contains_any(&["failed to connect", "couldn't connect", "connection refused"]))
+    {
+        GitConnectivityErrorKind::Proxy
+    } else if contains_any(&["could not resolve host", "name or service not known", "nodename nor servname"]) {
+        GitConnectivityErrorKind::Dns
+    } else if contains_any(&["ssl certificate", "certificate verify", "server certificate verification", "unable to get local issuer", "self signed certificate", "self-signed certificate"]) {
+        GitConnectivityErrorKind::Certificate
+    } else if contains_any(&["authentication failed", "could not read username", "could not read password", "terminal prompts disabled", "permission denied", "host key verification failed", "403"]) {
+        GitConnectivityErrorKind::Auth
+    } else if contains_any(&["timed out", "operation too slow", "timeout"]) {
+        GitConnectivityErrorKind::Timeout
+    } else {
+        GitConnectivityErrorKind::Other
+    }
+}
+
+// 用当前的代理和证书设置连接一次远程（只执行 ls-remote 取 HEAD），报告生效的设置和失败原因。
+// repo 不为空时 remote 可以是远程名称，并使用该仓库的配置
+pub fn test_network_connectivity(repo: Option<&Repository>, remote: &str, cancel: &CancellationToken) -> GitResult<GitConnectivityReport> {
+    let remote = remote.trim();
+    let git_dir = repo.map(|repo| common_dir(repo.path()));
+    let url = match repo.map(|repo| repo.find_remote(remote)) {
+        Some(Ok(found)) => found.url().unwrap_or(remote).to_string(),
+        Some(Err(e)) if e.code() != ErrorCode::NotFound && e.code() != ErrorCode::InvalidSpec => return Err(e.into()),
+        _ => remote.to_string(),
+    };
+    let transport = remote_url::parse_remote_url(&url)?.transport;
+
+    let mut report = GitConnectivityReport {
+        url: url.clone(),
+        transport,
+        proxy: None,
+        proxy_from_environment: false,
+        ssl_ca_info: None,
+        ssl_verify: true,
+        reachable: false,
+        elapsed_ms: 0,
+        error_kind: None,
+        error: None,
+    };
+    if matches!(transport, RemoteTransport::Http | RemoteTransport::Https) {
+        let git_dir = git_dir.as_deref();
+        report.proxy = git_url_config(git_dir, "http.proxy", &url)?;
+        if report.proxy.is_none() {
+            report.proxy = environment_proxy(transport);
+            report.proxy_from_environment = report.proxy.is_some();
+        }
+        report.proxy = report.proxy.as_deref().map(mask_proxy_password);
+        report.ssl_ca_info = git_url_config(git_dir, "http.sslCAInfo", &url)?;
+        // GIT_SSL_NO_VERIFY 环境变量也会关闭校验
+        report.ssl_verify = std::env::var_os("GIT_SSL_NO_VERIFY").is_none()
+            && git_url_config(git_dir, "http.sslVerify", &url)?.map_or(true, |verify| git2::Config::parse_bool(verify).unwrap_or(true));
+    }
+
+    let started = Instant::now();
+    let result = ls_remote(git_dir.as_deref(), remote, &["HEAD"], cancel);
+    report.elapsed_ms = started.elapsed().as_millis() as u64;
+    match result {
+        Ok(_) => report.reachable = true,
+        Err(GitError::CommandFailed { message, .. }) => {
+            report.error_kind = Some(connectivity_error_kind(&message, report.proxy.is_some()));
+            report.error = Some(remote_probe_error(&message));
+        }
+        Err(e) => return Err(e),
+    }
+    Ok(report)
+}
+
+const REMOTE_REF_PATTERNS: [&str; 3] = ["HEAD", "refs/heads/*", "refs/tags/*"];
+
+// 不下载对象，列出远程仓库的分支和标签（git ls-remote）。remote 可以是远程名称，也可以直接是地址
+pub fn list_remote_refs(repo: &Repository, remote: &str, cancel: &CancellationToken) -> GitResult<GitRemoteRefs> {
+    let remote = remote.trim();
+    if remote.is_empty() {
+        return Err(GitError::invalid("remote is required"));
+    }
+    if remote.starts_with('-') {
+        return Err(GitError::invalid(format!("invalid remote: {}", remote)));
+    }
+    match repo.find_remote(remote) {
+        Ok(_) => {}
+        Err(e) if e.code() == ErrorCode::NotFound || e.code() == ErrorCode::InvalidSpec => {
+            remote_url::parse_remote_url(remote)?;
+        }
+        Err(e) => return Err(e.into()),
+    }
+    ls_remote(Some(&common_dir(repo.path())), remote, &REMOTE_REF_PATTERNS, cancel)
+}
+
+// 克隆对话框中还没有仓库时使用
+pub fn list_remote_refs_for_url(url: &str, cancel: &CancellationToken) -> GitResult<GitRemoteRefs> {
+    remote_url::parse_remote_url(url)?;
+    ls_remote(None, url.trim(), &REMOTE_REF_PATTERNS, cancel)
+}
+
+pub fn remove_remote(repo: &Repository, name: &str) -> GitResult<()> {
+    repo.remote_delete(name)?;
+    Ok(())
+}
+
+// 返回无法自动改名的非默认 refspec，前端需要提示用户手动处理
+pub fn rename_remote(repo: &Repository, old_name: &str, new_name: &str) -> GitResult<Vec<String>> {
+    if !git2::Remote::is_valid_name(new_name) {
+        return Err(GitError::invalid(format!("invalid remote name: {}", new_name)));
+    }
+    let problems = repo.remote_rename(old_name, new_name)?;
+    Ok(problems.iter().flatten().map(|p| p.to_string()).collect())
+}
+
+// push 为 true 时修改 pushurl；传入空地址表示清除 pushurl，恢复使用 url 推送
+pub fn set_remote_url(repo: &Repository, name: &str, url: &str, push: bool) -> GitResult<()> {
+    repo.find_remote(name)?;
+
+    if push && url.trim().is_empty() {
+        let mut config = repo.config()?;
+        match config.remove(&format!("remote.{}.pushurl", name)) {
+            Ok(()) => {}
+            Err(e) if e.code() == ErrorCode::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        return Ok(());
+    }
+
+    validate_remote(name, url)?;
+    if push {
+        repo.remote_set_pushurl(name, Some(url.trim()))?;
+    } else {
+        repo.remote_set_url(name, url.trim())?;
+    }
+    Ok(())
+}
+
+pub const FETCH_PROGRESS_EVENT: &str = "fetch-progress";
+
+// 未指定远程时使用当前分支的上游远程，没有则用 origin
+fn default_fetch_remote(repo: &Repository) -> GitResult<String> {
+    let upstream = repo
+        .head()
+        .ok()
+        .and_then(|head| head.name().map(str::to_string))
+        .and_then(|name| repo.branch_upstream_remote(&name).ok())
+        .and_then(|remote| remote.as_str().map(str::to_string));
+    let remote = upstream.unwrap_or_else(|| "origin".to_string());
+    repo.find_remote(&remote)?;
+    Ok(remote)
+}
+
+// 下载浅克隆缺少的历史：deepen 为空时取回完整历史（git fetch --unshallow），否则把边界再向前加深 deepen 个提交。
+// 返回仓库是否仍是浅克隆
+pub fn fetch_unshallow(
+    repo: &Repository,
+    remote: Option<&str>,
+    deepen: Option<u32>,
+    cancel: &CancellationToken,
+    on_progress: impl FnMut(&str),
+) -> GitResult<bool> {
+    if !repo.is_shallow() {
+        return Err(GitError::invalid("repository is not a shallow clone"));
+    }
+    let remote = match remote {
+        Some(remote) => {
+            repo.find_remote(remote)?;
+            remote.to_string()
+        }
+        None => default_fetch_remote(repo)?,
+    };
+    let depth_arg = match deepen {
+        Some(0) => return Err(GitError::invalid("deepen must be at least 1")),
+        Some(deepen) => format!("--deepen={}", deepen),
+        None => "--unshallow".to_string(),
+    };
+    git_cli::run_git_with_progress(
+        &common_dir(repo.path()),
+        None,
+        &["fetch", "--progress", &depth_arg, &remote],
+        cancel,
+        on_progress,
+    )?;
+    Ok(common_dir(repo.path()).join("shallow").exists())
+}
+
+pub const CLONE_PROGRESS_EVENT: &str = "clone-progress";
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GitCloneOptions {
+    // 只取回最近的 depth 个提交（浅克隆），之后可以用 fetch_unshallow 补全
+    pub depth: Option<u32>,
+    // 只取回一个分支：branch 为空时为远程的默认分支
+    pub single_branch: bool,
+    // 克隆后检出的分支，为空时为远程的默认分支
+    pub branch: Option<String>,
+}
+
+// git clone 到 path（必须是绝对路径，且不存在或是空目录）。
+// git 在指定 --depth 时默认只取一个分支，single_branch 为 false 时传 --no-single-branch 取回所有分支
+pub fn clone_repo(
+    url: &str,
+    path: &str,
+    options: &GitCloneOptions,
+    cancel: &CancellationToken,
+    on_progress: impl FnMut(&str),
+) -> GitResult<()> {
+    let url = url.trim();
+    if url.starts_with('-') {
+        return Err(GitError::invalid(format!("invalid remote url: {}", url)));
+    }
+    remote_url::parse_remote_url(url)?;
+    let target = Path::new(path);
+    if !target.is_absolute() {
+        return Err(GitError::invalid(format!("clone destination must be an absolute path: {}", path)));
+    }
+    if fs::read_dir(target).is_ok_and(|mut entries| entries.next().is_some()) || target.is_file() {
+        return Err(GitError::AlreadyExists {
+            message: format!("'{}' already exists and is not an empty directory", path),
+        });
+    }
+    if let Some(branch) = &options.branch {
+        if !git2::Reference::is_valid_name(&format!("refs/heads/{}", branch)) {
+            return Err(GitError::invalid(format!("invalid branch name: {}", branch)));
+        }
+    }
+
+    let depth = match options.depth {
+        Some(0) => return Err(GitError::invalid("depth must be at least 1")),
+        Some(depth) => Some(depth.to_string()),
+        None => None,
+    };
+    let mut args = vec!["clone", "--progress"];
+    if let Some(depth) = &depth {
+        args.extend(["--depth", depth.as_str()]);
+    }
+    if options.single_branch {
+        args.push("--single-branch");
+    } else if depth.is_some() {
+        args.push("--no-single-branch");
+    }
+    if let Some(branch) = &options.branch {
+        args.extend(["--branch", branch.as_str()]);
+    }
+    args.extend(["--", url, path]);
+    git_cli::run_git_outside_repo_with_progress(&args, &[], cancel, on_progress)?;
+    Ok(())
+}
+
+pub const PUSH_PROGRESS_EVENT: &str = "push-progress";
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitPushResult {
+    pub remote: String,
+    pub branch: String,
+    // 推送前远程分支的提交（即 force-with-lease 预期的提交），远程分支原来不存在时为空
+    pub old_tip: Option<String>,
+    pub new_tip: String,
+    // 是否覆盖了远程的提交（非快进更新）
+    pub forced: bool,
+}
+
+// 未指定分支时推送当前分支；分离 HEAD 时没有可推送的分支
+fn push_branch_name(repo: &Repository, branch: Option<&str>) -> GitResult<String> {
+    if let Some(branch) = branch {
+        repo.find_branch(branch, BranchType::Local)?;
+        return Ok(branch.to_string());
+    }
+    let head = match repo.head() {
+        Ok(head) => head,
+        Err(e) if e.code() == ErrorCode::UnbornBranch => return Err(GitError::UnbornBranch),
+        Err(e) => return Err(e.into()),
+    };
+    if !head.is_branch() {
+        return Err(GitError::DetachedHead);
+    }
+    head.shorthand()
+        .map(str::to_string)
+        .ok_or_else(|| GitError::invalid("branch name is not valid UTF-8"))
+}
+
+fn remote_branch_tip(repo: &Repository, remote: &str, branch: &str) -> GitResult<Option<Oid>> {
+    let output = git_cli::run_git(
+        &common_dir(repo.path()),
+        &["ls-remote", "--heads", remote, &format!("refs/heads/{}", branch)],
+        &CancellationToken::default(),
+    )?;
+    Ok(output
+        .stdout
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .find(|(_, name)| name.strip_prefix("refs/heads/") == Some(branch))
+        .and_then(|(oid, _)| Oid::from_str(oid).ok()))
+}
+
+// 把本地分支推送到远程的同名分支。force_with_lease 为 true 时和 git push --force-with-lease 一样，
+// 只有远程分支仍是 expected（未传时为推送前记录的远程跟踪分支）时才强制覆盖，
+// 否则返回 GitError::RemoteChanged，前端确认后可以把其中的 actual 作为 expected 重新推送
+pub fn push_branch(
+    repo: &Repository,
+    remote: Option<&str>,
+    branch: Option<&str>,
+    force_with_lease: bool,
+    expected: Option<&str>,
+    cancel: &CancellationToken,
+    mut on_progress: impl FnMut(&str),
+) -> GitResult<GitPushResult> {
+    let branch = push_branch_name(repo, branch)?;
+    let remote = match remote {
+        Some(remote) => {
+            repo.find_remote(remote)?;
+            remote.to_string()
+        }
+        None => default_fetch_remote(repo)?,
+    };
+    let local = repo.find_branch(&branch, BranchType::Local)?;
+    let new_tip = local.get().peel_to_commit()?.id();
+
+    let expected = match expected {
+        // 远程的新提交通常还没有取回到本地，完整的哈希直接使用
+        Some(expected) if expected.len() == 40 => Some(Oid::from_str(expected)?),
+        Some(expected) => Some(repo.revparse_single(expected)?.peel_to_commit()?.id()),
+        None => match repo.find_reference(&format!("refs/remotes/{}/{}", remote, branch)) {
+            Ok(reference) => reference.target(),
+            Err(e) if e.code() == ErrorCode::NotFound => None,
+            Err(e) => return Err(e.into()),
+        },
+    };
+    let refspec = format!("refs/heads/{0}:refs/heads/{0}", branch);
+    // 预期值为空时要求远程分支不存在
+    let lease = format!(
+        "--force-with-lease=refs/heads/{}:{}",
+        branch,
+        expected.map(|oid| oid.to_string()).unwrap_or_default()
+    );
+    let mut args = vec!["push", "--porcelain", "--progress"];
+    if force_with_lease {
+        args.push(&lease);
+    }
+    args.extend([remote.as_str(), refspec.as_str()]);
+
+    // --porcelain 在 stdout 输出每个引用的结果，被拒绝时为 "!\t<refspec>\t[rejected] (<原因>)"
+    let mut rejected = None;
+    let mut forced = false;
+    let result = git_cli::run_git_with_progress(&common_dir(repo.path()), None, &args, cancel, |line| {
+        forced |= line.starts_with("+\t");
+        if line.starts_with("!\t") && line.contains("[rejected]") {
+            rejected = Some(line.to_string());
+        }
+        on_progress(line);
+    });
+    match result {
+        Ok(_) => Ok(GitPushResult {
+            remote,
+            branch,
+            old_tip: expected.map(|oid| oid.to_string()),
+            new_tip: new_tip.to_string(),
+            forced,
+        }),
+        Err(GitError::CommandFailed { .. }) if rejected.as_deref().is_some_and(|line| line.contains("(stale info)")) => {
+            let actual = remote_branch_tip(repo, &remote, &branch)?;
+            Err(GitError::RemoteChanged {
+                remote,
+                branch,
+                expected: expected.map(|oid| oid.to_string()),
+                actual: actual.map(|oid| oid.to_string()),
+            })
+        }
+        Err(GitError::CommandFailed { message, .. }) if rejected.is_some() => Err(GitError::NotFastForward { message }),
+        Err(e) => Err(e),
+    }
+}
+
+// 和 git push <remote> --delete <branch> 一样删除远程分支，同时删除对应的远程跟踪分支
+pub fn delete_remote_branch(repo: &Repository, remote: &str, branch: &str) -> GitResult<()> {
+    repo.find_remote(remote)?;
+    let branch = branch.strip_prefix("refs/heads/").unwrap_or(branch);
+    if branch.is_empty() || !git2::Reference::is_valid_name(&format!("refs/heads/{}", branch)) {
+        return Err(GitError::invalid(format!("invalid branch name: {}", branch)));
+    }
+    let refspec = format!(":refs/heads/{}", branch);
+    match git_cli::run_git(&common_dir(repo.path()), &["push", "--porcelain", remote, &refspec], &CancellationToken::default()) {
+        Ok(_) => Ok(()),
+        Err(GitError::CommandFailed { message, .. }) if message.contains("remote ref does not exist") => {
+            Err(GitError::not_found(format!("branch '{}' does not exist on {}", branch, remote)))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum GitBranchRenameStep {
+    Push,
+    SetUpstream,
+    DeleteOldRemote,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitBranchRenameFailure {
+    pub step: GitBranchRenameStep,
+    pub error: GitError,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitBranchRename {
+    pub branch: GitBranch,
+    pub old_name: String,
+    // 重命名前的上游，例如 origin/feature；没有上游或上游是本地分支时为空
+    pub old_upstream: Option<String>,
+    pub push: Option<GitPushResult>,
+    pub upstream_updated: bool,
+    pub old_remote_deleted: bool,
+    // 本地重命名之后的步骤失败时不回滚，在这里逐项说明
+    pub failures: Vec<GitBranchRenameFailure>,
+}
+
+// 重命名本地分支；分支有远程上游时可以顺带把新名称推送到同一个远程（push）、把上游改为远程的新分支，
+// 并在推送成功后删除远程的旧分支（delete_old_remote）。
+// 本地重命名失败时返回错误；之后的步骤失败不会回滚已完成的步骤，记在 failures 中
+pub fn rename_branch_with_remote(
+    repo: &Repository,
+    old_name: &str,
+    new_name: &str,
+    push: bool,
+    delete_old_remote: bool,
+    cancel: &CancellationToken,
+    on_progress: impl FnMut(&str),
+) -> GitResult<GitBranchRename> {
+    let mut branch = repo.find_branch(old_name, BranchType::Local)?;
+    // (远程名, 远程上的分支名)，remote 为 "." 时上游是本地分支
+    let config = repo.config()?;
+    let upstream = match (
+        config_string(&config, &format!("branch.{}.remote", old_name))?,
+        config_string(&config, &format!("branch.{}.merge", old_name))?,
+    ) {
+        (Some(remote), Some(merge)) if remote != "." => merge.strip_prefix("refs/heads/").map(|merge| (remote, merge.to_string())),
+        _ => None,
+    };
+
+    // libgit2 会把 branch.<旧名称> 配置一并改名，上游暂时仍指向远程的旧分支
+    let mut renamed = branch.rename(new_name, false)?;
+    let mut result = GitBranchRename {
+        branch: GitBranch {
+            name: new_name.to_string(),
+            is_current: renamed.is_head(),
+            is_remote: false,
+        },
+        old_name: old_name.to_string(),
+        old_upstream: upstream.as_ref().map(|(remote, merge)| format!("{}/{}", remote, merge)),
+        push: None,
+        upstream_updated: false,
+        old_remote_deleted: false,
+        failures: Vec::new(),
+    };
+    let Some((remote, old_remote_branch)) = upstream.filter(|_| push) else {
+        return Ok(result);
+    };
+
+    match push_branch(repo, Some(&remote), Some(new_name), false, None, cancel, on_progress) {
+        Ok(pushed) => result.push = Some(pushed),
+        Err(error) => {
+            result.failures.push(GitBranchRenameFailure {
+                step: GitBranchRenameStep::Push,
+                error,
+            });
+            return Ok(result);
+        }
+    }
+    match renamed.set_upstream(Some(&format!("{}/{}", remote, new_name))) {
+        Ok(()) => result.upstream_updated = true,
+        Err(e) => result.failures.push(GitBranchRenameFailure {
+            step: GitBranchRenameStep::SetUpstream,
+            error: e.into(),
+        }),
+    }
+    // 上游本来就和新名称相同时远程没有需要删除的旧分支
+    if delete_old_remote && old_remote_branch != new_name {
+        match delete_remote_branch(repo, &remote, &old_remote_branch) {
+            Ok(()) => result.old_remote_deleted = true,
+            Err(error) => result.failures.push(GitBranchRenameFailure {
+                step: GitBranchRenameStep::DeleteOldRemote,
+                error,
+            }),
+        }
+    }
+    Ok(result)
+}
+
+// 删除远程上已经不存在的远程跟踪分支（git remote prune），返回删除（dry_run 时为将要删除）的引用短名称
+pub fn prune_remote(repo: &Repository, remote: &str, dry_run: bool) -> GitResult<Vec<String>> {
+    repo.find_remote(remote)?;
+    let mut args = vec!["remote", "prune"];
+    if dry_run {
+        args.push("--dry-run");
+    }
+    args.push(remote);
+    let output = git_cli::run_git(&common_dir(repo.path()), &args, &CancellationToken::default())?;
+    // 每个引用输出一行 " * [pruned] origin/feature"，dry_run 时为 "[would prune]"
+    let marker = if dry_run { "[would prune] " } else { "[pruned] " };
+    Ok(output
+        .stdout
+        .lines()
+        .filter_map(|line| line.split_once(marker))
+        .map(|(_, name)| name.trim().to_string())
+        .collect())
+}
+
+pub const DEFAULT_COMMIT_PAGE_SIZE: usize = 50;
+pub const DEFAULT_COMMIT_GRAPH_LIMIT: usize = 2000;
+pub const DEFAULT_COMMIT_SEARCH_LIMIT: usize = 200;
+pub const DEFAULT_COMPARE_LIMIT: usize = 250;
+pub const COMMIT_SEARCH_RESULTS_EVENT: &str = "commit-search-results";
+pub const COMMIT_STREAM_EVENT: &str = "commit-stream";
+pub const COMMIT_STREAM_END_EVENT: &str = "commit-stream-end";
+
+// 攒够这么多结果，或距上次发送超过 COMMIT_SEARCH_FLUSH_INTERVAL 时发送一批
+const COMMIT_SEARCH_BATCH_SIZE: usize = 20;
+const COMMIT_SEARCH_FLUSH_INTERVAL: Duration = Duration::from_millis(100);
+// 流式加载的第一批尽量小，让历史视图尽快显示出第一屏
+const COMMIT_STREAM_FIRST_BATCH_SIZE: usize = 100;
+const COMMIT_STREAM_BATCH_SIZE: usize = 1000;
+const COMMIT_STREAM_FLUSH_INTERVAL: Duration = Duration::from_millis(200);
+// 太短的十六进制前缀几乎匹配所有提交，不按哈希匹配
+const MIN_HASH_QUERY_LENGTH: usize = 4;
+
+// 解析历史遍历的起点；仓库还没有任何提交（HEAD 指向未诞生的分支）时返回 None
+fn resolve_walk_start(repo: &Repository, rev: Option<&str>) -> GitResult<Option<Oid>> {
+    if let Some(rev) = rev {
+        return Ok(Some(repo.revparse_single(rev)?.peel_to_commit()?.id()));
+    }
+    match repo.head() {
+        Ok(head) => Ok(Some(head.peel_to_commit()?.id())),
+        Err(e) if e.code() == ErrorCode::UnbornBranch || e.code() == ErrorCode::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum MailmapMode {
+    // 不读取 .mailmap，返回提交中记录的身份
+    Off,
+    // 按 .mailmap 合并同一作者的多个名字和邮箱
+    #[default]
+    Mapped,
+    // 返回映射后的身份，同时附带原始身份
+    Both,
+}
+
+// 按 MailmapMode 把提交中的签名转换为返回给前端的身份
+struct IdentityMapper {
+    mailmap: Option<git2::Mailmap>,
+    include_raw: bool,
+}
+
+impl IdentityMapper {
+    fn new(repo: &Repository, mode: MailmapMode) -> GitResult<Self> {
+        Ok(IdentityMapper {
+            mailmap: if mode == MailmapMode::Off { None } else { Some(repo.mailmap()?) },
+            include_raw: mode == MailmapMode::Both,
+        })
+    }
+
+    fn resolve(&self, signature: &Signature) -> GitResult<Signature<'static>> {
+        match &self.mailmap {
+            Some(mailmap) => Ok(mailmap.resolve_signature(signature)?),
+            None => Ok(signature.to_owned()),
+        }
+    }
+
+    // 返回映射后的身份和（需要时）原始身份
+    fn identity(&self, signature: &Signature) -> GitResult<(GitIdentity, Option<GitIdentity>)> {
+        let mapped = identity_from_signature(&self.resolve(signature)?);
+        let raw = self.include_raw.then(|| identity_from_signature(signature));
+        Ok((mapped, raw))
+    }
+}
+
+fn commit_summary(commit: &Commit, refs: Vec<GitRefDecoration>, identities: &IdentityMapper) -> GitResult<GitCommit> {
+    let (author, raw_author) = identities.identity(&commit.author())?;
+    let (committer, raw_committer) = identities.identity(&commit.committer())?;
+    Ok(GitCommit {
+        hash: commit.id().to_string(),
+        author,
+        committer,
+        raw_author,
+        raw_committer,
+        message: commit.message().unwrap_or("").trim().to_string(),
+        parents: commit.parent_ids().map(|id| id.to_string()).collect(),
+        refs,
+    })
+}
+
+pub fn get_commits(
+    repo: &Repository,
+    rev: Option<&str>,
+    cursor: Option<&str>,
+    limit: usize,
+    filter: &GitCommitFilter,
+    mailmap: MailmapMode,
+    cancel: &CancellationToken,
+) -> GitResult<GitCommitPage> {
+    let limit = limit.max(1);
+    let identities = IdentityMapper::new(repo, mailmap)?;
+    let matcher = CommitMatcher::new(filter)?;
+    let mut decorations = ref_decorations(repo)?;
+    let shallow = shallow_commits(repo);
+    let mut shallow_boundary = false;
+    let mut commits = Vec::new();
+    
+    let mut revwalk = repo.revwalk()?;
+    // 拓扑排序保证父提交总在所有子提交之后出现，游标才能只记录遍历前沿
+    revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+    match cursor {
+        Some(cursor) => {
+            for hash in cursor.split(',').filter(|h| !h.is_empty()) {
+                revwalk.push(Oid::from_str(hash)?)?;
+            }
+        }
+        None => match resolve_walk_start(repo, rev)? {
+            Some(start) => revwalk.push(start)?,
+            None => {
+                return Ok(GitCommitPage {
+                    commits: Vec::new(),
+                    next_cursor: None,
+                    shallow_boundary: false,
+                });
+            }
+        },
+    }
+    
+    // 过滤掉的提交也算遍历过，游标前沿要基于全部遍历过的提交计算
+    let mut walked = HashSet::new();
+    let mut walked_parents = Vec::new();
+    let mut revwalk = revwalk.peekable();
+    while commits.len() < limit {
+        let oid = match revwalk.next() {
+            Some(oid) => oid?,
+            None => break,
+        };
+        cancel.check()?;
+        let commit = repo.find_commit(oid)?;
+        walked.insert(oid);
+        walked_parents.extend(commit.parent_ids());
+        shallow_boundary |= shallow.contains(&oid);
+        
+        if !matcher.matches(repo, &commit, &identities)? {
+            continue;
+        }
+        
+        commits.push(commit_summary(&commit, decorations.remove(&oid).unwrap_or_default(), &identities)?);
+    }
+    
+    let next_cursor = if revwalk.peek().is_some() {
+        Some(commit_walk_frontier(&walked, &walked_parents).join(","))
+    } else {
+        None
+    };
+    
+    Ok(GitCommitPage {
+        commits,
+        next_cursor,
+        shallow_boundary,
+    })
+}
+
+// 没有共同祖先时返回 None
+fn find_merge_base(repo: &Repository, one: Oid, two: Oid) -> GitResult<Option<Oid>> {
+    match repo.merge_base(one, two) {
+        Ok(oid) => Ok(Some(oid)),
+        Err(e) if e.code() == ErrorCode::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn get_merge_base(repo: &Repository, one: &str, two: &str) -> GitResult<Option<String>> {
+    let one = repo.revparse_single(one)?.peel_to_commit()?.id();
+    let two = repo.revparse_single(two)?.peel_to_commit()?.id();
+    Ok(find_merge_base(repo, one, two)?.map(|oid| oid.to_string()))
+}
+
+// 和 git merge-base --octopus 一样，求所有提交共同的祖先（章鱼合并的合并基础）
+pub fn get_octopus_merge_base(repo: &Repository, revs: &[String]) -> GitResult<Option<String>> {
+    if revs.len() < 2 {
+        return Err(GitError::invalid("at least two commits are required"));
+    }
+    let mut oids = Vec::new();
+    for rev in revs {
+        oids.push(repo.revparse_single(rev)?.peel_to_commit()?.id());
+    }
+    let mut base = oids[0];
+    for oid in &oids[1..] {
+        base = match find_merge_base(repo, base, *oid)? {
+            Some(oid) => oid,
+            None => return Ok(None),
+        };
+    }
+    Ok(Some(base.to_string()))
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitRefComparison {
+    // 解析后的提交哈希
+    pub base: String,
+    pub head: String,
+    // 两者没有共同祖先时为空
+    pub merge_base: Option<String>,
+    // head 独有的提交（合并到 base 时会带入的提交）和 base 独有的提交，各自最多 limit 个
+    pub ahead: Vec<GitCommit>,
+    pub behind: Vec<GitCommit>,
+    // 实际数量，可能大于列表长度
+    pub ahead_count: usize,
+    pub behind_count: usize,
+}
+
+fn unique_commits(
+    repo: &Repository,
+    from: Oid,
+    exclude: Oid,
+    limit: usize,
+    decorations: &mut HashMap<Oid, Vec<GitRefDecoration>>,
+    identities: &IdentityMapper,
+    cancel: &CancellationToken,
+) -> GitResult<Vec<GitCommit>> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+    revwalk.push(from)?;
+    revwalk.hide(exclude)?;
+    let mut commits = Vec::new();
+    for oid in revwalk.take(limit) {
+        let oid = oid?;
+        cancel.check()?;
+        let commit = repo.find_commit(oid)?;
+        commits.push(commit_summary(&commit, decorations.remove(&oid).unwrap_or_default(), identities)?);
+    }
+    Ok(commits)
+}
+
+// 比较两个引用（或任意提交），相当于 git log base..head 和 git log head..base
+pub fn compare_refs(
+    repo: &Repository,
+    base: &str,
+    head: &str,
+    limit: usize,
+    mailmap: MailmapMode,
+    cancel: &CancellationToken,
+) -> GitResult<GitRefComparison> {
+    let base = repo.revparse_single(base)?.peel_to_commit()?.id();
+    let head = repo.revparse_single(head)?.peel_to_commit()?.id();
+    let merge_base = find_merge_base(repo, base, head)?;
+    let (ahead_count, behind_count) = repo.graph_ahead_behind(head, base)?;
+    let identities = IdentityMapper::new(repo, mailmap)?;
+    let mut decorations = ref_decorations(repo)?;
+    let ahead = unique_commits(repo, head, base, limit, &mut decorations, &identities, cancel)?;
+    let behind = unique_commits(repo, base, head, limit, &mut decorations, &identities, cancel)?;
+
+    Ok(GitRefComparison {
+        base: base.to_string(),
+        head: head.to_string(),
+        merge_base: merge_base.map(|oid| oid.to_string()),
+        ahead,
+        behind,
+        ahead_count,
+        behind_count,
+    })
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum GitMergePreviewKind {
+    // 要合并的提交已经包含在 HEAD 中
+    UpToDate,
+    FastForward,
+    Clean,
+    Conflicts,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitMergePreview {
+    pub kind: GitMergePreviewKind,
+    // 分支未诞生时 head 为空
+    pub head: Option<String>,
+    pub target: String,
+    pub merge_base: Option<String>,
+    pub conflicts: Vec<String>,
+}
+
+// 在内存中把 rev 合并到 HEAD，不修改索引和工作区，只报告合并的结果
+pub fn preview_merge(repo: &Repository, rev: &str) -> GitResult<GitMergePreview> {
+    let target = repo.revparse_single(rev)?.peel_to_commit()?;
+    let head = head_commit(repo)?;
+    let preview = |kind, merge_base: Option<Oid>, conflicts| GitMergePreview {
+        kind,
+        head: head.as_ref().map(|commit| commit.id().to_string()),
+        target: target.id().to_string(),
+        merge_base: merge_base.map(|oid| oid.to_string()),
+        conflicts,
+    };
+    let head_commit = match &head {
+        Some(commit) => commit,
+        None => return Ok(preview(GitMergePreviewKind::FastForward, None, Vec::new())),
+    };
+
+    let merge_base = find_merge_base(repo, head_commit.id(), target.id())?;
+    if merge_base == Some(target.id()) {
+        return Ok(preview(GitMergePreviewKind::UpToDate, merge_base, Vec::new()));
+    }
+    if merge_base == Some(head_commit.id()) {
+        return Ok(preview(GitMergePreviewKind::FastForward, merge_base, Vec::new()));
+    }
+    // 和 git merge 一样，没有共同祖先的历史需要 --allow-unrelated-histories，这里仍按空的合并基础预览
+    let index = repo.merge_commits(head_commit, &target, None)?;
+    if index.has_conflicts() {
+        let conflicts = conflict_paths(&index)?;
+        return Ok(preview(GitMergePreviewKind::Conflicts, merge_base, conflicts));
+    }
+    Ok(preview(GitMergePreviewKind::Clean, merge_base, Vec::new()))
+}
+
+// 浅克隆的边界提交记录在 .git/shallow 中，libgit2 把它们当作没有父提交
+pub fn shallow_commits(repo: &Repository) -> HashSet<Oid> {
+    if !repo.is_shallow() {
+        return HashSet::new();
+    }
+    fs::read_to_string(common_dir(repo.path()).join("shallow"))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| Oid::from_str(line.trim()).ok())
+        .collect()
+}
+
+// 已遍历提交的父提交中尚未遍历的部分，即下一页遍历的起点
+fn commit_walk_frontier(walked: &HashSet<Oid>, walked_parents: &[Oid]) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut frontier = Vec::new();
+    for parent in walked_parents {
+        if !walked.contains(parent) && seen.insert(*parent) {
+            frontier.push(parent.to_string());
+        }
+    }
+    frontier
+}
+
+// 名字或邮箱包含 query（query 已转为小写）
+fn signature_contains(signature: &Signature, query: &str) -> bool {
+    signature.name().unwrap_or("").to_lowercase().contains(query) || signature.email().unwrap_or("").to_lowercase().contains(query)
+}
+
+fn search_field(commit: &Commit, query: &str, hash_query: bool, identities: &IdentityMapper) -> GitResult<Option<GitSearchField>> {
+    if hash_query && commit.id().to_string().starts_with(query) {
+        return Ok(Some(GitSearchField::Hash));
+    }
+    if commit.message().unwrap_or("").to_lowercase().contains(query) {
+        return Ok(Some(GitSearchField::Message));
+    }
+    let author = commit.author();
+    if signature_contains(&author, query) || signature_contains(&identities.resolve(&author)?, query) {
+        return Ok(Some(GitSearchField::Author));
+    }
+    Ok(None)
+}
+
+// 遍历 HEAD 以及所有分支、远程分支和标签可达的提交
+fn all_refs_revwalk(repo: &Repository, sorting: Sort) -> GitResult<git2::Revwalk<'_>> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(sorting)?;
+    if resolve_walk_start(repo, None)?.is_some() {
+        revwalk.push_head()?;
+    }
+    // 标签可能指向非提交对象，push_glob 会自动跳过
+    for glob in ["refs/heads", "refs/remotes", "refs/tags"] {
+        revwalk.push_glob(glob)?;
+    }
+    Ok(revwalk)
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitAuthorAvatar {
+    // 提交中记录的邮箱（小写），前端按它查找提交作者对应的头像
+    pub email: String,
+    // 经 .mailmap 映射后的作者，哈希按映射后的邮箱计算
+    pub name: String,
+    pub mapped_email: String,
+    // Gravatar 等服务使用的邮箱哈希（十六进制），计算前去掉首尾空白并转为小写
+    pub md5: String,
+    pub sha256: String,
+}
+
+// 所有提交作者去重后的头像哈希，按名称排序
+pub fn get_author_avatar_hashes(repo: &Repository, use_mailmap: bool, cancel: &CancellationToken) -> GitResult<Vec<GitAuthorAvatar>> {
+    let mailmap = if use_mailmap { Some(repo.mailmap()?) } else { None };
+    let mut authors: HashMap<String, GitAuthorAvatar> = HashMap::new();
+    for oid in all_refs_revwalk(repo, Sort::TIME)? {
+        cancel.check()?;
+        let commit = repo.find_commit(oid?)?;
+        let author = commit.author();
+        let email = author.email().unwrap_or("").trim().to_lowercase();
+        if email.is_empty() || authors.contains_key(&email) {
+            continue;
+        }
+        let mapped = match &mailmap {
+            Some(mailmap) => mailmap.resolve_signature(&author)?,
+            None => author.to_owned(),
+        };
+        let mapped_email = mapped.email().unwrap_or("").trim().to_lowercase();
+        authors.insert(
+            email.clone(),
+            GitAuthorAvatar {
+                email,
+                name: mapped.name().unwrap_or("").to_string(),
+                md5: format!("{:x}", md5::compute(mapped_email.as_bytes())),
+                sha256: format!("{:x}", Sha256::digest(mapped_email.as_bytes())),
+                mapped_email,
+            },
+        );
+    }
+    let mut authors: Vec<GitAuthorAvatar> = authors.into_values().collect();
+    authors.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()).then(a.email.cmp(&b.email)));
+    Ok(authors)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GitAuthorStats {
+    // 经 .mailmap 映射后的作者
+    pub name: String,
+    pub email: String,
+    pub commits: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+    pub first_commit_time: i64,
+    pub last_commit_time: i64,
+}
+
+// start 为该时段在作者本地时区的起始日期（按 UTC 零点的时间戳表示，前端按 UTC 格式化）
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GitActivityBucket {
+    pub start: i64,
+    pub commits: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GitRepoStats {
+    pub total_commits: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+    // 按提交数从多到少排序
+    pub authors: Vec<GitAuthorStats>,
+    // 按时间排序，只包含有提交的时段；周从周一开始
+    pub daily: Vec<GitActivityBucket>,
+    pub weekly: Vec<GitActivityBucket>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GitRepoStatsProgress {
+    pub stats_id: String,
+    // 已遍历的提交数（包括时间范围之外的）
+    pub walked_commits: usize,
+    // 截至目前的统计结果
+    pub stats: GitRepoStats,
+}
+
+pub const REPO_STATS_PROGRESS_EVENT: &str = "repo-stats-progress";
+const REPO_STATS_PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+const SECONDS_PER_DAY: i64 = 86400;
+
+#[derive(Default)]
+struct RepoStatsBuilder {
+    total_commits: usize,
+    insertions: usize,
+    deletions: usize,
+    // 以小写邮箱区分作者
+    authors: HashMap<String, GitAuthorStats>,
+    daily: BTreeMap<i64, GitActivityBucket>,
+    weekly: BTreeMap<i64, GitActivityBucket>,
+}
+
+fn add_to_bucket(buckets: &mut BTreeMap<i64, GitActivityBucket>, start: i64, insertions: usize, deletions: usize) {
+    let bucket = buckets.entry(start).or_insert_with(|| GitActivityBucket {
+        start,
+        commits: 0,
+        insertions: 0,
+        deletions: 0,
+    });
+    bucket.commits += 1;
+    bucket.insertions += insertions;
+    bucket.deletions += deletions;
+}
+
+impl RepoStatsBuilder {
+    fn add(&mut self, author: &Signature, insertions: usize, deletions: usize) {
+        let when = author.when();
+        self.total_commits += 1;
+        self.insertions += insertions;
+        self.deletions += deletions;
+
+        let email = author.email().unwrap_or("").trim().to_lowercase();
+        let stats = self.authors.entry(email.clone()).or_insert_with(|| GitAuthorStats {
+            name: author.name().unwrap_or("").to_string(),
+            email,
+            commits: 0,
+            insertions: 0,
+            deletions: 0,
+            first_commit_time: when.seconds(),
+            last_commit_time: when.seconds(),
+        });
+        stats.commits += 1;
+        stats.insertions += insertions;
+        stats.deletions += deletions;
+        stats.first_commit_time = stats.first_commit_time.min(when.seconds());
+        stats.last_commit_time = stats.last_commit_time.max(when.seconds());
+
+        // 按作者本地时区划分日期；1970-01-01 是周四，往前 3 天是周一
+        let day = (when.seconds() + when.offset_minutes() as i64 * 60).div_euclid(SECONDS_PER_DAY);
+        let week = day - (day + 3).rem_euclid(7);
+        add_to_bucket(&mut self.daily, day * SECONDS_PER_DAY, insertions, deletions);
+        add_to_bucket(&mut self.weekly, week * SECONDS_PER_DAY, insertions, deletions);
+    }
+
+    fn snapshot(&self) -> GitRepoStats {
+        let mut authors: Vec<GitAuthorStats> = self.authors.values().cloned().collect();
+        authors.sort_by(|a, b| b.commits.cmp(&a.commits).then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase())));
+        GitRepoStats {
+            total_commits: self.total_commits,
+            insertions: self.insertions,
+            deletions: self.deletions,
+            authors,
+            daily: self.daily.values().cloned().collect(),
+            weekly: self.weekly.values().cloned().collect(),
+        }
+    }
+}
+
+// 统计 HEAD 历史中提交时间在 [since, until] 内的提交，作者按 .mailmap 合并。
+// 合并提交计入提交数，但不计增删行数，避免重复统计被合并分支上的改动。
+// 遍历过程中每隔一段时间把当前结果交给 on_progress，大仓库也能逐步显示
+pub fn get_repo_stats(
+    repo: &Repository,
+    since: Option<i64>,
+    until: Option<i64>,
+    cancel: &CancellationToken,
+    mut on_progress: impl FnMut(usize, GitRepoStats),
+) -> GitResult<GitRepoStats> {
+    let mut builder = RepoStatsBuilder::default();
+    let start = match resolve_walk_start(repo, None)? {
+        Some(start) => start,
+        None => return Ok(builder.snapshot()),
+    };
+    let identities = IdentityMapper::new(repo, MailmapMode::Mapped)?;
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(Sort::TIME)?;
+    revwalk.push(start)?;
+
+    let mut walked = 0;
+    let mut last_progress = Instant::now();
+    for oid in revwalk {
+        let oid = oid?;
+        cancel.check()?;
+        walked += 1;
+        let commit = repo.find_commit(oid)?;
+        let time = commit.time().seconds();
+        if since.is_some_and(|since| time < since) || until.is_some_and(|until| time > until) {
+            continue;
+        }
+
+        let (insertions, deletions) = if commit.parent_count() > 1 {
+            (0, 0)
+        } else {
+            let parent_tree = match commit.parent_count() {
+                0 => None,
+                _ => Some(commit.parent(0)?.tree()?),
+            };
+            let stats = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?.stats()?;
+            (stats.insertions(), stats.deletions())
+        };
+        builder.add(&identities.resolve(&commit.author())?, insertions, deletions);
+
+        if last_progress.elapsed() >= REPO_STATS_PROGRESS_INTERVAL {
+            on_progress(walked, builder.snapshot());
+            last_progress = Instant::now();
+        }
+    }
+    Ok(builder.snapshot())
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GitFileChurn {
+    // 重命名过的文件按最新的路径合并统计
+    pub path: String,
+    // 修改过该文件的提交数
+    pub commits: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+    // 按 .mailmap 合并后的不同作者数
+    pub authors: usize,
+    pub last_modified: i64,
+    // HEAD 中是否仍存在该文件
+    pub exists: bool,
+}
+
+pub const DEFAULT_FILE_CHURN_LIMIT: usize = 100;
+
+// 统计 HEAD 历史中（提交时间不早于 since）每个文件被修改的次数和增删行数，按修改次数从多到少返回前 limit 个。
+// 合并提交不计入，重命名按相似度检测，旧路径上的修改计入新路径
+pub fn get_file_churn(repo: &Repository, since: Option<i64>, limit: usize, cancel: &CancellationToken) -> GitResult<Vec<GitFileChurn>> {
+    let start = match resolve_walk_start(repo, None)? {
+        Some(start) => start,
+        None => return Ok(Vec::new()),
+    };
+    let identities = IdentityMapper::new(repo, MailmapMode::Mapped)?;
+    let mut revwalk = repo.revwalk()?;
+    // 重命名的映射要求子提交总在父提交之前出现
+    revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+    revwalk.push(start)?;
+
+    let mut files: HashMap<String, (GitFileChurn, HashSet<String>)> = HashMap::new();
+    // 从新到旧遍历，遇到重命名时记下旧路径之后应计入的路径
+    let mut renamed_to: HashMap<String, String> = HashMap::new();
+    for oid in revwalk {
+        let oid = oid?;
+        cancel.check()?;
+        let commit = repo.find_commit(oid)?;
+        let time = commit.time().seconds();
+        if since.is_some_and(|since| time < since) || commit.parent_count() > 1 {
+            continue;
+        }
+        let parent_tree = match commit.parent_count() {
+            0 => None,
+            _ => Some(commit.parent(0)?.tree()?),
+        };
+        let mut diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+        diff.find_similar(None)?;
+        let author = identities.resolve(&commit.author())?.email().unwrap_or("").trim().to_lowercase();
+
+        for index in 0..diff.deltas().len() {
+            let delta = match diff.get_delta(index) {
+                Some(delta) => delta,
+                None => continue,
+            };
+            let new_path = delta.new_file().path().map(|p| p.to_string_lossy().to_string());
+            let old_path = delta.old_file().path().map(|p| p.to_string_lossy().to_string());
+            let path = match new_path.clone().or_else(|| old_path.clone()) {
+                Some(path) => path,
+                None => continue,
+            };
+            let path = renamed_to.get(&path).cloned().unwrap_or(path);
+            if delta.status() == Delta::Renamed {
+                if let Some(old_path) = old_path {
+                    renamed_to.insert(old_path, path.clone());
+                }
+            }
+            let (insertions, deletions) = match Patch::from_diff(&diff, index)? {
+                Some(patch) => {
+                    let (_, additions, removals) = patch.line_stats()?;
+                    (additions, removals)
+                }
+                None => (0, 0),
+            };
+
+            let (churn, authors) = files.entry(path.clone()).or_insert_with(|| {
+                (
+                    GitFileChurn {
+                        path,
+                        commits: 0,
+                        insertions: 0,
+                        deletions: 0,
+                        authors: 0,
+                        last_modified: time,
+                        exists: false,
+                    },
+                    HashSet::new(),
+                )
+            });
+            churn.commits += 1;
+            churn.insertions += insertions;
+            churn.deletions += deletions;
+            churn.last_modified = churn.last_modified.max(time);
+            authors.insert(author.clone());
+        }
+    }
+
+    let head_tree = repo.find_commit(start)?.tree()?;
+    let mut files: Vec<GitFileChurn> = files
+        .into_values()
+        .map(|(mut churn, authors)| {
+            churn.authors = authors.len();
+            churn.exists = head_tree.get_path(Path::new(&churn.path)).is_ok();
+            churn
+        })
+        .collect();
+    files.sort_by(|a, b| {
+        b.commits
+            .cmp(&a.commits)
+            .then_with(|| (b.insertions + b.deletions).cmp(&(a.insertions + a.deletions)))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    files.truncate(limit);
+    Ok(files)
+}
+
+// 在所有分支、远程分支和标签可达的提交中按哈希前缀、提交信息、作者查找（不区分大小写），
+// 边遍历边把结果分批交给 on_hits，返回匹配的总数
+pub fn search_commits(
+    repo: &Repository,
+    query: &str,
+    limit: usize,
+    cancel: &CancellationToken,
+    mut on_hits: impl FnMut(Vec<GitCommitSearchHit>),
+) -> GitResult<usize> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Ok(0);
+    }
+    let hash_query = query.len() >= MIN_HASH_QUERY_LENGTH && query.chars().all(|c| c.is_ascii_hexdigit());
+
+    let revwalk = all_refs_revwalk(repo, Sort::TIME)?;
+    let decorations = ref_decorations(repo)?;
+    let identities = IdentityMapper::new(repo, MailmapMode::Mapped)?;
+    let mut total = 0;
+    let mut batch = Vec::new();
+    let mut last_flush = Instant::now();
+    for oid in revwalk {
+        if total >= limit {
+            break;
+        }
+        let oid = oid?;
+        cancel.check()?;
+        let commit = repo.find_commit(oid)?;
+        if let Some(matched) = search_field(&commit, &query, hash_query, &identities)? {
+            let refs = decorations.get(&oid).cloned().unwrap_or_default();
+            batch.push(GitCommitSearchHit {
+                commit: commit_summary(&commit, refs, &identities)?,
+                matched,
+            });
+            total += 1;
+        }
+        if !batch.is_empty() && (batch.len() >= COMMIT_SEARCH_BATCH_SIZE || last_flush.elapsed() >= COMMIT_SEARCH_FLUSH_INTERVAL) {
+            on_hits(std::mem::take(&mut batch));
+            last_flush = Instant::now();
+        }
+    }
+    if !batch.is_empty() {
+        on_hits(batch);
+    }
+    Ok(total)
+}
+
+// 和 get_commits 相同的遍历和过滤，但一次走完整个历史（或前 limit 个匹配的提交），
+// 边遍历边把提交分批交给 on_batch，不必等整个数组生成后才返回
+#[allow(clippy::too_many_arguments)]
+pub fn stream_commits(
+    repo: &Repository,
+    rev: Option<&str>,
+    filter: &GitCommitFilter,
+    mailmap: MailmapMode,
+    limit: Option<usize>,
+    cancel: &CancellationToken,
+    mut on_batch: impl FnMut(Vec<GitCommit>),
+) -> GitResult<GitCommitStreamSummary> {
+    let mut summary = GitCommitStreamSummary::default();
+    let start = match resolve_walk_start(repo, rev)? {
+        Some(start) => start,
+        None => return Ok(summary),
+    };
+    let identities = IdentityMapper::new(repo, mailmap)?;
+    let matcher = CommitMatcher::new(filter)?;
+    let mut decorations = ref_decorations(repo)?;
+    let shallow = shallow_commits(repo);
+
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+    revwalk.push(start)?;
+
+    let mut batch = Vec::new();
+    let mut batch_size = COMMIT_STREAM_FIRST_BATCH_SIZE;
+    let mut last_flush = Instant::now();
+    for oid in revwalk {
+        if limit.is_some_and(|limit| summary.total >= limit) {
+            break;
+        }
+        let oid = oid?;
+        cancel.check()?;
+        let commit = repo.find_commit(oid)?;
+        summary.shallow_boundary |= shallow.contains(&oid);
+        if !matcher.matches(repo, &commit, &identities)? {
+            continue;
+        }
+        batch.push(commit_summary(&commit, decorations.remove(&oid).unwrap_or_default(), &identities)?);
+        summary.total += 1;
+        if batch.len() >= batch_size || last_flush.elapsed() >= COMMIT_STREAM_FLUSH_INTERVAL {
+            on_batch(std::mem::take(&mut batch));
+            batch_size = COMMIT_STREAM_BATCH_SIZE;
+            last_flush = Instant::now();
+        }
+    }
+    if !batch.is_empty() {
+        on_batch(batch);
+    }
+    Ok(summary)
+}
+
+struct CommitMatcher<'a> {
+    filter: &'a GitCommitFilter,
+    author: Option<String>,
+    message: Option<Regex>,
+}
+
+impl<'a> CommitMatcher<'a> {
+    fn new(filter: &'a GitCommitFilter) -> GitResult<Self> {
+        let message = match filter.message.as_deref().filter(|m| !m.is_empty()) {
+            Some(pattern) => Some(RegexBuilder::new(pattern).case_insensitive(true).build()?),
+            None => None,
+        };
+        Ok(CommitMatcher {
+            filter,
+            author: filter.author.as_ref().map(|a| a.to_lowercase()).filter(|a| !a.is_empty()),
+            message,
+        })
+    }
+
+    // 作者按原始身份或 .mailmap 映射后的身份匹配
+    fn matches(&self, repo: &Repository, commit: &git2::Commit, identities: &IdentityMapper) -> GitResult<bool> {
+        let time = commit.time().seconds();
+        if self.filter.since.is_some_and(|since| time < since) || self.filter.until.is_some_and(|until| time > until) {
+            return Ok(false);
+        }
+
+        if let Some(author) = &self.author {
+            let signature = commit.author();
+            if !signature_contains(&signature, author) && !signature_contains(&identities.resolve(&signature)?, author) {
+                return Ok(false);
+            }
+        }
+
+        if let Some(message) = &self.message {
+            if !message.is_match(commit.message().unwrap_or("")) {
+                return Ok(false);
+            }
+        }
+
+        if let Some(path) = self.filter.path.as_deref().filter(|p| !p.is_empty()) {
+            return commit_touches_path(repo, commit, path);
+        }
+
+        Ok(true)
+    }
+}
+
+// 与第一个父提交比较，判断该提交是否改动了指定路径
+fn commit_touches_path(repo: &Repository, commit: &git2::Commit, pathspec: &str) -> GitResult<bool> {
+    let tree = commit.tree()?;
+    let parent_tree = if commit.parent_count() > 0 {
+        Some(commit.parent(0)?.tree()?)
+    } else {
+        None
+    };
+
+    let mut diff_opts = DiffOptions::new();
+    diff_opts.pathspec(pathspec);
+    let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), Some(&mut diff_opts))?;
+    Ok(diff.deltas().len() > 0)
+}
+
+fn ref_decorations(repo: &Repository) -> GitResult<HashMap<Oid, Vec<GitRefDecoration>>> {
+    let mut decorations: HashMap<Oid, Vec<GitRefDecoration>> = HashMap::new();
+
+    if let Ok(head) = repo.head() {
+        if let Ok(commit) = head.peel_to_commit() {
+            decorations.entry(commit.id()).or_default().push(GitRefDecoration {
+                name: "HEAD".to_string(),
+                kind: GitRefKind::Head,
+            });
+        }
+    }
+
+    for reference in repo.references()? {
+        let reference = reference?;
+        let kind = if reference.is_branch() {
+            GitRefKind::LocalBranch
+        } else if reference.is_remote() {
+            GitRefKind::RemoteBranch
+        } else if reference.is_tag() {
+            GitRefKind::Tag
+        } else {
+            continue;
+        };
+        let name = match reference.shorthand() {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+        // origin/HEAD 这类符号引用只是别名，不单独显示
+        if kind == GitRefKind::RemoteBranch && name.ends_with("/HEAD") {
+            continue;
+        }
+        // 附注标签需要剥离到它指向的提交
+        let commit = match reference.peel_to_commit() {
+            Ok(commit) => commit,
+            Err(_) => continue,
+        };
+        decorations.entry(commit.id()).or_default().push(GitRefDecoration { name, kind });
+    }
+
+    Ok(decorations)
+}
+
+fn take_free_lane(lanes: &mut Vec<Option<Oid>>, oid: Oid) -> usize {
+    match lanes.iter().position(|lane| lane.is_none()) {
+        Some(index) => {
+            lanes[index] = Some(oid);
+            index
+        }
+        None => {
+            lanes.push(Some(oid));
+            lanes.len() - 1
+        }
+    }
+}
+
+pub fn get_commit_graph(repo: &Repository, rev: Option<&str>, limit: usize) -> GitResult<Vec<GitGraphNode>> {
+    let start = match resolve_walk_start(repo, rev)? {
+        Some(start) => start,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+    revwalk.push(start)?;
+
+    let mut decorations = ref_decorations(repo)?;
+    // 每条泳道记录它正在等待的下一个提交
+    let mut lanes: Vec<Option<Oid>> = Vec::new();
+    let mut nodes = Vec::new();
+
+    for oid in revwalk.take(limit) {
+        let oid = oid?;
+        let commit = repo.find_commit(oid)?;
+
+        let lane = match lanes.iter().position(|l| *l == Some(oid)) {
+            Some(index) => index,
+            None => take_free_lane(&mut lanes, oid),
+        };
+        // 其它同样等待该提交的泳道在此汇合并结束
+        for (index, waiting) in lanes.iter_mut().enumerate() {
+            if index != lane && *waiting == Some(oid) {
+                *waiting = None;
+            }
+        }
+        lanes[lane] = None;
+
+        let pass_through_lanes = lanes
+            .iter()
+            .enumerate()
+            .filter(|(_, waiting)| waiting.is_some())
+            .map(|(index, _)| index)
+            .collect();
+
+        let mut parent_edges = Vec::new();
+        for (parent_index, parent) in commit.parent_ids().enumerate() {
+            let to_lane = match lanes.iter().position(|l| *l == Some(parent)) {
+                Some(index) => index,
+                None if parent_index == 0 => {
+                    lanes[lane] = Some(parent);
+                    lane
+                }
+                None => take_free_lane(&mut lanes, parent),
+            };
+            parent_edges.push(GitGraphEdge {
+                parent_hash: parent.to_string(),
+                from_lane: lane,
+                to_lane,
+            });
+        }
+
+        while lanes.last() == Some(&None) {
+            lanes.pop();
+        }
+
+        nodes.push(GitGraphNode {
+            hash: oid.to_string(),
+            lane,
+            parent_edges,
+            pass_through_lanes,
+            lane_count: lanes.len().max(lane + 1),
+            refs: decorations.remove(&oid).unwrap_or_default(),
+        });
+    }
+
+    Ok(nodes)
+}
+
+// 读取工作区状态的选项，默认和 git status 相同：未跟踪的目录作为一项显示，不包含忽略的文件
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GitStatusOptions {
+    // 和 git status --untracked-files=all 一样，列出未跟踪目录中的每个文件
+    pub untracked_files_all: bool,
+    // 和 git status --ignored 一样包含被忽略的文件，状态为 ignored
+    pub include_ignored: bool,
+    // 和 git diff --submodule=log 一样，列出子模块新旧提交之间的提交
+    pub submodule_log: bool,
+}
+
+impl GitStatusOptions {
+    fn status_options(&self) -> StatusOptions {
+        let mut status_options = StatusOptions::new();
+        status_options
+            .show(StatusShow::Workdir)
+            .include_untracked(true)
+            .recurse_untracked_dirs(self.untracked_files_all)
+            .include_ignored(self.include_ignored)
+            .recurse_ignored_dirs(self.include_ignored && self.untracked_files_all);
+        status_options
+    }
+}
+
+// 只扫描部分路径的状态结果，elapsed_ms 是扫描耗时，供前端判断是否需要进一步缩小范围
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitStatusScan {
+    pub files: Vec<GitStatus>,
+    pub pathspec: Option<String>,
+    // 索引被锁定、没能写回 stat 信息时为 false
+    pub index_refreshed: bool,
+    pub elapsed_ms: u64,
+}
+
+pub fn get_status(repo: &Repository, options: &GitStatusOptions) -> GitResult<Vec<GitStatus>> {
+    collect_status(repo, &mut options.status_options(), options.submodule_log)
+}
+
+// 面向大仓库的状态读取：pathspec 把扫描限制在用户正在查看的目录中，
+// 并像 git status 一样把文件的 stat 信息写回索引，之后的扫描可以跳过未变化的文件而不必重新计算哈希。
+// 索引正被其他进程锁定时跳过写回，只返回结果
+pub fn get_status_fast(repo: &Repository, pathspec: Option<&str>, options: &GitStatusOptions) -> GitResult<GitStatusScan> {
+    let started = Instant::now();
+    let pathspec = match pathspec.map(str::trim).filter(|p| !p.is_empty() && *p != "." && *p != "/") {
+        Some(path) => Some(normalize_repo_path(path)?),
+        None => None,
+    };
+
+    let build_options = |update_index: bool| {
+        let mut status_options = options.status_options();
+        status_options.update_index(update_index);
+        if let Some(path) = &pathspec {
+            status_options.pathspec(path);
+        }
+        status_options
+    };
+    let (files, index_refreshed) = match collect_status(repo, &mut build_options(true), options.submodule_log) {
+        Ok(files) => (files, true),
+        Err(GitError::Locked { .. }) => (collect_status(repo, &mut build_options(false), options.submodule_log)?, false),
+        Err(e) => return Err(e),
+    };
+
+    Ok(GitStatusScan {
+        files,
+        pathspec,
+        index_refreshed,
+        elapsed_ms: started.elapsed().as_millis() as u64,
+    })
+}
+
+fn collect_status(repo: &Repository, status_options: &mut StatusOptions, submodule_log: bool) -> GitResult<Vec<GitStatus>> {
+    let statuses = repo.statuses(Some(status_options))?;
+    let index = repo.index()?;
+    let mut status_list = Vec::new();
+    
+    for entry in statuses.iter() {
+        let path = entry.path().unwrap_or("").to_string();
+        let mut status = status_label(entry.status());
+
+        // 子模块检出的提交和索引中记录的不同时报告为 submodule；只有未提交的修改时仍为 modified
+        let mut submodule = None;
+        if entry.status().contains(git2::Status::WT_MODIFIED) {
+            let recorded = index.get_path(Path::new(&path), 0).filter(|e| e.mode == GITLINK_MODE).map(|e| e.id);
+            let checked_out = recorded.and_then(|_| repo.find_submodule(&path).ok()).and_then(|sm| sm.workdir_id());
+            if let (Some(old), Some(new)) = (recorded, checked_out) {
+                if old != new {
+                    status = "submodule";
+                    submodule = Some(submodule_change(repo, &path, Some(old), Some(new), submodule_log));
+                }
+            }
+        }
+
+        status_list.push(GitStatus {
+            file_path: path,
+            status: status.to_string(),
+            submodule,
+        });
+    }
+    
+    Ok(status_list)
+}
+
+const GITLINK_MODE: u32 = 0o160000;
+pub const SUBMODULE_LOG_LIMIT: usize = 50;
+
+fn submodule_change(repo: &Repository, path: &str, old: Option<Oid>, new: Option<Oid>, submodule_log: bool) -> GitSubmoduleChange {
+    let (commits, commits_truncated) = match (old, new) {
+        (Some(old), Some(new)) if submodule_log => submodule_commits(repo, path, old, new).unwrap_or_default(),
+        _ => (Vec::new(), false),
+    };
+    GitSubmoduleChange {
+        old_commit: old.map(|oid| oid.to_string()),
+        new_commit: new.map(|oid| oid.to_string()),
+        commits,
+        commits_truncated,
+    }
+}
+
+// 和 git diff --submodule=log 一样，先列出新提交独有的提交，再列出旧提交独有的提交
+fn submodule_commits(repo: &Repository, path: &str, old: Oid, new: Oid) -> GitResult<(Vec<GitSubmoduleCommit>, bool)> {
+    let submodule = repo.find_submodule(path)?.open()?;
+    let mut commits = Vec::new();
+    for (from, to, removed) in [(new, old, false), (old, new, true)] {
+        let mut walk = submodule.revwalk()?;
+        walk.set_sorting(Sort::TOPOLOGICAL)?;
+        walk.push(from)?;
+        walk.hide(to)?;
+        for oid in walk {
+            if commits.len() == SUBMODULE_LOG_LIMIT {
+                return Ok((commits, true));
+            }
+            let commit = submodule.find_commit(oid?)?;
+            commits.push(GitSubmoduleCommit {
+                hash: commit.id().to_string(),
+                summary: commit.summary().unwrap_or("").to_string(),
+                removed,
+            });
+        }
+    }
+    Ok((commits, false))
+}
+
+fn status_label(status: git2::Status) -> &'static str {
+    if status.contains(git2::Status::INDEX_NEW) || status.contains(git2::Status::WT_NEW) {
+        "new"
+    } else if status.contains(git2::Status::INDEX_MODIFIED) || status.contains(git2::Status::WT_MODIFIED) {
+        "modified"
+    } else if status.contains(git2::Status::INDEX_DELETED) || status.contains(git2::Status::WT_DELETED) {
+        "deleted"
+    } else if status.contains(git2::Status::IGNORED) {
+        "ignored"
+    } else {
+        "unknown"
+    }
+}
+
+pub fn uncommitted_tracked_changes(repo: &Repository) -> GitResult<Vec<GitStatus>> {
+    let mut status_options = StatusOptions::new();
+    status_options.include_untracked(false).include_ignored(false);
+
+    let statuses = repo.statuses(Some(&mut status_options))?;
+    let mut changes = Vec::new();
+    for entry in statuses.iter() {
+        changes.push(GitStatus {
+            file_path: entry.path().unwrap_or("").to_string(),
+            status: status_label(entry.status()).to_string(),
+            submodule: None,
+        });
+    }
+    Ok(changes)
+}
+
+pub fn reset(repo: &Repository, target_ref: &str, mode: ResetMode, force: bool) -> GitResult<GitResetResult> {
+    let target = repo.revparse_single(target_ref)?.peel_to_commit()?;
+
+    // 硬重置前先检查会被覆盖的已跟踪文件，未跟踪文件不受 reset --hard 影响
+    if let ResetMode::Hard = mode {
+        let files_at_risk = uncommitted_tracked_changes(repo)?;
+        if !force && !files_at_risk.is_empty() {
+            return Ok(GitResetResult {
+                target_hash: target.id().to_string(),
+                performed: false,
+                files_at_risk,
+            });
+        }
+    }
+
+    let reset_type = match mode {
+        ResetMode::Soft => ResetType::Soft,
+        ResetMode::Mixed => ResetType::Mixed,
+        ResetMode::Hard => ResetType::Hard,
+    };
+    repo.reset(target.as_object(), reset_type, None)?;
+
+    Ok(GitResetResult {
+        target_hash: target.id().to_string(),
+        performed: true,
+        files_at_risk: Vec::new(),
+    })
+}
+
+pub fn conflict_paths(index: &git2::Index) -> GitResult<Vec<String>> {
+    Ok(index
+        .conflicts()?
+        .flatten()
+        .filter_map(|conflict| conflict.our.or(conflict.their).or(conflict.ancestor))
+        .map(|entry| String::from_utf8_lossy(&entry.path).to_string())
+        .collect())
+}
+
+fn head_commit(repo: &Repository) -> GitResult<Option<Commit<'_>>> {
+    match repo.head() {
+        Ok(head) => Ok(Some(head.peel_to_commit()?)),
+        Err(e) if e.code() == ErrorCode::UnbornBranch || e.code() == ErrorCode::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn default_signature(repo: &Repository) -> GitResult<Signature<'static>> {
+    repo.signature().map_err(|e| match e.code() {
+        ErrorCode::NotFound => GitError::invalid("user.name and user.email must be configured before committing"),
+        _ => e.into(),
+    })
+}
+
+// commit_signed 不会移动引用，需要手动把 HEAD（或它指向的分支）更新到新提交
+fn advance_head(repo: &Repository, oid: Oid, log_message: &str) -> GitResult<()> {
+    let head = repo.find_reference("HEAD")?;
+    match head.symbolic_target() {
+        Some(branch) => {
+            repo.reference(branch, oid, true, log_message)?;
+        }
+        None => repo.set_head_detached(oid)?,
+    }
+    Ok(())
+}
+
+// sign 为空时遵循 commit.gpgSign 配置
+fn should_sign(repo: &Repository, key: &str, sign: Option<bool>) -> GitResult<bool> {
+    match sign {
+        Some(sign) => Ok(sign),
+        None => match repo.config()?.get_bool(key) {
+            Ok(sign) => Ok(sign),
+            Err(e) if e.code() == ErrorCode::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        },
+    }
+}
+
+// commit-msg 钩子可以修改 .git/COMMIT_EDITMSG 中的提交说明，返回修改后的内容
+fn run_commit_msg_hook(
+    repo: &Repository,
+    message: &str,
+    cancel: &CancellationToken,
+    on_hook_output: &mut impl FnMut(&str, &str),
+) -> GitResult<String> {
+    let message_file = repo.path().join("COMMIT_EDITMSG");
+    fs::write(&message_file, message)?;
+    if !hooks::run_hook(repo, "commit-msg", &[&message_file.to_string_lossy()], cancel, |line| on_hook_output("commit-msg", line))? {
+        return Ok(message.to_string());
+    }
+    let message = git2::message_prettify(fs::read_to_string(&message_file)?, None)?;
+    if message.trim().is_empty() {
+        return Err(GitError::invalid("commit message is empty"));
+    }
+    Ok(message)
+}
+
+// 正在合并时 MERGE_HEAD 中记录的提交，没有合并时为空
+fn merge_heads(repo: &Repository) -> GitResult<Vec<Oid>> {
+    // mergehead_foreach 需要可变的句柄，缓存的仓库是共享的，单独打开一个
+    let mut merge_repo = Repository::open(repo.path())?;
+    let mut heads = Vec::new();
+    match merge_repo.mergehead_foreach(|oid| {
+        heads.push(*oid);
+        true
+    }) {
+        Ok(()) => Ok(heads),
+        Err(e) if e.code() == ErrorCode::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// 用暂存区的内容创建提交，sign 为空时遵循 commit.gpgSign 配置，trailers 中的 Signed-off-by 等追加到说明末尾。
+// 和 git commit 一样先执行 pre-commit 和 commit-msg 钩子，钩子的输出逐行交给 on_hook_output(钩子名, 行)；
+// no_verify 时跳过这两个钩子。正在合并时 MERGE_HEAD 中的提交作为其它父提交，message 为空时使用 MERGE_MSG，
+// 提交后记录冲突解决结果并结束合并状态
+pub fn create_commit(
+    repo: &Repository,
+    message: &str,
+    sign: Option<bool>,
+    trailers: &CommitTrailers,
+    no_verify: bool,
+    cancel: &CancellationToken,
+    mut on_hook_output: impl FnMut(&str, &str),
+) -> GitResult<GitCommit> {
+    let merge_heads = merge_heads(repo)?;
+    let message = match fs::read_to_string(repo.path().join("MERGE_MSG")) {
+        // MERGE_MSG 中 # 开头的是冲突列表等注释
+        Ok(merge_message) if message.trim().is_empty() && !merge_heads.is_empty() => git2::message_prettify(merge_message, Some(b'#'))?,
+        _ => git2::message_prettify(message, None)?,
+    };
+    if message.trim().is_empty() {
+        return Err(GitError::invalid("commit message is empty"));
+    }
+    let signature = default_signature(repo)?;
+    // trailer 在执行钩子之前加上，commit-msg 钩子看到的是完整的说明
+    let signer = (signature.name().unwrap_or(""), signature.email().unwrap_or(""));
+    let mut message = commit_message::apply_commit_trailers(&message, trailers, signer)?;
+    let mut index = repo.index()?;
+    if index.has_conflicts() {
+        return Err(GitError::MergeConflict { paths: conflict_paths(&index)? });
+    }
+    if !no_verify {
+        hooks::run_hook(repo, "pre-commit", &[], cancel, |line| on_hook_output("pre-commit", line))?;
+        message = run_commit_msg_hook(repo, &message, cancel, &mut on_hook_output)?;
+        // pre-commit 钩子可能修改并重新暂存了文件（例如格式化工具）
+        index.read(true)?;
+    }
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let mut parents: Vec<Commit> = head_commit(repo)?.into_iter().collect();
+    for oid in &merge_heads {
+        parents.push(repo.find_commit(*oid)?);
+    }
+    let parents: Vec<&Commit> = parents.iter().collect();
+
+    // 先不移动引用，之后和 git commit 一样按提交的种类写入 reflog
+    let oid = if should_sign(repo, "commit.gpgSign", sign)? {
+        let buffer = repo.commit_create_buffer(&signature, &signature, &message, &tree, &parents)?;
+        let buffer = buffer
+            .as_str()
+            .ok_or_else(|| GitError::invalid("commit content is not valid UTF-8"))?;
+        let commit_signature = Signer::from_config(&repo.config()?, &signature)?.sign(buffer.as_bytes())?;
+        repo.commit_signed(buffer, &commit_signature, None)?
+    } else {
+        repo.commit(None, &signature, &signature, &message, &tree, &parents)?
+    };
+    let summary = message.lines().next().unwrap_or("");
+    let log_message = match parents.len() {
+        0 => format!("commit (initial): {}", summary),
+        1 => format!("commit: {}", summary),
+        _ => format!("commit (merge): {}", summary),
+    };
+    advance_head(repo, oid, &log_message)?;
+
+    // 和 git commit 一样，提交成功后才记录冲突解决结果，再删除 MERGE_HEAD、MERGE_MSG、MERGE_RR 等合并状态
+    if !merge_heads.is_empty() {
+        if let Err(e) = rerere::record_resolutions(repo) {
+            log::warn!("failed to record conflict resolutions: {}", e);
+        }
+        repo.cleanup_state()?;
+        match fs::remove_file(repo.path().join("MERGE_RR")) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    let refs = ref_decorations(repo)?.remove(&oid).unwrap_or_default();
+    commit_summary(&repo.find_commit(oid)?, refs, &IdentityMapper::new(repo, MailmapMode::Mapped)?)
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitTag {
+    pub name: String,
+    // 附注标签指向的对象（通常是提交），不是标签对象本身
+    pub target_hash: String,
+    pub message: Option<String>,
+    pub is_annotated: bool,
+    pub is_signed: bool,
+}
+
+fn signature_header(signature: &Signature) -> String {
+    let when = signature.when();
+    let offset = when.offset_minutes();
+    format!(
+        "{} <{}> {} {}{:02}{:02}",
+        signature.name().unwrap_or(""),
+        signature.email().unwrap_or(""),
+        when.seconds(),
+        if offset < 0 { '-' } else { '+' },
+        offset.abs() / 60,
+        offset.abs() % 60
+    )
+}
+
+// 没有 message 且不签名时创建轻量标签；sign 为空时遵循 tag.gpgSign 配置
+pub fn create_tag(
+    repo: &Repository,
+    name: &str,
+    target: Option<&str>,
+    message: Option<&str>,
+    sign: Option<bool>,
+    force: bool,
+) -> GitResult<GitTag> {
+    let ref_name = format!("refs/tags/{}", name);
+    if !git2::Reference::is_valid_name(&ref_name) {
+        return Err(GitError::invalid(format!("invalid tag name: {}", name)));
+    }
+    let object = repo.revparse_single(target.unwrap_or("HEAD"))?;
+    let message = match message.filter(|m| !m.trim().is_empty()) {
+        Some(message) => Some(git2::message_prettify(message, None)?),
+        None => None,
+    };
+    let sign = should_sign(repo, "tag.gpgSign", sign)?;
+
+    let message = match (message, sign) {
+        (None, false) => {
+            repo.tag_lightweight(name, &object, force)?;
+            return Ok(GitTag {
+                name: name.to_string(),
+                target_hash: object.id().to_string(),
+                message: None,
+                is_annotated: false,
+                is_signed: false,
+            });
+        }
+        (None, true) => return Err(GitError::invalid("a signed tag needs a message")),
+        (Some(message), _) => message,
+    };
+
+    let tagger = default_signature(repo)?;
+    if sign {
+        // libgit2 没有签名标签的接口：按 git 的格式拼出标签对象，把签名附在消息之后再写入对象库
+        let object_type = object.kind().map(|kind| kind.str()).unwrap_or("commit");
+        let mut buffer = format!(
+            "object {}\ntype {}\ntag {}\ntagger {}\n\n{}",
+            object.id(),
+            object_type,
+            name,
+            signature_header(&tagger),
+            message
+        );
+        let tag_signature = Signer::from_config(&repo.config()?, &tagger)?.sign(buffer.as_bytes())?;
+        buffer.push_str(&tag_signature);
+        let oid = repo.odb()?.write(ObjectType::Tag, buffer.as_bytes())?;
+        repo.reference(&ref_name, oid, force, &format!("tag: {}", name))?;
+    } else {
+        repo.tag(name, &object, &tagger, &message, force)?;
+    }
+
+    Ok(GitTag {
+        name: name.to_string(),
+        target_hash: object.id().to_string(),
+        message: Some(message.trim_end().to_string()),
+        is_annotated: true,
+        is_signed: sign,
+    })
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitSignatureVerification {
+    // gpg / ssh / x509
+    pub format: String,
+    pub status: SignatureStatus,
+    // gpg 的用户 id 或 ssh allowedSigners 中的身份
+    pub signer: Option<String>,
+    // gpg 的指纹或 ssh 公钥的 SHA256 指纹
+    pub key_id: Option<String>,
+}
+
+fn verify_signed_payload(repo: &Repository, signature: &[u8], payload: &[u8]) -> GitResult<GitSignatureVerification> {
+    let check = signing::verify(&repo.config()?, signature, payload)?;
+    Ok(GitSignatureVerification {
+        format: SigningFormat::of_signature(signature).label().to_string(),
+        status: check.status,
+        signer: check.signer,
+        key_id: check.key_id,
+    })
+}
+
+// 未签名的提交返回 None
+pub fn verify_commit_signature(repo: &Repository, commit_hash: &str) -> GitResult<Option<GitSignatureVerification>> {
+    let commit = repo.revparse_single(commit_hash)?.peel_to_commit()?;
+    let (signature, payload) = match repo.extract_signature(&commit.id(), None) {
+        Ok(extracted) => extracted,
+        Err(e) if e.code() == ErrorCode::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    verify_signed_payload(repo, &signature, &payload).map(Some)
+}
+
+// 附注标签的签名以其中之一开头，附在标签消息之后
+const TAG_SIGNATURE_MARKERS: [&str; 3] = ["-----BEGIN PGP SIGNATURE-----", "-----BEGIN SSH SIGNATURE-----", "-----BEGIN SIGNED MESSAGE-----"];
+
+// 签名附在标签消息之后；轻量标签和未签名的附注标签返回 None
+pub fn verify_tag_signature(repo: &Repository, tag_name: &str) -> GitResult<Option<GitSignatureVerification>> {
+    let reference = repo.find_reference(&format!("refs/tags/{}", tag_name))?;
+    let tag = match reference.peel(ObjectType::Tag) {
+        Ok(object) => object,
+        Err(_) => return Ok(None),
+    };
+    let odb = repo.odb()?;
+    let raw = odb.read(tag.id())?;
+    let data = raw.data();
+    let start = TAG_SIGNATURE_MARKERS
+        .iter()
+        .filter_map(|marker| {
+            let marker = format!("\n{}", marker);
+            data.windows(marker.len()).rposition(|window| window == marker.as_bytes())
+        })
+        .max();
+    match start {
+        Some(start) => verify_signed_payload(repo, &data[start + 1..], &data[..start + 1]).map(Some),
+        None => Ok(None),
+    }
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitSigningKey {
+    // gpg / ssh，对应 gpg.format 的 openpgp / ssh
+    pub format: String,
+    // 选中后写入 user.signingkey 的值
+    pub key: String,
+    pub fingerprint: Option<String>,
+    pub user_ids: Vec<String>,
+    pub expires_at: Option<i64>,
+    // ssh 公钥文件的路径；只在 ssh-agent 中的密钥为空
+    pub path: Option<String>,
+    pub in_agent: bool,
+    // 就是当前 user.signingkey 配置的密钥
+    pub configured: bool,
+}
+
+// 供设置界面选择签名密钥：gpg 钥匙环中可签名的私钥，~/.ssh 中的密钥和 ssh-agent 中的密钥
+pub fn list_signing_keys(repo: &Repository) -> GitResult<Vec<GitSigningKey>> {
+    let config = repo.config()?;
+    let configured = config_string(&config, "user.signingkey")?;
+    let configured_format = signing::signing_format(&config).ok();
+    Ok(signing::list_signing_keys(&config)
+        .into_iter()
+        .map(|key| GitSigningKey {
+            format: key.format.label().to_string(),
+            configured: configured_format == Some(key.format)
+                && configured.as_deref().is_some_and(|configured| signing::is_configured_key(configured, &key)),
+            key: key.key,
+            fingerprint: key.fingerprint,
+            user_ids: key.user_ids,
+            expires_at: key.expires_at,
+            path: key.path,
+            in_agent: key.in_agent,
+        })
+        .collect())
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitNote {
+    // 注释所在的引用，例如 refs/notes/commits、refs/notes/review
+    pub notes_ref: String,
+    // core.notesRef 指定的（默认为 refs/notes/commits）引用，git log 默认显示它
+    pub is_default_ref: bool,
+    pub message: String,
+    pub author: GitIdentity,
+}
+
+// 和 git notes --ref 一样，不以 refs/ 开头的名称放在 refs/notes/ 下
+fn notes_ref_name(repo: &Repository, notes_ref: Option<&str>) -> GitResult<String> {
+    let name = match notes_ref.map(str::trim).filter(|name| !name.is_empty()) {
+        None => return Ok(repo.note_default_ref()?),
+        // 与 git 的 expand_notes_ref 一致，只接受 refs/notes/ 下的引用
+        Some(name) if name.starts_with("refs/notes/") => name.to_string(),
+        Some(name) if name.starts_with("notes/") => format!("refs/{}", name),
+        Some(name) => format!("refs/notes/{}", name),
+    };
+    if !git2::Reference::is_valid_name(&name) {
+        return Err(GitError::invalid(format!("invalid notes ref: {}", name)));
+    }
+    Ok(name)
+}
+
+fn find_note(repo: &Repository, notes_ref: &str, default_ref: &str, oid: Oid) -> GitResult<Option<GitNote>> {
+    let note = match repo.find_note(Some(notes_ref), oid) {
+        Ok(note) => note,
+        Err(e) if e.code() == ErrorCode::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let author = identity_from_signature(&note.author());
+    Ok(Some(GitNote {
+        notes_ref: notes_ref.to_string(),
+        is_default_ref: notes_ref == default_ref,
+        message: note.message().unwrap_or("").to_string(),
+        author,
+    }))
+}
+
+// 提交在所有 refs/notes/* 中的注释，默认引用排在最前
+pub fn get_commit_notes(repo: &Repository, commit_hash: &str) -> GitResult<Vec<GitNote>> {
+    let oid = repo.revparse_single(commit_hash)?.peel_to_commit()?.id();
+    let default_ref = repo.note_default_ref()?;
+    let mut notes = Vec::new();
+    for reference in repo.references_glob("refs/notes/*")? {
+        let reference = reference?;
+        if let Some(note) = reference.name().map(|name| find_note(repo, name, &default_ref, oid)).transpose()?.flatten() {
+            notes.push(note);
+        }
+    }
+    notes.sort_by(|a, b| b.is_default_ref.cmp(&a.is_default_ref).then_with(|| a.notes_ref.cmp(&b.notes_ref)));
+    Ok(notes)
+}
+
+// 添加或覆盖提交的注释，text 为空时删除；notes_ref 为空时使用默认引用。删除后返回 None
+pub fn set_commit_note(repo: &Repository, commit_hash: &str, text: &str, notes_ref: Option<&str>) -> GitResult<Option<GitNote>> {
+    let oid = repo.revparse_single(commit_hash)?.peel_to_commit()?.id();
+    let notes_ref = notes_ref_name(repo, notes_ref)?;
+    let signature = default_signature(repo)?;
+    let text = git2::message_prettify(text, None)?;
+    if text.trim().is_empty() {
+        match repo.note_delete(oid, Some(&notes_ref), &signature, &signature) {
+            Ok(()) => {}
+            Err(e) if e.code() == ErrorCode::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        return Ok(None);
+    }
+    repo.note(&signature, &signature, Some(&notes_ref), oid, &text, true)?;
+    find_note(repo, &notes_ref, &repo.note_default_ref()?, oid)
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitArchiveResult {
+    pub output_path: String,
+    pub commit_hash: String,
+    pub file_count: usize,
+}
+
+// export-ignore 按当前索引和工作区中的 .gitattributes 判断，相当于 git archive --worktree-attributes
+fn is_export_ignored(repo: &Repository, path: &str) -> GitResult<bool> {
+    let value = repo.get_attr(Path::new(path), "export-ignore", AttrCheckFlags::INDEX_THEN_FILE)?;
+    Ok(AttrValue::from_string(value) == AttrValue::True)
+}
+
+// 返回写入的文件数（含符号链接）
+fn archive_tree(
+    repo: &Repository,
+    tree: &Tree,
+    prefix: &str,
+    time: git2::Time,
+    writer: &mut ArchiveWriter,
+    cancel: &CancellationToken,
+) -> GitResult<usize> {
+    let mut file_count = 0;
+    for entry in tree.iter() {
+        cancel.check()?;
+        let path = format!("{}{}", prefix, String::from_utf8_lossy(entry.name_bytes()));
+        if is_export_ignored(repo, &path)? {
+            continue;
+        }
+        let mode = if entry.filemode() == 0o100755 { 0o755 } else { 0o644 };
+        let archive_entry = ArchiveEntry {
+            path: &path,
+            mode,
+            mtime: time.seconds(),
+            offset_minutes: time.offset_minutes(),
+        };
+        match entry.kind() {
+            Some(ObjectType::Tree) => {
+                writer.add_directory(&ArchiveEntry { mode: 0o755, ..archive_entry })?;
+                let subtree = repo.find_tree(entry.id())?;
+                file_count += archive_tree(repo, &subtree, &format!("{}/", path), time, writer, cancel)?;
+            }
+            Some(ObjectType::Blob) => {
+                let blob = repo.find_blob(entry.id())?;
+                if entry.filemode() == 0o120000 {
+                    writer.add_symlink(&ArchiveEntry { mode: 0o777, ..archive_entry }, &String::from_utf8_lossy(blob.content()))?;
+                } else {
+                    writer.add_file(&archive_entry, blob.content())?;
+                }
+                file_count += 1;
+            }
+            // 子模块和 git archive 一样只留下空目录
+            Some(ObjectType::Commit) => writer.add_directory(&ArchiveEntry { mode: 0o755, ..archive_entry })?,
+            _ => {}
+        }
+    }
+    Ok(file_count)
+}
+
+// 把 rev 对应提交的文件树写成 zip / tar / tar.gz，相当于 git archive；失败时删除写了一半的文件
+pub fn export_archive(
+    repo: &Repository,
+    rev: &str,
+    format: ArchiveFormat,
+    output_path: &str,
+    cancel: &CancellationToken,
+) -> GitResult<GitArchiveResult> {
+    if output_path.trim().is_empty() {
+        return Err(GitError::invalid("output path is required"));
+    }
+    let commit = repo.revparse_single(rev)?.peel_to_commit()?;
+    let tree = commit.tree()?;
+
+    let mut writer = ArchiveWriter::create(fs::File::create(output_path)?, format);
+    let result = archive_tree(repo, &tree, "", commit.time(), &mut writer, cancel).and_then(|file_count| {
+        writer.finish()?;
+        Ok(file_count)
+    });
+    match result {
+        Ok(file_count) => Ok(GitArchiveResult {
+            output_path: output_path.to_string(),
+            commit_hash: commit.id().to_string(),
+            file_count,
+        }),
+        Err(e) => {
+            let _ = fs::remove_file(output_path);
+            Err(e)
+        }
+    }
+}
+
+// git format-patch 的文件名：序号加上把非字母数字替换成 - 的标题
+const PATCH_FILE_SUBJECT_MAX: usize = 52;
+
+fn patch_file_name(index: usize, subject: &str) -> String {
+    let mut slug = String::new();
+    for c in subject.chars() {
+        if c.is_ascii_alphanumeric() || c == '.' || c == '_' {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+        if slug.len() >= PATCH_FILE_SUBJECT_MAX {
+            break;
+        }
+    }
+    let slug = slug.trim_end_matches(['-', '.']);
+    if slug.is_empty() {
+        format!("{:04}.patch", index)
+    } else {
+        format!("{:04}-{}.patch", index, slug)
+    }
+}
+
+// range 为 "A..B" 时导出 B 可达而 A 不可达的提交，单个版本时只导出该提交（同 git format-patch -1）；
+// 按从旧到新的顺序写成 mbox 格式的补丁文件，和 format-patch 一样跳过合并提交，返回写入的文件路径
+pub fn export_patches(repo: &Repository, range: &str, output_dir: &str, cancel: &CancellationToken) -> GitResult<Vec<String>> {
+    let range = range.trim();
+    if range.is_empty() {
+        return Err(GitError::invalid("commit range is required"));
+    }
+    if output_dir.trim().is_empty() {
+        return Err(GitError::invalid("output directory is required"));
+    }
+
+    let commits = if range.contains("..") {
+        let mut revwalk = repo.revwalk()?;
+        revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+        revwalk.push_range(range)?;
+        let mut commits = Vec::new();
+        for oid in revwalk {
+            let commit = repo.find_commit(oid?)?;
+            if commit.parent_count() <= 1 {
+                commits.push(commit);
+            }
+        }
+        commits
+    } else {
+        let commit = repo.revparse_single(range)?.peel_to_commit()?;
+        if commit.parent_count() > 1 {
+            return Err(GitError::invalid("cannot export a merge commit as a patch"));
+        }
+        vec![commit]
+    };
+
+    fs::create_dir_all(output_dir)?;
+    let mut files = Vec::new();
+    for (index, commit) in commits.iter().enumerate() {
+        cancel.check()?;
+        let parent_tree = match commit.parent(0) {
+            Ok(parent) => Some(parent.tree()?),
+            Err(_) => None,
+        };
+        let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+        let summary = commit.summary().unwrap_or("");
+        let email = Email::from_diff(
+            &diff,
+            index + 1,
+            commits.len(),
+            &commit.id(),
+            summary,
+            commit.body().unwrap_or(""),
+            &commit.author(),
+            &mut EmailCreateOptions::new(),
+        )?;
+        let path = Path::new(output_dir).join(patch_file_name(index + 1, summary));
+        fs::write(&path, email.as_slice())?;
+        files.push(path.to_string_lossy().to_string());
+    }
+    Ok(files)
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitApplyFailure {
+    pub path: String,
+    // 二进制补丁等没有文本块时为空，表示整个文件无法应用
+    pub hunk_index: Option<usize>,
+    pub hunk_header: Option<String>,
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitApplyResult {
+    // dry_run 时始终为 false；有任何一块无法应用时整个补丁都不会写入
+    pub applied: bool,
+    pub files: Vec<String>,
+    pub failures: Vec<GitApplyFailure>,
+}
+
+// 只应用第 delta_index 个文件的第 hunk_index 块（为空时应用该文件的全部），不写入任何内容
+fn check_apply(
+    repo: &Repository,
+    diff: &git2::Diff,
+    location: ApplyLocation,
+    delta_index: usize,
+    hunk_index: Option<usize>,
+) -> Result<(), git2::Error> {
+    let mut deltas_seen = 0;
+    let mut hunks_seen = 0;
+    let mut options = ApplyOptions::new();
+    options.check(true);
+    options.delta_callback(|_| {
+        deltas_seen += 1;
+        deltas_seen == delta_index + 1
+    });
+    options.hunk_callback(|_| {
+        hunks_seen += 1;
+        hunk_index.map_or(true, |index| hunks_seen == index + 1)
+    });
+    repo.apply(diff, location, Some(&mut options))
+}
+
+// 逐块检查，找出无法应用的块
+fn apply_failures(repo: &Repository, diff: &git2::Diff, location: ApplyLocation) -> GitResult<Vec<GitApplyFailure>> {
+    let mut failures = Vec::new();
+    for (delta_index, delta) in diff.deltas().enumerate() {
+        let path = delta
+            .new_file()
+            .path()
+            .or_else(|| delta.old_file().path())
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let patch = Patch::from_diff(diff, delta_index)?;
+        let hunk_count = patch.as_ref().map(|patch| patch.num_hunks()).unwrap_or(0);
+        if hunk_count == 0 {
+            if let Err(e) = check_apply(repo, diff, location, delta_index, None) {
+                failures.push(GitApplyFailure {
+                    path,
+                    hunk_index: None,
+                    hunk_header: None,
+                    message: e.message().to_string(),
+                });
+            }
+            continue;
+        }
+        for hunk_index in 0..hunk_count {
+            if let Err(e) = check_apply(repo, diff, location, delta_index, Some(hunk_index)) {
+                let header = match &patch {
+                    Some(patch) => Some(String::from_utf8_lossy(patch.hunk(hunk_index)?.0.header()).trim_end().to_string()),
+                    None => None,
+                };
+                failures.push(GitApplyFailure {
+                    path: path.clone(),
+                    hunk_index: Some(hunk_index),
+                    hunk_header: header,
+                    message: e.message().to_string(),
+                });
+            }
+        }
+    }
+    Ok(failures)
+}
+
+// 把统一格式的 diff（git diff / format-patch 的输出）应用到工作区或索引，相当于 git apply [--cached] [--check]
+pub fn apply_patch(repo: &Repository, patch_text: &str, to_index: bool, dry_run: bool) -> GitResult<GitApplyResult> {
+    if patch_text.trim().is_empty() {
+        return Err(GitError::invalid("patch is empty"));
+    }
+    if !to_index && repo.is_bare() {
+        return Err(GitError::BareRepository);
+    }
+    let diff = git2::Diff::from_buffer(patch_text.as_bytes()).map_err(|e| GitError::invalid(format!("invalid patch: {}", e.message())))?;
+    apply_diff(repo, &diff, to_index, dry_run)
+}
+
+fn apply_diff(repo: &Repository, diff: &git2::Diff, to_index: bool, dry_run: bool) -> GitResult<GitApplyResult> {
+    let location = if to_index { ApplyLocation::Index } else { ApplyLocation::WorkDir };
+    let files = diff
+        .deltas()
+        .filter_map(|delta| delta.new_file().path().or_else(|| delta.old_file().path()))
+        .map(|p| p.to_string_lossy().to_string())
+        .collect();
+
+    let mut options = ApplyOptions::new();
+    options.check(true);
+    if repo.apply(diff, location, Some(&mut options)).is_err() {
+        return Ok(GitApplyResult {
+            applied: false,
+            files,
+            failures: apply_failures(repo, diff, location)?,
+        });
+    }
+    if !dry_run {
+        repo.apply(diff, location, None)?;
+    }
+    Ok(GitApplyResult {
+        applied: !dry_run,
+        files,
+        failures: Vec::new(),
+    })
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitPatchFileSummary {
+    pub path: String,
+    // 重命名前的路径
+    pub old_path: Option<String>,
+    pub status: String,
+    pub additions: usize,
+    pub deletions: usize,
+    pub binary: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitPatchMessage {
+    // 来自邮件头，普通的 .diff/.patch 文件没有这些信息
+    pub author: Option<String>,
+    pub subject: Option<String>,
+    pub date: Option<String>,
+    pub files: Vec<GitPatchFileSummary>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitPatchImport {
+    // mbox 中的每封邮件各一项，没有改动的邮件（例如系列的封面信）不包含在内
+    pub messages: Vec<GitPatchMessage>,
+    pub result: GitApplyResult,
+}
+
+const MAX_PATCH_FILE_BYTES: u64 = 64 * 1024 * 1024;
+
+fn patch_file_summaries(diff: &git2::Diff) -> GitResult<Vec<GitPatchFileSummary>> {
+    let mut files = Vec::new();
+    for (index, delta) in diff.deltas().enumerate() {
+        let new_path = delta.new_file().path().map(|p| p.to_string_lossy().to_string());
+        let old_path = delta.old_file().path().map(|p| p.to_string_lossy().to_string());
+        let (additions, deletions) = match Patch::from_diff(diff, index)? {
+            Some(patch) => {
+                let (_, additions, deletions) = patch.line_stats()?;
+                (additions, deletions)
+            }
+            None => (0, 0),
+        };
+        files.push(GitPatchFileSummary {
+            path: new_path.clone().or_else(|| old_path.clone()).unwrap_or_default(),
+            old_path: if delta.status() == Delta::Renamed { old_path } else { None },
+            status: delta_status(&delta).to_string(),
+            additions,
+            deletions,
+            binary: delta.flags().is_binary(),
+        });
+    }
+    Ok(files)
+}
+
+// git apply 的错误输出，例如 "error: patch failed: a.txt:12" 和 "error: a.txt: patch does not apply"
+fn cli_apply_failures(message: &str) -> Vec<GitApplyFailure> {
+    message
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("error: "))
+        .map(|error| {
+            let path = match error.strip_prefix("patch failed: ") {
+                Some(location) => location.rsplit_once(':').map_or(location, |(path, _)| path),
+                None => error.split_once(": ").map_or("", |(path, _)| path),
+            };
+            GitApplyFailure {
+                path: path.to_string(),
+                hunk_index: None,
+                hunk_header: None,
+                message: error.to_string(),
+            }
+        })
+        .collect()
+}
+
+// 读取 .patch/.diff 或 mbox（git format-patch 的输出，可以包含多个提交）文件，按邮件汇总涉及的文件，
+// 然后把全部改动应用到工作区或索引（不创建提交）；dry_run 时只检查能否应用
+pub fn import_patch_from_file(repo: &Repository, path: &str, to_index: bool, dry_run: bool) -> GitResult<GitPatchImport> {
+    if !to_index && repo.is_bare() {
+        return Err(GitError::BareRepository);
+    }
+    let path = fs::canonicalize(path).map_err(|_| GitError::not_found(format!("patch file not found: {}", path)))?;
+    let size = fs::metadata(&path)?.len();
+    if size > MAX_PATCH_FILE_BYTES {
+        return Err(GitError::invalid(format!("patch file is too large ({} bytes)", size)));
+    }
+    let data = fs::read(&path)?;
+
+    let mut messages = Vec::new();
+    let mut paths = Vec::new();
+    let mut seen = HashSet::new();
+    // 系列中后面的提交再次修改同一个文件时，要在前一个提交的结果上应用
+    let mut sequential = false;
+    for mail in mbox::split_mbox(&data) {
+        if !mbox::has_diff(mail.body) {
+            continue;
+        }
+        let diff = git2::Diff::from_buffer(mail.body).map_err(|e| GitError::invalid(format!("invalid patch: {}", e.message())))?;
+        let files = patch_file_summaries(&diff)?;
+        if files.is_empty() {
+            continue;
+        }
+        for file in &files {
+            for file_path in std::iter::once(&file.path).chain(file.old_path.as_ref()) {
+                if !seen.insert(file_path.clone()) {
+                    sequential = true;
+                } else if file_path == &file.path {
+                    paths.push(file_path.clone());
+                }
+            }
+        }
+        messages.push(GitPatchMessage {
+            author: mail.author,
+            subject: mail.subject,
+            date: mail.date,
+            files,
+        });
+    }
+    if messages.is_empty() {
+        return Err(GitError::invalid("no changes found in patch file"));
+    }
+
+    if !sequential {
+        let diff = git2::Diff::from_buffer(&data).map_err(|e| GitError::invalid(format!("invalid patch: {}", e.message())))?;
+        let result = apply_diff(repo, &diff, to_index, dry_run)?;
+        return Ok(GitPatchImport { messages, result });
+    }
+
+    // libgit2 不能在一次应用中多次修改同一个文件，交给 git apply，它依次应用各个补丁，
+    // 全部检查通过后才写入，和上面一样不会只应用一部分
+    let path_arg = path.to_string_lossy().to_string();
+    let mut args = vec!["apply"];
+    if to_index {
+        args.push("--cached");
+    }
+    if dry_run {
+        args.push("--check");
+    }
+    args.push(&path_arg);
+    let result = match git_cli::run_git_with_progress(repo.path(), repo.workdir(), &args, &CancellationToken::default(), |_| {}) {
+        Ok(_) => GitApplyResult {
+            applied: !dry_run,
+            files: paths,
+            failures: Vec::new(),
+        },
+        Err(GitError::CommandFailed { message, .. }) => GitApplyResult {
+            applied: false,
+            files: paths,
+            failures: cli_apply_failures(&message),
+        },
+        Err(e) => return Err(e),
+    };
+    Ok(GitPatchImport { messages, result })
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitBundleRef {
+    pub name: String,
+    pub target_hash: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitBundleInfo {
+    pub refs: Vec<GitBundleRef>,
+    // 使用该 bundle 前仓库中必须已有的提交
+    pub prerequisites: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitBundleFetchResult {
+    pub updated_refs: Vec<GitBundleRef>,
+    // 本地已存在且指向不同对象的标签，不会被覆盖
+    pub skipped_refs: Vec<String>,
+}
+
+pub const DEFAULT_BUNDLE_REMOTE: &str = "bundle";
+
+fn bundle_info(header: &BundleHeader) -> GitBundleInfo {
+    GitBundleInfo {
+        refs: header
+            .refs
+            .iter()
+            .map(|(oid, name)| GitBundleRef {
+                name: name.clone(),
+                target_hash: oid.to_string(),
+            })
+            .collect(),
+        prerequisites: header.prerequisites.iter().map(|(oid, _)| oid.to_string()).collect(),
+    }
+}
+
+// 引用的完整名称和它直接指向的对象（附注标签为标签对象本身）
+fn resolve_bundle_ref(repo: &Repository, name: &str) -> GitResult<(String, Oid)> {
+    if name == "HEAD" {
+        let head = head_commit(repo)?.ok_or(GitError::UnbornBranch)?;
+        return Ok(("HEAD".to_string(), head.id()));
+    }
+    let reference = repo
+        .resolve_reference_from_short_name(name)
+        .map_err(|_| GitError::not_found(format!("reference not found: {}", name)))?;
+    let full_name = reference.name().unwrap_or(name).to_string();
+    let target = reference.resolve()?.target().ok_or_else(|| GitError::not_found(format!("reference has no target: {}", name)))?;
+    Ok((full_name, target))
+}
+
+fn write_bundle(output_path: &str, header: &BundleHeader, builder: &mut git2::PackBuilder) -> GitResult<()> {
+    let mut out = std::io::BufWriter::new(fs::File::create(output_path)?);
+    header.write(&mut out)?;
+    let mut write_error = None;
+    // 回调返回 false 时 foreach 只报告被中止，真正的原因是写文件的错误
+    let result = builder.foreach(|chunk| match out.write_all(chunk) {
+        Ok(()) => true,
+        Err(e) => {
+            write_error = Some(e);
+            false
+        }
+    });
+    if let Some(e) = write_error {
+        return Err(e.into());
+    }
+    result?;
+    out.flush()?;
+    Ok(())
+}
+
+// refs 中每一项是引用名，或 "base..ref" 形式的增量范围（接收方需已有 base）；相当于 git bundle create
+pub fn create_bundle(repo: &Repository, refs: &[String], output_path: &str) -> GitResult<GitBundleInfo> {
+    if refs.is_empty() {
+        return Err(GitError::invalid("at least one reference is required"));
+    }
+    if output_path.trim().is_empty() {
+        return Err(GitError::invalid("output path is required"));
+    }
+
+    let mut header = BundleHeader::default();
+    let mut revwalk = repo.revwalk()?;
+    let mut tag_objects = Vec::new();
+    for entry in refs {
+        let (base, name) = match entry.split_once("..") {
+            Some((base, name)) => (Some(base), name),
+            None => (None, entry.as_str()),
+        };
+        let (full_name, target) = resolve_bundle_ref(repo, name.trim())?;
+        revwalk.push(repo.find_object(target, None)?.peel_to_commit()?.id())?;
+        if repo.find_object(target, None)?.kind() == Some(ObjectType::Tag) {
+            tag_objects.push(target);
+        }
+        if let Some(base) = base {
+            let base = repo.revparse_single(base.trim())?.peel_to_commit()?;
+            revwalk.hide(base.id())?;
+            if !header.prerequisites.iter().any(|(oid, _)| *oid == base.id()) {
+                header.prerequisites.push((base.id(), base.summary().unwrap_or("").to_string()));
+            }
+        }
+        header.refs.push((target, full_name));
+    }
+
+    let mut builder = repo.packbuilder()?;
+    builder.insert_walk(&mut revwalk)?;
+    for tag in tag_objects {
+        builder.insert_object(tag, None)?;
+    }
+    if builder.object_count() == 0 {
+        return Err(GitError::invalid("refusing to create an empty bundle"));
+    }
+
+    if let Err(e) = write_bundle(output_path, &header, &mut builder) {
+        let _ = fs::remove_file(output_path);
+        return Err(e);
+    }
+    Ok(bundle_info(&header))
+}
+
+// 只读取 bundle 的文件头，打开 bundle 时展示其中的引用
+pub fn get_bundle_info(bundle_path: &str) -> GitResult<GitBundleInfo> {
+    let mut reader = std::io::BufReader::new(fs::File::open(bundle_path)?);
+    Ok(bundle_info(&BundleHeader::read(&mut reader)?))
+}
+
+// 把 bundle 中的对象写入仓库，分支更新到 refs/remotes/<remote_name>/ 下（强制更新），
+// 本地没有的标签直接创建；相当于以 bundle 为远程仓库执行 git fetch
+pub fn fetch_bundle(repo: &Repository, bundle_path: &str, remote_name: &str) -> GitResult<GitBundleFetchResult> {
+    let remote_name = remote_name.trim();
+    if remote_name.is_empty() || !git2::Reference::is_valid_name(&format!("refs/remotes/{}/HEAD", remote_name)) {
+        return Err(GitError::invalid(format!("invalid remote name: {}", remote_name)));
+    }
+
+    let mut reader = std::io::BufReader::new(fs::File::open(bundle_path)?);
+    let header = BundleHeader::read(&mut reader)?;
+    let odb = repo.odb()?;
+    for (oid, _) in &header.prerequisites {
+        if !odb.exists(*oid) {
+            return Err(GitError::not_found(format!("bundle requires commit {} which is not in this repository", oid)));
+        }
+    }
+
+    // bundle 里通常是 thin pack，写入时由 odb 补全引用到的已有对象
+    let mut writer = odb.packwriter()?;
+    std::io::copy(&mut reader, &mut writer)?;
+    writer.commit()?;
+
+    let log_message = format!("fetch: bundle {}", bundle_path);
+    let mut updated_refs = Vec::new();
+    let mut skipped_refs = Vec::new();
+    for (oid, name) in &header.refs {
+        let target = if let Some(branch) = name.strip_prefix("refs/heads/") {
+            format!("refs/remotes/{}/{}", remote_name, branch)
+        } else if name.starts_with("refs/tags/") {
+            match repo.refname_to_id(name) {
+                Ok(existing) if existing == *oid => continue,
+                Ok(_) => {
+                    skipped_refs.push(name.clone());
+                    continue;
+                }
+                Err(_) => name.clone(),
+            }
+        } else {
+            continue;
+        };
+        repo.reference(&target, *oid, true, &log_message)?;
+        updated_refs.push(GitBundleRef {
+            name: target,
+            target_hash: oid.to_string(),
+        });
+    }
+    Ok(GitBundleFetchResult { updated_refs, skipped_refs })
+}
+
+// 优先使用 origin，否则取第一个远程仓库；没有远程时返回 None
+pub fn preferred_remote(repo: &Repository) -> GitResult<Option<String>> {
+    let remotes = repo.remotes()?;
+    let names: Vec<&str> = remotes.iter().flatten().collect();
+    Ok(names.iter().find(|name| **name == "origin").or_else(|| names.first()).map(|name| name.to_string()))
+}
+
+// remote_name 为空时使用 preferred_remote
+pub fn get_remote_web_url(repo: &Repository, remote_name: Option<&str>, target: &RemoteWebTarget) -> GitResult<String> {
+    let remote_name = match remote_name.filter(|name| !name.is_empty()) {
+        Some(name) => name.to_string(),
+        None => preferred_remote(repo)?.ok_or_else(|| GitError::not_found("repository has no remotes"))?,
+    };
+    let remote = repo.find_remote(&remote_name)?;
+    let url = remote.url().unwrap_or("");
+    let web_repo = remote_url::parse(url).ok_or_else(|| GitError::Unsupported {
+        message: format!("cannot map remote url to a web page: {}", url),
+    })?;
+    Ok(web_repo.url_for(target))
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitCreatePrUrl {
+    pub url: String,
+    pub remote: String,
+    pub base: String,
+    pub head: String,
+    // 分支还没有推送或有新提交时先推送，没有推送时为空
+    pub push: Option<GitPushResult>,
+}
+
+// 远程的默认分支：refs/remotes/<remote>/HEAD 指向的分支，没有时依次尝试 main 和 master
+fn remote_default_branch(repo: &Repository, remote: &str) -> GitResult<Option<String>> {
+    let prefix = format!("refs/remotes/{}/", remote);
+    match repo.find_reference(&format!("{}HEAD", prefix)) {
+        Ok(reference) => {
+            if let Some(target) = reference.symbolic_target().and_then(|target| target.strip_prefix(&prefix)) {
+                return Ok(Some(target.to_string()));
+            }
+        }
+        Err(e) if e.code() == ErrorCode::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    Ok(["main", "master"]
+        .into_iter()
+        .find(|name| repo.find_reference(&format!("{}{}", prefix, name)).is_ok())
+        .map(str::to_string))
+}
+
+// 在网页上新建 pull request 的地址，已填好 base 和 head。head 为空时使用当前分支，base 为空时使用远程的默认分支；
+// head 还没有推送或本地有新的提交时先推送到其上游远程（没有上游时用 origin），推送进度交给 on_progress
+pub fn get_create_pr_url(
+    repo: &Repository,
+    base: Option<&str>,
+    head: Option<&str>,
+    cancel: &CancellationToken,
+    on_progress: impl FnMut(&str),
+) -> GitResult<GitCreatePrUrl> {
+    let head = push_branch_name(repo, head.filter(|head| !head.is_empty()))?;
+    let remote = match repo.branch_upstream_remote(&format!("refs/heads/{}", head)) {
+        Ok(remote) => remote.as_str().map(str::to_string).ok_or_else(|| GitError::invalid("remote name is not valid UTF-8"))?,
+        Err(e) if e.code() == ErrorCode::NotFound => default_fetch_remote(repo)?,
+        Err(e) => return Err(e.into()),
+    };
+    let url = repo.find_remote(&remote)?.url().unwrap_or("").to_string();
+    let web_repo = remote_url::parse(&url).ok_or_else(|| GitError::Unsupported {
+        message: format!("cannot map remote url to a web page: {}", url),
+    })?;
+    let base = match base.filter(|base| !base.is_empty()) {
+        Some(base) => base.to_string(),
+        None => remote_default_branch(repo, &remote)?.ok_or_else(|| GitError::invalid(format!("cannot determine the default branch of {}", remote)))?,
+    };
+    if base == head {
+        return Err(GitError::invalid(format!("'{}' cannot be both the base and the head of a pull request", head)));
+    }
+
+    // 远程分支已包含本地的所有提交（相同或本地落后）时不推送；与远程分叉时推送会被拒绝，
+    // 以 NotFastForward 报告，需要先拉取。远程的提交还没抓取到本地时无法判断，照常推送
+    let local_tip = repo.find_branch(&head, BranchType::Local)?.get().peel_to_commit()?.id();
+    let needs_push = match remote_branch_tip(repo, &remote, &head)? {
+        Some(remote_tip) => remote_tip != local_tip && !repo.graph_descendant_of(remote_tip, local_tip).unwrap_or(false),
+        None => true,
+    };
+    let push = if needs_push {
+        Some(push_branch(repo, Some(&remote), Some(&head), false, None, cancel, on_progress)?)
+    } else {
+        None
+    };
+    Ok(GitCreatePrUrl {
+        url: web_repo.url_for(&RemoteWebTarget::PullRequest {
+            base: base.clone(),
+            head: head.clone(),
+        }),
+        remote,
+        base,
+        head,
+        push,
+    })
+}
+
+// directory 为相对仓库根目录的路径，为空时写入根目录的 .gitignore；返回刷新后的状态
+pub fn add_to_gitignore(repo: &Repository, pattern: &str, directory: Option<&str>) -> GitResult<Vec<GitStatus>> {
+    let workdir = repo.workdir().ok_or(GitError::BareRepository)?;
+    let pattern = pattern.trim();
+    if pattern.is_empty() {
+        return Err(GitError::invalid("ignore pattern is empty"));
+    }
+
+    let gitignore_path = gitignore_path_in(workdir, directory)?;
+    let mut content = match fs::read_to_string(&gitignore_path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+
+    if !content.lines().any(|line| line.trim() == pattern) {
+        if !content.is_empty() && !content.ends_with('\n') {
+            content.push('\n');
+        }
+        content.push_str(pattern);
+        content.push('\n');
+        fs::write(&gitignore_path, content)?;
+    }
+
+    get_status(repo, &GitStatusOptions::default())
+}
+
+// 解析 directory 下的 .gitignore；目录必须已存在，且经符号链接解析后仍位于工作区内
+fn gitignore_path_in(workdir: &Path, directory: Option<&str>) -> GitResult<PathBuf> {
+    let directory = match directory.filter(|d| !d.trim_matches(|c| c == '/' || c == '\\').is_empty()) {
+        Some(directory) => {
+            if Path::new(directory).has_root() || Path::new(directory).is_absolute() {
+                return Err(GitError::invalid(format!("directory must be relative to the repository: {}", directory)));
+            }
+            let relative = normalize_repo_path(directory)?;
+            if Path::new(&relative).components().any(|c| !matches!(c, std::path::Component::Normal(_))) {
+                return Err(GitError::invalid(format!("invalid path: {}", relative)));
+            }
+            workdir.join(relative)
+        }
+        None => workdir.to_path_buf(),
+    };
+    let root = fs::canonicalize(workdir)?;
+    let resolved = fs::canonicalize(&directory).map_err(|_| GitError::not_found(format!("directory not found: {}", directory.display())))?;
+    if !resolved.is_dir() || !resolved.starts_with(&root) {
+        return Err(GitError::invalid(format!("directory is outside the repository: {}", directory.display())));
+    }
+    let gitignore_path = resolved.join(".gitignore");
+    if fs::symlink_metadata(&gitignore_path).map(|meta| meta.file_type().is_symlink()).unwrap_or(false) {
+        return Err(GitError::invalid(format!("refusing to write through a symlinked .gitignore: {}", gitignore_path.display())));
+    }
+    Ok(gitignore_path)
+}
+
+fn escape_gitignore_path(path: &str) -> String {
+    let mut escaped = String::with_capacity(path.len());
+    for (index, c) in path.chars().enumerate() {
+        let is_leading_special = index == 0 && (c == '#' || c == '!');
+        if is_leading_special || matches!(c, '*' | '?' | '[' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// 右键菜单的“忽略此文件/此扩展名/此文件夹”，写入该路径所在目录的 .gitignore，
+// 模式相对于该目录
+pub fn ignore_path(repo: &Repository, file_path: &str, target: IgnoreTarget) -> GitResult<Vec<GitStatus>> {
+    let workdir = repo.workdir().ok_or(GitError::BareRepository)?;
+    let relative = normalize_repo_path(file_path)?;
+    let (directory, name) = match relative.rsplit_once('/') {
+        Some((directory, name)) => (Some(directory), name),
+        None => (None, relative.as_str()),
+    };
+    let pattern = match target {
+        IgnoreTarget::File => format!("/{}", escape_gitignore_path(name)),
+        IgnoreTarget::Folder => {
+            if workdir.join(&relative).is_file() {
+                return Err(GitError::invalid(format!("not a folder: {}", relative)));
+            }
+            format!("/{}/", escape_gitignore_path(name))
+        }
+        IgnoreTarget::Extension => {
+            let extension = Path::new(name)
+                .extension()
+                .and_then(|e| e.to_str())
+                .ok_or_else(|| GitError::invalid(format!("file has no extension: {}", file_path)))?;
+            format!("*.{}", escape_gitignore_path(extension))
+        }
+    };
+    add_to_gitignore(repo, &pattern, directory)
+}
+
+pub fn get_ignored_status(repo: &Repository, paths: &[String]) -> GitResult<Vec<GitIgnoredStatus>> {
+    let mut result = Vec::with_capacity(paths.len());
+    for path in paths {
+        result.push(GitIgnoredStatus {
+            path: path.clone(),
+            ignored: repo.is_path_ignored(Path::new(path))?,
+        });
+    }
+    Ok(result)
+}
+
+pub fn get_commit_changes(repo: &Repository, commit_hash: &str, options: &GitDiffOptions) -> GitResult<Vec<GitCommitChange>> {
+    let oid = Oid::from_str(commit_hash)?;
+    let commit = repo.find_commit(oid)?;
+    let current_tree = commit.tree()?;
+
+    let parent_tree = if commit.parent_count() > 0 {
+        Some(commit.parent(0)?.tree()?)
+    } else {
+        None
+    };
+
+    let mut diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&current_tree), Some(&mut options.diff_options()))?;
+    // 默认不做重命名检测，保持原来按新增和删除列出的结果
+    if options.detect_copies {
+        options.find_similar(&mut diff)?;
+    }
+    let mut changes = Vec::new();
+
+    for delta in diff.deltas() {
+        let path = delta
+            .new_file()
+            .path()
+            .or_else(|| delta.old_file().path())
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let submodule = is_submodule_delta(&delta).then(|| {
+            let id = |file: git2::DiffFile| Some(file.id()).filter(|oid| !oid.is_zero() && file.mode() == FileMode::Commit);
+            submodule_change(repo, &path, id(delta.old_file()), id(delta.new_file()), options.submodule_log)
+        });
+
+        changes.push(GitCommitChange {
+            path,
+            status: delta_status(&delta).to_string(),
+            submodule,
+        });
+    }
+
+    Ok(changes)
+}
+
+fn is_submodule_delta(delta: &git2::DiffDelta) -> bool {
+    delta.old_file().mode() == FileMode::Commit || delta.new_file().mode() == FileMode::Commit
+}
+
+// 子模块指向的提交变化时报告为 submodule，新增和删除子模块仍按普通文件报告
+fn delta_status(delta: &git2::DiffDelta) -> &'static str {
+    if delta.status() == Delta::Modified && is_submodule_delta(delta) {
+        "submodule"
+    } else {
+        delta_label(delta.status())
+    }
+}
+
+fn delta_label(status: Delta) -> &'static str {
+    match status {
+        Delta::Added => "added",
+        Delta::Deleted => "deleted",
+        Delta::Modified => "modified",
+        Delta::Renamed => "renamed",
+        Delta::Copied => "copied",
+        Delta::Typechange => "typechange",
+        _ => "unknown",
+    }
+}
+
+fn identity_from_signature(signature: &git2::Signature) -> GitIdentity {
+    let when = signature.when();
+    GitIdentity {
+        name: signature.name().unwrap_or("").to_string(),
+        email: signature.email().unwrap_or("").to_string(),
+        time: when.seconds(),
+        offset_minutes: when.offset_minutes(),
+    }
+}
+
+pub fn get_commit_details(repo: &Repository, commit_hash: &str, mailmap: MailmapMode, options: &GitDiffOptions) -> GitResult<GitCommitDetails> {
+    let commit = repo.revparse_single(commit_hash)?.peel_to_commit()?;
+    let current_tree = commit.tree()?;
+
+    let parent_tree = if commit.parent_count() > 0 {
+        Some(commit.parent(0)?.tree()?)
+    } else {
+        None
+    };
+
+    let mut diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&current_tree), Some(&mut options.diff_options()))?;
+    options.find_similar(&mut diff)?;
+
+    let mut files = Vec::new();
+    for index in 0..diff.deltas().len() {
+        let delta = match diff.get_delta(index) {
+            Some(delta) => delta,
+            None => continue,
+        };
+        let new_path = delta.new_file().path().map(|p| p.to_string_lossy().to_string());
+        let old_path = delta.old_file().path().map(|p| p.to_string_lossy().to_string());
+        let (insertions, deletions) = match Patch::from_diff(&diff, index)? {
+            Some(patch) => {
+                let (_, additions, removals) = patch.line_stats()?;
+                (additions, removals)
+            }
+            None => (0, 0),
+        };
+
+        files.push(GitCommitFileStat {
+            path: new_path.clone().or_else(|| old_path.clone()).unwrap_or_default(),
+            old_path: if old_path != new_path { old_path } else { None },
+            status: delta_status(&delta).to_string(),
+            insertions,
+            deletions,
+            is_binary: delta.flags().is_binary(),
+        });
+    }
+
+    let stats = diff.stats()?;
+    let signature = repo.extract_signature(&commit.id(), None).ok();
+    let identities = IdentityMapper::new(repo, mailmap)?;
+    let (author, raw_author) = identities.identity(&commit.author())?;
+    let (committer, raw_committer) = identities.identity(&commit.committer())?;
+
+    Ok(GitCommitDetails {
+        hash: commit.id().to_string(),
+        summary: commit.summary().unwrap_or("").to_string(),
+        message: commit.message().unwrap_or("").to_string(),
+        author,
+        committer,
+        raw_author,
+        raw_committer,
+        parents: commit.parent_ids().map(|id| id.to_string()).collect(),
+        is_signed: signature.is_some(),
+        signature_format: signature.map(|(sig, _)| SigningFormat::of_signature(&sig).label().to_string()),
+        insertions: stats.insertions(),
+        deletions: stats.deletions(),
+        files,
+    })
+}
+
+fn read_file_from_tree(repo: &Repository, tree: &Tree, file_path: &str) -> GitResult<Option<String>> {
+    let entry = match tree.get_path(Path::new(file_path)) {
+        Ok(entry) => entry,
+        Err(_) => return Ok(None),
+    };
+    let object = entry.to_object(repo)?;
+    let blob = match object.as_blob() {
+        Some(blob) => blob,
+        None => return Ok(None),
+    };
+    Ok(Some(String::from_utf8_lossy(blob.content()).to_string()))
+}
+
+pub const DEFAULT_DIFF_MAX_BYTES: u64 = 2 * 1024 * 1024;
+
+// 文件内容的前 max_bytes 字节以及完整大小
+struct ContentSample {
+    bytes: Vec<u8>,
+    size: u64,
+}
+
+impl ContentSample {
+    fn from_slice(content: &[u8], max_bytes: u64) -> Self {
+        let keep = content.len().min(max_bytes as usize);
+        ContentSample {
+            bytes: content[..keep].to_vec(),
+            size: content.len() as u64,
+        }
+    }
+}
+
+// 与 git 相同的启发式：前 8000 字节内出现 NUL 即视为二进制
+fn looks_binary(bytes: &[u8]) -> bool {
+    bytes.iter().take(8000).any(|b| *b == 0)
+}
+
+fn sample_blob_from_tree(repo: &Repository, tree: &Tree, file_path: &str, max_bytes: u64) -> GitResult<Option<ContentSample>> {
+    let entry = match tree.get_path(Path::new(file_path)) {
+        Ok(entry) => entry,
+        Err(_) => return Ok(None),
+    };
+    let object = entry.to_object(repo)?;
+    Ok(object.as_blob().map(|blob| ContentSample::from_slice(blob.content(), max_bytes)))
+}
+
+fn build_file_diff(original: Option<ContentSample>, modified: Option<ContentSample>) -> GitCommitFileDiff {
+    let original_size = original.as_ref().map_or(0, |c| c.size);
+    let modified_size = modified.as_ref().map_or(0, |c| c.size);
+    let is_binary = [&original, &modified]
+        .iter()
+        .any(|side| side.as_ref().is_some_and(|c| looks_binary(&c.bytes)));
+    let truncated = [&original, &modified]
+        .iter()
+        .any(|side| side.as_ref().is_some_and(|c| (c.bytes.len() as u64) < c.size));
+
+    let text = |side: Option<ContentSample>| match side {
+        Some(content) if !is_binary => String::from_utf8_lossy(&content.bytes).to_string(),
+        _ => String::new(),
+    };
+
+    GitCommitFileDiff {
+        original: text(original),
+        modified: text(modified),
+        is_binary,
+        original_size,
+        modified_size,
+        truncated,
+    }
+}
+
+pub fn get_commit_file_diff(repo: &Repository, commit_hash: &str, file_path: &str, max_bytes: u64) -> GitResult<GitCommitFileDiff> {
+    let oid = Oid::from_str(commit_hash)?;
+    let commit = repo.find_commit(oid)?;
+    let current_tree = commit.tree()?;
+
+    let parent_tree = if commit.parent_count() > 0 {
+        Some(commit.parent(0)?.tree()?)
+    } else {
+        None
+    };
+
+    let mut diff_opts = DiffOptions::new();
+    diff_opts.pathspec(file_path);
+    let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&current_tree), Some(&mut diff_opts))?;
+
+    let delta = diff.deltas().next();
+    let old_path = delta
+        .as_ref()
+        .and_then(|d| d.old_file().path())
+        .map(|p| p.to_string_lossy().to_string());
+    let new_path = delta
+        .as_ref()
+        .and_then(|d| d.new_file().path())
+        .map(|p| p.to_string_lossy().to_string());
+
+    let original = match (&parent_tree, old_path.as_deref()) {
+        (Some(tree), Some(path)) => sample_blob_from_tree(repo, tree, path, max_bytes)?,
+        _ => None,
+    };
+
+    let modified = match new_path.as_deref() {
+        Some(path) => sample_blob_from_tree(repo, &current_tree, path, max_bytes)?,
+        None => None,
+    };
+
+    Ok(build_file_diff(original, modified))
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum GitTreeEntryKind {
+    File,
+    Directory,
+    Symlink,
+    Submodule,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitTreeEntry {
+    pub name: String,
+    // 相对仓库根目录的路径
+    pub path: String,
+    pub kind: GitTreeEntryKind,
+    // git 记录的文件模式，例如 100644、100755（可执行）
+    pub mode: u32,
+    // 只有文件和符号链接有大小
+    pub size: Option<u64>,
+    pub oid: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitBlobContent {
+    pub path: String,
+    pub oid: String,
+    // 二进制文件为空；超过 max_bytes 时只包含前 max_bytes 字节
+    pub content: String,
+    pub is_binary: bool,
+    pub size: u64,
+    pub truncated: bool,
+}
+
+fn rev_tree<'r>(repo: &'r Repository, rev: &str) -> GitResult<Tree<'r>> {
+    Ok(repo.revparse_single(rev)?.peel_to_tree()?)
+}
+
+// 列出某个版本中 subpath 目录（为空时为根目录）下的条目，目录排在前面
+pub fn get_tree(repo: &Repository, rev: &str, subpath: Option<&str>) -> GitResult<Vec<GitTreeEntry>> {
+    let root = rev_tree(repo, rev)?;
+    let subpath = subpath.map(|path| path.trim_matches('/')).filter(|path| !path.is_empty());
+    let tree = match subpath {
+        None => root,
+        Some(path) => {
+            let entry = root
+                .get_path(Path::new(path))
+                .map_err(|_| GitError::not_found(format!("'{}' does not exist in {}", path, rev)))?;
+            entry
+                .to_object(repo)?
+                .into_tree()
+                .map_err(|_| GitError::invalid(format!("'{}' is not a directory", path)))?
+        }
+    };
+
+    let odb = repo.odb()?;
+    let mut entries = Vec::new();
+    for entry in tree.iter() {
+        let name = String::from_utf8_lossy(entry.name_bytes()).to_string();
+        let kind = match entry.filemode() {
+            0o040000 => GitTreeEntryKind::Directory,
+            0o120000 => GitTreeEntryKind::Symlink,
+            0o160000 => GitTreeEntryKind::Submodule,
+            _ => GitTreeEntryKind::File,
+        };
+        // 只读对象头，不把大文件读进内存
+        let size = match kind {
+            GitTreeEntryKind::File | GitTreeEntryKind::Symlink => Some(odb.read_header(entry.id())?.0 as u64),
+            _ => None,
+        };
+        entries.push(GitTreeEntry {
+            path: subpath.map_or_else(|| name.clone(), |dir| format!("{}/{}", dir, name)),
+            name,
+            kind,
+            mode: entry.filemode() as u32,
+            size,
+            oid: entry.id().to_string(),
+        });
+    }
+    entries.sort_by(|a, b| {
+        (b.kind == GitTreeEntryKind::Directory)
+            .cmp(&(a.kind == GitTreeEntryKind::Directory))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    Ok(entries)
+}
+
+// 读取某个版本中的文件内容，只读取前 max_bytes 字节
+pub fn read_blob(repo: &Repository, rev: &str, file_path: &str, max_bytes: u64) -> GitResult<GitBlobContent> {
+    let tree = rev_tree(repo, rev)?;
+    let entry = tree
+        .get_path(Path::new(file_path))
+        .map_err(|_| GitError::not_found(format!("'{}' does not exist in {}", file_path, rev)))?;
+    let blob = entry
+        .to_object(repo)?
+        .into_blob()
+        .map_err(|_| GitError::invalid(format!("'{}' is not a file", file_path)))?;
+    Ok(blob_content(file_path, blob.id(), blob.content(), max_bytes))
+}
+
+fn blob_content(path: &str, oid: Oid, data: &[u8], max_bytes: u64) -> GitBlobContent {
+    let sample = ContentSample::from_slice(data, max_bytes);
+    let is_binary = looks_binary(&sample.bytes);
+    GitBlobContent {
+        path: path.to_string(),
+        oid: oid.to_string(),
+        content: if is_binary { String::new() } else { String::from_utf8_lossy(&sample.bytes).to_string() },
+        is_binary,
+        truncated: (sample.bytes.len() as u64) < sample.size,
+        size: sample.size,
+    }
+}
+
+// 冲突标记的样式，对应 merge.conflictStyle；diff3 在两边之间多出 ||||||| 段，显示共同祖先的内容
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum GitConflictStyle {
+    Merge,
+    Diff3,
+}
+
+// 冲突文件的三个版本和工作区中的当前内容；某一边删除了该文件时对应的版本为空，
+// 两边各自新增的文件没有 base
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitConflictFile {
+    pub path: String,
+    pub base: Option<GitBlobContent>,
+    pub ours: Option<GitBlobContent>,
+    pub theirs: Option<GitBlobContent>,
+    // 工作区中带冲突标记（或已经部分解决）的内容，文件被删除时为空
+    pub working: Option<GitBlobContent>,
+    pub style: GitConflictStyle,
+}
+
+// 没有传入时按 merge.conflictStyle 配置，zdiff3 按 diff3 处理
+fn conflict_style(repo: &Repository, style: Option<GitConflictStyle>) -> GitResult<GitConflictStyle> {
+    if let Some(style) = style {
+        return Ok(style);
+    }
+    Ok(match config_string(&repo.config()?, "merge.conflictStyle")?.as_deref() {
+        Some("diff3") | Some("zdiff3") => GitConflictStyle::Diff3,
+        _ => GitConflictStyle::Merge,
+    })
+}
+
+pub fn get_conflict_file(repo: &Repository, file_path: &str, max_bytes: u64) -> GitResult<GitConflictFile> {
+    let workdir = repo.workdir().ok_or(GitError::BareRepository)?;
+    let index = repo.index()?;
+    let conflict = index
+        .conflicts()?
+        .flatten()
+        .find(|conflict| {
+            [&conflict.our, &conflict.their, &conflict.ancestor]
+                .into_iter()
+                .flatten()
+                .any(|entry| entry.path == file_path.as_bytes())
+        })
+        .ok_or_else(|| GitError::not_found(format!("'{}' has no conflict", file_path)))?;
+
+    let side = |entry: &Option<git2::IndexEntry>| -> GitResult<Option<GitBlobContent>> {
+        match entry {
+            Some(entry) => {
+                let blob = repo.find_blob(entry.id)?;
+                Ok(Some(blob_content(&String::from_utf8_lossy(&entry.path), entry.id, blob.content(), max_bytes)))
+            }
+            None => Ok(None),
+        }
+    };
+    let working_path = workdir.join(file_path);
+    let working = match fs::read(&working_path) {
+        Ok(data) => Some(blob_content(file_path, Oid::hash_object(ObjectType::Blob, &data)?, &data, max_bytes)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+    Ok(GitConflictFile {
+        path: file_path.to_string(),
+        base: side(&conflict.ancestor)?,
+        ours: side(&conflict.our)?,
+        theirs: side(&conflict.their)?,
+        working,
+        style: conflict_style(repo, None)?,
+    })
+}
+
+// 相当于 git checkout --conflict=<style> -- <path>：按索引中的三个版本重新生成工作区文件中的冲突标记，
+// 丢弃对该文件已经做的手动解决
+pub fn restore_conflict_markers(repo: &Repository, file_path: &str, style: Option<GitConflictStyle>, max_bytes: u64) -> GitResult<GitConflictFile> {
+    // 先确认文件确实有冲突，避免覆盖普通文件的修改
+    get_conflict_file(repo, file_path, max_bytes)?;
+    let style = conflict_style(repo, style)?;
+    let mut checkout = git2::build::CheckoutBuilder::new();
+    checkout
+        .force()
+        .allow_conflicts(true)
+        .path(file_path)
+        .ancestor_label("base")
+        .our_label("ours")
+        .their_label("theirs");
+    match style {
+        GitConflictStyle::Merge => checkout.conflict_style_merge(true),
+        GitConflictStyle::Diff3 => checkout.conflict_style_diff3(true),
+    };
+    repo.checkout_index(None, Some(&mut checkout))?;
+    let mut file = get_conflict_file(repo, file_path, max_bytes)?;
+    file.style = style;
+    Ok(file)
+}
+
+pub fn get_blame(
+    repo: &Repository,
+    file_path: &str,
+    rev: Option<&str>,
+    ignore_whitespace: bool,
+    mailmap: MailmapMode,
+) -> GitResult<GitBlame> {
+    let commit = repo.revparse_single(rev.unwrap_or("HEAD"))?.peel_to_commit()?;
+
+    let mut blame_opts = BlameOptions::new();
+    blame_opts
+        .newest_commit(commit.id())
+        .ignore_whitespace(ignore_whitespace)
+        .use_mailmap(mailmap != MailmapMode::Off);
+    let blame = repo.blame_file(Path::new(file_path), Some(&mut blame_opts))?;
+
+    let mut hunks = Vec::new();
+    for hunk in blame.iter() {
+        let signature = hunk.final_signature();
+        // 启用 mailmap 时 final_signature 已是映射后的身份，原始身份要从提交中读取
+        let raw_author = if mailmap == MailmapMode::Both {
+            Some(repo.find_commit(hunk.final_commit_id())?.author().to_owned())
+        } else {
+            None
+        };
+        hunks.push(GitBlameHunk {
+            commit_hash: hunk.final_commit_id().to_string(),
+            author: signature.name().unwrap_or("").to_string(),
+            author_email: signature.email().unwrap_or("").to_string(),
+            raw_author: raw_author.as_ref().map(|s| s.name().unwrap_or("").to_string()),
+            raw_author_email: raw_author.as_ref().map(|s| s.email().unwrap_or("").to_string()),
+            date: signature.when().seconds(),
+            start_line: hunk.final_start_line(),
+            line_count: hunk.lines_in_hunk(),
+            original_start_line: hunk.orig_start_line(),
+            original_path: hunk
+                .path()
+                .map(|p| p.to_string_lossy().to_string())
+                .unwrap_or_else(|| file_path.to_string()),
+            is_boundary: hunk.is_boundary(),
+        });
+    }
+
+    let content = read_file_from_tree(repo, &commit.tree()?, file_path)?.unwrap_or_default();
+    let lines = content.lines().map(|line| line.to_string()).collect();
+
+    Ok(GitBlame {
+        commit_hash: commit.id().to_string(),
+        lines,
+        hunks,
+    })
+}
+
+pub const DEFAULT_DIFF_CONTEXT_LINES: u32 = 3;
+
+// libgit2 不支持 histogram 算法，这里只提供它支持的几种
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum GitDiffAlgorithm {
+    #[default]
+    Myers,
+    Minimal,
+    Patience,
+}
+
+// 生成 diff 的选项，未传的字段和 git diff 的默认行为相同
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GitDiffOptions {
+    // 分别对应 git diff -w、-b、--ignore-space-at-eol 和 --ignore-blank-lines
+    pub ignore_whitespace: bool,
+    pub ignore_whitespace_change: bool,
+    pub ignore_whitespace_eol: bool,
+    pub ignore_blank_lines: bool,
+    pub algorithm: GitDiffAlgorithm,
+    // 和 git diff -C 一样，在同一提交修改过的文件中查找复制来源
+    pub detect_copies: bool,
+    // 和 git diff --submodule=log 一样，列出子模块新旧提交之间的提交
+    pub submodule_log: bool,
+}
+
+impl GitDiffOptions {
+    fn apply(&self, diff_opts: &mut DiffOptions) {
+        diff_opts
+            .ignore_whitespace(self.ignore_whitespace)
+            .ignore_whitespace_change(self.ignore_whitespace_change)
+            .ignore_whitespace_eol(self.ignore_whitespace_eol)
+            .ignore_blank_lines(self.ignore_blank_lines)
+            .minimal(self.algorithm == GitDiffAlgorithm::Minimal)
+            .patience(self.algorithm == GitDiffAlgorithm::Patience);
+    }
+
+    fn diff_options(&self) -> DiffOptions {
+        let mut diff_opts = DiffOptions::new();
+        self.apply(&mut diff_opts);
+        diff_opts
+    }
+
+    // 重命名检测使用 libgit2 的默认设置（diff.renames），需要时再加上复制检测
+    fn find_similar(&self, diff: &mut git2::Diff) -> GitResult<()> {
+        let mut find_opts = DiffFindOptions::new();
+        find_opts.renames(true).copies(self.detect_copies);
+        diff.find_similar(Some(&mut find_opts))?;
+        Ok(())
+    }
+}
+
+fn first_parent_tree<'r>(commit: &git2::Commit<'r>) -> GitResult<Option<Tree<'r>>> {
+    if commit.parent_count() > 0 {
+        Ok(Some(commit.parent(0)?.tree()?))
+    } else {
+        Ok(None)
+    }
+}
+
+fn to_diff_ranges(ranges: Vec<word_diff::Span>) -> Vec<GitDiffRange> {
+    ranges.into_iter().map(|(start, end)| GitDiffRange { start, end }).collect()
+}
+
+// 将连续的删除行与紧随其后的新增行按顺序两两配对，计算行内变化区间
+fn highlight_changed_words(lines: &mut [GitDiffLine]) {
+    let mut index = 0;
+    while index < lines.len() {
+        if lines[index].origin != "-" {
+            index += 1;
+            continue;
+        }
+        let removed_start = index;
+        while index < lines.len() && lines[index].origin == "-" {
+            index += 1;
+        }
+        let added_start = index;
+        while index < lines.len() && lines[index].origin == "+" {
+            index += 1;
+        }
+
+        let pairs = (added_start - removed_start).min(index - added_start);
+        for offset in 0..pairs {
+            let (removed, added) = (removed_start + offset, added_start + offset);
+            if let Some((old_ranges, new_ranges)) = word_diff::changed_ranges(&lines[removed].content, &lines[added].content) {
+                lines[removed].highlights = to_diff_ranges(old_ranges);
+                lines[added].highlights = to_diff_ranges(new_ranges);
+            }
+        }
+    }
+}
+
+// 将只包含单个文件的 diff 转换为补丁文本和结构化的 hunk 列表
+fn file_patch_from_diff(diff: &git2::Diff, file_path: &str, word_diff: bool, max_bytes: u64) -> GitResult<GitFilePatch> {
+    let delta = match diff.get_delta(0) {
+        Some(delta) => delta,
+        None => {
+            return Ok(GitFilePatch {
+                path: file_path.to_string(),
+                old_path: None,
+                status: "unmodified".to_string(),
+                is_binary: false,
+                insertions: 0,
+                deletions: 0,
+                patch: String::new(),
+                hunks: Vec::new(),
+                truncated: false,
+            });
+        }
+    };
+    let new_path = delta.new_file().path().map(|p| p.to_string_lossy().to_string());
+    let old_path = delta.old_file().path().map(|p| p.to_string_lossy().to_string());
+    let truncated = delta.flags().is_binary() && (delta.old_file().size() > max_bytes || delta.new_file().size() > max_bytes);
+
+    let mut patch = match Patch::from_diff(diff, 0)? {
+        Some(patch) => patch,
+        None => {
+            return Ok(GitFilePatch {
+                path: new_path.unwrap_or_else(|| file_path.to_string()),
+                old_path: None,
+                status: delta_status(&delta).to_string(),
+                is_binary: true,
+                insertions: 0,
+                deletions: 0,
+                patch: String::new(),
+                hunks: Vec::new(),
+                truncated,
+            });
+        }
+    };
+
+    let mut hunks = Vec::new();
+    for hunk_index in 0..patch.num_hunks() {
+        let (hunk, line_count) = patch.hunk(hunk_index)?;
+        let mut lines = Vec::with_capacity(line_count);
+        for line_index in 0..line_count {
+            let line = patch.line_in_hunk(hunk_index, line_index)?;
+            lines.push(GitDiffLine {
+                origin: line.origin().to_string(),
+                content: String::from_utf8_lossy(line.content()).to_string(),
+                old_line_no: line.old_lineno(),
+                new_line_no: line.new_lineno(),
+                highlights: Vec::new(),
+            });
+        }
+        if word_diff {
+            highlight_changed_words(&mut lines);
+        }
+        hunks.push(GitDiffHunk {
+            header: String::from_utf8_lossy(hunk.header()).trim_end().to_string(),
+            old_start: hunk.old_start(),
+            old_lines: hunk.old_lines(),
+            new_start: hunk.new_start(),
+            new_lines: hunk.new_lines(),
+            lines,
+        });
+    }
+
+    let (_, insertions, deletions) = patch.line_stats()?;
+    let text = patch.to_buf()?;
+
+    Ok(GitFilePatch {
+        path: new_path.clone().or_else(|| old_path.clone()).unwrap_or_else(|| file_path.to_string()),
+        old_path: if old_path != new_path { old_path } else { None },
+        status: delta_status(&delta).to_string(),
+        is_binary: delta.flags().is_binary(),
+        insertions,
+        deletions,
+        patch: String::from_utf8_lossy(&text).to_string(),
+        hunks,
+        truncated,
+    })
+}
+
+pub fn get_commit_file_patch(
+    repo: &Repository,
+    commit_hash: &str,
+    file_path: &str,
+    context_lines: u32,
+    word_diff: bool,
+    options: &GitDiffOptions,
+    max_bytes: u64,
+) -> GitResult<GitFilePatch> {
+    let commit = repo.revparse_single(commit_hash)?.peel_to_commit()?;
+    let current_tree = commit.tree()?;
+    let parent_tree = first_parent_tree(&commit)?;
+
+    let mut diff_opts = options.diff_options();
+    // 超过上限的文件由 libgit2 按二进制处理，不生成补丁文本
+    diff_opts
+        .pathspec(file_path)
+        .context_lines(context_lines)
+        .max_size(i64::try_from(max_bytes).unwrap_or(i64::MAX));
+    let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&current_tree), Some(&mut diff_opts))?;
+
+    file_patch_from_diff(&diff, file_path, word_diff, max_bytes)
+}
+
+fn head_tree(repo: &Repository) -> GitResult<Option<Tree<'_>>> {
+    match repo.head() {
+        Ok(head) => Ok(Some(head.peel_to_tree()?)),
+        Err(e) if e.code() == ErrorCode::UnbornBranch || e.code() == ErrorCode::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn sample_blob_from_index(repo: &Repository, file_path: &str, max_bytes: u64) -> GitResult<Option<ContentSample>> {
+    let index = repo.index()?;
+    let entry = match index.get_path(Path::new(file_path), 0) {
+        Some(entry) => entry,
+        None => return Ok(None),
+    };
+    let blob = repo.find_blob(entry.id)?;
+    Ok(Some(ContentSample::from_slice(blob.content(), max_bytes)))
+}
+
+// 工作区文件只读取上限以内的部分，避免把超大文件整个读进内存
+fn sample_file_from_workdir(repo: &Repository, file_path: &str, max_bytes: u64) -> GitResult<Option<ContentSample>> {
+    let workdir = repo.workdir().ok_or(GitError::BareRepository)?;
+    let file = match fs::File::open(workdir.join(file_path)) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let size = file.metadata()?.len();
+    let mut bytes = Vec::new();
+    file.take(max_bytes).read_to_end(&mut bytes)?;
+    Ok(Some(ContentSample { bytes, size }))
+}
+
+// staged 为 true 时比较 HEAD 与暂存区（即将提交的内容），否则比较暂存区与工作区
+pub fn get_workdir_file_diff(repo: &Repository, file_path: &str, staged: bool, max_bytes: u64) -> GitResult<GitCommitFileDiff> {
+    let (original, modified) = if staged {
+        let original = match head_tree(repo)? {
+            Some(tree) => sample_blob_from_tree(repo, &tree, file_path, max_bytes)?,
+            None => None,
+        };
+        (original, sample_blob_from_index(repo, file_path, max_bytes)?)
+    } else {
+        (
+            sample_blob_from_index(repo, file_path, max_bytes)?,
+            sample_file_from_workdir(repo, file_path, max_bytes)?,
+        )
+    };
+
+    Ok(build_file_diff(original, modified))
+}
+
+fn workdir_diff<'r>(repo: &'r Repository, staged: bool, diff_opts: &mut DiffOptions) -> GitResult<git2::Diff<'r>> {
+    if staged {
+        let tree = head_tree(repo)?;
+        Ok(repo.diff_tree_to_index(tree.as_ref(), None, Some(diff_opts))?)
+    } else {
+        diff_opts
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .show_untracked_content(true);
+        Ok(repo.diff_index_to_workdir(None, Some(diff_opts))?)
+    }
+}
+
+pub fn get_workdir_file_patch(
+    repo: &Repository,
+    file_path: &str,
+    staged: bool,
+    context_lines: u32,
+    word_diff: bool,
+    options: &GitDiffOptions,
+    max_bytes: u64,
+) -> GitResult<GitFilePatch> {
+    let mut diff_opts = options.diff_options();
+    diff_opts
+        .pathspec(file_path)
+        .disable_pathspec_match(true)
+        .context_lines(context_lines)
+        .max_size(i64::try_from(max_bytes).unwrap_or(i64::MAX));
+    let diff = workdir_diff(repo, staged, &mut diff_opts)?;
+
+    file_patch_from_diff(&diff, file_path, word_diff, max_bytes)
+}
+
+// 和 git restore --source=<rev> 一样，把文件（或目录）恢复成某个版本中的内容，该版本中不存在的文件会被删除。
+// to_index 为 true 时同时更新暂存区（git restore --source=<rev> --staged --worktree），否则只改工作区
+pub fn checkout_file_at(repo: &Repository, rev: &str, file_path: &str, to_index: bool) -> GitResult<Vec<GitStatus>> {
+    let workdir = repo.workdir().ok_or(GitError::BareRepository)?;
+    let file_path = file_path.trim_matches('/');
+    if file_path.is_empty() {
+        return Err(GitError::invalid("file path is empty"));
+    }
+    let tree = rev_tree(repo, rev)?;
+    let in_rev = tree.get_path(Path::new(file_path)).is_ok();
+    let in_index = {
+        let index = repo.index()?;
+        let prefix = format!("{}/", file_path);
+        index.iter().any(|entry| {
+            let path = String::from_utf8_lossy(&entry.path);
+            path == file_path || path.starts_with(&prefix)
+        })
+    };
+    if !in_rev && !in_index && !workdir.join(file_path).exists() {
+        return Err(GitError::not_found(format!("'{}' does not exist in {} or the working tree", file_path, rev)));
+    }
+
+    let mut checkout = git2::build::CheckoutBuilder::new();
+    checkout
+        .force()
+        .path(file_path)
+        .update_index(to_index);
+    repo.checkout_tree(tree.as_object(), Some(&mut checkout))?;
+    get_status(repo, &GitStatusOptions::default())
+}
+
+fn normalize_repo_path(path: &str) -> GitResult<String> {
+    let path = path.replace('\\', "/").trim_matches('/').to_string();
+    if path.is_empty() || path.split('/').any(|part| part == ".." || part == "." || part == ".git") {
+        return Err(GitError::invalid(format!("invalid path: {}", path)));
+    }
+    Ok(path)
+}
+
+// 索引中位于 path（文件或目录）下的条目；有冲突的条目返回 MergeConflict
+fn tracked_entries(index: &git2::Index, path: &str) -> GitResult<Vec<git2::IndexEntry>> {
+    let prefix = format!("{}/", path);
+    let entries: Vec<git2::IndexEntry> = index
+        .iter()
+        .filter(|entry| {
+            let entry_path = String::from_utf8_lossy(&entry.path);
+            entry_path == path || entry_path.starts_with(&prefix)
+        })
+        .collect();
+    if entries.is_empty() {
+        return Err(GitError::not_found(format!("'{}' is not tracked", path)));
+    }
+    // 条目的 stage 记录在 flags 的第 12、13 位
+    let conflicted: Vec<String> = entries
+        .iter()
+        .filter(|entry| (entry.flags >> 12) & 0x3 != 0)
+        .map(|entry| String::from_utf8_lossy(&entry.path).to_string())
+        .collect();
+    if !conflicted.is_empty() {
+        return Err(GitError::MergeConflict { paths: conflicted });
+    }
+    Ok(entries)
+}
+
+// 和 git mv 一样移动或重命名已跟踪的文件或目录，同时更新工作区和索引；
+// to 是已存在的目录时移动到该目录下
+pub fn move_tracked_file(repo: &Repository, from: &str, to: &str) -> GitResult<Vec<GitStatus>> {
+    let workdir = repo.workdir().ok_or(GitError::BareRepository)?;
+    let from = normalize_repo_path(from)?;
+    let mut to = normalize_repo_path(to)?;
+    if workdir.join(&to).is_dir() {
+        let name = from.rsplit('/').next().unwrap_or(&from);
+        to = format!("{}/{}", to, name);
+    }
+    if to == from || to.starts_with(&format!("{}/", from)) {
+        return Err(GitError::invalid(format!("cannot move '{}' to '{}'", from, to)));
+    }
+
+    let mut index = repo.index()?;
+    let entries = tracked_entries(&index, &from)?;
+    if workdir.join(&to).symlink_metadata().is_ok() || index.get_path(Path::new(&to), 0).is_some() {
+        return Err(GitError::AlreadyExists {
+            message: format!("destination already exists: {}", to),
+        });
+    }
+
+    if let Some(parent) = workdir.join(&to).parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::rename(workdir.join(&from), workdir.join(&to))?;
+    let update_index = |index: &mut git2::Index| -> GitResult<()> {
+        for entry in &entries {
+            let old_path = String::from_utf8_lossy(&entry.path).to_string();
+            let moved = git2::IndexEntry {
+                path: format!("{}{}", to, &old_path[from.len()..]).into_bytes(),
+                ..*entry
+            };
+            index.remove_path(Path::new(&old_path))?;
+            index.add(&moved)?;
+        }
+        index.write()?;
+        Ok(())
+    };
+    // 索引写入失败时把文件移回原处，工作区和索引保持一致
+    if let Err(e) = update_index(&mut index) {
+        let _ = fs::rename(workdir.join(&to), workdir.join(&from));
+        return Err(e);
+    }
+    get_status(repo, &GitStatusOptions::default())
+}
+
+// 和 git rm -r 一样从索引中删除文件或目录；keep_on_disk 为 true 时保留工作区中的文件（git rm --cached）。
+// 删除磁盘上的文件前检查未提交的修改，有修改时返回 DirtyWorkdir，避免丢失内容
+pub fn remove_tracked_file(repo: &Repository, path: &str, keep_on_disk: bool) -> GitResult<Vec<GitStatus>> {
+    let workdir = repo.workdir().ok_or(GitError::BareRepository)?;
+    let path = normalize_repo_path(path)?;
+    let mut index = repo.index()?;
+    let entries = tracked_entries(&index, &path)?;
+    let paths: Vec<String> = entries.iter().map(|entry| String::from_utf8_lossy(&entry.path).to_string()).collect();
+
+    if !keep_on_disk {
+        let modified: Vec<String> = paths
+            .iter()
+            .filter(|path| {
+                repo.status_file(Path::new(path)).is_ok_and(|status| {
+                    status.intersects(
+                        git2::Status::INDEX_NEW
+                            | git2::Status::INDEX_MODIFIED
+                            | git2::Status::INDEX_TYPECHANGE
+                            | git2::Status::WT_MODIFIED
+                            | git2::Status::WT_TYPECHANGE,
+                    )
+                })
+            })
+            .cloned()
+            .collect();
+        if !modified.is_empty() {
+            return Err(GitError::DirtyWorkdir { files: modified });
+        }
+    }
+
+    for path in &paths {
+        index.remove_path(Path::new(path))?;
+    }
+    index.write()?;
+
+    if !keep_on_disk {
+        for path in &paths {
+            match fs::remove_file(workdir.join(path)) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+            // 和 git rm 一样清理删空的父目录
+            let mut parent = Path::new(path).parent();
+            while let Some(dir) = parent.filter(|dir| !dir.as_os_str().is_empty()) {
+                if fs::remove_dir(workdir.join(dir)).is_err() {
+                    break;
+                }
+                parent = dir.parent();
+            }
+        }
+    }
+    get_status(repo, &GitStatusOptions::default())
+}
+
+pub fn checkout_branch(repo: &Repository, branch_name: &str) -> GitResult<()> {
+    let branch = repo.find_branch(branch_name, BranchType::Local)?;
+    let target = branch
+        .get()
+        .target()
+        .ok_or_else(|| GitError::not_found(format!("branch '{}' has no target", branch_name)))?;
+    let commit = repo.find_commit(target)?;
+    let object = commit.as_object();
     
-    let mut revwalk = repo.revwalk()?;
-    revwalk.push(commit.id())?;
+    repo.checkout_tree(object, None)?;
+    repo.set_head(&format!("refs/heads/{}", branch_name))?;
     
-    for oid in revwalk {
-        let oid = oid?;
-        let commit = repo.find_commit(oid)?;
-        
-        let author = commit.author().name().unwrap_or("").to_string();
-        // 手动格式化时间
-        let time = commit.author().when();
-        let date = format!("{}", time.seconds());
-        let message = commit.message().unwrap_or("").trim().to_string();
-        let parents = commit.parent_ids().map(|id| id.to_string()).collect();
-        
-        commits.push(GitCommit {
-            hash: oid.to_string(),
-            author,
-            date,
-            message,
-            parents,
+    Ok(())
+}
+
+pub fn get_submodules(repo: &Repository) -> GitResult<Vec<GitSubmodule>> {
+    let mut result = Vec::new();
+
+    for submodule in repo.submodules()? {
+        let name = submodule.name().unwrap_or("").to_string();
+        let status = repo.submodule_status(&name, SubmoduleIgnore::Unspecified)?;
+        let is_initialized = !status.is_wd_uninitialized() && status.contains(SubmoduleStatus::IN_WD);
+
+        result.push(GitSubmodule {
+            path: submodule.path().to_string_lossy().to_string(),
+            url: submodule.url().map(|u| u.to_string()),
+            branch: submodule.branch().map(|b| b.to_string()),
+            recorded_commit: submodule.head_id().map(|id| id.to_string()),
+            checked_out_commit: submodule.workdir_id().map(|id| id.to_string()),
+            is_initialized,
+            is_commit_mismatch: is_initialized && status.is_wd_modified(),
+            has_local_changes: status.is_wd_wd_modified() || status.contains(SubmoduleStatus::WD_INDEX_MODIFIED) || status.is_wd_untracked(),
+            name,
         });
-        
-        if commits.len() >= 50 {
-            break;
-        }
     }
-    
-    Ok(commits)
+
+    Ok(result)
 }
 
-pub fn get_status(repo_path: &str) -> Result<Vec<GitStatus>, Box<dyn Error>> {
-    let repo = open_repo(repo_path)?;
-    let mut status_options = StatusOptions::new();
-    status_options.show(StatusShow::Workdir);
-    
-    let statuses = repo.statuses(Some(&mut status_options))?;
-    let mut status_list = Vec::new();
-    
-    for entry in statuses.iter() {
-        let path = entry.path().unwrap_or("").to_string();
-        let status = entry.status();
-        
-        let status_str = if status.contains(git2::Status::INDEX_NEW) || status.contains(git2::Status::WT_NEW) {
-            "new"
-        } else if status.contains(git2::Status::INDEX_MODIFIED) || status.contains(git2::Status::WT_MODIFIED) {
-            "modified"
-        } else if status.contains(git2::Status::INDEX_DELETED) || status.contains(git2::Status::WT_DELETED) {
-            "deleted"
-        } else {
-            "unknown"
-        };
-        
-        status_list.push(GitStatus {
-            file_path: path,
-            status: status_str.to_string(),
+pub fn init_submodule(repo: &Repository, submodule_path: &str) -> GitResult<()> {
+    let mut submodule = repo.find_submodule(submodule_path)?;
+    submodule.init(false)?;
+    Ok(())
+}
+
+// 将子模块检出到父仓库记录的提交，init 为 true 时顺带初始化
+pub fn update_submodule(repo: &Repository, submodule_path: &str, init: bool) -> GitResult<()> {
+    let mut submodule = repo.find_submodule(submodule_path)?;
+    submodule.update(init, None)?;
+    Ok(())
+}
+
+pub const DEFAULT_REFLOG_LIMIT: usize = 200;
+
+pub fn get_reflog(repo: &Repository, ref_name: &str, limit: usize) -> GitResult<Vec<GitReflogEntry>> {
+    let reflog = repo.reflog(ref_name)?;
+
+    let mut entries = Vec::new();
+    for (index, entry) in reflog.iter().enumerate().take(limit) {
+        entries.push(GitReflogEntry {
+            index,
+            old_hash: entry.id_old().to_string(),
+            new_hash: entry.id_new().to_string(),
+            message: entry.message().unwrap_or("").to_string(),
+            committer: identity_from_signature(&entry.committer()),
         });
     }
-    
-    Ok(status_list)
+    Ok(entries)
 }
 
-pub fn get_commit_changes(repo_path: &str, commit_hash: &str) -> Result<Vec<GitCommitChange>, Box<dyn Error>> {
-    let repo = open_repo(repo_path)?;
-    let oid = Oid::from_str(commit_hash)?;
-    let commit = repo.find_commit(oid)?;
-    let current_tree = commit.tree()?;
+fn reflog_entry_target(repo: &Repository, ref_name: &str, index: usize) -> GitResult<Oid> {
+    let reflog = repo.reflog(ref_name)?;
+    let entry = reflog
+        .get(index)
+        .ok_or_else(|| GitError::not_found(format!("reflog entry {}@{{{}}} not found", ref_name, index)))?;
+    Ok(entry.id_new())
+}
 
-    let parent_tree = if commit.parent_count() > 0 {
-        Some(commit.parent(0)?.tree()?)
-    } else {
-        None
-    };
+fn checkout_detached(repo: &Repository, commit: &Commit) -> GitResult<()> {
+    repo.checkout_tree(commit.as_object(), None)?;
+    repo.set_head_detached(commit.id())?;
+    Ok(())
+}
 
-    let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&current_tree), None)?;
-    let mut changes = Vec::new();
+// 以分离 HEAD 的方式检出某条 reflog 记录，便于查看被重置掉的提交
+pub fn checkout_reflog_entry(repo: &Repository, ref_name: &str, index: usize) -> GitResult<String> {
+    let oid = reflog_entry_target(repo, ref_name, index)?;
+    checkout_detached(repo, &repo.find_commit(oid)?)?;
+    Ok(oid.to_string())
+}
 
-    for delta in diff.deltas() {
-        let status = match delta.status() {
-            Delta::Added => "added",
-            Delta::Deleted => "deleted",
-            Delta::Modified => "modified",
-            Delta::Renamed => "renamed",
-            Delta::Copied => "copied",
-            Delta::Typechange => "typechange",
-            _ => "unknown",
-        };
+// 以分离 HEAD 的方式检出任意修订（提交、标签或分支名都会解析到提交），返回检出后的 HEAD 状态
+pub fn checkout_commit(repo: &Repository, rev: &str) -> GitResult<GitHeadState> {
+    let commit = repo.revparse_single(rev)?.peel_to_commit()?;
+    checkout_detached(repo, &commit)?;
+    get_head_state(repo)
+}
 
-        let path = delta
-            .new_file()
-            .path()
-            .or_else(|| delta.old_file().path())
-            .map(|p| p.to_string_lossy().to_string())
-            .unwrap_or_default();
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitTagCheckout {
+    pub tag: GitTag,
+    pub commit: GitOperationCommit,
+    // 检出后为分离 HEAD，可以用 create_branch_here 从这里创建分支
+    pub head: GitHeadState,
+}
 
-        changes.push(GitCommitChange {
-            path,
-            status: status.to_string(),
-        });
-    }
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitTagCheckoutResult {
+    pub checkout: GitTagCheckout,
+    // 使用了自动暂存且确实有改动被暂存时不为空
+    pub autostash: Option<autostash::GitAutostash>,
+}
 
-    Ok(changes)
+// 以分离 HEAD 的方式检出标签指向的提交，例如查看某个发布版本
+pub fn checkout_tag(repo: &Repository, tag_name: &str) -> GitResult<GitTagCheckout> {
+    let tag_name = tag_name.strip_prefix("refs/tags/").unwrap_or(tag_name);
+    let reference = match repo.find_reference(&format!("refs/tags/{}", tag_name)) {
+        Ok(reference) => reference,
+        Err(e) if e.code() == ErrorCode::NotFound => return Err(GitError::not_found(format!("tag '{}' not found", tag_name))),
+        Err(e) => return Err(e.into()),
+    };
+    let commit = reference
+        .peel_to_commit()
+        .map_err(|_| GitError::invalid(format!("tag '{}' does not point to a commit", tag_name)))?;
+    let annotation = reference.peel(ObjectType::Tag).ok().and_then(|object| object.into_tag().ok());
+    // 签名附在附注标签的消息之后，不作为说明返回
+    let (message, is_signed) = match annotation.as_ref().and_then(|tag| tag.message()) {
+        Some(message) => match TAG_SIGNATURE_MARKERS.iter().filter_map(|marker| message.find(marker)).min() {
+            Some(start) => (Some(message[..start].trim_end().to_string()), true),
+            None => (Some(message.trim_end().to_string()), false),
+        },
+        None => (None, false),
+    };
+
+    checkout_detached(repo, &commit)?;
+    Ok(GitTagCheckout {
+        tag: GitTag {
+            name: tag_name.to_string(),
+            target_hash: commit.id().to_string(),
+            message,
+            is_annotated: annotation.is_some(),
+            is_signed,
+        },
+        commit: GitOperationCommit {
+            hash: commit.id().to_string(),
+            summary: commit.summary().unwrap_or("").to_string(),
+        },
+        head: get_head_state(repo)?,
+    })
 }
 
-fn read_file_from_tree(repo: &Repository, tree: &Tree, file_path: &str) -> Result<Option<String>, Box<dyn Error>> {
-    let entry = match tree.get_path(Path::new(file_path)) {
-        Ok(entry) => entry,
-        Err(_) => return Ok(None),
+// 在当前提交上创建分支，通常用于检出标签或提交之后；checkout 为 true 时切换到新分支，
+// 提交没有变化，工作目录和暂存区保持原样
+pub fn create_branch_here(repo: &Repository, branch_name: &str, checkout: bool) -> GitResult<GitBranch> {
+    let commit = match repo.head() {
+        Ok(head) => head.peel_to_commit()?,
+        Err(e) if e.code() == ErrorCode::UnbornBranch => return Err(GitError::UnbornBranch),
+        Err(e) => return Err(e.into()),
     };
-    let object = entry.to_object(repo)?;
-    let blob = match object.as_blob() {
-        Some(blob) => blob,
-        None => return Ok(None),
+    let branch = match repo.branch(branch_name, &commit, false) {
+        Ok(branch) => branch,
+        Err(e) if e.code() == ErrorCode::Exists => {
+            return Err(GitError::AlreadyExists {
+                message: format!("branch '{}' already exists", branch_name),
+            })
+        }
+        Err(e) => return Err(e.into()),
     };
-    Ok(Some(String::from_utf8_lossy(blob.content()).to_string()))
+    if checkout {
+        repo.set_head(&format!("refs/heads/{}", branch_name))?;
+    }
+
+    Ok(GitBranch {
+        name: branch.name()?.unwrap_or(branch_name).to_string(),
+        is_current: branch.is_head(),
+        is_remote: false,
+    })
 }
 
-pub fn get_commit_file_diff(repo_path: &str, commit_hash: &str, file_path: &str) -> Result<GitCommitFileDiff, Box<dyn Error>> {
-    let repo = open_repo(repo_path)?;
-    let oid = Oid::from_str(commit_hash)?;
+pub fn create_branch_from_reflog(repo: &Repository, ref_name: &str, index: usize, branch_name: &str) -> GitResult<GitBranch> {
+    let oid = reflog_entry_target(repo, ref_name, index)?;
     let commit = repo.find_commit(oid)?;
-    let current_tree = commit.tree()?;
+    let branch = repo.branch(branch_name, &commit, false)?;
 
-    let parent_tree = if commit.parent_count() > 0 {
-        Some(commit.parent(0)?.tree()?)
-    } else {
-        None
+    Ok(GitBranch {
+        name: branch.name()?.unwrap_or(branch_name).to_string(),
+        is_current: branch.is_head(),
+        is_remote: false,
+    })
+}
+
+pub fn get_head_state(repo: &Repository) -> GitResult<GitHeadState> {
+    let head = repo.find_reference("HEAD")?;
+    let ref_name = match head.symbolic_target() {
+        Some(ref_name) => ref_name,
+        None => {
+            return Ok(GitHeadState {
+                kind: GitHeadKind::Detached,
+                branch: None,
+                target: head.target().map(|oid| oid.to_string()),
+            });
+        }
     };
+    let branch = Some(ref_name.strip_prefix("refs/heads/").unwrap_or(ref_name).to_string());
+    match repo.refname_to_id(ref_name) {
+        Ok(oid) => Ok(GitHeadState {
+            kind: GitHeadKind::Branch,
+            branch,
+            target: Some(oid.to_string()),
+        }),
+        Err(e) if e.code() == ErrorCode::NotFound => Ok(GitHeadState {
+            kind: GitHeadKind::Unborn,
+            branch,
+            target: None,
+        }),
+        Err(e) => Err(e.into()),
+    }
+}
 
-    let mut diff_opts = DiffOptions::new();
-    diff_opts.pathspec(file_path);
-    let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&current_tree), Some(&mut diff_opts))?;
+// 进行到一半、需要继续或中止的操作，和 git status 的判断顺序相同
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum GitOperationKind {
+    Rebase,
+    RebaseInteractive,
+    // git am 应用邮件补丁
+    ApplyMailbox,
+    Merge,
+    CherryPick,
+    Revert,
+    Bisect,
+}
 
-    let delta = diff.deltas().next();
-    let old_path = delta
-        .as_ref()
-        .and_then(|d| d.old_file().path())
-        .map(|p| p.to_string_lossy().to_string());
-    let new_path = delta
-        .as_ref()
-        .and_then(|d| d.new_file().path())
-        .map(|p| p.to_string_lossy().to_string());
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GitOperationCommit {
+    pub hash: String,
+    pub summary: String,
+}
 
-    let original = match (&parent_tree, old_path.as_deref()) {
-        (Some(tree), Some(path)) => read_file_from_tree(&repo, tree, path)?.unwrap_or_default(),
-        _ => String::new(),
-    };
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitOperationStatus {
+    // 没有进行中的操作时为空
+    pub kind: Option<GitOperationKind>,
+    // 变基或二分开始前所在的分支
+    pub head_name: Option<String>,
+    // 变基的目标提交
+    pub onto: Option<GitOperationCommit>,
+    // 操作开始前 HEAD 指向的提交，中止后回到这里
+    pub orig_head: Option<GitOperationCommit>,
+    // 正在合并、拣选、还原或变基中停下来的提交；章鱼合并有多个
+    pub incoming: Vec<GitOperationCommit>,
+    // 变基、am 和多个提交的拣选/还原的进度，从 1 开始
+    pub step: Option<usize>,
+    pub total_steps: Option<usize>,
+    pub conflicts: Vec<String>,
+    // MERGE_MSG（am 时为当前补丁）中准备好的提交说明
+    pub message: Option<String>,
+}
 
-    let modified = match new_path.as_deref() {
-        Some(path) => read_file_from_tree(&repo, &current_tree, path)?.unwrap_or_default(),
-        None => String::new(),
+fn read_git_file(dir: &Path, name: &str) -> Option<String> {
+    fs::read_to_string(dir.join(name)).ok().map(|content| content.trim().to_string()).filter(|content| !content.is_empty())
+}
+
+fn operation_commit(repo: &Repository, rev: &str) -> Option<GitOperationCommit> {
+    let commit = repo.revparse_single(rev).ok()?.peel_to_commit().ok()?;
+    Some(GitOperationCommit {
+        hash: commit.id().to_string(),
+        summary: commit.summary().unwrap_or("").to_string(),
+    })
+}
+
+// sequencer/todo 中剩下的拣选/还原指令数，不含注释和空行
+fn sequencer_remaining(git_dir: &Path) -> Option<usize> {
+    let todo = fs::read_to_string(git_dir.join("sequencer").join("todo")).ok()?;
+    Some(todo.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')).count())
+}
+
+// 检查 .git 中 rebase-merge/、rebase-apply/、MERGE_HEAD、CHERRY_PICK_HEAD、REVERT_HEAD、BISECT_START 等状态文件，
+// 返回进行中的操作和涉及的提交，供前端显示提示条和继续/中止操作
+pub fn get_operation_status(repo: &Repository) -> GitResult<GitOperationStatus> {
+    let git_dir = repo.path();
+    let mut status = GitOperationStatus {
+        kind: None,
+        head_name: None,
+        onto: None,
+        orig_head: None,
+        incoming: Vec::new(),
+        step: None,
+        total_steps: None,
+        conflicts: conflict_paths(&repo.index()?)?,
+        message: None,
     };
+    let branch_name = |name: String| name.strip_prefix("refs/heads/").map(str::to_string).unwrap_or(name);
 
-    Ok(GitCommitFileDiff { original, modified })
+    let rebase_merge = git_dir.join("rebase-merge");
+    let rebase_apply = git_dir.join("rebase-apply");
+    if rebase_merge.is_dir() {
+        // 默认的 merge 后端在普通变基时也会写入 interactive，和 git status 一样报告为交互式变基
+        status.kind = Some(if rebase_merge.join("interactive").is_file() {
+            GitOperationKind::RebaseInteractive
+        } else {
+            GitOperationKind::Rebase
+        });
+        status.head_name = read_git_file(&rebase_merge, "head-name").filter(|name| name != "detached HEAD").map(branch_name);
+        status.onto = read_git_file(&rebase_merge, "onto").and_then(|rev| operation_commit(repo, &rev));
+        status.orig_head = read_git_file(&rebase_merge, "orig-head").and_then(|rev| operation_commit(repo, &rev));
+        status.incoming = read_git_file(&rebase_merge, "stopped-sha").and_then(|rev| operation_commit(repo, &rev)).into_iter().collect();
+        status.step = read_git_file(&rebase_merge, "msgnum").and_then(|n| n.parse().ok());
+        status.total_steps = read_git_file(&rebase_merge, "end").and_then(|n| n.parse().ok());
+    } else if rebase_apply.is_dir() {
+        // rebase-apply/applying 只在 git am 时存在
+        if rebase_apply.join("applying").is_file() {
+            status.kind = Some(GitOperationKind::ApplyMailbox);
+            status.orig_head = read_git_file(git_dir, "ORIG_HEAD").and_then(|rev| operation_commit(repo, &rev));
+            // 当前补丁的提交说明
+            status.message = read_git_file(&rebase_apply, "final-commit");
+        } else {
+            status.kind = Some(GitOperationKind::Rebase);
+            status.head_name = read_git_file(&rebase_apply, "head-name").filter(|name| name != "detached HEAD").map(branch_name);
+            status.onto = read_git_file(&rebase_apply, "onto").and_then(|rev| operation_commit(repo, &rev));
+            status.orig_head = read_git_file(&rebase_apply, "orig-head").and_then(|rev| operation_commit(repo, &rev));
+            status.incoming = read_git_file(&rebase_apply, "original-commit").and_then(|rev| operation_commit(repo, &rev)).into_iter().collect();
+        }
+        status.step = read_git_file(&rebase_apply, "next").and_then(|n| n.parse().ok());
+        status.total_steps = read_git_file(&rebase_apply, "last").and_then(|n| n.parse().ok());
+    } else if let Some(merge_heads) = read_git_file(git_dir, "MERGE_HEAD") {
+        status.kind = Some(GitOperationKind::Merge);
+        status.incoming = merge_heads.lines().filter_map(|rev| operation_commit(repo, rev.trim())).collect();
+        status.orig_head = read_git_file(git_dir, "ORIG_HEAD").and_then(|rev| operation_commit(repo, &rev));
+        status.message = read_git_file(git_dir, "MERGE_MSG");
+    } else if let Some((kind, head)) = [("CHERRY_PICK_HEAD", GitOperationKind::CherryPick), ("REVERT_HEAD", GitOperationKind::Revert)]
+        .into_iter()
+        .find_map(|(file, kind)| read_git_file(git_dir, file).map(|head| (kind, head)))
+    {
+        status.kind = Some(kind);
+        status.incoming = operation_commit(repo, &head).into_iter().collect();
+        status.message = read_git_file(git_dir, "MERGE_MSG");
+        // 多个提交的拣选/还原由 sequencer 记录，todo 的第一行就是当前停下来的提交
+        if let (Some(remaining), Some(done)) = (sequencer_remaining(git_dir), read_git_file(&git_dir.join("sequencer"), "head")) {
+            status.orig_head = operation_commit(repo, &done);
+            let finished = repo
+                .revparse_single("HEAD")
+                .ok()
+                .and_then(|head| {
+                    let mut walk = repo.revwalk().ok()?;
+                    walk.push(head.id()).ok()?;
+                    walk.hide(Oid::from_str(&done).ok()?).ok()?;
+                    Some(walk.count())
+                })
+                .unwrap_or(0);
+            status.step = Some(finished + 1);
+            status.total_steps = Some(finished + remaining);
+        }
+    } else if bisect::is_bisecting(repo) {
+        status.kind = Some(GitOperationKind::Bisect);
+        status.head_name = read_git_file(git_dir, "BISECT_START").map(branch_name);
+        status.incoming = operation_commit(repo, "HEAD").into_iter().collect();
+    }
+    Ok(status)
 }
 
-pub fn checkout_branch(repo_path: &str, branch_name: &str) -> Result<(), Box<dyn Error>> {
-    let repo = open_repo(repo_path)?;
-    let branch = repo.find_branch(branch_name, BranchType::Local)?;
-    let target = branch.get().target().unwrap();
-    let commit = repo.find_commit(target)?;
-    let object = commit.as_object();
-    
-    repo.checkout_tree(object, None)?;
-    repo.set_head(&format!("refs/heads/{}", branch_name))?;
-    
-    Ok(())
+fn worktree_entry(name: &str, path: String, head: Option<GitHeadState>) -> Worktree {
+    Worktree {
+        name: name.to_string(),
+        path,
+        branch: head.as_ref().and_then(|head| head.branch.clone()).unwrap_or_default(),
+        head,
+    }
+}
+
+// 主工作树的根目录，裸仓库为 git 目录
+pub fn repo_root(repo: &Repository) -> &Path {
+    repo.workdir().unwrap_or_else(|| repo.path())
 }
 
-pub fn get_worktrees(repo_path: &str) -> Result<Vec<Worktree>, Box<dyn Error>> {
-    let repo = open_repo(repo_path)?;
+// 在链接工作树中打开时返回主仓库（共享的 .git 目录所在的仓库），否则为空。
+// 链接工作树的 HEAD 和 index 是自己的，引用、对象和配置与主仓库共享
+pub fn main_repository(repo: &Repository) -> GitResult<Option<Repository>> {
+    if !repo.is_worktree() {
+        return Ok(None);
+    }
+    Ok(Some(Repository::open(common_dir(repo.path()))?))
+}
+
+// 从链接工作树中打开时也以主仓库为准，主工作树始终排在第一个
+pub fn get_worktrees(repo: &Repository) -> GitResult<Vec<Worktree>> {
+    let main = main_repository(repo)?;
+    let repo = main.as_ref().unwrap_or(repo);
     let mut result = Vec::new();
     
     // 获取主工作树
     let main_worktree_path = repo.workdir().unwrap_or_else(|| repo.path()).to_str().unwrap_or("").to_string();
-    let main_branch = if let Ok(head) = repo.head() {
-        if let Ok(reference) = head.resolve() {
-            reference.shorthand().unwrap_or("").to_string()
-        } else {
-            "".to_string()
+    result.push(worktree_entry("", main_worktree_path, Some(get_head_state(repo)?)));
+    
+    // 获取其他工作树，各自的 HEAD 需要从工作树对应的仓库读取
+    for name in repo.worktrees()?.iter().flatten() {
+        if let Ok(worktree) = repo.find_worktree(name) {
+            let path = worktree.path().to_str().unwrap_or("").to_string();
+            let head = Repository::open_from_worktree(&worktree).ok().and_then(|worktree_repo| get_head_state(&worktree_repo).ok());
+            result.push(worktree_entry(name, path, head));
+        }
+    }
+    
+    Ok(result)
+}
+
+// 主工作树和所有关联工作树当前检出的分支引用名
+fn checked_out_branches(repo: &Repository) -> GitResult<Vec<(String, String)>> {
+    let main = main_repository(repo)?;
+    let repo = main.as_ref().unwrap_or(repo);
+    let mut checked_out = Vec::new();
+    let main_path = repo.workdir().unwrap_or_else(|| repo.path()).to_string_lossy().to_string();
+    if let Ok(head) = repo.head() {
+        if head.is_branch() {
+            checked_out.push((head.name().unwrap_or("").to_string(), main_path));
+        }
+    }
+
+    for name in repo.worktrees()?.iter().flatten() {
+        let worktree = match repo.find_worktree(name) {
+            Ok(worktree) => worktree,
+            Err(_) => continue,
+        };
+        let worktree_repo = match Repository::open_from_worktree(&worktree) {
+            Ok(worktree_repo) => worktree_repo,
+            Err(_) => continue,
+        };
+        if let Ok(head) = worktree_repo.head() {
+            if head.is_branch() {
+                checked_out.push((
+                    head.name().unwrap_or("").to_string(),
+                    worktree.path().to_string_lossy().to_string(),
+                ));
+            }
+        };
+    }
+    Ok(checked_out)
+}
+
+pub fn add_worktree(repo: &Repository, worktree_path: &str, branch_name: &str, create_branch: bool) -> GitResult<Worktree> {
+    let target = Path::new(worktree_path);
+
+    if target.exists() {
+        let is_empty_dir = target.is_dir() && fs::read_dir(target)?.next().is_none();
+        if !is_empty_dir {
+            return Err(GitError::invalid(format!("worktree path is not empty: {}", worktree_path)));
         }
+    }
+
+    let branch = if create_branch {
+        let head_commit = repo.head()?.peel_to_commit()?;
+        repo.branch(branch_name, &head_commit, false)?
     } else {
-        "".to_string()
+        repo.find_branch(branch_name, BranchType::Local)?
     };
-    
-    result.push(Worktree {
-        path: main_worktree_path,
-        branch: main_branch,
-    });
-    
-    // 获取其他工作树
-    let worktrees = repo.worktrees()?;
-    for i in 0..worktrees.len() {
-        if let Some(name) = worktrees.get(i) {
-            if let Ok(worktree) = repo.find_worktree(name) {
-                let path = worktree.path().to_str().unwrap_or("").to_string();
-                // 对于其他工作树，暂时使用空字符串作为分支名称
-                // 因为 libgit2 的 Worktree API 没有直接提供获取当前分支的方法
-                result.push(Worktree {
-                    path,
-                    branch: "".to_string(),
+
+    let branch_ref = branch.get().name().unwrap_or("").to_string();
+    if let Some((_, path)) = checked_out_branches(repo)?.into_iter().find(|(name, _)| *name == branch_ref) {
+        return Err(GitError::AlreadyExists {
+            message: format!("branch '{}' is already checked out at {}", branch_name, path),
+        });
+    }
+
+    // libgit2 要求目标目录不存在，空目录先删掉
+    if target.exists() {
+        fs::remove_dir(target)?;
+    }
+
+    let name = target
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .ok_or_else(|| GitError::invalid("invalid worktree path"))?;
+    let mut add_opts = WorktreeAddOptions::new();
+    add_opts.reference(Some(branch.get()));
+    let worktree = repo.worktree(&name, target, Some(&add_opts))?;
+
+    let head = Repository::open_from_worktree(&worktree).ok().and_then(|worktree_repo| get_head_state(&worktree_repo).ok());
+    Ok(worktree_entry(&name, worktree.path().to_string_lossy().to_string(), head))
+}
+
+// force 为 false 时，工作树存在未提交改动或被锁定则拒绝删除
+pub fn remove_worktree(repo: &Repository, name: &str, force: bool) -> GitResult<()> {
+    let worktree = repo.find_worktree(name)?;
+
+    if !force {
+        if let WorktreeLockStatus::Locked(reason) = worktree.is_locked()? {
+            return Err(GitError::Locked {
+                message: format!("worktree '{}' is locked: {}", name, reason.unwrap_or_default()),
+            });
+        }
+        if worktree.validate().is_ok() {
+            let worktree_repo = Repository::open_from_worktree(&worktree)?;
+            let changes = uncommitted_tracked_changes(&worktree_repo)?;
+            if !changes.is_empty() {
+                return Err(GitError::DirtyWorkdir {
+                    files: changes.into_iter().map(|c| c.file_path).collect(),
                 });
             }
         }
     }
-    
-    Ok(result)
+
+    let mut prune_opts = WorktreePruneOptions::new();
+    prune_opts.valid(true).working_tree(true).locked(force);
+    worktree.prune(Some(&mut prune_opts))?;
+    Ok(())
+}
+
+// 清理工作目录已经不存在的工作树记录，返回被清理的名称
+pub fn prune_worktrees(repo: &Repository) -> GitResult<Vec<String>> {
+    let mut pruned = Vec::new();
+
+    for name in repo.worktrees()?.iter().flatten() {
+        let worktree = repo.find_worktree(name)?;
+        if worktree.is_prunable(None)? {
+            worktree.prune(None)?;
+            pruned.push(name.to_string());
+        }
+    }
+
+    Ok(pruned)
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitSparseCheckout {
+    pub enabled: bool,
+    // cone 模式下 paths 是检出的目录，否则是 .gitignore 风格的模式
+    pub cone_mode: bool,
+    pub paths: Vec<String>,
+}
+
+// 稀疏检出的配置和模式文件按工作树分别存放，git 命令要在当前工作树上执行
+fn sparse_checkout_git(repo: &Repository, args: &[&str]) -> GitResult<String> {
+    let workdir = repo.workdir().ok_or(GitError::BareRepository)?;
+    let output = git_cli::run_git_with_progress(repo.path(), Some(workdir), args, &CancellationToken::default(), |_| {})?;
+    Ok(output.stdout)
+}
+
+pub fn get_sparse_checkout(repo: &Repository) -> GitResult<GitSparseCheckout> {
+    // git 会把这两项写到 config.worktree 中，libgit2 读不到，交给 git config 读取；一项都没有时退出码为 1
+    let settings = match sparse_checkout_git(repo, &["config", "--bool", "--get-regexp", "^core\\.sparsecheckout(cone)?$"]) {
+        Ok(output) => output,
+        Err(GitError::CommandFailed { exit_code: Some(1), .. }) => String::new(),
+        Err(e) => return Err(e),
+    };
+    // 同一项在多个配置文件中出现时以最后一个为准
+    let setting = |name: &str| {
+        settings
+            .lines()
+            .filter_map(|line| line.split_once(' '))
+            .rfind(|(key, _)| key.eq_ignore_ascii_case(name))
+            .is_some_and(|(_, value)| value == "true")
+    };
+    let enabled = setting("core.sparsecheckout");
+    let cone_mode = setting("core.sparsecheckoutcone");
+    let paths = if enabled {
+        sparse_checkout_git(repo, &["sparse-checkout", "list"])?
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(str::to_string)
+            .collect()
+    } else {
+        Vec::new()
+    };
+    Ok(GitSparseCheckout { enabled, cone_mode, paths })
+}
+
+// 只检出 paths 中的内容；paths 为空时关闭稀疏检出，恢复完整的工作目录。
+// 有未提交修改的文件即使不在范围内也会保留在工作目录中
+pub fn set_sparse_checkout(repo: &Repository, paths: &[String], cone_mode: bool) -> GitResult<GitSparseCheckout> {
+    if paths.is_empty() {
+        sparse_checkout_git(repo, &["sparse-checkout", "disable"])?;
+        return get_sparse_checkout(repo);
+    }
+    let paths: Vec<&str> = paths
+        .iter()
+        .map(|path| if cone_mode { path.trim().trim_matches('/') } else { path.trim() })
+        .filter(|path| !path.is_empty())
+        .collect();
+    if let Some(path) = paths.iter().find(|path| path.starts_with('-') || path.contains(['\n', '\r'])) {
+        return Err(GitError::invalid(format!("invalid sparse checkout path: {}", path)));
+    }
+    let mut args = vec!["sparse-checkout", "set", if cone_mode { "--cone" } else { "--no-cone" }];
+    args.extend(paths);
+    sparse_checkout_git(repo, &args)?;
+    get_sparse_checkout(repo)
 }
 
-pub fn get_repo_info(repo_path: &str) -> Result<GitRepoInfo, Box<dyn Error>> {
-    let repo = open_repo(repo_path)?;
+pub fn get_repo_info(
+    repo: &Repository,
+    repo_path: &str,
+    sizes: &SizeCache,
+    refresh: bool,
+    cancel: &CancellationToken,
+    on_progress: impl Fn(GitSizeScanScope, u64, u64) + Sync,
+) -> GitResult<GitRepoInfo> {
     let git_dir = repo.path();
     let worktree_path = repo.workdir().unwrap_or(git_dir);
     let is_bare = repo.is_bare();
 
-    let worktree_size_bytes = if is_bare {
-        0
-    } else {
-        dir_size(worktree_path, Some(".git"))
-    };
-    let git_metadata_size_bytes = dir_size(git_dir, None);
-    let git_objects_size_bytes = dir_size(&git_dir.join("objects"), None);
-    let git_packfiles_size_bytes = dir_size(&git_dir.join("objects").join("pack"), None);
-    let git_refs_size_bytes = dir_size(&git_dir.join("refs"), None);
-    let lfs_objects_size_bytes = dir_size(&git_dir.join("lfs").join("objects"), None);
-    let lfs_enabled = detect_lfs_enabled(&repo, worktree_path, git_dir);
+    let (worktree, git_dir_usage) = scan_repo_sizes(repo, sizes, refresh, cancel, &on_progress)?;
+    let subdir_size = |rel_path: &str| git_dir_usage.descendant(rel_path).map_or(0, |dir| dir.total_bytes);
+    let worktree_size_bytes = worktree.map_or(0, |usage| usage.total_bytes);
+    let git_metadata_size_bytes = git_dir_usage.total_bytes;
+    let git_objects_size_bytes = subdir_size("objects");
+    let git_packfiles_size_bytes = subdir_size("objects/pack");
+    let git_refs_size_bytes = subdir_size("refs");
+    let lfs_objects_size_bytes = subdir_size("lfs/objects");
+    let lfs_enabled = detect_lfs_enabled(repo, worktree_path, &common_dir(git_dir));
+    let main_repo_path = main_repository(repo)?.map(|main| repo_root(&main).to_string_lossy().to_string());
 
     Ok(GitRepoInfo {
         repo_path: repo_path.to_string(),
         git_dir_path: git_dir.to_string_lossy().to_string(),
         worktree_path: worktree_path.to_string_lossy().to_string(),
         is_bare,
+        is_worktree: main_repo_path.is_some(),
+        main_repo_path,
         total_size_bytes: worktree_size_bytes.saturating_add(git_metadata_size_bytes),
         worktree_size_bytes,
         git_metadata_size_bytes,
@@ -443,5 +5878,115 @@ pub fn get_repo_info(repo_path: &str) -> Result<GitRepoInfo, Box<dyn Error>> {
         git_refs_size_bytes,
         lfs_enabled,
         lfs_objects_size_bytes,
+        identity_warning: identity_warning(repo),
     })
 }
+
+pub const DEFAULT_LARGEST_BLOBS_COUNT: usize = 20;
+const MAX_LARGE_BLOB_PATHS: usize = 10;
+
+// 递归查找树中的目标对象；已遍历过的子树直接跳过，因此同一子树出现在其它目录下时不会再记录新路径
+fn find_large_blobs_in_tree(
+    repo: &Repository,
+    tree: &Tree,
+    prefix: &str,
+    commit: &Commit,
+    visited_trees: &mut HashSet<Oid>,
+    blobs: &mut HashMap<Oid, GitLargeBlob>,
+) -> GitResult<()> {
+    for entry in tree.iter() {
+        let name = entry.name().unwrap_or("");
+        let path = if prefix.is_empty() { name.to_string() } else { format!("{}/{}", prefix, name) };
+        match entry.kind() {
+            Some(ObjectType::Tree) if visited_trees.insert(entry.id()) => {
+                let subtree = repo.find_tree(entry.id())?;
+                find_large_blobs_in_tree(repo, &subtree, &path, commit, visited_trees, blobs)?;
+            }
+            Some(ObjectType::Blob) => {
+                if let Some(blob) = blobs.get_mut(&entry.id()) {
+                    if blob.introduced_by.is_none() {
+                        blob.introduced_by = Some(commit.id().to_string());
+                        blob.introduced_at = Some(commit.time().seconds());
+                    }
+                    if blob.paths.len() < MAX_LARGE_BLOB_PATHS && !blob.paths.contains(&path) {
+                        blob.paths.push(path);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+// 扫描对象库（松散对象和所有 packfile）找出最大的 top_n 个 blob，
+// 再从最早的提交开始遍历所有引用可达的树，找出它们的路径和引入它们的提交
+pub fn get_largest_blobs(repo: &Repository, top_n: usize, cancel: &CancellationToken) -> GitResult<Vec<GitLargeBlob>> {
+    let top_n = top_n.max(1);
+    let odb = repo.odb()?;
+    // 小顶堆，只保留目前最大的 top_n 个；同一对象可能同时存在于多个 pack 中，需要去重
+    let mut largest: BinaryHeap<Reverse<(u64, Oid)>> = BinaryHeap::new();
+    let mut in_heap = HashSet::new();
+    let mut scan_error = None;
+    odb.foreach(|oid| {
+        if let Err(e) = cancel.check() {
+            scan_error = Some(e);
+            return false;
+        }
+        if in_heap.contains(oid) {
+            return true;
+        }
+        let size = match odb.read_header(*oid) {
+            Ok((size, ObjectType::Blob)) => size as u64,
+            Ok(_) => return true,
+            Err(e) => {
+                scan_error = Some(e.into());
+                return false;
+            }
+        };
+        if largest.len() < top_n {
+            largest.push(Reverse((size, *oid)));
+            in_heap.insert(*oid);
+        } else if largest.peek().is_some_and(|Reverse((smallest, _))| size > *smallest) {
+            if let Some(Reverse((_, evicted))) = largest.pop() {
+                in_heap.remove(&evicted);
+            }
+            largest.push(Reverse((size, *oid)));
+            in_heap.insert(*oid);
+        }
+        true
+    })?;
+    if let Some(e) = scan_error {
+        return Err(e);
+    }
+
+    let mut blobs: HashMap<Oid, GitLargeBlob> = largest
+        .into_iter()
+        .map(|Reverse((size, oid))| {
+            (
+                oid,
+                GitLargeBlob {
+                    oid: oid.to_string(),
+                    size_bytes: size,
+                    paths: Vec::new(),
+                    introduced_by: None,
+                    introduced_at: None,
+                },
+            )
+        })
+        .collect();
+
+    let mut visited_trees = HashSet::new();
+    for oid in all_refs_revwalk(repo, Sort::TOPOLOGICAL | Sort::REVERSE)? {
+        cancel.check()?;
+        let commit = repo.find_commit(oid?)?;
+        let tree = commit.tree()?;
+        if visited_trees.insert(tree.id()) {
+            find_large_blobs_in_tree(repo, &tree, "", &commit, &mut visited_trees, &mut blobs)?;
+        }
+    }
+
+    let mut blobs: Vec<GitLargeBlob> = blobs.into_values().collect();
+    blobs.sort_by(|a, b| b.size_bytes.cmp(&a.size_bytes).then_with(|| a.oid.cmp(&b.oid)));
+    Ok(blobs)
+}
@@ -0,0 +1,212 @@
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cancel::CancellationToken;
+use crate::error::{GitError, GitResult};
+use crate::known_hosts;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+pub struct GitCommandOutput {
+    pub stdout: String,
+    pub stderr: String,
+}
+
+// git 和 git lfs 在 stderr 输出的一行进度
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GitCommandProgress {
+    pub operation_id: Option<String>,
+    // 冒号前的阶段说明，例如 "Receiving objects"、"Downloading LFS objects"
+    pub phase: String,
+    pub completed: Option<u64>,
+    pub total: Option<u64>,
+    // 原始的一行输出
+    pub message: String,
+}
+
+// 进度行形如 "Receiving objects:  50% (1/2), 5 KiB | 1 KiB/s"；服务端发来的行带有 "remote: " 前缀
+pub fn parse_progress(line: &str, operation_id: Option<String>) -> GitCommandProgress {
+    let line = line.trim();
+    let text = line.strip_prefix("remote:").map_or(line, str::trim_start);
+    let phase = text.split_once(':').map_or(text, |(phase, _)| phase).trim().to_string();
+    let counts = text
+        .split_once('(')
+        .and_then(|(_, rest)| rest.split_once(')'))
+        .and_then(|(counts, _)| counts.split_once('/'))
+        .and_then(|(completed, total)| Some((completed.trim().parse().ok()?, total.trim().parse().ok()?)));
+    GitCommandProgress {
+        operation_id,
+        phase,
+        completed: counts.map(|(completed, _)| completed),
+        total: counts.map(|(_, total)| total),
+        message: line.to_string(),
+    }
+}
+
+// 在后台线程中逐行读完管道（避免输出写满管道缓冲区时子进程阻塞），并把每行发给调用方；
+// 进度行以单独的 \r 结尾、不断覆盖，只有以 \n 或 \r\n 结尾的行保留在返回的输出中
+fn read_lines_in_background(pipe: Option<impl Read + Send + 'static>, lines: Sender<String>) -> JoinHandle<String> {
+    thread::spawn(move || {
+        let mut output = String::new();
+        let mut pipe = match pipe {
+            Some(pipe) => BufReader::new(pipe),
+            None => return output,
+        };
+        let mut line = Vec::new();
+        let mut byte = [0_u8; 1];
+        loop {
+            let end = match pipe.read(&mut byte) {
+                Ok(0) | Err(_) => None,
+                Ok(_) if byte[0] == b'\r' => match pipe.fill_buf() {
+                    Ok([b'\n', ..]) => {
+                        pipe.consume(1);
+                        Some(b'\n')
+                    }
+                    _ => Some(b'\r'),
+                },
+                Ok(_) if byte[0] == b'\n' => Some(b'\n'),
+                Ok(_) => {
+                    line.push(byte[0]);
+                    continue;
+                }
+            };
+            let text = String::from_utf8_lossy(&line).to_string();
+            line.clear();
+            if end != Some(b'\r') && !text.is_empty() {
+                output.push_str(&text);
+                output.push('\n');
+            }
+            if !text.trim().is_empty() {
+                let _ = lines.send(text);
+            }
+            if end.is_none() {
+                return output;
+            }
+        }
+    })
+}
+
+// 启动子进程并等待它结束，stdout 和 stderr 的每一行都交给 on_line；
+// 取消时结束子进程并返回 GitError::Cancelled
+pub fn run_process(
+    command: &mut Command,
+    cancel: &CancellationToken,
+    mut on_line: impl FnMut(&str),
+) -> GitResult<(ExitStatus, GitCommandOutput)> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| GitError::Io {
+            message: format!("failed to run {}: {}", command.get_program().to_string_lossy(), e),
+        })?;
+    let (line_sender, line_receiver) = mpsc::channel();
+    let stdout = read_lines_in_background(child.stdout.take(), line_sender.clone());
+    let stderr = read_lines_in_background(child.stderr.take(), line_sender);
+
+    let status = loop {
+        match line_receiver.recv_timeout(POLL_INTERVAL) {
+            Ok(line) => on_line(&line),
+            // 输出管道都已关闭但进程还没退出
+            Err(RecvTimeoutError::Disconnected) => thread::sleep(POLL_INTERVAL),
+            Err(RecvTimeoutError::Timeout) => {}
+        }
+        if cancel.is_cancelled() {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(GitError::Cancelled);
+        }
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+    };
+    let output = GitCommandOutput {
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    };
+    for line in line_receiver.try_iter() {
+        on_line(&line);
+    }
+    Ok((status, output))
+}
+
+// ssh 因主机密钥未知或已变化而失败时返回 HostKeyUnverified，交给前端确认
+fn command_failed(args: &[&str], status: ExitStatus, output: &GitCommandOutput) -> GitError {
+    known_hosts::host_key_error(&output.stderr).unwrap_or_else(|| GitError::CommandFailed {
+        command: format!("git {}", args.join(" ")),
+        exit_code: status.code(),
+        message: output.stderr.trim().to_string(),
+    })
+}
+
+// libgit2 不支持的操作（gc、repack 等）交给 git 命令行完成。
+// 命令在 git_dir 上执行，不会弹出凭据提示；取消时结束子进程并返回 GitError::Cancelled
+pub fn run_git(git_dir: &Path, args: &[&str], cancel: &CancellationToken) -> GitResult<GitCommandOutput> {
+    run_git_with_progress(git_dir, None, args, cancel, |_| {})
+}
+
+// 在临时目录中执行，不受程序当前目录所在仓库的影响，例如克隆前用 ls-remote 检查地址；
+// 需要仓库时在 args 中传 --git-dir。env 中的变量会覆盖继承的环境变量
+pub fn run_git_outside_repo(args: &[&str], env: &[(&str, &str)], cancel: &CancellationToken) -> GitResult<GitCommandOutput> {
+    run_git_outside_repo_with_progress(args, env, cancel, |_| {})
+}
+
+// 和 run_git_outside_repo 相同，另外把输出的每一行交给 on_line，例如 git clone 的进度
+pub fn run_git_outside_repo_with_progress(
+    args: &[&str],
+    env: &[(&str, &str)],
+    cancel: &CancellationToken,
+    on_line: impl FnMut(&str),
+) -> GitResult<GitCommandOutput> {
+    let mut command = Command::new("git");
+    // 避免读到当前目录所在仓库的配置
+    command.args(args).current_dir(std::env::temp_dir()).env("GIT_TERMINAL_PROMPT", "0");
+    if let Some(ssh_command) = known_hosts::ssh_command(None, &[]) {
+        command.env("GIT_SSH_COMMAND", ssh_command);
+    }
+    for (key, value) in env {
+        command.env(key, value);
+    }
+    let (status, output) = run_process(&mut command, cancel, on_line)?;
+    if !status.success() {
+        return Err(command_failed(args, status, &output));
+    }
+    Ok(output)
+}
+
+// 和 run_git 相同，另外把输出的每一行（包括以 \r 刷新的进度行）交给 on_line；
+// 需要修改工作目录的命令（例如 git lfs pull）要传 work_tree
+pub fn run_git_with_progress(
+    git_dir: &Path,
+    work_tree: Option<&Path>,
+    args: &[&str],
+    cancel: &CancellationToken,
+    on_line: impl FnMut(&str),
+) -> GitResult<GitCommandOutput> {
+    let mut command = Command::new("git");
+    command.arg("--git-dir").arg(git_dir);
+    if let Some(work_tree) = work_tree {
+        command.arg("--work-tree").arg(work_tree).current_dir(work_tree);
+    }
+    command
+        .args(args)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        // stderr 不是终端时 git lfs 默认不输出进度
+        .env("GIT_LFS_FORCE_PROGRESS", "1");
+    if let Some(ssh_command) = known_hosts::ssh_command(Some(git_dir), &[]) {
+        command.env("GIT_SSH_COMMAND", ssh_command);
+    }
+    let (status, output) = run_process(&mut command, cancel, on_line)?;
+    if !status.success() {
+        return Err(command_failed(args, status, &output));
+    }
+    Ok(output)
+}
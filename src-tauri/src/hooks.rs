@@ -0,0 +1,246 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use git2::Repository;
+use serde::{Deserialize, Serialize};
+
+use crate::cancel::CancellationToken;
+use crate::error::{GitError, GitResult};
+use crate::git_cli;
+use crate::repo_cache::common_dir;
+
+// git 会调用的钩子，参见 git 的 Documentation/githooks.txt
+const KNOWN_HOOKS: &[&str] = &[
+    "applypatch-msg",
+    "pre-applypatch",
+    "post-applypatch",
+    "pre-commit",
+    "pre-merge-commit",
+    "prepare-commit-msg",
+    "commit-msg",
+    "post-commit",
+    "pre-rebase",
+    "post-checkout",
+    "post-merge",
+    "pre-push",
+    "pre-receive",
+    "update",
+    "proc-receive",
+    "post-receive",
+    "post-update",
+    "reference-transaction",
+    "push-to-checkout",
+    "pre-auto-gc",
+    "post-rewrite",
+    "sendemail-validate",
+    "fsmonitor-watchman",
+    "p4-changelist",
+    "p4-prepare-changelist",
+    "p4-post-changelist",
+    "p4-pre-submit",
+    "post-index-change",
+];
+const SAMPLE_SUFFIX: &str = ".sample";
+// 停用的钩子改名加上这个后缀，git 不再调用，启用时改回原名
+const DISABLED_SUFFIX: &str = ".disabled";
+
+pub const HOOK_OUTPUT_EVENT: &str = "hook-output";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum GitHookState {
+    // git 会执行的钩子
+    Active,
+    // 改名停用，或者没有可执行权限
+    Disabled,
+    // git init 自带的示例脚本
+    Sample,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitHook {
+    // 钩子名称，不含 .sample / .disabled 后缀
+    pub name: String,
+    pub file_name: String,
+    // 绝对路径，可以直接传给 open_in_editor
+    pub path: String,
+    pub executable: bool,
+    pub state: GitHookState,
+    // 是否是 git 认识的钩子名称
+    pub known: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitHooks {
+    pub hooks_dir: String,
+    // 是否通过 core.hooksPath 指定了钩子目录
+    pub custom_hooks_path: bool,
+    pub hooks: Vec<GitHook>,
+}
+
+// 钩子执行时 stdout 或 stderr 输出的一行
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GitHookOutput {
+    pub operation_id: Option<String>,
+    pub hook: String,
+    pub line: String,
+}
+
+// core.hooksPath 的相对路径和 git 一样相对于执行钩子的目录（非裸仓库为工作目录）
+pub fn hooks_dir(repo: &Repository) -> GitResult<(PathBuf, bool)> {
+    match repo.config()?.get_path("core.hooksPath") {
+        Ok(path) if path.is_absolute() => Ok((path, true)),
+        Ok(path) => Ok((repo.workdir().unwrap_or_else(|| repo.path()).join(path), true)),
+        Err(_) => Ok((common_dir(repo.path()).join("hooks"), false)),
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+// Windows 上 git 不看可执行权限，只要文件存在就会执行
+#[cfg(target_os = "windows")]
+pub fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(not(target_os = "windows"))]
+fn make_executable(path: &Path) -> GitResult<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mut permissions = fs::metadata(path)?.permissions();
+    // 和 chmod +x 一样，只给有读权限的用户加上执行权限
+    let mode = permissions.mode();
+    permissions.set_mode(mode | ((mode & 0o444) >> 2));
+    fs::set_permissions(path, permissions)?;
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn make_executable(_path: &Path) -> GitResult<()> {
+    Ok(())
+}
+
+pub fn get_hooks(repo: &Repository) -> GitResult<GitHooks> {
+    let (dir, custom_hooks_path) = hooks_dir(repo)?;
+    let mut hooks = Vec::new();
+    for entry in fs::read_dir(&dir).map(|entries| entries.flatten().collect::<Vec<_>>()).unwrap_or_default() {
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let executable = is_executable(&path);
+        let (name, state) = if let Some(name) = file_name.strip_suffix(SAMPLE_SUFFIX) {
+            (name, GitHookState::Sample)
+        } else if let Some(name) = file_name.strip_suffix(DISABLED_SUFFIX) {
+            (name, GitHookState::Disabled)
+        } else if executable {
+            (file_name.as_str(), GitHookState::Active)
+        } else {
+            (file_name.as_str(), GitHookState::Disabled)
+        };
+        hooks.push(GitHook {
+            name: name.to_string(),
+            known: KNOWN_HOOKS.contains(&name),
+            path: path.to_string_lossy().to_string(),
+            file_name,
+            executable,
+            state,
+        });
+    }
+    hooks.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.file_name.cmp(&b.file_name)));
+    Ok(GitHooks {
+        hooks_dir: dir.to_string_lossy().to_string(),
+        custom_hooks_path,
+        hooks,
+    })
+}
+
+// 启用：已有的钩子加上执行权限，停用过的改回原名，都没有时从示例复制一份；
+// 停用：改名为 <name>.disabled，保留脚本内容
+pub fn set_hook_enabled(repo: &Repository, name: &str, enabled: bool) -> GitResult<GitHooks> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        return Err(GitError::invalid(format!("invalid hook name: {}", name)));
+    }
+    let (dir, _) = hooks_dir(repo)?;
+    let hook = dir.join(name);
+    let disabled = dir.join(format!("{}{}", name, DISABLED_SUFFIX));
+    let sample = dir.join(format!("{}{}", name, SAMPLE_SUFFIX));
+
+    if enabled {
+        if !hook.is_file() {
+            if disabled.is_file() {
+                fs::rename(&disabled, &hook)?;
+            } else if sample.is_file() {
+                fs::copy(&sample, &hook)?;
+            } else {
+                return Err(GitError::not_found(format!("hook '{}' does not exist", name)));
+            }
+        }
+        make_executable(&hook)?;
+    } else if hook.is_file() {
+        if disabled.exists() {
+            return Err(GitError::invalid(format!("{} already exists", disabled.display())));
+        }
+        fs::rename(&hook, &disabled)?;
+    }
+    get_hooks(repo)
+}
+
+// Windows 上的钩子通常是 shell 脚本，和 Git for Windows 一样交给 sh 执行
+#[cfg(not(target_os = "windows"))]
+fn hook_command(path: &Path) -> Command {
+    Command::new(path)
+}
+
+#[cfg(target_os = "windows")]
+fn hook_command(path: &Path) -> Command {
+    let mut command = Command::new("sh");
+    command.arg(path);
+    command
+}
+
+// 和 git 一样在工作目录（裸仓库为 .git 目录）中执行钩子，并通过 GIT_INDEX_FILE 指明索引文件。
+// 钩子不存在或没有执行权限时返回 false；以非零状态退出时返回 GitError::HookRejected
+pub fn run_hook(
+    repo: &Repository,
+    name: &str,
+    args: &[&str],
+    cancel: &CancellationToken,
+    mut on_output: impl FnMut(&str),
+) -> GitResult<bool> {
+    let (dir, _) = hooks_dir(repo)?;
+    let path = dir.join(name);
+    if !is_executable(&path) {
+        return Ok(false);
+    }
+    let mut command = hook_command(&path);
+    command
+        .args(args)
+        .current_dir(repo.workdir().unwrap_or_else(|| repo.path()))
+        .env("GIT_INDEX_FILE", repo.path().join("index"))
+        // 钩子不能打开交互式编辑器
+        .env("GIT_EDITOR", ":")
+        .env("GIT_TERMINAL_PROMPT", "0");
+    let mut output = String::new();
+    let (status, _) = git_cli::run_process(&mut command, cancel, |line| {
+        output.push_str(line);
+        output.push('\n');
+        on_output(line);
+    })?;
+    if !status.success() {
+        return Err(GitError::HookRejected {
+            hook: name.to_string(),
+            exit_code: status.code(),
+            output,
+        });
+    }
+    Ok(true)
+}
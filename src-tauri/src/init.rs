@@ -0,0 +1,248 @@
+// 新建仓库：git init 之后按需写入 .gitignore、LICENSE 和 README.md，并创建初始提交。
+// 模板随程序一起编译进来，不需要联网
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use git2::{Config, ErrorCode, IndexAddOption, Repository, RepositoryInitOptions};
+use serde::{Deserialize, Serialize};
+
+use crate::cancel::CancellationToken;
+use crate::commit_message::CommitTrailers;
+use crate::error::{GitError, GitResult};
+use crate::git::{self, GitCommit};
+use crate::sessions::{RepoSession, RepoSessions};
+
+// (id, 显示名称, 内容)
+const GITIGNORE_TEMPLATES: &[(&str, &str, &str)] = &[
+    ("node", "Node", include_str!("../templates/gitignore/node.gitignore")),
+    ("rust", "Rust", include_str!("../templates/gitignore/rust.gitignore")),
+    ("python", "Python", include_str!("../templates/gitignore/python.gitignore")),
+    ("go", "Go", include_str!("../templates/gitignore/go.gitignore")),
+    ("java", "Java", include_str!("../templates/gitignore/java.gitignore")),
+    ("dotnet", ".NET", include_str!("../templates/gitignore/dotnet.gitignore")),
+    ("macos", "macOS", include_str!("../templates/gitignore/macos.gitignore")),
+    ("windows", "Windows", include_str!("../templates/gitignore/windows.gitignore")),
+    ("jetbrains", "JetBrains", include_str!("../templates/gitignore/jetbrains.gitignore")),
+    ("vscode", "Visual Studio Code", include_str!("../templates/gitignore/vscode.gitignore")),
+];
+
+// id 为 SPDX 标识；MIT 等较短的许可证中的 {year} 和 {holder} 会被替换
+const LICENSE_TEMPLATES: &[(&str, &str, &str)] = &[
+    ("MIT", "MIT License", include_str!("../templates/licenses/MIT.txt")),
+    ("Apache-2.0", "Apache License 2.0", include_str!("../templates/licenses/Apache-2.0.txt")),
+    ("GPL-3.0", "GNU General Public License v3.0", include_str!("../templates/licenses/GPL-3.0.txt")),
+    ("MPL-2.0", "Mozilla Public License 2.0", include_str!("../templates/licenses/MPL-2.0.txt")),
+    ("BSD-3-Clause", "BSD 3-Clause License", include_str!("../templates/licenses/BSD-3-Clause.txt")),
+    ("Unlicense", "The Unlicense", include_str!("../templates/licenses/Unlicense.txt")),
+];
+
+pub const DEFAULT_INITIAL_COMMIT_MESSAGE: &str = "Initial commit";
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitInitTemplate {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitInitTemplates {
+    pub gitignore: Vec<GitInitTemplate>,
+    pub licenses: Vec<GitInitTemplate>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GitInitOptions {
+    // 为空时使用 init.defaultBranch 配置
+    pub initial_branch: Option<String>,
+    pub bare: bool,
+    // 可以选多个，按顺序合并到同一个 .gitignore
+    pub gitignore_templates: Vec<String>,
+    pub license: Option<String>,
+    // 许可证中的版权所有者，为空时使用 user.name
+    pub license_holder: Option<String>,
+    pub readme: bool,
+    // 写在 README.md 标题下面
+    pub description: Option<String>,
+    pub initial_commit: bool,
+    pub commit_message: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitInitResult {
+    pub session: RepoSession,
+    pub created_files: Vec<String>,
+    // 目录中已经存在、没有覆盖的文件
+    pub skipped_files: Vec<String>,
+    pub initial_commit: Option<GitCommit>,
+}
+
+pub fn list_init_templates() -> GitInitTemplates {
+    let templates = |list: &[(&str, &str, &str)]| {
+        list.iter()
+            .map(|(id, name, _)| GitInitTemplate {
+                id: id.to_string(),
+                name: name.to_string(),
+            })
+            .collect()
+    };
+    GitInitTemplates {
+        gitignore: templates(GITIGNORE_TEMPLATES),
+        licenses: templates(LICENSE_TEMPLATES),
+    }
+}
+
+// 返回 (显示名称, 内容)
+fn find_template<'a>(list: &[(&str, &'a str, &'a str)], id: &str, kind: &str) -> GitResult<(&'a str, &'a str)> {
+    list.iter()
+        .find(|(template_id, _, _)| template_id.eq_ignore_ascii_case(id.trim()))
+        .map(|(_, name, content)| (*name, *content))
+        .ok_or_else(|| GitError::invalid(format!("unknown {} template: {}", kind, id)))
+}
+
+fn global_config_string(key: &str) -> GitResult<Option<String>> {
+    match Config::open_default()?.get_string(key) {
+        Ok(value) if !value.trim().is_empty() => Ok(Some(value.trim().to_string())),
+        Ok(_) => Ok(None),
+        Err(e) if e.code() == ErrorCode::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// 公历年份，由距 1970-01-01 的天数换算
+fn current_year() -> i64 {
+    let days = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() / 86_400).unwrap_or(0) as i64;
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let year = year_of_era + era * 400;
+    if month >= 10 {
+        year + 1
+    } else {
+        year
+    }
+}
+
+fn gitignore_content(templates: &[String]) -> GitResult<String> {
+    let mut content = String::new();
+    for id in templates {
+        let (name, template) = find_template(GITIGNORE_TEMPLATES, id, "gitignore")?;
+        if !content.is_empty() {
+            content.push('\n');
+        }
+        content.push_str(&format!("### {} ###\n", name));
+        content.push_str(template);
+    }
+    Ok(content)
+}
+
+fn license_content(id: &str, holder: Option<&str>) -> GitResult<String> {
+    let (_, template) = find_template(LICENSE_TEMPLATES, id, "license")?;
+    if !template.contains("{holder}") {
+        return Ok(template.trim_start_matches('\n').to_string());
+    }
+    let holder = match holder.map(str::trim).filter(|holder| !holder.is_empty()) {
+        Some(holder) => holder.to_string(),
+        None => global_config_string("user.name")?.ok_or_else(|| GitError::invalid("license holder is required when user.name is not configured"))?,
+    };
+    Ok(template.replace("{year}", &current_year().to_string()).replace("{holder}", &holder))
+}
+
+// 已存在的文件不覆盖，例如在已有项目的目录中初始化时
+fn write_new_file(dir: &Path, name: &str, content: &str, created: &mut Vec<String>, skipped: &mut Vec<String>) -> GitResult<()> {
+    let path = dir.join(name);
+    if path.exists() {
+        skipped.push(name.to_string());
+    } else {
+        fs::write(path, content)?;
+        created.push(name.to_string());
+    }
+    Ok(())
+}
+
+// 在 repo_path 初始化仓库（目录不存在时创建）并打开会话。
+// 初始提交包含目录中所有未被忽略的文件，和在已有项目中执行 git add -A 后提交一样
+pub fn init_repo(sessions: &RepoSessions, repo_path: &str, options: &GitInitOptions) -> GitResult<GitInitResult> {
+    if repo_path.trim().is_empty() {
+        return Err(GitError::invalid("repository path is required"));
+    }
+    let path = Path::new(repo_path);
+    if Repository::open(path).is_ok() {
+        return Err(GitError::AlreadyExists {
+            message: format!("'{}' is already a git repository", repo_path),
+        });
+    }
+    if options.bare && (options.readme || options.license.is_some() || !options.gitignore_templates.is_empty() || options.initial_commit) {
+        return Err(GitError::invalid("a bare repository has no working tree for initial files or commits"));
+    }
+    if let Some(branch) = &options.initial_branch {
+        if !git2::Reference::is_valid_name(&format!("refs/heads/{}", branch)) {
+            return Err(GitError::invalid(format!("invalid branch name: {}", branch)));
+        }
+    }
+    // 先生成所有文件的内容，模板或参数有误时不留下初始化了一半的目录
+    let gitignore = gitignore_content(&options.gitignore_templates)?;
+    let license = match &options.license {
+        Some(id) => Some(license_content(id, options.license_holder.as_deref())?),
+        None => None,
+    };
+    let message = options.commit_message.as_deref().unwrap_or(DEFAULT_INITIAL_COMMIT_MESSAGE);
+    if options.initial_commit {
+        if git2::message_prettify(message, None)?.trim().is_empty() {
+            return Err(GitError::invalid("commit message is empty"));
+        }
+        // 新仓库还没有自己的配置，提交时使用的就是全局的身份
+        if global_config_string("user.name")?.is_none() || global_config_string("user.email")?.is_none() {
+            return Err(GitError::invalid("user.name and user.email must be configured before committing"));
+        }
+    }
+
+    let mut init_options = RepositoryInitOptions::new();
+    init_options.bare(options.bare).mkpath(true);
+    if let Some(branch) = &options.initial_branch {
+        init_options.initial_head(branch);
+    }
+    let repo = Repository::init_opts(path, &init_options)?;
+
+    let mut created_files = Vec::new();
+    let mut skipped_files = Vec::new();
+    if let Some(workdir) = repo.workdir() {
+        if !gitignore.is_empty() {
+            write_new_file(workdir, ".gitignore", &gitignore, &mut created_files, &mut skipped_files)?;
+        }
+        if let Some(license) = &license {
+            write_new_file(workdir, "LICENSE", license, &mut created_files, &mut skipped_files)?;
+        }
+        if options.readme {
+            let name = workdir.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+            let mut readme = format!("# {}\n", name);
+            if let Some(description) = options.description.as_deref().map(str::trim).filter(|description| !description.is_empty()) {
+                readme.push_str(&format!("\n{}\n", description));
+            }
+            write_new_file(workdir, "README.md", &readme, &mut created_files, &mut skipped_files)?;
+        }
+    }
+
+    let initial_commit = if options.initial_commit {
+        let mut index = repo.index()?;
+        index.add_all(["*"], IndexAddOption::DEFAULT, None)?;
+        index.write()?;
+        Some(git::create_commit(&repo, message, None, &CommitTrailers::default(), false, &CancellationToken::default(), |_, _| {})?)
+    } else {
+        None
+    };
+
+    Ok(GitInitResult {
+        session: sessions.open(repo_path)?,
+        created_files,
+        skipped_files,
+        initial_commit,
+    })
+}
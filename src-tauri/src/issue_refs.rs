@@ -0,0 +1,185 @@
+// 识别提交说明中的问题引用并给出链接，供提交详情中显示超链接：
+// #123 和 owner/repo#123 链接到远程所在平台的 issue（GitLab 的 !123 是 merge request），
+// PROJ-123 形式的 Jira 键在配置了 Jira 地址后识别，另外可以自定义正则表达式和链接模板
+use std::sync::OnceLock;
+
+use git2::Repository;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::error::GitResult;
+use crate::git;
+use crate::remote_url::{self, RemoteProvider, RemoteWebRepo, RemoteWebTarget};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct IssueLinkPattern {
+    pub pattern: String,
+    // 链接模板，$1、${name} 替换为对应的捕获组，$0 为整个匹配
+    pub url: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct IssueLinkSettings {
+    // 例如 https://company.atlassian.net/browse/，键直接拼在后面；为空时不识别 Jira 键
+    pub jira_url: Option<String>,
+    // 只识别这些项目的键，为空时识别所有形如 ABC-123 的键
+    pub jira_projects: Vec<String>,
+    // 优先于内置的规则
+    pub patterns: Vec<IssueLinkPattern>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum IssueReferenceKind {
+    Issue,
+    MergeRequest,
+    Jira,
+    Custom,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct IssueReference {
+    pub kind: IssueReferenceKind,
+    pub text: String,
+    pub url: String,
+    // 行号从 0 开始，列为 UTF-16 偏移的左闭右开区间，和提交说明检查的结果一致
+    pub line: usize,
+    pub start_column: usize,
+    pub end_column: usize,
+}
+
+fn forge_regex() -> &'static Regex {
+    static FORGE: OnceLock<Regex> = OnceLock::new();
+    FORGE.get_or_init(|| Regex::new(r"(?:(?P<repo>[A-Za-z0-9_.-]+(?:/[A-Za-z0-9_.-]+)+))?(?P<sigil>[#!])(?P<number>[0-9]+)\b").unwrap())
+}
+
+// 没有限定项目时，这些常见的写法（UTF-8、SHA-256、RFC-2119 等）不当作 Jira 键
+const NOT_JIRA_PROJECTS: &[&str] = &["UTF", "UCS", "SHA", "MD", "ISO", "RFC", "CVE", "CWE", "PEP", "AES", "RSA", "HTTP", "TLS", "X"];
+
+fn jira_regex() -> &'static Regex {
+    static JIRA: OnceLock<Regex> = OnceLock::new();
+    JIRA.get_or_init(|| Regex::new(r"\b(?P<project>[A-Z][A-Z0-9_]+)-(?P<number>[0-9]+)\b").unwrap())
+}
+
+// regex 不支持后顾断言，前一个字符另外检查，避免把 HTML 实体 &#123; 或 abc#1 这样的内容当作引用
+fn starts_word(line: &str, start: usize) -> bool {
+    line[..start].chars().next_back().map_or(true, |c| !(c.is_alphanumeric() || matches!(c, '_' | '&' | '-' | '/' | '#' | '!')))
+}
+
+fn utf16_len(text: &str) -> usize {
+    text.encode_utf16().count()
+}
+
+// (字节范围, 类型, 链接)
+type LineMatch = (std::ops::Range<usize>, IssueReferenceKind, String);
+
+fn forge_matches(line: &str, web_repo: &RemoteWebRepo, matches: &mut Vec<LineMatch>) {
+    for captures in forge_regex().captures_iter(line) {
+        let whole = captures.get(0).unwrap();
+        let Ok(number) = captures["number"].parse::<u64>() else {
+            continue;
+        };
+        if !starts_word(line, whole.start()) {
+            continue;
+        }
+        let (kind, target) = match &captures["sigil"] {
+            "!" if web_repo.provider == RemoteProvider::GitLab => (IssueReferenceKind::MergeRequest, RemoteWebTarget::MergeRequest { number }),
+            "!" => continue,
+            _ => (IssueReferenceKind::Issue, RemoteWebTarget::Issue { number }),
+        };
+        // owner/repo#123 指向同一平台上的另一个仓库
+        let repo = match captures.name("repo") {
+            Some(other) => {
+                // base_url 形如 https://host/owner/repo，前三段是 "https:"、"" 和主机
+                let origin = web_repo.base_url.splitn(4, '/').take(3).collect::<Vec<_>>().join("/");
+                RemoteWebRepo {
+                    provider: web_repo.provider,
+                    base_url: format!("{}/{}", origin, other.as_str()),
+                }
+            }
+            None => web_repo.clone(),
+        };
+        matches.push((whole.range(), kind, repo.url_for(&target)));
+    }
+}
+
+fn jira_matches(line: &str, jira_url: &str, projects: &[String], matches: &mut Vec<LineMatch>) {
+    for captures in jira_regex().captures_iter(line) {
+        let whole = captures.get(0).unwrap();
+        if !starts_word(line, whole.start()) {
+            continue;
+        }
+        let project = &captures["project"];
+        let allowed = if projects.is_empty() {
+            !NOT_JIRA_PROJECTS.contains(&project)
+        } else {
+            projects.iter().any(|allowed| allowed == project)
+        };
+        if !allowed {
+            continue;
+        }
+        matches.push((whole.range(), IssueReferenceKind::Jira, format!("{}{}", jira_url, whole.as_str())));
+    }
+}
+
+// web_repo 为远程在网页上的位置，没有时不识别 #123。
+// 多条规则匹配到重叠的文字时，自定义规则优先，其次是 Jira 键
+pub fn parse_issue_references(message: &str, web_repo: Option<&RemoteWebRepo>, settings: &IssueLinkSettings) -> GitResult<Vec<IssueReference>> {
+    let mut patterns = Vec::new();
+    for pattern in &settings.patterns {
+        patterns.push((Regex::new(&pattern.pattern)?, pattern.url.as_str()));
+    }
+    let jira_url = settings.jira_url.as_deref().map(str::trim).filter(|url| !url.is_empty());
+
+    let mut references = Vec::new();
+    for (index, line) in message.lines().enumerate() {
+        let mut matches: Vec<LineMatch> = Vec::new();
+        for (regex, url) in &patterns {
+            for captures in regex.captures_iter(line) {
+                let whole = captures.get(0).unwrap();
+                if whole.is_empty() {
+                    continue;
+                }
+                let mut expanded = String::new();
+                captures.expand(url, &mut expanded);
+                matches.push((whole.range(), IssueReferenceKind::Custom, expanded));
+            }
+        }
+        if let Some(jira_url) = jira_url {
+            jira_matches(line, jira_url, &settings.jira_projects, &mut matches);
+        }
+        if let Some(web_repo) = web_repo {
+            forge_matches(line, web_repo, &mut matches);
+        }
+
+        // 按优先级依次加入，跳过和已加入的范围重叠的匹配
+        let mut accepted: Vec<LineMatch> = Vec::new();
+        for candidate in matches {
+            if !accepted.iter().any(|(range, _, _)| range.start < candidate.0.end && candidate.0.start < range.end) {
+                accepted.push(candidate);
+            }
+        }
+        accepted.sort_by_key(|(range, _, _)| range.start);
+        references.extend(accepted.into_iter().map(|(range, kind, url)| IssueReference {
+            kind,
+            text: line[range.clone()].to_string(),
+            url,
+            line: index,
+            start_column: utf16_len(&line[..range.start]),
+            end_column: utf16_len(&line[..range.end]),
+        }));
+    }
+    Ok(references)
+}
+
+// 平台由 preferred_remote 的地址决定
+pub fn get_issue_references(repo: &Repository, message: &str, settings: &IssueLinkSettings) -> GitResult<Vec<IssueReference>> {
+    let web_repo = match git::preferred_remote(repo)? {
+        Some(remote) => repo.find_remote(&remote)?.url().and_then(remote_url::parse),
+        None => None,
+    };
+    parse_issue_references(message, web_repo.as_ref(), settings)
+}
@@ -0,0 +1,174 @@
+// SSH 主机密钥的确认。fetch、push、克隆都由 git 命令行经 ssh 完成，没有终端回答
+// "Are you sure you want to continue connecting"，所以 ssh 以 StrictHostKeyChecking=yes 运行：
+// 主机密钥未知或已变化时直接失败，既不会挂起，也不会自动信任。失败后用 ssh-keyscan 取得服务器的密钥，
+// 以 GitError::HostKeyUnverified 交给前端；用户核对指纹并确认后由 accept_host_key 写入 ~/.ssh/known_hosts，再重试原来的操作
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use git2::{Config, Repository};
+
+use crate::error::{GitError, GitResult};
+use crate::signing;
+
+const KEYSCAN_TIMEOUT_SECS: &str = "10";
+
+// 网络操作使用的 ssh 命令，options 是额外的 ssh 参数。
+// 用户通过 GIT_SSH_COMMAND、GIT_SSH 或 core.sshCommand 自行指定了 ssh 时不覆盖，返回 None
+pub fn ssh_command(git_dir: Option<&Path>, options: &[&str]) -> Option<String> {
+    if env::var_os("GIT_SSH_COMMAND").is_some() || env::var_os("GIT_SSH").is_some() {
+        return None;
+    }
+    let config = match git_dir {
+        Some(git_dir) => Repository::open(git_dir).and_then(|repo| repo.config()),
+        None => Config::open_default(),
+    };
+    if config.and_then(|config| config.get_string("core.sshCommand")).is_ok() {
+        return None;
+    }
+    let mut command = vec!["ssh", "-o", "StrictHostKeyChecking=yes"];
+    command.extend_from_slice(options);
+    Some(command.join(" "))
+}
+
+// known_hosts 中的主机写法，非默认端口时为 [host]:port
+fn host_spec(host: &str, port: u16) -> String {
+    if port == 22 {
+        host.to_string()
+    } else {
+        format!("[{}]:{}", host, port)
+    }
+}
+
+fn parse_host_spec(spec: &str) -> (String, u16) {
+    spec.strip_prefix('[')
+        .and_then(|rest| rest.split_once("]:"))
+        .and_then(|(host, port)| Some((host.to_string(), port.parse().ok()?)))
+        .unwrap_or_else(|| (spec.to_string(), 22))
+}
+
+fn text_between<'a>(text: &'a str, start: &str, end: &str) -> Option<&'a str> {
+    let (_, rest) = text.split_once(start)?;
+    rest.split_once(end).map(|(value, _)| value)
+}
+
+// ssh-keyscan 取得的 "类型 base64" 及其 SHA256 指纹；key_type 为 ssh 的类型名（ed25519、rsa 等）
+fn scan_host_keys(host: &str, port: u16, key_type: Option<&str>) -> GitResult<Vec<(String, String)>> {
+    let port_arg = port.to_string();
+    let mut args = vec!["-T", KEYSCAN_TIMEOUT_SECS, "-p", &port_arg];
+    if let Some(key_type) = key_type {
+        args.extend(["-t", key_type]);
+    }
+    args.push(host);
+    let output = signing::run_with_input("ssh-keyscan", &args, &[])?;
+    let mut keys = Vec::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines().filter(|line| !line.starts_with('#')) {
+        let mut parts = line.split_whitespace().skip(1);
+        let (Some(key_type), Some(blob)) = (parts.next(), parts.next()) else {
+            continue;
+        };
+        let fingerprint = signing::run_with_input("ssh-keygen", &["-l", "-E", "sha256", "-f", "-"], format!("{}\n", line).as_bytes())?;
+        if let Some(fingerprint) = signing::ssh_fingerprint(&String::from_utf8_lossy(&fingerprint.stdout)) {
+            keys.push((format!("{} {}", key_type, blob), fingerprint));
+        }
+    }
+    Ok(keys)
+}
+
+// 把 ssh 因主机密钥未知或已变化而失败的输出转换为 HostKeyUnverified，并附上服务器当前密钥的指纹。
+// 其他失败或取不到服务器密钥时返回 None，由调用方照常报告命令失败
+pub fn host_key_error(stderr: &str) -> Option<GitError> {
+    if !stderr.contains("Host key verification failed") {
+        return None;
+    }
+    // 未知："No ED25519 host key is known for [host]:port and you have requested strict checking."
+    // 已变化："The fingerprint for the ED25519 key sent by the remote host is ..." 和 "Host key for host has changed and ..."
+    let (spec, key_type, changed) = match text_between(stderr, "Host key for ", " has changed") {
+        Some(spec) => (spec, text_between(stderr, "The fingerprint for the ", " key sent"), true),
+        None => (
+            text_between(stderr, "host key is known for ", " and you have")?,
+            text_between(stderr, "No ", " host key is known"),
+            false,
+        ),
+    };
+    let (host, port) = parse_host_spec(spec.trim());
+    let key_type = key_type.map(str::to_ascii_lowercase);
+    let (public_key, fingerprint) = scan_host_keys(&host, port, key_type.as_deref()).ok()?.into_iter().next()?;
+    Some(GitError::HostKeyUnverified {
+        host,
+        port,
+        key_type: public_key.split(' ').next().unwrap_or("").to_string(),
+        fingerprint,
+        changed,
+    })
+}
+
+fn user_known_hosts() -> GitResult<PathBuf> {
+    let path = signing::expand_home("~/.ssh/known_hosts");
+    if !path.is_absolute() {
+        return Err(GitError::not_found("home directory not found"));
+    }
+    Ok(path)
+}
+
+// 把用户确认过的主机密钥写入 ~/.ssh/known_hosts。重新取一次服务器的密钥，只有指纹和确认的一致时才写入；
+// 同类型的旧密钥（主机密钥已变化的情况）会先被删除
+pub fn accept_host_key(host: &str, port: u16, fingerprint: &str) -> GitResult<()> {
+    let host = host.trim();
+    if host.is_empty() || host.starts_with('-') || host.contains(char::is_whitespace) {
+        return Err(GitError::invalid(format!("invalid host: {}", host)));
+    }
+    let spec = host_spec(host, port);
+    let public_key = scan_host_keys(host, port, None)?
+        .into_iter()
+        .find(|(_, scanned)| scanned == fingerprint.trim())
+        .map(|(public_key, _)| public_key)
+        .ok_or_else(|| GitError::NetworkError {
+            message: format!("{} did not offer a host key with fingerprint {}", spec, fingerprint),
+        })?;
+    let key_type = public_key.split(' ').next().unwrap_or("");
+
+    let path = user_known_hosts()?;
+    let path_arg = path.to_string_lossy().to_string();
+    if path.is_file() {
+        // ssh-keygen -F 也能找到 HashKnownHosts 写入的散列条目
+        let output = signing::run_with_input("ssh-keygen", &["-F", &spec, "-f", &path_arg], &[])?;
+        let known = String::from_utf8_lossy(&output.stdout).to_string();
+        let known_keys: Vec<String> = known
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| {
+                let mut parts = line.split_whitespace().skip(1);
+                Some(format!("{} {}", parts.next()?, parts.next()?))
+            })
+            .collect();
+        if known_keys.contains(&public_key) {
+            return Ok(());
+        }
+        if known_keys.iter().any(|known| known.split(' ').next() == Some(key_type)) {
+            let output = signing::run_with_input("ssh-keygen", &["-R", &spec, "-f", &path_arg], &[])?;
+            if !output.status.success() {
+                return Err(GitError::Io {
+                    message: format!("failed to remove the old host key: {}", String::from_utf8_lossy(&output.stderr).trim()),
+                });
+            }
+        }
+    } else if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(dir, fs::Permissions::from_mode(0o700))?;
+        }
+    }
+
+    // 文件末尾可能没有换行
+    let needs_newline = fs::read(&path).map(|content| !content.is_empty() && !content.ends_with(b"\n")).unwrap_or(false);
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+    if needs_newline {
+        file.write_all(b"\n")?;
+    }
+    file.write_all(format!("{} {}\n", spec, public_key).as_bytes())?;
+    Ok(())
+}
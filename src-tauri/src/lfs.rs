@@ -0,0 +1,463 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use git2::{AttrCheckFlags, Repository};
+use serde::{Deserialize, Serialize};
+
+use crate::cancel::CancellationToken;
+use crate::disk_usage::SizeCache;
+use crate::error::{GitError, GitResult};
+use crate::git_cli;
+use crate::repo_cache::common_dir;
+
+// 指针文件格式参见 git-lfs 的 docs/spec.md；旧版本客户端写的是 hawser 的地址
+const POINTER_VERSIONS: [&str; 2] = ["https://git-lfs.github.com/spec/v1", "https://hawser.github.com/spec/v1"];
+const MAX_POINTER_SIZE: usize = 1024;
+const LFS_ATTRIBUTES: &str = "filter=lfs diff=lfs merge=lfs -text";
+pub const DEFAULT_LFS_CANDIDATE_THRESHOLD: u64 = 10 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitLfsPattern {
+    pub pattern: String,
+    // 模式所在的属性文件，相对于工作目录，例如 .gitattributes、assets/.gitattributes 或 .git/info/attributes
+    pub source: String,
+    pub lockable: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum GitLfsWorktreeState {
+    // 工作目录中是真实内容
+    CheckedOut,
+    // 工作目录中仍是指针文件，通常是对象没有下载或者 smudge 过滤器没有运行
+    Pointer,
+    // 工作目录中没有这个文件
+    Missing,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitLfsFile {
+    pub path: String,
+    // 指针中记录的 sha256
+    pub oid: String,
+    pub size_bytes: u64,
+    // 对象是否已下载到本地的 LFS 存储中
+    pub downloaded: bool,
+    pub worktree: GitLfsWorktreeState,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitLfsFiles {
+    pub patterns: Vec<GitLfsPattern>,
+    // 索引中按 LFS 过滤器存储的文件，按路径排序
+    pub files: Vec<GitLfsFile>,
+    pub total_size_bytes: u64,
+    pub missing_objects: usize,
+    pub missing_size_bytes: u64,
+}
+
+pub struct LfsPointer {
+    pub oid: String,
+    pub size: u64,
+}
+
+// 指针文件每行一个 "键 值"，version 必须是第一行
+pub fn parse_pointer(content: &[u8]) -> Option<LfsPointer> {
+    if content.len() > MAX_POINTER_SIZE {
+        return None;
+    }
+    let content = std::str::from_utf8(content).ok()?;
+    let mut lines = content.lines();
+    let version = lines.next()?.strip_prefix("version ")?;
+    if !POINTER_VERSIONS.contains(&version) {
+        return None;
+    }
+    let (mut oid, mut size) = (None, None);
+    for line in lines {
+        match line.split_once(' ') {
+            Some(("oid", value)) => {
+                let hex = value.strip_prefix("sha256:")?;
+                if hex.len() != 64 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+                    return None;
+                }
+                oid = Some(hex.to_ascii_lowercase());
+            }
+            Some(("size", value)) => size = Some(value.parse().ok()?),
+            Some(_) => {}
+            None if line.is_empty() => {}
+            None => return None,
+        }
+    }
+    Some(LfsPointer { oid: oid?, size: size? })
+}
+
+// LFS 对象存放在 <lfs.storage>/objects/ab/cd/abcd...，lfs.storage 默认为主 .git 目录下的 lfs
+pub fn storage_dir(repo: &Repository) -> PathBuf {
+    let git_dir = common_dir(repo.path());
+    match repo.config().and_then(|config| config.get_path("lfs.storage")) {
+        Ok(path) if path.is_absolute() => path,
+        Ok(path) => git_dir.join(path),
+        Err(_) => git_dir.join("lfs"),
+    }
+}
+
+pub fn object_path(storage: &Path, oid: &str) -> PathBuf {
+    storage.join("objects").join(&oid[0..2]).join(&oid[2..4]).join(oid)
+}
+
+pub fn is_lfs_path(repo: &Repository, path: &str) -> GitResult<bool> {
+    let value = repo.get_attr(Path::new(path), "filter", AttrCheckFlags::FILE_THEN_INDEX)?;
+    Ok(value == Some("lfs"))
+}
+
+// 一行属性：第一个字段是模式（可以用双引号包起来），之后是属性
+fn parse_attributes_line(line: &str) -> Option<(String, Vec<&str>)> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let (pattern, rest) = match line.strip_prefix('"') {
+        Some(quoted) => {
+            let end = quoted.find('"')?;
+            (quoted[..end].to_string(), &quoted[end + 1..])
+        }
+        None => match line.split_once(char::is_whitespace) {
+            Some((pattern, rest)) => (pattern.to_string(), rest),
+            None => (line.to_string(), ""),
+        },
+    };
+    Some((pattern, rest.split_whitespace().collect()))
+}
+
+fn lfs_patterns(content: &str, source: &str) -> Vec<GitLfsPattern> {
+    content
+        .lines()
+        .filter_map(parse_attributes_line)
+        .filter(|(_, attributes)| attributes.contains(&"filter=lfs"))
+        .map(|(pattern, attributes)| GitLfsPattern {
+            pattern,
+            source: source.to_string(),
+            lockable: attributes.contains(&"lockable"),
+        })
+        .collect()
+}
+
+// 工作目录中所有 .gitattributes（取自索引，工作目录中的版本优先）和 .git/info/attributes 里的 LFS 模式
+fn collect_patterns(repo: &Repository, workdir: &Path, index: &git2::Index) -> GitResult<Vec<GitLfsPattern>> {
+    let mut patterns = Vec::new();
+    for entry in index.iter() {
+        let path = String::from_utf8_lossy(&entry.path).to_string();
+        if path != ".gitattributes" && !path.ends_with("/.gitattributes") {
+            continue;
+        }
+        let content = match fs::read(workdir.join(&path)) {
+            Ok(content) => content,
+            Err(_) => repo.find_blob(entry.id)?.content().to_vec(),
+        };
+        patterns.extend(lfs_patterns(&String::from_utf8_lossy(&content), &path));
+    }
+    if let Ok(content) = fs::read_to_string(common_dir(repo.path()).join("info").join("attributes")) {
+        patterns.extend(lfs_patterns(&content, ".git/info/attributes"));
+    }
+    Ok(patterns)
+}
+
+fn worktree_state(path: &Path) -> GitLfsWorktreeState {
+    match fs::symlink_metadata(path) {
+        Err(_) => GitLfsWorktreeState::Missing,
+        Ok(metadata) if metadata.len() as usize <= MAX_POINTER_SIZE => match fs::read(path) {
+            Ok(content) if parse_pointer(&content).is_some() => GitLfsWorktreeState::Pointer,
+            _ => GitLfsWorktreeState::CheckedOut,
+        },
+        Ok(_) => GitLfsWorktreeState::CheckedOut,
+    }
+}
+
+// 相当于 git lfs ls-files 加上对象是否已下载；索引中内容不是指针的文件（例如启用 LFS 之前提交的）不列出
+pub fn get_lfs_files(repo: &Repository, cancel: &CancellationToken) -> GitResult<GitLfsFiles> {
+    let workdir = repo.workdir().ok_or(GitError::BareRepository)?;
+    let index = repo.index()?;
+    let odb = repo.odb()?;
+    let storage = storage_dir(repo);
+    let mut result = GitLfsFiles {
+        patterns: collect_patterns(repo, workdir, &index)?,
+        files: Vec::new(),
+        total_size_bytes: 0,
+        missing_objects: 0,
+        missing_size_bytes: 0,
+    };
+
+    for (i, entry) in index.iter().enumerate() {
+        if i % 1024 == 0 {
+            cancel.check()?;
+        }
+        // 只看未冲突的条目
+        if (entry.flags >> 12) & 0x3 != 0 {
+            continue;
+        }
+        let path = String::from_utf8_lossy(&entry.path).to_string();
+        if !is_lfs_path(repo, &path)? {
+            continue;
+        }
+        match odb.read_header(entry.id) {
+            Ok((size, _)) if size <= MAX_POINTER_SIZE => {}
+            _ => continue,
+        }
+        let pointer = match parse_pointer(repo.find_blob(entry.id)?.content()) {
+            Some(pointer) => pointer,
+            None => continue,
+        };
+        let downloaded = fs::metadata(object_path(&storage, &pointer.oid)).is_ok_and(|metadata| metadata.len() == pointer.size);
+        result.total_size_bytes += pointer.size;
+        if !downloaded {
+            result.missing_objects += 1;
+            result.missing_size_bytes += pointer.size;
+        }
+        result.files.push(GitLfsFile {
+            worktree: worktree_state(&workdir.join(&path)),
+            path,
+            oid: pointer.oid,
+            size_bytes: pointer.size,
+            downloaded,
+        });
+    }
+    Ok(result)
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitLfsCommandResult {
+    // 本地 LFS 存储（包括未完成的下载）在命令前后的大小
+    pub storage_size_before: u64,
+    pub storage_size_after: u64,
+    pub output: String,
+}
+
+pub const LFS_PROGRESS_EVENT: &str = "lfs-progress";
+
+fn storage_size(repo: &Repository, sizes: &SizeCache, cancel: &CancellationToken) -> GitResult<u64> {
+    let storage = storage_dir(repo);
+    if !storage.is_dir() {
+        return Ok(0);
+    }
+    Ok(sizes.scan(&storage, None, false, cancel, &|_, _| {})?.total_bytes)
+}
+
+fn run_lfs(
+    repo: &Repository,
+    args: &[&str],
+    sizes: &SizeCache,
+    cancel: &CancellationToken,
+    on_progress: impl FnMut(&str),
+) -> GitResult<GitLfsCommandResult> {
+    let storage_size_before = storage_size(repo, sizes, cancel)?;
+    let output = git_cli::run_git_with_progress(repo.path(), repo.workdir(), args, cancel, on_progress)
+        .map_err(|e| match e {
+            GitError::CommandFailed { ref message, .. } if message.contains("'lfs' is not a git command") => GitError::Unsupported {
+                message: "Git LFS is not installed".to_string(),
+            },
+            e => e,
+        })?;
+    Ok(GitLfsCommandResult {
+        storage_size_before,
+        storage_size_after: storage_size(repo, sizes, cancel)?,
+        output: [output.stdout.trim(), output.stderr.trim()]
+            .into_iter()
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n"),
+    })
+}
+
+// 远程名必须是已配置的远程，避免被当成 git lfs 的选项
+fn lfs_remote_args<'a>(repo: &Repository, args: &mut Vec<&'a str>, remote: Option<&'a str>) -> GitResult<()> {
+    if let Some(remote) = remote {
+        repo.find_remote(remote)?;
+        args.push(remote);
+    }
+    Ok(())
+}
+
+// 只下载对象到本地存储，不改动工作目录；all 时下载所有引用历史中的对象，否则只下载当前检出需要的
+pub fn lfs_fetch(
+    repo: &Repository,
+    remote: Option<&str>,
+    all: bool,
+    sizes: &SizeCache,
+    cancel: &CancellationToken,
+    on_progress: impl FnMut(&str),
+) -> GitResult<GitLfsCommandResult> {
+    let mut args = vec!["lfs", "fetch"];
+    if all {
+        args.push("--all");
+    }
+    lfs_remote_args(repo, &mut args, remote)?;
+    run_lfs(repo, &args, sizes, cancel, on_progress)
+}
+
+// 下载当前检出需要的对象，并把工作目录中的指针文件替换为真实内容
+pub fn lfs_pull(
+    repo: &Repository,
+    remote: Option<&str>,
+    sizes: &SizeCache,
+    cancel: &CancellationToken,
+    on_progress: impl FnMut(&str),
+) -> GitResult<GitLfsCommandResult> {
+    repo.workdir().ok_or(GitError::BareRepository)?;
+    let mut args = vec!["lfs", "pull"];
+    lfs_remote_args(repo, &mut args, remote)?;
+    run_lfs(repo, &args, sizes, cancel, on_progress)
+}
+
+// 删除本地存储中当前检出和最近引用都不需要的对象；verify_remote 时只删除确认远程已有的对象
+pub fn lfs_prune(
+    repo: &Repository,
+    verify_remote: bool,
+    dry_run: bool,
+    sizes: &SizeCache,
+    cancel: &CancellationToken,
+    on_progress: impl FnMut(&str),
+) -> GitResult<GitLfsCommandResult> {
+    let mut args = vec!["lfs", "prune"];
+    if verify_remote {
+        args.push("--verify-remote");
+    }
+    if dry_run {
+        args.push("--dry-run");
+    }
+    run_lfs(repo, &args, sizes, cancel, on_progress)
+}
+
+// 和 git lfs track 一样，模式中的空格写成 [[:space:]]，其它字符原样写入
+fn escape_pattern(pattern: &str) -> GitResult<String> {
+    let pattern = pattern.trim();
+    if pattern.is_empty() || pattern.contains(['\n', '\r']) || pattern.starts_with('#') || pattern.starts_with('!') {
+        return Err(GitError::invalid(format!("invalid LFS pattern: {}", pattern)));
+    }
+    Ok(pattern.replace(' ', "[[:space:]]"))
+}
+
+fn same_pattern(written: &str, escaped: &str) -> bool {
+    written == escaped || written.replace(' ', "[[:space:]]") == escaped
+}
+
+// 修改工作目录根部的 .gitattributes 并暂存；保留原有的换行符风格
+fn update_root_attributes(repo: &Repository, edit: impl FnOnce(Vec<&str>) -> Vec<String>) -> GitResult<Vec<GitLfsPattern>> {
+    let workdir = repo.workdir().ok_or(GitError::BareRepository)?;
+    let path = workdir.join(".gitattributes");
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let newline = if content.contains("\r\n") { "\r\n" } else { "\n" };
+    let lines = edit(content.lines().collect());
+    let mut updated = lines.join(newline);
+    if !updated.is_empty() {
+        updated.push_str(newline);
+    }
+    if updated != content {
+        fs::write(&path, updated)?;
+    }
+
+    let mut index = repo.index()?;
+    index.add_path(Path::new(".gitattributes"))?;
+    index.write()?;
+    collect_patterns(repo, workdir, &index)
+}
+
+// 把模式加入 .gitattributes，已经按 LFS 跟踪时只补上 lockable；相当于 git lfs track [--lockable]
+pub fn lfs_track(repo: &Repository, pattern: &str, lockable: bool) -> GitResult<Vec<GitLfsPattern>> {
+    let escaped = escape_pattern(pattern)?;
+    update_root_attributes(repo, |lines| {
+        let mut tracked = false;
+        let mut lines: Vec<String> = lines
+            .into_iter()
+            .map(|line| match parse_attributes_line(line) {
+                Some((written, attributes)) if same_pattern(&written, &escaped) && attributes.contains(&"filter=lfs") => {
+                    tracked = true;
+                    if lockable && !attributes.contains(&"lockable") {
+                        format!("{} lockable", line.trim_end())
+                    } else {
+                        line.to_string()
+                    }
+                }
+                _ => line.to_string(),
+            })
+            .collect();
+        if !tracked {
+            let lockable = if lockable { " lockable" } else { "" };
+            lines.push(format!("{} {}{}", escaped, LFS_ATTRIBUTES, lockable));
+        }
+        lines
+    })
+}
+
+// 从 .gitattributes 中删除该模式的 LFS 规则；已经以指针形式提交的文件不会被转换回来
+pub fn lfs_untrack(repo: &Repository, pattern: &str) -> GitResult<Vec<GitLfsPattern>> {
+    let escaped = escape_pattern(pattern)?;
+    update_root_attributes(repo, |lines| {
+        lines
+            .into_iter()
+            .filter(|line| match parse_attributes_line(line) {
+                Some((written, attributes)) => !(same_pattern(&written, &escaped) && attributes.contains(&"filter=lfs")),
+                None => true,
+            })
+            .map(str::to_string)
+            .collect()
+    })
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitLfsCandidate {
+    pub path: String,
+    pub size_bytes: u64,
+    // 已经匹配 LFS 模式但按普通文件提交（例如在添加模式之前提交的），需要重新暂存或迁移历史
+    pub matches_lfs_pattern: bool,
+    // 建议加入 LFS 的模式：有扩展名时为 *.ext，否则为文件路径
+    pub suggested_pattern: String,
+}
+
+fn suggested_pattern(path: &str) -> String {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() && !extension.is_empty() => format!("*.{}", extension),
+        _ => path.to_string(),
+    }
+}
+
+// 索引中不小于 threshold_bytes、又没有以 LFS 指针存储的文件，按大小从大到小排列
+pub fn get_lfs_candidates(repo: &Repository, threshold_bytes: u64, cancel: &CancellationToken) -> GitResult<Vec<GitLfsCandidate>> {
+    let index = repo.index()?;
+    let odb = repo.odb()?;
+    let mut candidates = Vec::new();
+    for (i, entry) in index.iter().enumerate() {
+        if i % 1024 == 0 {
+            cancel.check()?;
+        }
+        if (entry.flags >> 12) & 0x3 != 0 {
+            continue;
+        }
+        // 索引中记录的是工作目录文件的大小（只有 32 位，LFS 文件检出后也是真实大小），以对象库中 blob 的大小为准
+        let size = match odb.read_header(entry.id) {
+            Ok((size, git2::ObjectType::Blob)) => size as u64,
+            _ => continue,
+        };
+        if size < threshold_bytes {
+            continue;
+        }
+        let path = String::from_utf8_lossy(&entry.path).to_string();
+        candidates.push(GitLfsCandidate {
+            matches_lfs_pattern: is_lfs_path(repo, &path)?,
+            suggested_pattern: suggested_pattern(&path),
+            path,
+            size_bytes: size,
+        });
+    }
+    candidates.sort_by(|a, b| b.size_bytes.cmp(&a.size_bytes).then_with(|| a.path.cmp(&b.path)));
+    Ok(candidates)
+}
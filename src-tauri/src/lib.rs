@@ -1,23 +1,216 @@
+mod archive;
+mod auto_fetch;
+mod autostash;
+mod bisect;
+mod bundle;
+mod cancel;
+mod commit_graph;
+mod commit_message;
+mod disk_usage;
+mod drafts;
+mod error;
+mod external;
+mod forge;
 mod git;
+mod git_cli;
+mod hooks;
+mod init;
+mod issue_refs;
+mod known_hosts;
+mod lfs;
+mod mailbox;
+mod maintenance;
+mod mbox;
+mod mergetool;
+mod pack;
+mod remote_url;
+mod repo_cache;
+mod repos;
+mod rerere;
+mod sessions;
+mod signing;
+mod undo;
+mod watcher;
+mod word_diff;
 use std::process::Command;
 
+use auto_fetch::AutoFetchers;
+use cancel::OperationRegistry;
+use disk_usage::SizeCache;
+use drafts::CommitDrafts;
+use error::GitError;
+use external::ExternalTools;
+use git2::Repository;
+use repos::RecentRepos;
+use sessions::RepoSessions;
+use tauri::{AppHandle, Emitter, Manager, State};
+use watcher::RepoWatchers;
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
   tauri::Builder::default()
     .plugin(tauri_plugin_fs::init())
+    .manage(OperationRegistry::default())
+    .manage(RepoSessions::default())
+    .manage(RepoWatchers::default())
+    .manage(AutoFetchers::default())
+    .manage(SizeCache::default())
     .invoke_handler(tauri::generate_handler![
       select_folder,
+      open_repo,
+      list_init_templates,
+      init_repo,
+      clone_repo,
+      close_repo,
+      list_open_repos,
       get_branches,
+      get_branch_tracking_overview,
+      delete_merged_branches,
       get_remotes,
+      add_remote,
+      validate_remote_url,
+      list_remote_refs,
+      test_network_connectivity,
+      get_forge_status,
+      accept_host_key,
+      remove_remote,
+      rename_remote,
+      set_remote_url,
+      fetch_unshallow,
+      push_branch,
+      get_create_pr_url,
+      delete_remote_branch,
+      rename_branch_with_remote,
+      prune_remote,
+      get_remote_web_url,
       get_commits,
+      compare_refs,
+      get_merge_base,
+      get_octopus_merge_base,
+      preview_merge,
+      search_commits,
+      stream_commits,
+      get_author_avatar_hashes,
+      get_repo_stats,
+      get_file_churn,
+      get_commit_graph,
       get_commit_changes,
+      get_commit_details,
       get_commit_file_diff,
+      get_tree,
+      read_blob,
+      get_conflict_file,
+      restore_conflict_markers,
+      launch_external_difftool,
+      launch_external_mergetool,
+      get_commit_file_patch,
+      get_workdir_file_diff,
+      get_workdir_file_patch,
+      get_blame,
+      export_archive,
+      export_patches,
+      apply_patch,
+      import_patch_from_file,
+      apply_mailbox,
+      mailbox_continue,
+      mailbox_skip,
+      mailbox_abort,
+      create_bundle,
+      get_bundle_info,
+      fetch_bundle,
       get_repo_info,
+      get_largest_blobs,
+      get_size_breakdown,
+      run_gc,
+      repack,
+      check_repo_integrity,
+      get_object_stats,
+      get_commit_graph_status,
+      write_commit_graph,
+      get_lfs_files,
+      lfs_fetch,
+      lfs_pull,
+      lfs_prune,
+      lfs_track,
+      lfs_untrack,
+      get_lfs_candidates,
       get_status,
+      get_status_fast,
+      add_to_gitignore,
+      ignore_path,
+      get_ignored_status,
       checkout_branch,
-      get_worktrees
+      checkout_commit,
+      checkout_tag,
+      create_branch_here,
+      checkout_file_at,
+      move_tracked_file,
+      remove_tracked_file,
+      get_head_state,
+      get_operation_status,
+      get_worktrees,
+      get_config,
+      set_config,
+      get_config_value,
+      set_config_value,
+      get_repo_identity,
+      set_repo_identity,
+      add_worktree,
+      remove_worktree,
+      prune_worktrees,
+      get_sparse_checkout,
+      set_sparse_checkout,
+      get_bisect_status,
+      bisect_start,
+      bisect_mark,
+      bisect_reset,
+      get_rerere_status,
+      rerere_apply,
+      rerere_forget,
+      get_reflog,
+      checkout_reflog_entry,
+      create_branch_from_reflog,
+      get_submodules,
+      init_submodule,
+      update_submodule,
+      reset,
+      get_undo_preview,
+      undo_last_operation,
+      uncommit,
+      create_commit,
+      get_commit_template,
+      save_commit_draft,
+      load_commit_draft,
+      validate_commit_message,
+      get_issue_references,
+      get_hooks,
+      set_hook_enabled,
+      create_tag,
+      verify_commit_signature,
+      verify_tag_signature,
+      list_signing_keys,
+      get_commit_notes,
+      set_commit_note,
+      watch_repo,
+      unwatch_repo,
+      get_auto_fetch,
+      set_auto_fetch,
+      list_recent_repos,
+      add_recent_repo,
+      remove_recent_repo,
+      set_recent_repo_pinned,
+      get_external_tools,
+      set_external_tools,
+      open_in_editor,
+      open_in_terminal,
+      reveal_in_file_manager,
+      cancel_operation
     ])
     .setup(|app| {
+      let data_dir = app.path().app_data_dir()?;
+      app.manage(RecentRepos::load(&data_dir));
+      app.manage(ExternalTools::load(&data_dir));
+      app.manage(CommitDrafts::load(&data_dir));
       if cfg!(debug_assertions) {
         app.handle().plugin(
           tauri_plugin_log::Builder::default()
@@ -31,19 +224,60 @@ pub fn run() {
     .expect("error while running tauri application");
 }
 
+// libgit2 的调用都是同步阻塞的，放到阻塞线程池里执行，避免一个慢命令卡住其它命令
+async fn run_blocking<T, F>(task: F) -> Result<T, GitError>
+where
+    F: FnOnce() -> Result<T, GitError> + Send + 'static,
+    T: Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(task)
+        .await
+        .map_err(|e| GitError::Internal { message: e.to_string() })?
+}
+
+// 只读命令：按句柄找到仓库，复用缓存中的仓库对象
+async fn with_repo<T, F>(sessions: &RepoSessions, repo_id: String, task: F) -> Result<T, GitError>
+where
+    F: FnOnce(&Repository) -> Result<T, GitError> + Send + 'static,
+    T: Send + 'static,
+{
+    let sessions = sessions.clone();
+    run_blocking(move || sessions.with_repo(&repo_id, task)).await
+}
+
+// 写命令：先取得仓库的写锁（已有写操作时返回 operationInProgress），
+// 结束后无论成功与否都让该仓库的缓存失效，后续命令重新打开
+async fn with_repo_mut<T, F>(sessions: &RepoSessions, repo_id: String, operation: &'static str, task: F) -> Result<T, GitError>
+where
+    F: FnOnce(&Repository) -> Result<T, GitError> + Send + 'static,
+    T: Send + 'static,
+{
+    let write_guard = sessions.lock_for_write(&repo_id, operation)?;
+    let sessions = sessions.clone();
+    run_blocking(move || {
+        let _write_guard = write_guard;
+        let result = sessions.with_repo(&repo_id, task);
+        sessions.invalidate(&repo_id);
+        result
+    })
+    .await
+}
+
 #[tauri::command]
-async fn select_folder() -> Result<Option<String>, String> {
+async fn select_folder() -> Result<Option<String>, GitError> {
     #[cfg(target_os = "macos")]
     {
         let output = Command::new("osascript")
             .arg("-e")
             .arg("POSIX path of (choose folder with prompt \"Select Git Repository\")")
             .output()
-            .map_err(|e| format!("failed to open folder dialog: {}", e))?;
+            .map_err(|e| GitError::Io {
+                message: format!("failed to open folder dialog: {}", e),
+            })?;
 
         if output.status.success() {
             let path = String::from_utf8(output.stdout)
-                .map_err(|e| format!("invalid dialog output: {}", e))?
+                .map_err(|e| GitError::invalid(format!("invalid dialog output: {}", e)))?
                 .trim()
                 .to_string();
             if path.is_empty() {
@@ -58,56 +292,1549 @@ async fn select_folder() -> Result<Option<String>, String> {
             return Ok(None);
         }
 
-        Err(format!("folder dialog failed: {}", stderr))
+        Err(GitError::Io {
+            message: format!("folder dialog failed: {}", stderr),
+        })
     }
 
     #[cfg(not(target_os = "macos"))]
     {
-        Err("select_folder is only implemented on macOS".to_string())
+        Err(GitError::Unsupported {
+            message: "select_folder is only implemented on macOS".to_string(),
+        })
     }
 }
 
+// 打开仓库并返回句柄，之后的命令都用句柄 repoId 指定仓库
+#[tauri::command]
+async fn open_repo(sessions: State<'_, RepoSessions>, repo_path: String) -> Result<sessions::RepoSession, GitError> {
+    let sessions = sessions.inner().clone();
+    run_blocking(move || sessions.open(&repo_path)).await
+}
+
+#[tauri::command]
+fn list_init_templates() -> init::GitInitTemplates {
+    init::list_init_templates()
+}
+
+// 新建仓库并像 open_repo 一样返回句柄
+#[tauri::command]
+async fn init_repo(sessions: State<'_, RepoSessions>, repo_path: String, options: Option<init::GitInitOptions>) -> Result<init::GitInitResult, GitError> {
+    let sessions = sessions.inner().clone();
+    run_blocking(move || init::init_repo(&sessions, &repo_path, &options.unwrap_or_default())).await
+}
+
+// 克隆完成后直接打开新仓库，返回它的句柄
+#[tauri::command]
+async fn clone_repo(
+    app: AppHandle,
+    operations: State<'_, OperationRegistry>,
+    sessions: State<'_, RepoSessions>,
+    url: String,
+    repo_path: String,
+    options: Option<git::GitCloneOptions>,
+    operation_id: Option<String>,
+) -> Result<sessions::RepoSession, GitError> {
+    let operation = operations.register(operation_id.as_deref());
+    let sessions = sessions.inner().clone();
+    run_blocking(move || {
+        let on_progress = command_progress_emitter(app, git::CLONE_PROGRESS_EVENT, operation_id);
+        git::clone_repo(&url, &repo_path, &options.unwrap_or_default(), operation.token(), on_progress)?;
+        sessions.open(&repo_path)
+    })
+    .await
+}
+
+#[tauri::command]
+fn close_repo(
+    sessions: State<'_, RepoSessions>,
+    watchers: State<'_, RepoWatchers>,
+    auto_fetchers: State<'_, AutoFetchers>,
+    repo_id: String,
+) -> bool {
+    watchers.unwatch(&repo_id);
+    auto_fetchers.stop(&repo_id);
+    sessions.close(&repo_id)
+}
+
+#[tauri::command]
+fn list_open_repos(sessions: State<'_, RepoSessions>) -> Vec<sessions::RepoSession> {
+    sessions.list()
+}
+
+#[tauri::command]
+async fn get_branches(sessions: State<'_, RepoSessions>, repo_id: String) -> Result<Vec<git::GitBranch>, GitError> {
+    with_repo(&sessions, repo_id, git::get_branches).await
+}
+
+#[tauri::command]
+async fn get_branch_tracking_overview(sessions: State<'_, RepoSessions>, repo_id: String) -> Result<Vec<git::GitBranchTracking>, GitError> {
+    with_repo(&sessions, repo_id, git::get_branch_tracking_overview).await
+}
+
+#[tauri::command]
+async fn delete_merged_branches(
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    target_ref: String,
+    dry_run: Option<bool>,
+) -> Result<git::GitMergedBranchCleanup, GitError> {
+    with_repo_mut(&sessions, repo_id, "delete_merged_branches", move |repo| {
+        git::delete_merged_branches(repo, &target_ref, dry_run.unwrap_or(false))
+    })
+    .await
+}
+
+#[tauri::command]
+async fn get_remotes(sessions: State<'_, RepoSessions>, repo_id: String) -> Result<Vec<git::GitRemote>, GitError> {
+    with_repo(&sessions, repo_id, git::get_remotes).await
+}
+
+#[tauri::command]
+async fn add_remote(sessions: State<'_, RepoSessions>, repo_id: String, name: String, url: String) -> Result<git::GitRemote, GitError> {
+    with_repo_mut(&sessions, repo_id, "add_remote", move |repo| git::add_remote(repo, &name, &url)).await
+}
+
+// 克隆对话框在开始之前检查地址，probe 时实际连接远程（可以用 operation_id 取消）
+#[tauri::command]
+async fn validate_remote_url(
+    operations: State<'_, OperationRegistry>,
+    url: String,
+    probe: Option<bool>,
+    operation_id: Option<String>,
+) -> Result<git::GitRemoteUrlCheck, GitError> {
+    let operation = operations.register(operation_id.as_deref());
+    run_blocking(move || git::validate_remote_url(&url, probe.unwrap_or(false), operation.token())).await
+}
+
+// 有 repo_id 时 remote 可以是该仓库的远程名称（使用仓库的配置和凭据），否则必须是地址
+#[tauri::command]
+async fn list_remote_refs(
+    operations: State<'_, OperationRegistry>,
+    sessions: State<'_, RepoSessions>,
+    repo_id: Option<String>,
+    remote: String,
+    operation_id: Option<String>,
+) -> Result<git::GitRemoteRefs, GitError> {
+    let operation = operations.register(operation_id.as_deref());
+    match repo_id {
+        Some(repo_id) => with_repo(&sessions, repo_id, move |repo| git::list_remote_refs(repo, &remote, operation.token())).await,
+        None => run_blocking(move || git::list_remote_refs_for_url(&remote, operation.token())).await,
+    }
+}
+
+#[tauri::command]
+async fn test_network_connectivity(
+    operations: State<'_, OperationRegistry>,
+    sessions: State<'_, RepoSessions>,
+    repo_id: Option<String>,
+    remote: String,
+    operation_id: Option<String>,
+) -> Result<git::GitConnectivityReport, GitError> {
+    let operation = operations.register(operation_id.as_deref());
+    match repo_id {
+        Some(repo_id) => with_repo(&sessions, repo_id, move |repo| git::test_network_connectivity(Some(repo), &remote, operation.token())).await,
+        None => run_blocking(move || git::test_network_connectivity(None, &remote, operation.token())).await,
+    }
+}
+
+// 分支标题旁的 PR / CI 指示器；远程不是 GitHub / GitLab 时返回 null
+#[tauri::command]
+async fn get_forge_status(sessions: State<'_, RepoSessions>, repo_id: String) -> Result<Option<forge::GitForgeStatus>, GitError> {
+    with_repo(&sessions, repo_id, forge::get_forge_status).await
+}
+
+// 用户核对 hostKeyUnverified 错误中的指纹后调用，之后重试原来的操作
+#[tauri::command]
+async fn accept_host_key(host: String, port: Option<u16>, fingerprint: String) -> Result<(), GitError> {
+    run_blocking(move || known_hosts::accept_host_key(&host, port.unwrap_or(22), &fingerprint)).await
+}
+
+#[tauri::command]
+async fn remove_remote(sessions: State<'_, RepoSessions>, repo_id: String, name: String) -> Result<(), GitError> {
+    with_repo_mut(&sessions, repo_id, "remove_remote", move |repo| git::remove_remote(repo, &name)).await
+}
+
+#[tauri::command]
+async fn rename_remote(sessions: State<'_, RepoSessions>, repo_id: String, old_name: String, new_name: String) -> Result<Vec<String>, GitError> {
+    with_repo_mut(&sessions, repo_id, "rename_remote", move |repo| git::rename_remote(repo, &old_name, &new_name)).await
+}
+
+#[tauri::command]
+async fn set_remote_url(sessions: State<'_, RepoSessions>, repo_id: String, name: String, url: String, push: Option<bool>) -> Result<(), GitError> {
+    with_repo_mut(&sessions, repo_id, "set_remote_url", move |repo| git::set_remote_url(repo, &name, &url, push.unwrap_or(false))).await
+}
+
+#[tauri::command]
+async fn fetch_unshallow(
+    app: AppHandle,
+    operations: State<'_, OperationRegistry>,
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    remote: Option<String>,
+    deepen: Option<u32>,
+    operation_id: Option<String>,
+) -> Result<bool, GitError> {
+    let operation = operations.register(operation_id.as_deref());
+    with_repo_mut(&sessions, repo_id, "fetch_unshallow", move |repo| {
+        let on_progress = command_progress_emitter(app, git::FETCH_PROGRESS_EVENT, operation_id);
+        git::fetch_unshallow(repo, remote.as_deref(), deepen, operation.token(), on_progress)
+    })
+    .await
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn push_branch(
+    app: AppHandle,
+    operations: State<'_, OperationRegistry>,
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    remote: Option<String>,
+    branch: Option<String>,
+    force_with_lease: Option<bool>,
+    expected: Option<String>,
+    operation_id: Option<String>,
+) -> Result<git::GitPushResult, GitError> {
+    let operation = operations.register(operation_id.as_deref());
+    with_repo_mut(&sessions, repo_id, "push_branch", move |repo| {
+        let on_progress = command_progress_emitter(app, git::PUSH_PROGRESS_EVENT, operation_id);
+        git::push_branch(repo, remote.as_deref(), branch.as_deref(), force_with_lease.unwrap_or(false), expected.as_deref(), operation.token(), on_progress)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn get_create_pr_url(
+    app: AppHandle,
+    operations: State<'_, OperationRegistry>,
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    base: Option<String>,
+    head: Option<String>,
+    operation_id: Option<String>,
+) -> Result<git::GitCreatePrUrl, GitError> {
+    let operation = operations.register(operation_id.as_deref());
+    with_repo_mut(&sessions, repo_id, "get_create_pr_url", move |repo| {
+        let on_progress = command_progress_emitter(app, git::PUSH_PROGRESS_EVENT, operation_id);
+        git::get_create_pr_url(repo, base.as_deref(), head.as_deref(), operation.token(), on_progress)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn delete_remote_branch(sessions: State<'_, RepoSessions>, repo_id: String, remote: String, branch: String) -> Result<(), GitError> {
+    with_repo_mut(&sessions, repo_id, "delete_remote_branch", move |repo| git::delete_remote_branch(repo, &remote, &branch)).await
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn rename_branch_with_remote(
+    app: AppHandle,
+    operations: State<'_, OperationRegistry>,
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    old_name: String,
+    new_name: String,
+    push: Option<bool>,
+    delete_old_remote: Option<bool>,
+    operation_id: Option<String>,
+) -> Result<git::GitBranchRename, GitError> {
+    let operation = operations.register(operation_id.as_deref());
+    with_repo_mut(&sessions, repo_id, "rename_branch_with_remote", move |repo| {
+        let on_progress = command_progress_emitter(app, git::PUSH_PROGRESS_EVENT, operation_id);
+        git::rename_branch_with_remote(
+            repo,
+            &old_name,
+            &new_name,
+            push.unwrap_or(false),
+            delete_old_remote.unwrap_or(false),
+            operation.token(),
+            on_progress,
+        )
+    })
+    .await
+}
+
+#[tauri::command]
+async fn prune_remote(sessions: State<'_, RepoSessions>, repo_id: String, remote: String, dry_run: Option<bool>) -> Result<Vec<String>, GitError> {
+    with_repo_mut(&sessions, repo_id, "prune_remote", move |repo| git::prune_remote(repo, &remote, dry_run.unwrap_or(false))).await
+}
+
+// 把提交、文件、分支或比较页面映射为 GitHub / GitLab / Bitbucket / Gitea 上的网页地址
+#[tauri::command]
+async fn get_remote_web_url(
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    remote: Option<String>,
+    target: remote_url::RemoteWebTarget,
+) -> Result<String, GitError> {
+    with_repo(&sessions, repo_id, move |repo| git::get_remote_web_url(repo, remote.as_deref(), &target)).await
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn get_commits(
+    operations: State<'_, OperationRegistry>,
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    rev: Option<String>,
+    cursor: Option<String>,
+    limit: Option<usize>,
+    filter: Option<git::GitCommitFilter>,
+    mailmap: Option<git::MailmapMode>,
+    operation_id: Option<String>,
+) -> Result<git::GitCommitPage, GitError> {
+    let limit = limit.unwrap_or(git::DEFAULT_COMMIT_PAGE_SIZE);
+    let filter = filter.unwrap_or_default();
+    let operation = operations.register(operation_id.as_deref());
+    with_repo(&sessions, repo_id, move |repo| {
+        git::get_commits(repo, rev.as_deref(), cursor.as_deref(), limit, &filter, mailmap.unwrap_or_default(), operation.token())
+    })
+    .await
+}
+
+// 立即返回，提交在后台分批通过 commit-stream 事件发送，结束（完成、取消或出错）时发送 commit-stream-end；
+// stream_id 同时作为操作 id，可用 cancel_operation 中止
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+fn stream_commits(
+    app: AppHandle,
+    operations: State<'_, OperationRegistry>,
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    stream_id: String,
+    rev: Option<String>,
+    filter: Option<git::GitCommitFilter>,
+    mailmap: Option<git::MailmapMode>,
+    limit: Option<usize>,
+) -> Result<(), GitError> {
+    sessions.get(&repo_id)?;
+    let filter = filter.unwrap_or_default();
+    let operation = operations.register(Some(&stream_id));
+    let sessions = sessions.inner().clone();
+    tauri::async_runtime::spawn(async move {
+        let batch_app = app.clone();
+        let batch_stream_id = stream_id.clone();
+        let result = with_repo(&sessions, repo_id, move |repo| {
+            git::stream_commits(repo, rev.as_deref(), &filter, mailmap.unwrap_or_default(), limit, operation.token(), |commits| {
+                let payload = git::GitCommitStreamBatch {
+                    stream_id: batch_stream_id.clone(),
+                    commits,
+                };
+                if let Err(e) = batch_app.emit(git::COMMIT_STREAM_EVENT, payload) {
+                    log::warn!("failed to emit {}: {}", git::COMMIT_STREAM_EVENT, e);
+                }
+            })
+        })
+        .await;
+        let (summary, cancelled, error) = match result {
+            Ok(summary) => (summary, false, None),
+            Err(GitError::Cancelled) => (Default::default(), true, None),
+            Err(e) => (Default::default(), false, Some(e.to_string())),
+        };
+        let payload = git::GitCommitStreamEnd {
+            stream_id,
+            total: summary.total,
+            shallow_boundary: summary.shallow_boundary,
+            cancelled,
+            error,
+        };
+        if let Err(e) = app.emit(git::COMMIT_STREAM_END_EVENT, payload) {
+            log::warn!("failed to emit {}: {}", git::COMMIT_STREAM_END_EVENT, e);
+        }
+    });
+    Ok(())
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn compare_refs(
+    operations: State<'_, OperationRegistry>,
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    base: String,
+    head: String,
+    limit: Option<usize>,
+    mailmap: Option<git::MailmapMode>,
+    operation_id: Option<String>,
+) -> Result<git::GitRefComparison, GitError> {
+    let limit = limit.unwrap_or(git::DEFAULT_COMPARE_LIMIT);
+    let operation = operations.register(operation_id.as_deref());
+    with_repo(&sessions, repo_id, move |repo| {
+        git::compare_refs(repo, &base, &head, limit, mailmap.unwrap_or_default(), operation.token())
+    })
+    .await
+}
+
+#[tauri::command]
+async fn get_merge_base(sessions: State<'_, RepoSessions>, repo_id: String, one: String, two: String) -> Result<Option<String>, GitError> {
+    with_repo(&sessions, repo_id, move |repo| git::get_merge_base(repo, &one, &two)).await
+}
+
+#[tauri::command]
+async fn get_octopus_merge_base(sessions: State<'_, RepoSessions>, repo_id: String, revs: Vec<String>) -> Result<Option<String>, GitError> {
+    with_repo(&sessions, repo_id, move |repo| git::get_octopus_merge_base(repo, &revs)).await
+}
+
+#[tauri::command]
+async fn preview_merge(sessions: State<'_, RepoSessions>, repo_id: String, branch: String) -> Result<git::GitMergePreview, GitError> {
+    with_repo(&sessions, repo_id, move |repo| git::preview_merge(repo, &branch)).await
+}
+
+// 搜索结果通过 commit-search-results 事件分批发送，命令在搜索结束后返回匹配总数；
+// search_id 同时作为操作 id，可用 cancel_operation 中止
+#[tauri::command]
+async fn search_commits(
+    app: AppHandle,
+    operations: State<'_, OperationRegistry>,
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    search_id: String,
+    query: String,
+    limit: Option<usize>,
+) -> Result<usize, GitError> {
+    let limit = limit.unwrap_or(git::DEFAULT_COMMIT_SEARCH_LIMIT);
+    let operation = operations.register(Some(&search_id));
+    with_repo(&sessions, repo_id, move |repo| {
+        git::search_commits(repo, &query, limit, operation.token(), |hits| {
+            let payload = git::GitCommitSearchBatch {
+                search_id: search_id.clone(),
+                hits,
+            };
+            if let Err(e) = app.emit(git::COMMIT_SEARCH_RESULTS_EVENT, payload) {
+                log::warn!("failed to emit {}: {}", git::COMMIT_SEARCH_RESULTS_EVENT, e);
+            }
+        })
+    })
+    .await
+}
+
+#[tauri::command]
+async fn get_repo_stats(
+    app: AppHandle,
+    operations: State<'_, OperationRegistry>,
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    stats_id: String,
+    since: Option<i64>,
+    until: Option<i64>,
+) -> Result<git::GitRepoStats, GitError> {
+    let operation = operations.register(Some(&stats_id));
+    with_repo(&sessions, repo_id, move |repo| {
+        git::get_repo_stats(repo, since, until, operation.token(), |walked_commits, stats| {
+            let payload = git::GitRepoStatsProgress {
+                stats_id: stats_id.clone(),
+                walked_commits,
+                stats,
+            };
+            if let Err(e) = app.emit(git::REPO_STATS_PROGRESS_EVENT, payload) {
+                log::warn!("failed to emit {}: {}", git::REPO_STATS_PROGRESS_EVENT, e);
+            }
+        })
+    })
+    .await
+}
+
+#[tauri::command]
+async fn get_file_churn(
+    operations: State<'_, OperationRegistry>,
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    since: Option<i64>,
+    limit: Option<usize>,
+    operation_id: Option<String>,
+) -> Result<Vec<git::GitFileChurn>, GitError> {
+    let limit = limit.unwrap_or(git::DEFAULT_FILE_CHURN_LIMIT);
+    let operation = operations.register(operation_id.as_deref());
+    with_repo(&sessions, repo_id, move |repo| git::get_file_churn(repo, since, limit, operation.token())).await
+}
+
+#[tauri::command]
+async fn get_author_avatar_hashes(
+    operations: State<'_, OperationRegistry>,
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    use_mailmap: Option<bool>,
+    operation_id: Option<String>,
+) -> Result<Vec<git::GitAuthorAvatar>, GitError> {
+    let operation = operations.register(operation_id.as_deref());
+    with_repo(&sessions, repo_id, move |repo| {
+        git::get_author_avatar_hashes(repo, use_mailmap.unwrap_or(true), operation.token())
+    })
+    .await
+}
+
+#[tauri::command]
+async fn get_commit_graph(sessions: State<'_, RepoSessions>, repo_id: String, rev: Option<String>, limit: Option<usize>) -> Result<Vec<git::GitGraphNode>, GitError> {
+    let limit = limit.unwrap_or(git::DEFAULT_COMMIT_GRAPH_LIMIT);
+    with_repo(&sessions, repo_id, move |repo| git::get_commit_graph(repo, rev.as_deref(), limit)).await
+}
+
+#[tauri::command]
+async fn get_commit_changes(
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    commit_hash: String,
+    options: Option<git::GitDiffOptions>,
+) -> Result<Vec<git::GitCommitChange>, GitError> {
+    with_repo(&sessions, repo_id, move |repo| git::get_commit_changes(repo, &commit_hash, &options.unwrap_or_default())).await
+}
+
+#[tauri::command]
+async fn get_commit_details(
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    commit_hash: String,
+    mailmap: Option<git::MailmapMode>,
+    options: Option<git::GitDiffOptions>,
+) -> Result<git::GitCommitDetails, GitError> {
+    with_repo(&sessions, repo_id, move |repo| {
+        git::get_commit_details(repo, &commit_hash, mailmap.unwrap_or_default(), &options.unwrap_or_default())
+    })
+    .await
+}
+
+#[tauri::command]
+async fn get_commit_file_diff(sessions: State<'_, RepoSessions>, repo_id: String, commit_hash: String, file_path: String, max_bytes: Option<u64>) -> Result<git::GitCommitFileDiff, GitError> {
+    let max_bytes = max_bytes.unwrap_or(git::DEFAULT_DIFF_MAX_BYTES);
+    with_repo(&sessions, repo_id, move |repo| git::get_commit_file_diff(repo, &commit_hash, &file_path, max_bytes)).await
+}
+
+#[tauri::command]
+async fn get_tree(sessions: State<'_, RepoSessions>, repo_id: String, rev: String, subpath: Option<String>) -> Result<Vec<git::GitTreeEntry>, GitError> {
+    with_repo(&sessions, repo_id, move |repo| git::get_tree(repo, &rev, subpath.as_deref())).await
+}
+
+#[tauri::command]
+async fn read_blob(sessions: State<'_, RepoSessions>, repo_id: String, rev: String, file_path: String, max_bytes: Option<u64>) -> Result<git::GitBlobContent, GitError> {
+    let max_bytes = max_bytes.unwrap_or(git::DEFAULT_DIFF_MAX_BYTES);
+    with_repo(&sessions, repo_id, move |repo| git::read_blob(repo, &rev, &file_path, max_bytes)).await
+}
+
+#[tauri::command]
+async fn get_conflict_file(sessions: State<'_, RepoSessions>, repo_id: String, file_path: String, max_bytes: Option<u64>) -> Result<git::GitConflictFile, GitError> {
+    let max_bytes = max_bytes.unwrap_or(git::DEFAULT_DIFF_MAX_BYTES);
+    with_repo(&sessions, repo_id, move |repo| git::get_conflict_file(repo, &file_path, max_bytes)).await
+}
+
+#[tauri::command]
+async fn restore_conflict_markers(
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    file_path: String,
+    style: Option<git::GitConflictStyle>,
+    max_bytes: Option<u64>,
+) -> Result<git::GitConflictFile, GitError> {
+    let max_bytes = max_bytes.unwrap_or(git::DEFAULT_DIFF_MAX_BYTES);
+    with_repo_mut(&sessions, repo_id, "restore_conflict_markers", move |repo| {
+        git::restore_conflict_markers(repo, &file_path, style, max_bytes)
+    })
+    .await
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn launch_external_difftool(
+    operations: State<'_, OperationRegistry>,
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    file_path: String,
+    left_rev: Option<String>,
+    right_rev: Option<String>,
+    tool: Option<String>,
+    operation_id: Option<String>,
+) -> Result<String, GitError> {
+    let operation = operations.register(operation_id.as_deref());
+    with_repo(&sessions, repo_id, move |repo| {
+        mergetool::launch_external_difftool(repo, &file_path, left_rev.as_deref(), right_rev.as_deref(), tool.as_deref(), operation.token())
+    })
+    .await
+}
+
+#[tauri::command]
+async fn launch_external_mergetool(
+    operations: State<'_, OperationRegistry>,
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    file_path: String,
+    tool: Option<String>,
+    operation_id: Option<String>,
+) -> Result<mergetool::GitMergetoolResult, GitError> {
+    let operation = operations.register(operation_id.as_deref());
+    with_repo_mut(&sessions, repo_id, "launch_external_mergetool", move |repo| {
+        mergetool::launch_external_mergetool(repo, &file_path, tool.as_deref(), operation.token())
+    })
+    .await
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn get_commit_file_patch(
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    commit_hash: String,
+    file_path: String,
+    context_lines: Option<u32>,
+    word_diff: Option<bool>,
+    options: Option<git::GitDiffOptions>,
+    max_bytes: Option<u64>,
+) -> Result<git::GitFilePatch, GitError> {
+    let context_lines = context_lines.unwrap_or(git::DEFAULT_DIFF_CONTEXT_LINES);
+    let options = options.unwrap_or_default();
+    let max_bytes = max_bytes.unwrap_or(git::DEFAULT_DIFF_MAX_BYTES);
+    with_repo(&sessions, repo_id, move |repo| {
+        git::get_commit_file_patch(repo, &commit_hash, &file_path, context_lines, word_diff.unwrap_or(false), &options, max_bytes)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn get_workdir_file_diff(sessions: State<'_, RepoSessions>, repo_id: String, file_path: String, staged: Option<bool>, max_bytes: Option<u64>) -> Result<git::GitCommitFileDiff, GitError> {
+    let max_bytes = max_bytes.unwrap_or(git::DEFAULT_DIFF_MAX_BYTES);
+    with_repo(&sessions, repo_id, move |repo| git::get_workdir_file_diff(repo, &file_path, staged.unwrap_or(false), max_bytes)).await
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn get_workdir_file_patch(
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    file_path: String,
+    staged: Option<bool>,
+    context_lines: Option<u32>,
+    word_diff: Option<bool>,
+    options: Option<git::GitDiffOptions>,
+    max_bytes: Option<u64>,
+) -> Result<git::GitFilePatch, GitError> {
+    let context_lines = context_lines.unwrap_or(git::DEFAULT_DIFF_CONTEXT_LINES);
+    let options = options.unwrap_or_default();
+    let max_bytes = max_bytes.unwrap_or(git::DEFAULT_DIFF_MAX_BYTES);
+    with_repo(&sessions, repo_id, move |repo| {
+        git::get_workdir_file_patch(repo, &file_path, staged.unwrap_or(false), context_lines, word_diff.unwrap_or(false), &options, max_bytes)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn get_blame(
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    file_path: String,
+    rev: Option<String>,
+    ignore_whitespace: Option<bool>,
+    mailmap: Option<git::MailmapMode>,
+) -> Result<git::GitBlame, GitError> {
+    with_repo(&sessions, repo_id, move |repo| {
+        git::get_blame(repo, &file_path, rev.as_deref(), ignore_whitespace.unwrap_or(false), mailmap.unwrap_or_default())
+    })
+    .await
+}
+
+#[tauri::command]
+async fn export_archive(
+    operations: State<'_, OperationRegistry>,
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    rev: String,
+    format: archive::ArchiveFormat,
+    output_path: String,
+    operation_id: Option<String>,
+) -> Result<git::GitArchiveResult, GitError> {
+    let operation = operations.register(operation_id.as_deref());
+    with_repo(&sessions, repo_id, move |repo| git::export_archive(repo, &rev, format, &output_path, operation.token())).await
+}
+
+#[tauri::command]
+async fn export_patches(
+    operations: State<'_, OperationRegistry>,
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    range: String,
+    output_dir: String,
+    operation_id: Option<String>,
+) -> Result<Vec<String>, GitError> {
+    let operation = operations.register(operation_id.as_deref());
+    with_repo(&sessions, repo_id, move |repo| git::export_patches(repo, &range, &output_dir, operation.token())).await
+}
+
+// dry_run 只检查能否应用，不占用仓库的写锁
+#[tauri::command]
+async fn apply_patch(
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    patch_text: String,
+    to_index: Option<bool>,
+    dry_run: Option<bool>,
+) -> Result<git::GitApplyResult, GitError> {
+    let to_index = to_index.unwrap_or(false);
+    if dry_run.unwrap_or(false) {
+        return with_repo(&sessions, repo_id, move |repo| git::apply_patch(repo, &patch_text, to_index, true)).await;
+    }
+    with_repo_mut(&sessions, repo_id, "apply_patch", move |repo| git::apply_patch(repo, &patch_text, to_index, false)).await
+}
+
+#[tauri::command]
+async fn import_patch_from_file(
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    path: String,
+    to_index: Option<bool>,
+    dry_run: Option<bool>,
+) -> Result<git::GitPatchImport, GitError> {
+    let to_index = to_index.unwrap_or(false);
+    if dry_run.unwrap_or(false) {
+        return with_repo(&sessions, repo_id, move |repo| git::import_patch_from_file(repo, &path, to_index, true)).await;
+    }
+    with_repo_mut(&sessions, repo_id, "import_patch_from_file", move |repo| git::import_patch_from_file(repo, &path, to_index, false)).await
+}
+
+// 每开始应用一个补丁发送一次 mailbox-progress，completed 是该补丁的序号（从 1 开始）
+fn mailbox_progress_emitter(app: AppHandle, operation_id: Option<String>) -> impl FnMut(usize, usize, &str) {
+    move |current, total, subject| {
+        let payload = git_cli::GitCommandProgress {
+            operation_id: operation_id.clone(),
+            phase: "Applying".to_string(),
+            completed: Some(current as u64),
+            total: Some(total as u64),
+            message: subject.to_string(),
+        };
+        if let Err(e) = app.emit(mailbox::MAILBOX_PROGRESS_EVENT, payload) {
+            log::warn!("failed to emit {}: {}", mailbox::MAILBOX_PROGRESS_EVENT, e);
+        }
+    }
+}
+
+#[tauri::command]
+async fn apply_mailbox(
+    app: AppHandle,
+    operations: State<'_, OperationRegistry>,
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    mbox_path: String,
+    three_way: Option<bool>,
+    operation_id: Option<String>,
+) -> Result<mailbox::GitMailboxResult, GitError> {
+    let operation = operations.register(operation_id.as_deref());
+    with_repo_mut(&sessions, repo_id, "apply_mailbox", move |repo| {
+        let on_patch = mailbox_progress_emitter(app, operation_id);
+        mailbox::apply_mailbox(repo, &mbox_path, three_way.unwrap_or(true), operation.token(), on_patch)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn mailbox_continue(
+    app: AppHandle,
+    operations: State<'_, OperationRegistry>,
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    operation_id: Option<String>,
+) -> Result<mailbox::GitMailboxResult, GitError> {
+    let operation = operations.register(operation_id.as_deref());
+    with_repo_mut(&sessions, repo_id, "mailbox_continue", move |repo| {
+        let on_patch = mailbox_progress_emitter(app, operation_id);
+        mailbox::mailbox_continue(repo, operation.token(), on_patch)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn mailbox_skip(
+    app: AppHandle,
+    operations: State<'_, OperationRegistry>,
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    operation_id: Option<String>,
+) -> Result<mailbox::GitMailboxResult, GitError> {
+    let operation = operations.register(operation_id.as_deref());
+    with_repo_mut(&sessions, repo_id, "mailbox_skip", move |repo| {
+        let on_patch = mailbox_progress_emitter(app, operation_id);
+        mailbox::mailbox_skip(repo, operation.token(), on_patch)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn mailbox_abort(sessions: State<'_, RepoSessions>, repo_id: String) -> Result<git::GitOperationStatus, GitError> {
+    with_repo_mut(&sessions, repo_id, "mailbox_abort", mailbox::mailbox_abort).await
+}
+
+#[tauri::command]
+async fn create_bundle(sessions: State<'_, RepoSessions>, repo_id: String, refs: Vec<String>, output_path: String) -> Result<git::GitBundleInfo, GitError> {
+    with_repo(&sessions, repo_id, move |repo| git::create_bundle(repo, &refs, &output_path)).await
+}
+
+#[tauri::command]
+async fn get_bundle_info(bundle_path: String) -> Result<git::GitBundleInfo, GitError> {
+    run_blocking(move || git::get_bundle_info(&bundle_path)).await
+}
+
+#[tauri::command]
+async fn fetch_bundle(
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    bundle_path: String,
+    remote_name: Option<String>,
+) -> Result<git::GitBundleFetchResult, GitError> {
+    let remote_name = remote_name.unwrap_or_else(|| git::DEFAULT_BUNDLE_REMOTE.to_string());
+    with_repo_mut(&sessions, repo_id, "fetch_bundle", move |repo| git::fetch_bundle(repo, &bundle_path, &remote_name)).await
+}
+
+#[tauri::command]
+async fn get_repo_info(
+    app: AppHandle,
+    operations: State<'_, OperationRegistry>,
+    sessions: State<'_, RepoSessions>,
+    sizes: State<'_, SizeCache>,
+    repo_id: String,
+    refresh: Option<bool>,
+    operation_id: Option<String>,
+) -> Result<git::GitRepoInfo, GitError> {
+    let operation = operations.register(operation_id.as_deref());
+    let repo_path = sessions.get(&repo_id)?.path;
+    let sizes = sizes.inner().clone();
+    with_repo(&sessions, repo_id, move |repo| {
+        let on_progress = size_progress_emitter(app, operation_id);
+        git::get_repo_info(repo, &repo_path, &sizes, refresh.unwrap_or(false), operation.token(), on_progress)
+    })
+    .await
+}
+
+// 把大小扫描的进度转发给前端
+fn size_progress_emitter(app: AppHandle, operation_id: Option<String>) -> impl Fn(git::GitSizeScanScope, u64, u64) + Sync {
+    move |scope, scanned_files, scanned_bytes| {
+        let payload = git::GitSizeScanProgress {
+            operation_id: operation_id.clone(),
+            scope,
+            scanned_files,
+            scanned_bytes,
+        };
+        if let Err(e) = app.emit(git::REPO_SIZE_PROGRESS_EVENT, payload) {
+            log::warn!("failed to emit {}: {}", git::REPO_SIZE_PROGRESS_EVENT, e);
+        }
+    }
+}
+
+#[tauri::command]
+async fn get_largest_blobs(
+    operations: State<'_, OperationRegistry>,
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    top_n: Option<usize>,
+    operation_id: Option<String>,
+) -> Result<Vec<git::GitLargeBlob>, GitError> {
+    let top_n = top_n.unwrap_or(git::DEFAULT_LARGEST_BLOBS_COUNT);
+    let operation = operations.register(operation_id.as_deref());
+    with_repo(&sessions, repo_id, move |repo| git::get_largest_blobs(repo, top_n, operation.token())).await
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn get_size_breakdown(
+    app: AppHandle,
+    operations: State<'_, OperationRegistry>,
+    sessions: State<'_, RepoSessions>,
+    sizes: State<'_, SizeCache>,
+    repo_id: String,
+    depth: Option<usize>,
+    refresh: Option<bool>,
+    operation_id: Option<String>,
+) -> Result<git::GitSizeBreakdown, GitError> {
+    let depth = depth.unwrap_or(git::DEFAULT_SIZE_BREAKDOWN_DEPTH);
+    let operation = operations.register(operation_id.as_deref());
+    let sizes = sizes.inner().clone();
+    with_repo(&sessions, repo_id, move |repo| {
+        let on_progress = size_progress_emitter(app, operation_id);
+        git::get_size_breakdown(repo, depth, &sizes, refresh.unwrap_or(false), operation.token(), on_progress)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn run_gc(
+    operations: State<'_, OperationRegistry>,
+    sessions: State<'_, RepoSessions>,
+    sizes: State<'_, SizeCache>,
+    repo_id: String,
+    aggressive: Option<bool>,
+    operation_id: Option<String>,
+) -> Result<maintenance::GitMaintenanceResult, GitError> {
+    let operation = operations.register(operation_id.as_deref());
+    let sizes = sizes.inner().clone();
+    with_repo_mut(&sessions, repo_id, "run_gc", move |repo| {
+        maintenance::run_gc(repo, aggressive.unwrap_or(false), &sizes, operation.token())
+    })
+    .await
+}
+
+#[tauri::command]
+async fn repack(
+    operations: State<'_, OperationRegistry>,
+    sessions: State<'_, RepoSessions>,
+    sizes: State<'_, SizeCache>,
+    repo_id: String,
+    operation_id: Option<String>,
+) -> Result<maintenance::GitMaintenanceResult, GitError> {
+    let operation = operations.register(operation_id.as_deref());
+    let sizes = sizes.inner().clone();
+    with_repo_mut(&sessions, repo_id, "repack", move |repo| maintenance::repack(repo, &sizes, operation.token())).await
+}
+
+#[tauri::command]
+async fn get_commit_graph_status(sessions: State<'_, RepoSessions>, repo_id: String) -> Result<maintenance::GitCommitGraphStatus, GitError> {
+    with_repo(&sessions, repo_id, maintenance::get_commit_graph_status).await
+}
+
+// 写命令结束后缓存失效，重新打开的仓库会读取新生成的 commit-graph
+#[tauri::command]
+async fn write_commit_graph(
+    operations: State<'_, OperationRegistry>,
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    operation_id: Option<String>,
+) -> Result<maintenance::GitCommitGraphStatus, GitError> {
+    let operation = operations.register(operation_id.as_deref());
+    with_repo_mut(&sessions, repo_id, "write_commit_graph", move |repo| maintenance::write_commit_graph(repo, operation.token())).await
+}
+
+#[tauri::command]
+async fn check_repo_integrity(
+    app: AppHandle,
+    operations: State<'_, OperationRegistry>,
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    operation_id: Option<String>,
+) -> Result<maintenance::GitIntegrityReport, GitError> {
+    let operation = operations.register(operation_id.as_deref());
+    with_repo(&sessions, repo_id, move |repo| {
+        maintenance::check_repo_integrity(repo, operation.token(), |checked_objects, total_objects| {
+            let payload = maintenance::GitIntegrityProgress {
+                operation_id: operation_id.clone(),
+                checked_objects,
+                total_objects,
+            };
+            if let Err(e) = app.emit(maintenance::INTEGRITY_PROGRESS_EVENT, payload) {
+                log::warn!("failed to emit {}: {}", maintenance::INTEGRITY_PROGRESS_EVENT, e);
+            }
+        })
+    })
+    .await
+}
+
+#[tauri::command]
+async fn get_object_stats(
+    operations: State<'_, OperationRegistry>,
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    operation_id: Option<String>,
+) -> Result<maintenance::GitObjectStats, GitError> {
+    let operation = operations.register(operation_id.as_deref());
+    with_repo(&sessions, repo_id, move |repo| maintenance::get_object_stats(repo, operation.token())).await
+}
+
+#[tauri::command]
+async fn get_lfs_files(
+    operations: State<'_, OperationRegistry>,
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    operation_id: Option<String>,
+) -> Result<lfs::GitLfsFiles, GitError> {
+    let operation = operations.register(operation_id.as_deref());
+    with_repo(&sessions, repo_id, move |repo| lfs::get_lfs_files(repo, operation.token())).await
+}
+
+fn command_progress_emitter(app: AppHandle, event: &'static str, operation_id: Option<String>) -> impl FnMut(&str) {
+    move |line| {
+        if let Err(e) = app.emit(event, git_cli::parse_progress(line, operation_id.clone())) {
+            log::warn!("failed to emit {}: {}", event, e);
+        }
+    }
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn lfs_fetch(
+    app: AppHandle,
+    operations: State<'_, OperationRegistry>,
+    sessions: State<'_, RepoSessions>,
+    sizes: State<'_, SizeCache>,
+    repo_id: String,
+    remote: Option<String>,
+    all: Option<bool>,
+    operation_id: Option<String>,
+) -> Result<lfs::GitLfsCommandResult, GitError> {
+    let operation = operations.register(operation_id.as_deref());
+    let sizes = sizes.inner().clone();
+    with_repo(&sessions, repo_id, move |repo| {
+        let on_progress = command_progress_emitter(app, lfs::LFS_PROGRESS_EVENT, operation_id);
+        lfs::lfs_fetch(repo, remote.as_deref(), all.unwrap_or(false), &sizes, operation.token(), on_progress)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn lfs_pull(
+    app: AppHandle,
+    operations: State<'_, OperationRegistry>,
+    sessions: State<'_, RepoSessions>,
+    sizes: State<'_, SizeCache>,
+    repo_id: String,
+    remote: Option<String>,
+    operation_id: Option<String>,
+) -> Result<lfs::GitLfsCommandResult, GitError> {
+    let operation = operations.register(operation_id.as_deref());
+    let sizes = sizes.inner().clone();
+    with_repo_mut(&sessions, repo_id, "lfs_pull", move |repo| {
+        let on_progress = command_progress_emitter(app, lfs::LFS_PROGRESS_EVENT, operation_id);
+        lfs::lfs_pull(repo, remote.as_deref(), &sizes, operation.token(), on_progress)
+    })
+    .await
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn lfs_prune(
+    app: AppHandle,
+    operations: State<'_, OperationRegistry>,
+    sessions: State<'_, RepoSessions>,
+    sizes: State<'_, SizeCache>,
+    repo_id: String,
+    verify_remote: Option<bool>,
+    dry_run: Option<bool>,
+    operation_id: Option<String>,
+) -> Result<lfs::GitLfsCommandResult, GitError> {
+    let operation = operations.register(operation_id.as_deref());
+    let sizes = sizes.inner().clone();
+    let verify_remote = verify_remote.unwrap_or(false);
+    if dry_run.unwrap_or(false) {
+        return with_repo(&sessions, repo_id, move |repo| {
+            let on_progress = command_progress_emitter(app, lfs::LFS_PROGRESS_EVENT, operation_id);
+            lfs::lfs_prune(repo, verify_remote, true, &sizes, operation.token(), on_progress)
+        })
+        .await;
+    }
+    with_repo_mut(&sessions, repo_id, "lfs_prune", move |repo| {
+        let on_progress = command_progress_emitter(app, lfs::LFS_PROGRESS_EVENT, operation_id);
+        lfs::lfs_prune(repo, verify_remote, false, &sizes, operation.token(), on_progress)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn lfs_track(sessions: State<'_, RepoSessions>, repo_id: String, pattern: String, lockable: Option<bool>) -> Result<Vec<lfs::GitLfsPattern>, GitError> {
+    with_repo_mut(&sessions, repo_id, "lfs_track", move |repo| lfs::lfs_track(repo, &pattern, lockable.unwrap_or(false))).await
+}
+
+#[tauri::command]
+async fn lfs_untrack(sessions: State<'_, RepoSessions>, repo_id: String, pattern: String) -> Result<Vec<lfs::GitLfsPattern>, GitError> {
+    with_repo_mut(&sessions, repo_id, "lfs_untrack", move |repo| lfs::lfs_untrack(repo, &pattern)).await
+}
+
+#[tauri::command]
+async fn get_lfs_candidates(
+    operations: State<'_, OperationRegistry>,
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    threshold_bytes: Option<u64>,
+    operation_id: Option<String>,
+) -> Result<Vec<lfs::GitLfsCandidate>, GitError> {
+    let operation = operations.register(operation_id.as_deref());
+    let threshold_bytes = threshold_bytes.unwrap_or(lfs::DEFAULT_LFS_CANDIDATE_THRESHOLD);
+    with_repo(&sessions, repo_id, move |repo| lfs::get_lfs_candidates(repo, threshold_bytes, operation.token())).await
+}
+
+#[tauri::command]
+async fn get_status(sessions: State<'_, RepoSessions>, repo_id: String, options: Option<git::GitStatusOptions>) -> Result<Vec<git::GitStatus>, GitError> {
+    with_repo(&sessions, repo_id, move |repo| git::get_status(repo, &options.unwrap_or_default())).await
+}
+
+#[tauri::command]
+async fn get_status_fast(sessions: State<'_, RepoSessions>, repo_id: String, pathspec: Option<String>, options: Option<git::GitStatusOptions>) -> Result<git::GitStatusScan, GitError> {
+    with_repo(&sessions, repo_id, move |repo| git::get_status_fast(repo, pathspec.as_deref(), &options.unwrap_or_default())).await
+}
+
+#[tauri::command]
+async fn add_to_gitignore(sessions: State<'_, RepoSessions>, repo_id: String, pattern: String, directory: Option<String>) -> Result<Vec<git::GitStatus>, GitError> {
+    with_repo_mut(&sessions, repo_id, "add_to_gitignore", move |repo| git::add_to_gitignore(repo, &pattern, directory.as_deref())).await
+}
+
+#[tauri::command]
+async fn ignore_path(sessions: State<'_, RepoSessions>, repo_id: String, file_path: String, target: git::IgnoreTarget) -> Result<Vec<git::GitStatus>, GitError> {
+    with_repo_mut(&sessions, repo_id, "ignore_path", move |repo| git::ignore_path(repo, &file_path, target)).await
+}
+
+#[tauri::command]
+async fn get_ignored_status(sessions: State<'_, RepoSessions>, repo_id: String, paths: Vec<String>) -> Result<Vec<git::GitIgnoredStatus>, GitError> {
+    with_repo(&sessions, repo_id, move |repo| git::get_ignored_status(repo, &paths)).await
+}
+
+#[tauri::command]
+async fn checkout_branch(sessions: State<'_, RepoSessions>, repo_id: String, branch_name: String, autostash: Option<bool>) -> Result<git::GitCheckoutResult, GitError> {
+    with_repo_mut(&sessions, repo_id, "checkout_branch", move |repo| {
+        let (head, autostash) = autostash::with_autostash(repo, autostash.unwrap_or(false), |repo| {
+            git::checkout_branch(repo, &branch_name)?;
+            git::get_head_state(repo)
+        })?;
+        Ok(git::GitCheckoutResult { head, autostash })
+    })
+    .await
+}
+
+#[tauri::command]
+async fn checkout_commit(sessions: State<'_, RepoSessions>, repo_id: String, rev: String, autostash: Option<bool>) -> Result<git::GitCheckoutResult, GitError> {
+    with_repo_mut(&sessions, repo_id, "checkout_commit", move |repo| {
+        let (head, autostash) = autostash::with_autostash(repo, autostash.unwrap_or(false), |repo| git::checkout_commit(repo, &rev))?;
+        Ok(git::GitCheckoutResult { head, autostash })
+    })
+    .await
+}
+
+#[tauri::command]
+async fn checkout_tag(sessions: State<'_, RepoSessions>, repo_id: String, tag: String, autostash: Option<bool>) -> Result<git::GitTagCheckoutResult, GitError> {
+    with_repo_mut(&sessions, repo_id, "checkout_tag", move |repo| {
+        let (checkout, autostash) = autostash::with_autostash(repo, autostash.unwrap_or(false), |repo| git::checkout_tag(repo, &tag))?;
+        Ok(git::GitTagCheckoutResult { checkout, autostash })
+    })
+    .await
+}
+
+#[tauri::command]
+async fn create_branch_here(sessions: State<'_, RepoSessions>, repo_id: String, name: String, checkout: Option<bool>) -> Result<git::GitBranch, GitError> {
+    with_repo_mut(&sessions, repo_id, "create_branch_here", move |repo| git::create_branch_here(repo, &name, checkout.unwrap_or(true))).await
+}
+
+#[tauri::command]
+async fn checkout_file_at(
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    rev: String,
+    file_path: String,
+    to_index: Option<bool>,
+) -> Result<Vec<git::GitStatus>, GitError> {
+    with_repo_mut(&sessions, repo_id, "checkout_file_at", move |repo| {
+        git::checkout_file_at(repo, &rev, &file_path, to_index.unwrap_or(false))
+    })
+    .await
+}
+
+#[tauri::command]
+async fn move_tracked_file(sessions: State<'_, RepoSessions>, repo_id: String, from: String, to: String) -> Result<Vec<git::GitStatus>, GitError> {
+    with_repo_mut(&sessions, repo_id, "move_tracked_file", move |repo| git::move_tracked_file(repo, &from, &to)).await
+}
+
+#[tauri::command]
+async fn remove_tracked_file(sessions: State<'_, RepoSessions>, repo_id: String, path: String, keep_on_disk: Option<bool>) -> Result<Vec<git::GitStatus>, GitError> {
+    with_repo_mut(&sessions, repo_id, "remove_tracked_file", move |repo| git::remove_tracked_file(repo, &path, keep_on_disk.unwrap_or(false))).await
+}
+
+#[tauri::command]
+async fn get_head_state(sessions: State<'_, RepoSessions>, repo_id: String) -> Result<git::GitHeadState, GitError> {
+    with_repo(&sessions, repo_id, git::get_head_state).await
+}
+
+#[tauri::command]
+async fn get_operation_status(sessions: State<'_, RepoSessions>, repo_id: String) -> Result<git::GitOperationStatus, GitError> {
+    with_repo(&sessions, repo_id, git::get_operation_status).await
+}
+
+#[tauri::command]
+async fn get_worktrees(sessions: State<'_, RepoSessions>, repo_id: String) -> Result<Vec<git::Worktree>, GitError> {
+    with_repo(&sessions, repo_id, git::get_worktrees).await
+}
+
+#[tauri::command]
+async fn get_config(sessions: State<'_, RepoSessions>, repo_id: String, scope: Option<git::ConfigScope>) -> Result<git::GitConfigSettings, GitError> {
+    with_repo(&sessions, repo_id, move |repo| git::get_config(repo, scope)).await
+}
+
+#[tauri::command]
+async fn set_config(sessions: State<'_, RepoSessions>, repo_id: String, scope: Option<git::ConfigScope>, settings: git::GitConfigSettings) -> Result<(), GitError> {
+    with_repo_mut(&sessions, repo_id, "set_config", move |repo| git::set_config(repo, scope.unwrap_or(git::ConfigScope::Repo), &settings)).await
+}
+
+#[tauri::command]
+async fn get_config_value(sessions: State<'_, RepoSessions>, repo_id: String, key: String, scope: Option<git::ConfigScope>) -> Result<Option<String>, GitError> {
+    with_repo(&sessions, repo_id, move |repo| git::get_config_value(repo, &key, scope)).await
+}
+
+#[tauri::command]
+async fn set_config_value(sessions: State<'_, RepoSessions>, repo_id: String, key: String, value: Option<String>, scope: Option<git::ConfigScope>) -> Result<(), GitError> {
+    let scope = scope.unwrap_or(git::ConfigScope::Repo);
+    with_repo_mut(&sessions, repo_id, "set_config_value", move |repo| git::set_config_value(repo, &key, value.as_deref(), scope)).await
+}
+
+#[tauri::command]
+async fn get_repo_identity(sessions: State<'_, RepoSessions>, repo_id: String) -> Result<git::GitRepoIdentity, GitError> {
+    with_repo(&sessions, repo_id, git::get_repo_identity).await
+}
+
+#[tauri::command]
+async fn set_repo_identity(sessions: State<'_, RepoSessions>, repo_id: String, name: Option<String>, email: Option<String>) -> Result<git::GitRepoIdentity, GitError> {
+    with_repo_mut(&sessions, repo_id, "set_repo_identity", move |repo| git::set_repo_identity(repo, name.as_deref(), email.as_deref())).await
+}
+
+#[tauri::command]
+async fn add_worktree(sessions: State<'_, RepoSessions>, repo_id: String, worktree_path: String, branch_name: String, create_branch: Option<bool>) -> Result<git::Worktree, GitError> {
+    with_repo_mut(&sessions, repo_id, "add_worktree", move |repo| {
+        git::add_worktree(repo, &worktree_path, &branch_name, create_branch.unwrap_or(false))
+    })
+    .await
+}
+
+#[tauri::command]
+async fn remove_worktree(sessions: State<'_, RepoSessions>, repo_id: String, name: String, force: Option<bool>) -> Result<(), GitError> {
+    with_repo_mut(&sessions, repo_id, "remove_worktree", move |repo| git::remove_worktree(repo, &name, force.unwrap_or(false))).await
+}
+
+#[tauri::command]
+async fn prune_worktrees(sessions: State<'_, RepoSessions>, repo_id: String) -> Result<Vec<String>, GitError> {
+    with_repo_mut(&sessions, repo_id, "prune_worktrees", git::prune_worktrees).await
+}
+
+#[tauri::command]
+async fn get_sparse_checkout(sessions: State<'_, RepoSessions>, repo_id: String) -> Result<git::GitSparseCheckout, GitError> {
+    with_repo(&sessions, repo_id, git::get_sparse_checkout).await
+}
+
+#[tauri::command]
+async fn set_sparse_checkout(sessions: State<'_, RepoSessions>, repo_id: String, paths: Vec<String>, cone_mode: Option<bool>) -> Result<git::GitSparseCheckout, GitError> {
+    with_repo_mut(&sessions, repo_id, "set_sparse_checkout", move |repo| git::set_sparse_checkout(repo, &paths, cone_mode.unwrap_or(true))).await
+}
+
+#[tauri::command]
+async fn get_bisect_status(sessions: State<'_, RepoSessions>, repo_id: String) -> Result<bisect::GitBisectStatus, GitError> {
+    with_repo(&sessions, repo_id, bisect::get_bisect_status).await
+}
+
+#[tauri::command]
+async fn bisect_start(sessions: State<'_, RepoSessions>, repo_id: String, good: String, bad: String) -> Result<bisect::GitBisectStatus, GitError> {
+    with_repo_mut(&sessions, repo_id, "bisect_start", move |repo| bisect::bisect_start(repo, &good, &bad)).await
+}
+
+#[tauri::command]
+async fn bisect_mark(
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    commit: Option<String>,
+    mark: bisect::GitBisectMark,
+) -> Result<bisect::GitBisectStatus, GitError> {
+    with_repo_mut(&sessions, repo_id, "bisect_mark", move |repo| bisect::bisect_mark(repo, commit.as_deref(), mark)).await
+}
+
+#[tauri::command]
+async fn bisect_reset(sessions: State<'_, RepoSessions>, repo_id: String) -> Result<bisect::GitBisectStatus, GitError> {
+    with_repo_mut(&sessions, repo_id, "bisect_reset", bisect::bisect_reset).await
+}
+
+#[tauri::command]
+async fn get_rerere_status(sessions: State<'_, RepoSessions>, repo_id: String) -> Result<rerere::GitRerereStatus, GitError> {
+    with_repo(&sessions, repo_id, rerere::get_rerere_status).await
+}
+
+#[tauri::command]
+async fn rerere_apply(sessions: State<'_, RepoSessions>, repo_id: String) -> Result<rerere::GitRerereResult, GitError> {
+    with_repo_mut(&sessions, repo_id, "rerere_apply", rerere::rerere_apply).await
+}
+
+#[tauri::command]
+async fn rerere_forget(sessions: State<'_, RepoSessions>, repo_id: String, path: String) -> Result<rerere::GitRerereStatus, GitError> {
+    with_repo_mut(&sessions, repo_id, "rerere_forget", move |repo| rerere::rerere_forget(repo, &path)).await
+}
+
+#[tauri::command]
+async fn get_reflog(sessions: State<'_, RepoSessions>, repo_id: String, ref_name: Option<String>, limit: Option<usize>) -> Result<Vec<git::GitReflogEntry>, GitError> {
+    let ref_name = ref_name.unwrap_or_else(|| "HEAD".to_string());
+    let limit = limit.unwrap_or(git::DEFAULT_REFLOG_LIMIT);
+    with_repo(&sessions, repo_id, move |repo| git::get_reflog(repo, &ref_name, limit)).await
+}
+
+#[tauri::command]
+async fn checkout_reflog_entry(sessions: State<'_, RepoSessions>, repo_id: String, ref_name: String, index: usize) -> Result<String, GitError> {
+    with_repo_mut(&sessions, repo_id, "checkout_reflog_entry", move |repo| git::checkout_reflog_entry(repo, &ref_name, index)).await
+}
+
+#[tauri::command]
+async fn create_branch_from_reflog(sessions: State<'_, RepoSessions>, repo_id: String, ref_name: String, index: usize, branch_name: String) -> Result<git::GitBranch, GitError> {
+    with_repo_mut(&sessions, repo_id, "create_branch_from_reflog", move |repo| git::create_branch_from_reflog(repo, &ref_name, index, &branch_name)).await
+}
+
+#[tauri::command]
+async fn get_submodules(sessions: State<'_, RepoSessions>, repo_id: String) -> Result<Vec<git::GitSubmodule>, GitError> {
+    with_repo(&sessions, repo_id, git::get_submodules).await
+}
+
+#[tauri::command]
+async fn init_submodule(sessions: State<'_, RepoSessions>, repo_id: String, submodule_path: String) -> Result<(), GitError> {
+    with_repo_mut(&sessions, repo_id, "init_submodule", move |repo| git::init_submodule(repo, &submodule_path)).await
+}
+
+#[tauri::command]
+async fn update_submodule(sessions: State<'_, RepoSessions>, repo_id: String, submodule_path: String, init: Option<bool>) -> Result<(), GitError> {
+    with_repo_mut(&sessions, repo_id, "update_submodule", move |repo| git::update_submodule(repo, &submodule_path, init.unwrap_or(true))).await
+}
+
+#[tauri::command]
+async fn reset(sessions: State<'_, RepoSessions>, repo_id: String, target_ref: String, mode: git::ResetMode, force: Option<bool>) -> Result<git::GitResetResult, GitError> {
+    with_repo_mut(&sessions, repo_id, "reset", move |repo| git::reset(repo, &target_ref, mode, force.unwrap_or(false))).await
+}
+
+#[tauri::command]
+async fn get_undo_preview(sessions: State<'_, RepoSessions>, repo_id: String) -> Result<undo::GitUndoPreview, GitError> {
+    with_repo(&sessions, repo_id, undo::get_undo_preview).await
+}
+
+#[tauri::command]
+async fn undo_last_operation(sessions: State<'_, RepoSessions>, repo_id: String) -> Result<undo::GitUndoResult, GitError> {
+    with_repo_mut(&sessions, repo_id, "undo_last_operation", undo::undo_last_operation).await
+}
+
+#[tauri::command]
+async fn uncommit(sessions: State<'_, RepoSessions>, repo_id: String, force: Option<bool>) -> Result<undo::GitUncommitResult, GitError> {
+    with_repo_mut(&sessions, repo_id, "uncommit", move |repo| undo::uncommit(repo, force.unwrap_or(false))).await
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn create_commit(
+    app: AppHandle,
+    operations: State<'_, OperationRegistry>,
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    message: String,
+    sign: Option<bool>,
+    trailers: Option<commit_message::CommitTrailers>,
+    no_verify: Option<bool>,
+    operation_id: Option<String>,
+) -> Result<git::GitCommit, GitError> {
+    let operation = operations.register(operation_id.as_deref());
+    let trailers = trailers.unwrap_or_default();
+    with_repo_mut(&sessions, repo_id, "create_commit", move |repo| {
+        git::create_commit(repo, &message, sign, &trailers, no_verify.unwrap_or(false), operation.token(), |hook, line| {
+            let payload = hooks::GitHookOutput {
+                operation_id: operation_id.clone(),
+                hook: hook.to_string(),
+                line: line.to_string(),
+            };
+            if let Err(e) = app.emit(hooks::HOOK_OUTPUT_EVENT, payload) {
+                log::warn!("failed to emit {}: {}", hooks::HOOK_OUTPUT_EVENT, e);
+            }
+        })
+    })
+    .await
+}
+
+#[tauri::command]
+async fn get_commit_template(sessions: State<'_, RepoSessions>, repo_id: String) -> Result<Option<String>, GitError> {
+    with_repo(&sessions, repo_id, commit_message::get_commit_template).await
+}
+
+// branch 为空时使用当前分支；message 只有空白时删除草稿
+#[tauri::command]
+async fn save_commit_draft(
+    sessions: State<'_, RepoSessions>,
+    drafts: State<'_, CommitDrafts>,
+    repo_id: String,
+    message: String,
+    branch: Option<String>,
+) -> Result<Option<drafts::CommitDraft>, GitError> {
+    let drafts = drafts.inner().clone();
+    with_repo(&sessions, repo_id, move |repo| {
+        let (repo_path, branch) = drafts::draft_key(repo, branch.as_deref())?;
+        drafts.save(&repo_path, &branch, &message)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn load_commit_draft(
+    sessions: State<'_, RepoSessions>,
+    drafts: State<'_, CommitDrafts>,
+    repo_id: String,
+    branch: Option<String>,
+) -> Result<Option<drafts::CommitDraft>, GitError> {
+    let drafts = drafts.inner().clone();
+    with_repo(&sessions, repo_id, move |repo| {
+        let (repo_path, branch) = drafts::draft_key(repo, branch.as_deref())?;
+        Ok(drafts.get(&repo_path, &branch))
+    })
+    .await
+}
+
+// 纯文本检查，不需要打开仓库；rules 为空时使用默认规则
+#[tauri::command]
+async fn validate_commit_message(
+    message: String,
+    rules: Option<commit_message::CommitMessageRules>,
+) -> Result<Vec<commit_message::CommitMessageViolation>, GitError> {
+    commit_message::validate_commit_message(&message, &rules.unwrap_or_default())
+}
+
+// 提交详情中的问题引用链接；settings 为空时只识别远程平台上的 #123
+#[tauri::command]
+async fn get_issue_references(
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    message: String,
+    settings: Option<issue_refs::IssueLinkSettings>,
+) -> Result<Vec<issue_refs::IssueReference>, GitError> {
+    with_repo(&sessions, repo_id, move |repo| issue_refs::get_issue_references(repo, &message, &settings.unwrap_or_default())).await
+}
+
+#[tauri::command]
+async fn get_hooks(sessions: State<'_, RepoSessions>, repo_id: String) -> Result<hooks::GitHooks, GitError> {
+    with_repo(&sessions, repo_id, hooks::get_hooks).await
+}
+
+#[tauri::command]
+async fn set_hook_enabled(sessions: State<'_, RepoSessions>, repo_id: String, name: String, enabled: bool) -> Result<hooks::GitHooks, GitError> {
+    with_repo_mut(&sessions, repo_id, "set_hook_enabled", move |repo| hooks::set_hook_enabled(repo, &name, enabled)).await
+}
+
+#[tauri::command]
+async fn create_tag(
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    name: String,
+    target: Option<String>,
+    message: Option<String>,
+    sign: Option<bool>,
+    force: Option<bool>,
+) -> Result<git::GitTag, GitError> {
+    with_repo_mut(&sessions, repo_id, "create_tag", move |repo| {
+        git::create_tag(repo, &name, target.as_deref(), message.as_deref(), sign, force.unwrap_or(false))
+    })
+    .await
+}
+
+#[tauri::command]
+async fn verify_commit_signature(sessions: State<'_, RepoSessions>, repo_id: String, commit_hash: String) -> Result<Option<git::GitSignatureVerification>, GitError> {
+    with_repo(&sessions, repo_id, move |repo| git::verify_commit_signature(repo, &commit_hash)).await
+}
+
+#[tauri::command]
+async fn verify_tag_signature(sessions: State<'_, RepoSessions>, repo_id: String, tag_name: String) -> Result<Option<git::GitSignatureVerification>, GitError> {
+    with_repo(&sessions, repo_id, move |repo| git::verify_tag_signature(repo, &tag_name)).await
+}
+
+#[tauri::command]
+async fn list_signing_keys(sessions: State<'_, RepoSessions>, repo_id: String) -> Result<Vec<git::GitSigningKey>, GitError> {
+    with_repo(&sessions, repo_id, git::list_signing_keys).await
+}
+
+#[tauri::command]
+async fn get_commit_notes(sessions: State<'_, RepoSessions>, repo_id: String, commit_hash: String) -> Result<Vec<git::GitNote>, GitError> {
+    with_repo(&sessions, repo_id, move |repo| git::get_commit_notes(repo, &commit_hash)).await
+}
+
+#[tauri::command]
+async fn set_commit_note(
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    commit_hash: String,
+    text: String,
+    notes_ref: Option<String>,
+) -> Result<Option<git::GitNote>, GitError> {
+    with_repo_mut(&sessions, repo_id, "set_commit_note", move |repo| git::set_commit_note(repo, &commit_hash, &text, notes_ref.as_deref())).await
+}
+
+// 开始监听仓库的文件变化，变化时发出 repo-changed 事件
+#[tauri::command]
+async fn watch_repo(
+    app: AppHandle,
+    watchers: State<'_, RepoWatchers>,
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+) -> Result<(), GitError> {
+    let session = sessions.get(&repo_id)?;
+    let cache = sessions.cache().clone();
+    let watchers = watchers.inner().clone();
+    run_blocking(move || watchers.watch(app, cache, &session)).await
+}
+
+#[tauri::command]
+fn unwatch_repo(watchers: State<'_, RepoWatchers>, repo_id: String) -> bool {
+    watchers.unwatch(&repo_id)
+}
+
+#[tauri::command]
+fn get_auto_fetch(auto_fetchers: State<'_, AutoFetchers>, repo_id: String) -> auto_fetch::AutoFetchSettings {
+    auto_fetchers.settings(&repo_id)
+}
+
+// 开启后定期抓取所有远程（不占用仓库的写锁，关闭或停止时取消正在进行的抓取），远程跟踪分支变化时发出 auto-fetch 事件
+#[tauri::command]
+fn set_auto_fetch(
+    app: AppHandle,
+    auto_fetchers: State<'_, AutoFetchers>,
+    sessions: State<'_, RepoSessions>,
+    repo_id: String,
+    enabled: bool,
+    interval_secs: Option<u64>,
+) -> Result<auto_fetch::AutoFetchSettings, GitError> {
+    if !enabled {
+        auto_fetchers.stop(&repo_id);
+        return Ok(auto_fetchers.settings(&repo_id));
+    }
+    let interval_secs = interval_secs.unwrap_or(auto_fetch::DEFAULT_AUTO_FETCH_INTERVAL_SECS);
+    auto_fetchers.start(app, sessions.inner().clone(), &repo_id, interval_secs)
+}
+
+#[tauri::command]
+async fn list_recent_repos(recent: State<'_, RecentRepos>) -> Result<Vec<repos::RecentRepoEntry>, GitError> {
+    let recent = recent.inner().clone();
+    run_blocking(move || Ok(recent.list())).await
+}
+
 #[tauri::command]
-async fn get_branches(repo_path: String) -> Result<Vec<git::GitBranch>, String> {
-    git::get_branches(&repo_path).map_err(|e| e.to_string())
+async fn add_recent_repo(recent: State<'_, RecentRepos>, repo_path: String, display_name: Option<String>) -> Result<repos::RecentRepo, GitError> {
+    let recent = recent.inner().clone();
+    run_blocking(move || recent.add(&repo_path, display_name.as_deref())).await
 }
 
 #[tauri::command]
-async fn get_remotes(repo_path: String) -> Result<Vec<git::GitRemote>, String> {
-    git::get_remotes(&repo_path).map_err(|e| e.to_string())
+async fn remove_recent_repo(recent: State<'_, RecentRepos>, repo_path: String) -> Result<bool, GitError> {
+    let recent = recent.inner().clone();
+    run_blocking(move || recent.remove(&repo_path)).await
 }
 
 #[tauri::command]
-async fn get_commits(repo_path: String) -> Result<Vec<git::GitCommit>, String> {
-    git::get_commits(&repo_path).map_err(|e| e.to_string())
+async fn set_recent_repo_pinned(recent: State<'_, RecentRepos>, repo_path: String, pinned: bool) -> Result<(), GitError> {
+    let recent = recent.inner().clone();
+    run_blocking(move || recent.set_pinned(&repo_path, pinned)).await
 }
 
 #[tauri::command]
-async fn get_commit_changes(repo_path: String, commit_hash: String) -> Result<Vec<git::GitCommitChange>, String> {
-    git::get_commit_changes(&repo_path, &commit_hash).map_err(|e| e.to_string())
+fn get_external_tools(tools: State<'_, ExternalTools>) -> external::ExternalToolSettings {
+    tools.get()
 }
 
 #[tauri::command]
-async fn get_commit_file_diff(repo_path: String, commit_hash: String, file_path: String) -> Result<git::GitCommitFileDiff, String> {
-    git::get_commit_file_diff(&repo_path, &commit_hash, &file_path).map_err(|e| e.to_string())
+async fn set_external_tools(tools: State<'_, ExternalTools>, settings: external::ExternalToolSettings) -> Result<(), GitError> {
+    let tools = tools.inner().clone();
+    run_blocking(move || tools.set(settings)).await
 }
 
+// line 从 1 开始，只有编辑器命令模板中含 {line} 时才生效
 #[tauri::command]
-async fn get_repo_info(repo_path: String) -> Result<git::GitRepoInfo, String> {
-    git::get_repo_info(&repo_path).map_err(|e| e.to_string())
+async fn open_in_editor(tools: State<'_, ExternalTools>, path: String, line: Option<u32>) -> Result<(), GitError> {
+    let tools = tools.inner().clone();
+    run_blocking(move || tools.open_in_editor(&path, line)).await
 }
 
 #[tauri::command]
-async fn get_status(repo_path: String) -> Result<Vec<git::GitStatus>, String> {
-    git::get_status(&repo_path).map_err(|e| e.to_string())
+async fn open_in_terminal(tools: State<'_, ExternalTools>, path: String) -> Result<(), GitError> {
+    let tools = tools.inner().clone();
+    run_blocking(move || tools.open_in_terminal(&path)).await
 }
 
 #[tauri::command]
-async fn checkout_branch(repo_path: String, branch_name: String) -> Result<(), String> {
-    git::checkout_branch(&repo_path, &branch_name).map_err(|e| e.to_string())
+async fn reveal_in_file_manager(path: String) -> Result<(), GitError> {
+    run_blocking(move || external::reveal_in_file_manager(&path)).await
 }
 
+// 前端的取消按钮：通知对应操作尽快停止，返回该操作是否仍在运行
 #[tauri::command]
-async fn get_worktrees(repo_path: String) -> Result<Vec<git::Worktree>, String> {
-    git::get_worktrees(&repo_path).map_err(|e| e.to_string())
+fn cancel_operation(operations: State<'_, OperationRegistry>, operation_id: String) -> bool {
+    operations.cancel(&operation_id)
 }
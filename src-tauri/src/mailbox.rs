@@ -0,0 +1,167 @@
+// 把 git format-patch 生成的邮件补丁逐个应用为提交（git am），保留原作者、日期和提交说明。
+// 状态保存在 git 自己的 rebase-apply/ 中，停下来之后也可以在命令行中 git am --continue
+use std::fs;
+use std::path::Path;
+
+use git2::{Oid, Repository, Sort};
+use serde::{Deserialize, Serialize};
+
+use crate::cancel::CancellationToken;
+use crate::error::{GitError, GitResult};
+use crate::git::{self, GitOperationCommit, GitOperationStatus};
+use crate::git_cli;
+use crate::mbox;
+
+pub const MAILBOX_PROGRESS_EVENT: &str = "mailbox-progress";
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitMailboxResult {
+    // 本次应用成功的提交，从旧到新
+    pub applied: Vec<GitOperationCommit>,
+    // 停在某个补丁上时 git 的说明，例如 "Patch failed at 0002 fix typo"
+    pub failed_patch: Option<String>,
+    pub message: Option<String>,
+    // 停下来时 kind 为 ApplyMailbox，step/total_steps 是当前补丁的序号，conflicts 是需要解决的文件
+    pub status: GitOperationStatus,
+}
+
+fn rebase_apply_number(repo: &Repository, name: &str) -> Option<usize> {
+    fs::read_to_string(repo.path().join("rebase-apply").join(name)).ok()?.trim().parse().ok()
+}
+
+fn is_applying_mailbox(repo: &Repository) -> bool {
+    repo.path().join("rebase-apply").join("applying").is_file()
+}
+
+fn head_oid(repo: &Repository) -> Option<Oid> {
+    repo.head().ok().and_then(|head| head.target())
+}
+
+fn commits_since(repo: &Repository, since: Option<Oid>) -> GitResult<Vec<GitOperationCommit>> {
+    let Some(head) = head_oid(repo) else {
+        return Ok(Vec::new());
+    };
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+    revwalk.push(head)?;
+    if let Some(since) = since {
+        revwalk.hide(since)?;
+    }
+    let mut commits = Vec::new();
+    for oid in revwalk {
+        let commit = repo.find_commit(oid?)?;
+        commits.push(GitOperationCommit {
+            hash: commit.id().to_string(),
+            summary: commit.summary().unwrap_or("").to_string(),
+        });
+    }
+    Ok(commits)
+}
+
+// git am 在另一个进程中修改索引，先从磁盘重新读取
+fn reload_status(repo: &Repository) -> GitResult<GitOperationStatus> {
+    repo.index()?.read(false)?;
+    git::get_operation_status(repo)
+}
+
+fn rebase_apply_position(repo: &Repository) -> Option<(usize, usize)> {
+    Some((rebase_apply_number(repo, "next")?, rebase_apply_number(repo, "last")?))
+}
+
+// 运行 git am，每开始应用一个补丁就回调 on_patch(序号, 总数, 标题)，total 是 mbox 中的邮件数。
+// 补丁无法应用时 git am 以非零退出并留下 rebase-apply/，这不是错误，返回停下来的状态
+fn run_am(
+    repo: &Repository,
+    args: &[&str],
+    total: usize,
+    cancel: &CancellationToken,
+    mut on_patch: impl FnMut(usize, usize, &str),
+) -> GitResult<GitMailboxResult> {
+    let workdir = repo.workdir().ok_or(GitError::BareRepository)?;
+    let start = head_oid(repo);
+    let mut command_args = vec!["am"];
+    command_args.extend_from_slice(args);
+
+    // 序号从 rebase-apply/next 读取；输出是在后台读取的，读到最后几行时 git am 可能已经结束并删除了
+    // rebase-apply/，这时按上一个补丁的序号递增
+    let mut position = rebase_apply_position(repo).map_or((0, total), |(next, last)| (next.saturating_sub(1), last));
+    let mut failed_patch = None;
+    let output = git_cli::run_git_with_progress(repo.path(), Some(workdir), &command_args, cancel, |line| {
+        let line = line.trim();
+        if let Some(subject) = line.strip_prefix("Applying: ") {
+            position = rebase_apply_position(repo).unwrap_or((position.0 + 1, position.1));
+            on_patch(position.0, position.1, subject);
+        } else if line.starts_with("Patch failed at ") {
+            failed_patch = Some(line.to_string());
+        }
+    });
+    let message = match output {
+        Ok(_) => None,
+        Err(GitError::CommandFailed { message, .. }) if is_applying_mailbox(repo) => {
+            let message = message.lines().filter(|line| !line.starts_with("hint:")).collect::<Vec<_>>().join("\n");
+            Some(message).filter(|message| !message.is_empty())
+        }
+        Err(e) => return Err(e),
+    };
+
+    Ok(GitMailboxResult {
+        applied: commits_since(repo, start)?,
+        failed_patch: if is_applying_mailbox(repo) { failed_patch } else { None },
+        message,
+        status: reload_status(repo)?,
+    })
+}
+
+// three_way 时补丁无法直接应用会用补丁记录的原始 blob 做三方合并，冲突留在索引中，
+// 可以和合并冲突一样解决后 mailbox_continue
+pub fn apply_mailbox(
+    repo: &Repository,
+    mbox_path: &str,
+    three_way: bool,
+    cancel: &CancellationToken,
+    on_patch: impl FnMut(usize, usize, &str),
+) -> GitResult<GitMailboxResult> {
+    if git::get_operation_status(repo)?.kind.is_some() {
+        return Err(GitError::invalid("another operation is in progress"));
+    }
+    let path = fs::canonicalize(Path::new(mbox_path)).map_err(|_| GitError::not_found(format!("mailbox file not found: {}", mbox_path)))?;
+    let total = mbox::split_mbox(&fs::read(&path)?).len();
+    let path_arg = path.to_string_lossy().to_string();
+    let mut args = vec![if three_way { "--3way" } else { "--no-3way" }];
+    // 丢弃没有改动的邮件，例如 format-patch --cover-letter 生成的封面信，否则 git am 会停在那里
+    args.push("--empty=drop");
+    args.push(&path_arg);
+    run_am(repo, &args, total, cancel, on_patch)
+}
+
+fn require_mailbox(repo: &Repository) -> GitResult<()> {
+    if !is_applying_mailbox(repo) {
+        return Err(GitError::invalid("no mailbox is being applied"));
+    }
+    Ok(())
+}
+
+// 冲突解决并加入暂存区后，提交当前补丁并继续应用剩下的补丁
+pub fn mailbox_continue(repo: &Repository, cancel: &CancellationToken, on_patch: impl FnMut(usize, usize, &str)) -> GitResult<GitMailboxResult> {
+    require_mailbox(repo)?;
+    let conflicts = reload_status(repo)?.conflicts;
+    if !conflicts.is_empty() {
+        return Err(GitError::MergeConflict { paths: conflicts });
+    }
+    run_am(repo, &["--continue"], 0, cancel, on_patch)
+}
+
+// 放弃当前补丁（丢弃它已经造成的改动），继续应用剩下的补丁
+pub fn mailbox_skip(repo: &Repository, cancel: &CancellationToken, on_patch: impl FnMut(usize, usize, &str)) -> GitResult<GitMailboxResult> {
+    require_mailbox(repo)?;
+    run_am(repo, &["--skip"], 0, cancel, on_patch)
+}
+
+// 中止并回到开始应用前的提交，已经应用的补丁也会被撤销
+pub fn mailbox_abort(repo: &Repository) -> GitResult<GitOperationStatus> {
+    require_mailbox(repo)?;
+    let workdir = repo.workdir().ok_or(GitError::BareRepository)?;
+    git_cli::run_git_with_progress(repo.path(), Some(workdir), &["am", "--abort"], &CancellationToken::default(), |_| {})?;
+    reload_status(repo)
+}
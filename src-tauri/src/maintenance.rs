@@ -0,0 +1,519 @@
+use std::collections::HashSet;
+use std::fs;
+use std::time::{Duration, Instant};
+
+use git2::{ObjectType, Oid, Repository};
+use serde::{Deserialize, Serialize};
+
+use crate::cancel::CancellationToken;
+use crate::commit_graph::{self, CommitGraphFile};
+use crate::disk_usage::SizeCache;
+use crate::error::GitResult;
+use crate::git;
+use crate::git_cli;
+use crate::pack::{self, PackIndex};
+use crate::repo_cache::common_dir;
+
+// 对象都存放在共享的主 .git 目录中，链接工作树也按它统计
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitStorageSizes {
+    pub git_dir_size_bytes: u64,
+    pub objects_size_bytes: u64,
+    pub packfiles_size_bytes: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitMaintenanceResult {
+    pub before: GitStorageSizes,
+    pub after: GitStorageSizes,
+    // git 的输出（stdout 和 stderr），供前端展示详情
+    pub output: String,
+}
+
+// 复用仓库信息的大小缓存；gc 增删 pack 和松散对象会改变所在目录的修改时间，之后的扫描会重新统计这些目录
+fn storage_sizes(repo: &Repository, sizes: &SizeCache, cancel: &CancellationToken) -> GitResult<GitStorageSizes> {
+    let git_dir = sizes.scan(&common_dir(repo.path()), None, false, cancel, &|_, _| {})?;
+    let subdir_size = |rel_path: &str| git_dir.descendant(rel_path).map_or(0, |dir| dir.total_bytes);
+    Ok(GitStorageSizes {
+        git_dir_size_bytes: git_dir.total_bytes,
+        objects_size_bytes: subdir_size("objects"),
+        packfiles_size_bytes: subdir_size("objects/pack"),
+    })
+}
+
+fn run_maintenance(repo: &Repository, args: &[&str], sizes: &SizeCache, cancel: &CancellationToken) -> GitResult<GitMaintenanceResult> {
+    let before = storage_sizes(repo, sizes, cancel)?;
+    let output = git_cli::run_git(&common_dir(repo.path()), args, cancel)?;
+    let after = storage_sizes(repo, sizes, cancel)?;
+    Ok(GitMaintenanceResult {
+        before,
+        after,
+        output: [output.stdout.trim(), output.stderr.trim()]
+            .into_iter()
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n"),
+    })
+}
+
+// aggressive 对应 git gc --aggressive，重新计算所有增量，耗时明显更长
+pub fn run_gc(repo: &Repository, aggressive: bool, sizes: &SizeCache, cancel: &CancellationToken) -> GitResult<GitMaintenanceResult> {
+    let mut args = vec!["gc"];
+    if aggressive {
+        args.push("--aggressive");
+    }
+    run_maintenance(repo, &args, sizes, cancel)
+}
+
+// 把所有对象合并到一个 pack 中并删除多余的 pack；旧 pack 中的不可达对象变为松散对象，留给 gc 按过期时间清理
+pub fn repack(repo: &Repository, sizes: &SizeCache, cancel: &CancellationToken) -> GitResult<GitMaintenanceResult> {
+    run_maintenance(repo, &["repack", "-A", "-d"], sizes, cancel)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub enum GitIntegrityIssueKind {
+    // 对象无法读取、内容与哈希不符或无法解析
+    Corrupt,
+    // 被引用或其它对象指向，但对象库中不存在
+    Missing,
+    // 没有任何引用、reflog、索引或其它对象指向的对象，通常可以被 gc 清理
+    Dangling,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitIntegrityIssue {
+    pub kind: GitIntegrityIssueKind,
+    pub oid: String,
+    // commit / tree / blob / tag，缺失或无法读取的对象为空
+    pub object_type: Option<String>,
+    pub message: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitIntegrityReport {
+    pub checked_objects: usize,
+    // 按 corrupt、missing、dangling 排序
+    pub issues: Vec<GitIntegrityIssue>,
+    // 问题超过 MAX_INTEGRITY_ISSUES 个时只返回前面的部分
+    pub truncated: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GitIntegrityProgress {
+    pub operation_id: Option<String>,
+    pub checked_objects: usize,
+    pub total_objects: usize,
+}
+
+pub const INTEGRITY_PROGRESS_EVENT: &str = "repo-integrity-progress";
+const INTEGRITY_PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+const MAX_INTEGRITY_ISSUES: usize = 5000;
+const GITLINK_MODE: i32 = 0o160000;
+
+#[derive(Default)]
+struct IntegrityCheck {
+    objects: HashSet<Oid>,
+    // 被其它对象指向的对象
+    referenced: HashSet<Oid>,
+    // 引用、reflog、索引等直接指向的对象
+    roots: HashSet<Oid>,
+    reported_missing: HashSet<Oid>,
+    issues: Vec<GitIntegrityIssue>,
+    // 浅克隆边界上的提交，它们的父提交本来就不在本地
+    shallow: HashSet<Oid>,
+    // 部分克隆中 .promisor 标记的 pack，其中对象指向的对象可以按需从远程获取
+    promisor_packs: Vec<PackIndex>,
+}
+
+impl IntegrityCheck {
+    fn issue(&mut self, kind: GitIntegrityIssueKind, oid: Oid, object_type: Option<ObjectType>, message: Option<String>) {
+        self.issues.push(GitIntegrityIssue {
+            kind,
+            oid: oid.to_string(),
+            object_type: object_type.map(|kind| kind.str().to_string()),
+            message,
+        });
+    }
+
+    fn root(&mut self, oid: Oid, source: &str) {
+        if oid.is_zero() || !self.roots.insert(oid) {
+            return;
+        }
+        if !self.objects.contains(&oid) && self.reported_missing.insert(oid) {
+            self.issue(GitIntegrityIssueKind::Missing, oid, None, Some(format!("referenced by {}", source)));
+        }
+    }
+
+    fn link(&mut self, from: Oid, from_type: ObjectType, to: Oid) {
+        self.referenced.insert(to);
+        if !self.objects.contains(&to) && !self.is_promised(from) && self.reported_missing.insert(to) {
+            self.issue(GitIntegrityIssueKind::Missing, to, None, Some(format!("referenced by {} {}", from_type.str(), from)));
+        }
+    }
+
+    fn is_promised(&self, from: Oid) -> bool {
+        self.promisor_packs.iter().any(|index| index.contains(from))
+    }
+
+    // 对象能读出（libgit2 读取时会校验哈希）并解析，再检查它指向的对象是否存在
+    fn check_object(&mut self, repo: &Repository, oid: Oid) {
+        let object = match repo.find_object(oid, None) {
+            Ok(object) => object,
+            Err(e) => {
+                self.issue(GitIntegrityIssueKind::Corrupt, oid, None, Some(e.message().to_string()));
+                return;
+            }
+        };
+        match object.kind() {
+            Some(ObjectType::Commit) => match object.as_commit() {
+                Some(commit) => {
+                    self.link(oid, ObjectType::Commit, commit.tree_id());
+                    for parent in commit.parent_ids() {
+                        if self.shallow.contains(&oid) {
+                            self.referenced.insert(parent);
+                        } else {
+                            self.link(oid, ObjectType::Commit, parent);
+                        }
+                    }
+                }
+                None => self.issue(GitIntegrityIssueKind::Corrupt, oid, object.kind(), Some("invalid commit".to_string())),
+            },
+            Some(ObjectType::Tree) => match object.as_tree() {
+                Some(tree) => {
+                    // 子模块记录的是其它仓库中的提交
+                    for entry in tree.iter().filter(|entry| entry.filemode() != GITLINK_MODE) {
+                        self.link(oid, ObjectType::Tree, entry.id());
+                    }
+                }
+                None => self.issue(GitIntegrityIssueKind::Corrupt, oid, object.kind(), Some("invalid tree".to_string())),
+            },
+            Some(ObjectType::Tag) => match object.as_tag() {
+                Some(tag) => self.link(oid, ObjectType::Tag, tag.target_id()),
+                None => self.issue(GitIntegrityIssueKind::Corrupt, oid, object.kind(), Some("invalid tag".to_string())),
+            },
+            _ => {}
+        }
+    }
+
+    // 和 git fsck 一样，所有引用、HEAD、reflog 记录和索引中的对象都视为可达的起点；链接工作树的 HEAD 和索引也算
+    fn collect_roots(&mut self, repo: &Repository) -> GitResult<()> {
+        let mut ref_names = vec!["HEAD".to_string()];
+        for reference in repo.references()? {
+            let reference = reference?;
+            if let Some(name) = reference.name() {
+                ref_names.push(name.to_string());
+            }
+        }
+        for name in &ref_names {
+            if let Ok(oid) = repo.refname_to_id(name) {
+                self.root(oid, name);
+            }
+            if let Ok(reflog) = repo.reflog(name) {
+                for entry in reflog.iter() {
+                    self.root(entry.id_old(), &format!("reflog of {}", name));
+                    self.root(entry.id_new(), &format!("reflog of {}", name));
+                }
+            }
+        }
+        self.index_roots(repo, "index")?;
+
+        for name in repo.worktrees()?.iter().flatten() {
+            let worktree_repo = match repo.find_worktree(name).and_then(|worktree| Repository::open_from_worktree(&worktree)) {
+                Ok(worktree_repo) => worktree_repo,
+                Err(_) => continue,
+            };
+            if let Ok(oid) = worktree_repo.refname_to_id("HEAD") {
+                self.root(oid, &format!("HEAD of worktree {}", name));
+            }
+            self.index_roots(&worktree_repo, &format!("index of worktree {}", name))?;
+        }
+        Ok(())
+    }
+
+    fn load_promisor_packs(&mut self, repo: &Repository) -> GitResult<()> {
+        let pack_dir = common_dir(repo.path()).join("objects").join("pack");
+        for entry in fs::read_dir(&pack_dir).map(|entries| entries.flatten().collect::<Vec<_>>()).unwrap_or_default() {
+            let path = entry.path();
+            if path.extension().is_some_and(|extension| extension == "promisor") && path.with_extension("idx").is_file() {
+                self.promisor_packs.push(PackIndex::open(&path.with_extension("idx"))?);
+            }
+        }
+        Ok(())
+    }
+
+    fn index_roots(&mut self, repo: &Repository, source: &str) -> GitResult<()> {
+        if repo.is_bare() {
+            return Ok(());
+        }
+        for entry in repo.index()?.iter() {
+            if entry.mode as i32 != GITLINK_MODE {
+                self.root(entry.id, source);
+            }
+        }
+        Ok(())
+    }
+}
+
+// 读取对象库中的每个对象并检查它们之间的链接，报告损坏、缺失和悬空的对象；
+// 遍历过程中每隔一段时间通过 on_progress 报告已检查和总共的对象数
+pub fn check_repo_integrity(
+    repo: &Repository,
+    cancel: &CancellationToken,
+    mut on_progress: impl FnMut(usize, usize),
+) -> GitResult<GitIntegrityReport> {
+    let mut check = IntegrityCheck {
+        shallow: git::shallow_commits(repo),
+        ..IntegrityCheck::default()
+    };
+    check.load_promisor_packs(repo)?;
+    let odb = repo.odb()?;
+    // 同一对象可能同时存在于多个 pack 或既是松散对象又在 pack 中
+    let mut scan_error = None;
+    odb.foreach(|oid| {
+        if let Err(e) = cancel.check() {
+            scan_error = Some(e);
+            return false;
+        }
+        check.objects.insert(*oid);
+        true
+    })?;
+    if let Some(e) = scan_error {
+        return Err(e);
+    }
+
+    let total_objects = check.objects.len();
+    let oids: Vec<Oid> = check.objects.iter().copied().collect();
+    let mut last_progress = Instant::now();
+    for (checked, oid) in oids.iter().enumerate() {
+        cancel.check()?;
+        check.check_object(repo, *oid);
+        if last_progress.elapsed() >= INTEGRITY_PROGRESS_INTERVAL {
+            on_progress(checked, total_objects);
+            last_progress = Instant::now();
+        }
+    }
+    on_progress(total_objects, total_objects);
+
+    check.collect_roots(repo)?;
+    let mut dangling: Vec<Oid> = oids
+        .into_iter()
+        .filter(|oid| !check.referenced.contains(oid) && !check.roots.contains(oid))
+        .collect();
+    dangling.sort();
+    for oid in dangling {
+        let object_type = odb.read_header(oid).ok().map(|(_, kind)| kind);
+        check.issue(GitIntegrityIssueKind::Dangling, oid, object_type, None);
+    }
+
+    let mut issues = check.issues;
+    issues.sort_by(|a, b| a.kind.cmp(&b.kind).then_with(|| a.oid.cmp(&b.oid)));
+    let truncated = issues.len() > MAX_INTEGRITY_ISSUES;
+    issues.truncate(MAX_INTEGRITY_ISSUES);
+    Ok(GitIntegrityReport {
+        checked_objects: total_objects,
+        issues,
+        truncated,
+    })
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitPackStats {
+    // pack 文件名，例如 pack-<hash>.pack
+    pub name: String,
+    pub object_count: usize,
+    pub size_bytes: u64,
+    pub delta_objects: usize,
+    pub max_delta_depth: usize,
+}
+
+// 对应 git count-objects -v 的各项，另外给出增量链的统计
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitObjectStats {
+    pub loose_objects: usize,
+    pub loose_size_bytes: u64,
+    pub pack_count: usize,
+    pub packed_objects: usize,
+    pub packs_size_bytes: u64,
+    // 已经打包、可以由 git prune-packed 删除的松散对象
+    pub prune_packable: usize,
+    // objects 和 objects/pack 中不属于任何有效对象或 pack 的文件（例如中断的 gc 留下的临时 pack）
+    pub garbage_files: usize,
+    pub garbage_size_bytes: u64,
+    pub delta_objects: usize,
+    pub max_delta_depth: usize,
+    pub average_delta_depth: f64,
+    pub packs: Vec<GitPackStats>,
+}
+
+fn is_hex(name: &str, len: usize) -> bool {
+    name.len() == len && name.bytes().all(|b| b.is_ascii_hexdigit() && !b.is_ascii_uppercase())
+}
+
+// pack 旁边的 .idx 以外的附属文件，不算作垃圾
+fn is_pack_sidecar(extension: &str) -> bool {
+    matches!(extension, "rev" | "bitmap" | "keep" | "promisor" | "mtimes")
+}
+
+pub fn get_object_stats(repo: &Repository, cancel: &CancellationToken) -> GitResult<GitObjectStats> {
+    let objects_dir = common_dir(repo.path()).join("objects");
+    let pack_dir = objects_dir.join("pack");
+    let mut stats = GitObjectStats {
+        loose_objects: 0,
+        loose_size_bytes: 0,
+        pack_count: 0,
+        packed_objects: 0,
+        packs_size_bytes: 0,
+        prune_packable: 0,
+        garbage_files: 0,
+        garbage_size_bytes: 0,
+        delta_objects: 0,
+        max_delta_depth: 0,
+        average_delta_depth: 0.0,
+        packs: Vec::new(),
+    };
+
+    // 只有 .pack 和 .idx 成对存在的才是有效的 pack
+    let mut indexes = Vec::new();
+    let mut total_depth = 0_u64;
+    let mut pack_files: Vec<_> = fs::read_dir(&pack_dir).map(|entries| entries.flatten().collect()).unwrap_or_default();
+    pack_files.sort_by_key(|entry| entry.file_name());
+    for entry in &pack_files {
+        cancel.check()?;
+        let path = entry.path();
+        let size = entry.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        let has_pack = path.with_extension("pack").is_file();
+        let has_index = path.with_extension("idx").is_file();
+        match extension {
+            "pack" if has_index => {
+                let index = PackIndex::open(&path.with_extension("idx"))?;
+                let deltas = pack::delta_stats(&path, &index, cancel)?;
+                stats.pack_count += 1;
+                stats.packed_objects += index.len();
+                stats.packs_size_bytes += size;
+                stats.delta_objects += deltas.delta_objects;
+                stats.max_delta_depth = stats.max_delta_depth.max(deltas.max_depth);
+                total_depth += deltas.total_depth;
+                stats.packs.push(GitPackStats {
+                    name: entry.file_name().to_string_lossy().to_string(),
+                    object_count: index.len(),
+                    size_bytes: size,
+                    delta_objects: deltas.delta_objects,
+                    max_delta_depth: deltas.max_depth,
+                });
+                indexes.push(index);
+            }
+            "idx" if has_pack => {}
+            extension if is_pack_sidecar(extension) && has_pack => {}
+            // multi-pack-index 等与单个 pack 无关的文件
+            _ if path.file_name().is_some_and(|name| name.to_string_lossy().starts_with("multi-pack-index")) => {}
+            _ => {
+                stats.garbage_files += 1;
+                stats.garbage_size_bytes += size;
+            }
+        }
+    }
+    if stats.delta_objects > 0 {
+        stats.average_delta_depth = total_depth as f64 / stats.delta_objects as f64;
+    }
+
+    for dir in fs::read_dir(&objects_dir).map(|entries| entries.flatten().collect::<Vec<_>>()).unwrap_or_default() {
+        let dir_name = dir.file_name().to_string_lossy().to_string();
+        if !is_hex(&dir_name, 2) || !dir.path().is_dir() {
+            continue;
+        }
+        cancel.check()?;
+        for file in fs::read_dir(dir.path())?.flatten() {
+            let file_name = file.file_name().to_string_lossy().to_string();
+            let size = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+            let oid = match Oid::from_str(&format!("{}{}", dir_name, file_name)) {
+                Ok(oid) if is_hex(&file_name, 38) => oid,
+                _ => {
+                    stats.garbage_files += 1;
+                    stats.garbage_size_bytes += size;
+                    continue;
+                }
+            };
+            stats.loose_objects += 1;
+            stats.loose_size_bytes += size;
+            if indexes.iter().any(|index| index.contains(oid)) {
+                stats.prune_packable += 1;
+            }
+        }
+    }
+    Ok(stats)
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitCommitGraphStatus {
+    // objects/info/commit-graph 存在且可以读取
+    pub exists: bool,
+    // 存在 git commit-graph write --split 生成的链；libgit2 不读取它，需要重新生成单个文件
+    pub split_chain: bool,
+    pub commit_count: usize,
+    pub size_bytes: u64,
+    // 不在 commit-graph 中的分支和远程分支，生成之后的新提交只能逐个解析，数量多时应重新生成
+    pub missing_tips: Vec<String>,
+}
+
+pub fn get_commit_graph_status(repo: &Repository) -> GitResult<GitCommitGraphStatus> {
+    let objects_dir = common_dir(repo.path()).join("objects");
+    let split_chain = commit_graph::graph_chain_path(&objects_dir).is_file();
+    let graph_path = commit_graph::graph_file_path(&objects_dir);
+    let graph = match CommitGraphFile::open(&graph_path) {
+        Ok(graph) => graph,
+        // 文件不存在或已损坏都按没有处理，重新生成即可
+        Err(e) => {
+            if graph_path.exists() {
+                log::warn!("ignoring unreadable commit-graph: {}", e);
+            }
+            return Ok(GitCommitGraphStatus {
+                exists: false,
+                split_chain,
+                commit_count: 0,
+                size_bytes: 0,
+                missing_tips: Vec::new(),
+            });
+        }
+    };
+
+    let mut missing_tips = Vec::new();
+    for reference in repo.references()? {
+        let reference = reference?;
+        if !(reference.is_branch() || reference.is_remote()) || reference.symbolic_target().is_some() {
+            continue;
+        }
+        let tip = match reference.peel_to_commit() {
+            Ok(commit) => commit.id(),
+            Err(_) => continue,
+        };
+        if !graph.contains(tip) {
+            missing_tips.push(reference.shorthand().unwrap_or("").to_string());
+        }
+    }
+    missing_tips.sort();
+
+    Ok(GitCommitGraphStatus {
+        exists: true,
+        split_chain,
+        commit_count: graph.len(),
+        size_bytes: graph.size_bytes(),
+        missing_tips,
+    })
+}
+
+// 为所有引用可达的提交生成单个 commit-graph 文件（不加 --split，已有的链会被合并替换），
+// 之后 libgit2 遍历历史、计算领先/落后和 describe 时可以直接读取父提交和代数而不必解压提交对象
+pub fn write_commit_graph(repo: &Repository, cancel: &CancellationToken) -> GitResult<GitCommitGraphStatus> {
+    git_cli::run_git(&common_dir(repo.path()), &["commit-graph", "write", "--reachable", "--no-progress"], cancel)?;
+    get_commit_graph_status(repo)
+}
@@ -0,0 +1,94 @@
+// 拆分 git format-patch 生成的 mbox 文件。每封邮件以 "From <提交> <日期>" 行开头，
+// 正文中以 "From " 开头的行会被转义为 ">From "，所以可以直接按行首的 "From " 拆分。
+// 单封邮件（.eml）直接以邮件头开头，普通的 .diff/.patch 没有邮件头
+
+// 一封邮件（或整个普通补丁），body 包含邮件头，libgit2 解析补丁时会跳过 diff 之前的内容
+pub struct MailPatch<'a> {
+    pub author: Option<String>,
+    pub subject: Option<String>,
+    pub date: Option<String>,
+    pub body: &'a [u8],
+}
+
+fn is_header_line(line: &str) -> bool {
+    line.split_once(':').is_some_and(|(name, _)| {
+        !name.is_empty() && name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+    })
+}
+
+// 去掉 git am 也会去掉的 "[PATCH v2 1/3]" 之类的前缀
+fn clean_subject(subject: &str) -> String {
+    let mut subject = subject.trim();
+    while let Some(rest) = subject.strip_prefix('[') {
+        match rest.split_once(']') {
+            Some((_, rest)) => subject = rest.trim_start(),
+            None => break,
+        }
+    }
+    subject.to_string()
+}
+
+fn parse_mail(body: &[u8], skip_first_line: bool) -> MailPatch<'_> {
+    let text = String::from_utf8_lossy(body);
+    let mut lines = text.lines();
+    if skip_first_line {
+        lines.next();
+    }
+
+    // 邮件头以空行结束，以空白开头的行是上一个头的续行
+    let mut headers: Vec<(String, String)> = Vec::new();
+    for line in lines {
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+            continue;
+        }
+        match line.split_once(':') {
+            Some((name, value)) if is_header_line(line) => headers.push((name.to_ascii_lowercase(), value.trim().to_string())),
+            _ => break,
+        }
+    }
+    let header = |name: &str| headers.iter().find(|(key, _)| key == name).map(|(_, value)| value.clone());
+    MailPatch {
+        author: header("from"),
+        subject: header("subject").map(|subject| clean_subject(&subject)),
+        date: header("date"),
+        body,
+    }
+}
+
+// 系列的封面信等邮件只有说明，没有改动
+pub fn has_diff(body: &[u8]) -> bool {
+    body.split(|&b| b == b'\n').any(|line| line.starts_with(b"diff --git ") || line.starts_with(b"--- "))
+}
+
+pub fn split_mbox(data: &[u8]) -> Vec<MailPatch<'_>> {
+    if !data.starts_with(b"From ") {
+        let first_line = data.split(|&b| b == b'\n').next().unwrap_or_default();
+        if is_header_line(&String::from_utf8_lossy(first_line)) {
+            return vec![parse_mail(data, false)];
+        }
+        return vec![MailPatch {
+            author: None,
+            subject: None,
+            date: None,
+            body: data,
+        }];
+    }
+
+    let mut starts = vec![0];
+    let mut at = 0;
+    while let Some(offset) = data[at..].iter().position(|&b| b == b'\n') {
+        at += offset + 1;
+        if data[at..].starts_with(b"From ") {
+            starts.push(at);
+        }
+    }
+    starts.push(data.len());
+    starts.windows(2).map(|range| parse_mail(&data[range[0]..range[1]], true)).collect()
+}
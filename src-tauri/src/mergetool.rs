@@ -0,0 +1,109 @@
+// 用用户在 git 中配置的外部比较/合并工具打开文件。临时文件的生成、工具的调用（包括内置支持的
+// meld、kdiff3、bc 等和 difftool.<tool>.cmd 自定义命令）以及合并结果的判断都交给 git difftool / mergetool，
+// 行为和命令行一致。子进程没有终端，vimdiff 之类的终端工具无法使用
+use git2::{ErrorCode, Repository};
+use serde::{Deserialize, Serialize};
+
+use crate::cancel::CancellationToken;
+use crate::error::{GitError, GitResult};
+use crate::git_cli;
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitMergetoolResult {
+    pub path: String,
+    pub tool: String,
+    // 工具退出后文件已不在冲突中（git mergetool 会把解决后的文件加入暂存区）
+    pub resolved: bool,
+    // 没有解决时 git mergetool 的输出，例如 "merge of a.txt failed"
+    pub message: Option<String>,
+}
+
+fn config_tool(repo: &Repository, keys: &[&str]) -> GitResult<Option<String>> {
+    let config = repo.config()?;
+    for key in keys {
+        match config.get_string(key) {
+            Ok(tool) if !tool.trim().is_empty() => return Ok(Some(tool.trim().to_string())),
+            Ok(_) => {}
+            Err(e) if e.code() == ErrorCode::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(None)
+}
+
+// 优先使用图形界面的工具；和 git difftool 一样，没有配置比较工具时使用合并工具。
+// 不让 git 自行猜测，否则可能选中需要终端的工具而一直挂起
+fn resolve_tool(repo: &Repository, tool: Option<&str>, keys: &[&str], kind: &str) -> GitResult<String> {
+    match tool.map(str::trim).filter(|tool| !tool.is_empty()) {
+        Some(tool) => Ok(tool.to_string()),
+        None => config_tool(repo, keys)?.ok_or_else(|| GitError::invalid(format!("no {} tool is configured", kind))),
+    }
+}
+
+// 等外部工具关闭后返回。只传 left_rev 时和工作区比较，都不传时比较暂存区和工作区
+pub fn launch_external_difftool(
+    repo: &Repository,
+    file_path: &str,
+    left_rev: Option<&str>,
+    right_rev: Option<&str>,
+    tool: Option<&str>,
+    cancel: &CancellationToken,
+) -> GitResult<String> {
+    let tool = resolve_tool(repo, tool, &["diff.guitool", "diff.tool", "merge.guitool", "merge.tool"], "diff")?;
+    if left_rev.is_none() && right_rev.is_some() {
+        return Err(GitError::invalid("left_rev is required when right_rev is given"));
+    }
+    if repo.workdir().is_none() && right_rev.is_none() {
+        return Err(GitError::BareRepository);
+    }
+    let mut revs = Vec::new();
+    for rev in [left_rev, right_rev].into_iter().flatten() {
+        revs.push(repo.revparse_single(rev)?.peel_to_commit()?.id().to_string());
+    }
+
+    let tool_arg = format!("--tool={}", tool);
+    let mut args = vec!["difftool", "--no-prompt", &tool_arg];
+    args.extend(revs.iter().map(String::as_str));
+    args.extend(["--", file_path]);
+    git_cli::run_git_with_progress(repo.path(), repo.workdir(), &args, cancel, |_| {})?;
+    Ok(tool)
+}
+
+fn is_conflicted(repo: &Repository, file_path: &str) -> GitResult<bool> {
+    // git mergetool 在另一个进程中修改索引，先从磁盘重新读取
+    let mut index = repo.index()?;
+    index.read(false)?;
+    let conflicted = index.conflicts()?.flatten().any(|conflict| {
+        [&conflict.our, &conflict.their, &conflict.ancestor]
+            .into_iter()
+            .flatten()
+            .any(|entry| entry.path == file_path.as_bytes())
+    });
+    Ok(conflicted)
+}
+
+// 用合并工具解决一个冲突文件，等工具关闭后根据索引判断是否已解决
+pub fn launch_external_mergetool(repo: &Repository, file_path: &str, tool: Option<&str>, cancel: &CancellationToken) -> GitResult<GitMergetoolResult> {
+    let workdir = repo.workdir().ok_or(GitError::BareRepository)?;
+    if !is_conflicted(repo, file_path)? {
+        return Err(GitError::not_found(format!("'{}' has no conflict", file_path)));
+    }
+    let tool = resolve_tool(repo, tool, &["merge.guitool", "merge.tool"], "merge")?;
+    let tool_arg = format!("--tool={}", tool);
+
+    let output = git_cli::run_git_with_progress(repo.path(), Some(workdir), &["mergetool", "--no-prompt", &tool_arg, "--", file_path], cancel, |_| {});
+    // git mergetool 在工具报告失败或文件没有改动时以非零退出，这不是错误，只是还没有解决
+    let message = match output {
+        Ok(_) => None,
+        Err(GitError::CommandFailed { message, .. }) => Some(message).filter(|message| !message.is_empty()),
+        Err(e) => return Err(e),
+    };
+    let resolved = !is_conflicted(repo, file_path)?;
+    Ok(GitMergetoolResult {
+        path: file_path.to_string(),
+        tool,
+        resolved,
+        message: if resolved { None } else { message },
+    })
+}
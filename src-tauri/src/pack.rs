@@ -0,0 +1,220 @@
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use git2::Oid;
+
+use crate::cancel::CancellationToken;
+use crate::error::{GitError, GitResult};
+
+// pack 索引与 pack 文件的格式参见 git 的 Documentation/gitformat-pack.txt
+const IDX_V2_MAGIC: [u8; 4] = [0xff, b't', b'O', b'c'];
+const FANOUT_SIZE: usize = 256 * 4;
+const OID_SIZE: usize = 20;
+const OBJ_OFS_DELTA: u8 = 6;
+const OBJ_REF_DELTA: u8 = 7;
+// 对象头（类型和大小）最长 10 字节，加上 REF_DELTA 的基对象 id
+const MAX_ENTRY_HEADER: usize = 10 + OID_SIZE;
+
+fn be_u32(data: &[u8], at: usize) -> u32 {
+    u32::from_be_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]])
+}
+
+// .idx 文件整体读入内存，按 fanout 表二分查找
+pub struct PackIndex {
+    data: Vec<u8>,
+    version: u32,
+    count: usize,
+}
+
+impl PackIndex {
+    pub fn open(path: &Path) -> GitResult<PackIndex> {
+        let data = fs::read(path)?;
+        let invalid = || GitError::invalid(format!("invalid pack index: {}", path.display()));
+        let version = if data.starts_with(&IDX_V2_MAGIC) {
+            if data.len() < 8 || be_u32(&data, 4) != 2 {
+                return Err(invalid());
+            }
+            2
+        } else {
+            1
+        };
+        let fanout = if version == 2 { 8 } else { 0 };
+        if data.len() < fanout + FANOUT_SIZE {
+            return Err(invalid());
+        }
+        let count = be_u32(&data, fanout + FANOUT_SIZE - 4) as usize;
+        let entries_size = if version == 2 { count * (OID_SIZE + 8) } else { count * (OID_SIZE + 4) };
+        if data.len() < fanout + FANOUT_SIZE + entries_size {
+            return Err(invalid());
+        }
+        Ok(PackIndex { data, version, count })
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    fn fanout_start(&self) -> usize {
+        if self.version == 2 {
+            8
+        } else {
+            0
+        }
+    }
+
+    fn oid_at(&self, i: usize) -> &[u8] {
+        let table = self.fanout_start() + FANOUT_SIZE;
+        let at = if self.version == 2 { table + i * OID_SIZE } else { table + i * (OID_SIZE + 4) + 4 };
+        &self.data[at..at + OID_SIZE]
+    }
+
+    // v2 中最高位为 1 的偏移量是 64 位偏移量表的下标
+    pub fn offset_at(&self, i: usize) -> u64 {
+        let table = self.fanout_start() + FANOUT_SIZE;
+        if self.version == 1 {
+            return be_u32(&self.data, table + i * (OID_SIZE + 4)) as u64;
+        }
+        let offsets = table + self.count * (OID_SIZE + 4);
+        let offset = be_u32(&self.data, offsets + i * 4);
+        if offset & 0x8000_0000 == 0 {
+            return offset as u64;
+        }
+        let at = offsets + self.count * 4 + (offset & 0x7fff_ffff) as usize * 8;
+        match self.data.get(at..at + 8) {
+            Some(bytes) => u64::from_be_bytes(bytes.try_into().unwrap_or_default()),
+            None => 0,
+        }
+    }
+
+    pub fn find(&self, oid: &[u8]) -> Option<usize> {
+        let fanout = self.fanout_start();
+        let first = oid[0] as usize;
+        let start = if first == 0 { 0 } else { be_u32(&self.data, fanout + (first - 1) * 4) as usize };
+        let end = (be_u32(&self.data, fanout + first * 4) as usize).min(self.count);
+        let (mut low, mut high) = (start, end);
+        while low < high {
+            let mid = (low + high) / 2;
+            match self.oid_at(mid).cmp(oid) {
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+                std::cmp::Ordering::Equal => return Some(mid),
+            }
+        }
+        None
+    }
+
+    pub fn contains(&self, oid: Oid) -> bool {
+        self.find(oid.as_bytes()).is_some()
+    }
+}
+
+#[derive(Default)]
+pub struct DeltaStats {
+    pub delta_objects: usize,
+    pub max_depth: usize,
+    // 所有增量对象链长之和，用于计算平均链长
+    pub total_depth: u64,
+}
+
+enum DeltaBase {
+    // 基对象在 pack 中的偏移量
+    Offset(u64),
+    Oid([u8; OID_SIZE]),
+}
+
+// 读取对象头，返回它的增量基对象（非增量对象为 None）
+fn read_delta_base(pack: &mut File, offset: u64) -> GitResult<Option<DeltaBase>> {
+    let mut header = [0_u8; MAX_ENTRY_HEADER];
+    pack.seek(SeekFrom::Start(offset))?;
+    let mut filled = 0;
+    while filled < header.len() {
+        match pack.read(&mut header[filled..])? {
+            0 => break,
+            read => filled += read,
+        }
+    }
+    let header = &header[..filled];
+    let invalid = || GitError::invalid(format!("invalid pack entry at offset {}", offset));
+
+    let kind = (header.first().ok_or_else(invalid)? >> 4) & 0x7;
+    // 跳过变长编码的对象大小
+    let mut at = 0;
+    while header.get(at).ok_or_else(invalid)? & 0x80 != 0 {
+        at += 1;
+    }
+    at += 1;
+
+    match kind {
+        OBJ_OFS_DELTA => {
+            let mut byte = *header.get(at).ok_or_else(invalid)?;
+            let mut distance = (byte & 0x7f) as u64;
+            while byte & 0x80 != 0 {
+                at += 1;
+                byte = *header.get(at).ok_or_else(invalid)?;
+                distance = ((distance + 1) << 7) | (byte & 0x7f) as u64;
+            }
+            Ok(Some(DeltaBase::Offset(offset.checked_sub(distance).ok_or_else(invalid)?)))
+        }
+        OBJ_REF_DELTA => {
+            let oid = header.get(at..at + OID_SIZE).ok_or_else(invalid)?;
+            Ok(Some(DeltaBase::Oid(oid.try_into().map_err(|_| invalid())?)))
+        }
+        _ => Ok(None),
+    }
+}
+
+// 只读取每个对象的头部，不解压内容；按偏移量顺序读取，尽量顺序访问磁盘
+pub fn delta_stats(pack_path: &Path, index: &PackIndex, cancel: &CancellationToken) -> GitResult<DeltaStats> {
+    let mut pack = File::open(pack_path)?;
+    let mut offsets: Vec<u64> = (0..index.len()).map(|i| index.offset_at(i)).collect();
+    offsets.sort_unstable();
+
+    // 每个对象的基对象在 offsets 中的下标
+    let mut bases: Vec<Option<usize>> = Vec::with_capacity(offsets.len());
+    for (i, offset) in offsets.iter().enumerate() {
+        if i % 4096 == 0 {
+            cancel.check()?;
+        }
+        let base_offset = match read_delta_base(&mut pack, *offset)? {
+            None => None,
+            Some(DeltaBase::Offset(base)) => Some(base),
+            Some(DeltaBase::Oid(oid)) => index.find(&oid).map(|i| index.offset_at(i)),
+        };
+        bases.push(base_offset.and_then(|base| offsets.binary_search(&base).ok()));
+    }
+
+    // 沿增量链找到已知链长的对象，再回填链上每个对象的链长；链中出现环时按损坏处理，不再深入
+    let mut depths: Vec<Option<usize>> = vec![None; offsets.len()];
+    let mut stats = DeltaStats::default();
+    let mut chain = Vec::new();
+    for start in 0..offsets.len() {
+        chain.clear();
+        let mut current = start;
+        let mut depth = loop {
+            if let Some(depth) = depths[current] {
+                break depth;
+            }
+            match bases[current] {
+                Some(base) if chain.len() <= offsets.len() => {
+                    chain.push(current);
+                    current = base;
+                }
+                _ => {
+                    depths[current] = Some(0);
+                    break 0;
+                }
+            }
+        };
+        for object in chain.iter().rev() {
+            depth += 1;
+            depths[*object] = Some(depth);
+        }
+    }
+    for depth in depths.into_iter().flatten().filter(|depth| *depth > 0) {
+        stats.delta_objects += 1;
+        stats.max_depth = stats.max_depth.max(depth);
+        stats.total_depth += depth as u64;
+    }
+    Ok(stats)
+}
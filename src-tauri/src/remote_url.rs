@@ -0,0 +1,367 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{GitError, GitResult};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum RemoteProvider {
+    GitHub,
+    GitLab,
+    Bitbucket,
+    Gitea,
+}
+
+// 要在网页上打开的对象
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum RemoteWebTarget {
+    Commit { hash: String },
+    // line / end_line 从 1 开始，end_line 为空时只高亮一行
+    File { rev: String, path: String, line: Option<u32>, end_line: Option<u32> },
+    Branch { name: String },
+    Compare { base: String, head: String },
+    // 新建 pull request / merge request 的页面，已填好目标分支 base 和源分支 head
+    PullRequest { base: String, head: String },
+    Issue { number: u64 },
+    // 已有的 pull request / merge request
+    MergeRequest { number: u64 },
+}
+
+// 远程仓库在网页上的位置，base_url 形如 https://github.com/owner/repo
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteWebRepo {
+    pub provider: RemoteProvider,
+    pub base_url: String,
+}
+
+// 自建实例无法可靠识别，只按主机名中的常见字样猜测
+fn provider_for_host(host: &str) -> Option<RemoteProvider> {
+    let host = host.to_lowercase();
+    if host.contains("github") {
+        Some(RemoteProvider::GitHub)
+    } else if host.contains("gitlab") {
+        Some(RemoteProvider::GitLab)
+    } else if host.contains("bitbucket") {
+        Some(RemoteProvider::Bitbucket)
+    } else if host.contains("gitea") || host.contains("codeberg") || host.contains("forgejo") {
+        Some(RemoteProvider::Gitea)
+    } else {
+        None
+    }
+}
+
+// 拆出主机名和仓库路径，支持 https://host/path、ssh://user@host:port/path 和 scp 形式的 user@host:path
+fn host_and_path(url: &str) -> Option<(String, String)> {
+    let url = url.trim();
+    let (host, path) = match url.split_once("://") {
+        Some((scheme, rest)) => {
+            if !matches!(scheme, "http" | "https" | "ssh" | "git" | "git+ssh" | "ssh+git") {
+                return None;
+            }
+            let (authority, path) = rest.split_once('/')?;
+            let host = authority.rsplit('@').next()?;
+            // SSH 端口与网页无关，http(s) 的端口则保留
+            let host = if scheme.starts_with("http") {
+                host.to_string()
+            } else {
+                host.split(':').next()?.to_string()
+            };
+            (host, path.to_string())
+        }
+        None => {
+            let (authority, path) = url.split_once(':')?;
+            if authority.contains('/') {
+                return None;
+            }
+            (authority.rsplit('@').next()?.to_string(), path.to_string())
+        }
+    };
+    let path = path.trim_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path).trim_end_matches('/');
+    if host.is_empty() || path.is_empty() {
+        return None;
+    }
+    Some((host, path.to_string()))
+}
+
+pub fn parse(url: &str) -> Option<RemoteWebRepo> {
+    let (host, path) = host_and_path(url)?;
+    let provider = provider_for_host(&host)?;
+    // Bitbucket Server 的 HTTPS 克隆地址带有 scm/ 前缀，网页地址没有
+    let path = path.strip_prefix("scm/").unwrap_or(&path);
+    let scheme = if url.trim().starts_with("http://") { "http" } else { "https" };
+    Some(RemoteWebRepo {
+        provider,
+        base_url: format!("{}://{}/{}", scheme, host, path),
+    })
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum RemoteTransport {
+    Https,
+    Http,
+    Ssh,
+    // git:// 协议，不加密也不认证
+    Git,
+    // 本地路径或 file://
+    File,
+}
+
+// 克隆地址的组成部分，用于在连接之前检查地址是否有效
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteUrlParts {
+    pub transport: RemoteTransport,
+    pub user: Option<String>,
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub path: String,
+}
+
+fn is_valid_host(host: &str) -> bool {
+    // [::1] 形式的 IPv6 地址
+    if let Some(address) = host.strip_prefix('[').and_then(|host| host.strip_suffix(']')) {
+        return !address.is_empty() && address.chars().all(|c| c.is_ascii_hexdigit() || c == ':' || c == '.');
+    }
+    !host.is_empty() && !host.starts_with(['-', '.']) && host.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_'))
+}
+
+// 拆出 user@host:port
+fn split_authority(authority: &str, allow_port: bool) -> GitResult<(Option<String>, String, Option<u16>)> {
+    let (user, host_port) = match authority.rsplit_once('@') {
+        Some((user, host_port)) => (Some(user.to_string()).filter(|user| !user.is_empty()), host_port),
+        None => (None, authority),
+    };
+    // IPv6 地址本身带冒号，端口在 ] 之后
+    let port_at = match host_port.rfind(']') {
+        Some(end) => host_port[end..].find(':').map(|offset| end + offset),
+        None => host_port.find(':'),
+    };
+    let (host, port) = match port_at {
+        Some(at) if allow_port => {
+            let port = &host_port[at + 1..];
+            let port = if port.is_empty() {
+                None
+            } else {
+                Some(port.parse::<u16>().ok().filter(|port| *port > 0).ok_or_else(|| GitError::invalid(format!("invalid port: {}", port)))?)
+            };
+            (&host_port[..at], port)
+        }
+        _ => (host_port, None),
+    };
+    if !is_valid_host(host) {
+        return Err(GitError::invalid(format!("invalid host name: {}", host)));
+    }
+    Ok((user, host.to_string(), port))
+}
+
+// 和 git 一样识别 scheme://、scp 形式的 user@host:path 和本地路径；本地路径必须是绝对路径，
+// 否则会相对于程序的工作目录解析
+pub fn parse_remote_url(url: &str) -> GitResult<RemoteUrlParts> {
+    let url = url.trim();
+    if url.is_empty() {
+        return Err(GitError::invalid("URL is empty"));
+    }
+    if url.chars().any(char::is_control) {
+        return Err(GitError::invalid("URL must not contain control characters"));
+    }
+
+    if let Some((scheme, rest)) = url.split_once("://") {
+        let transport = match scheme.to_ascii_lowercase().as_str() {
+            "https" => RemoteTransport::Https,
+            "http" => RemoteTransport::Http,
+            "ssh" | "git+ssh" | "ssh+git" => RemoteTransport::Ssh,
+            "git" => RemoteTransport::Git,
+            "file" => RemoteTransport::File,
+            _ => return Err(GitError::invalid(format!("unsupported protocol: {}", scheme))),
+        };
+        if transport == RemoteTransport::File {
+            if rest.is_empty() {
+                return Err(GitError::invalid("repository path is missing"));
+            }
+            return Ok(RemoteUrlParts {
+                transport,
+                user: None,
+                host: None,
+                port: None,
+                path: rest.to_string(),
+            });
+        }
+        // URL 中的空格要写成 %20，本地路径和 scp 形式中则可以有空格
+        if rest.contains(' ') {
+            return Err(GitError::invalid("URL must not contain spaces"));
+        }
+        let (authority, path) = rest.split_once('/').unwrap_or((rest, ""));
+        let (user, host, port) = split_authority(authority, true)?;
+        let path = path.split(['?', '#']).next().unwrap_or("").trim_matches('/');
+        if path.is_empty() {
+            return Err(GitError::invalid("repository path is missing"));
+        }
+        return Ok(RemoteUrlParts {
+            transport,
+            user,
+            host: Some(host),
+            port,
+            path: path.to_string(),
+        });
+    }
+
+    // scp 形式：冒号之前没有 /，且不是 Windows 的盘符（C:\repo）
+    if let Some((authority, path)) = url.split_once(':') {
+        let is_drive = authority.len() == 1 && authority.chars().all(|c| c.is_ascii_alphabetic());
+        if !authority.contains('/') && !authority.contains('\\') && !is_drive {
+            let (user, host, _) = split_authority(authority, false)?;
+            let path = path.trim_matches('/');
+            if path.is_empty() {
+                return Err(GitError::invalid("repository path is missing"));
+            }
+            return Ok(RemoteUrlParts {
+                transport: RemoteTransport::Ssh,
+                user,
+                host: Some(host),
+                port: None,
+                path: path.to_string(),
+            });
+        }
+    }
+
+    if !Path::new(url).is_absolute() {
+        return Err(GitError::invalid("local repository path must be absolute"));
+    }
+    Ok(RemoteUrlParts {
+        transport: RemoteTransport::File,
+        user: None,
+        host: None,
+        port: None,
+        path: url.to_string(),
+    })
+}
+
+// 按 URL 路径段的规则转义，保留 /
+fn encode_path(path: &str) -> String {
+    let mut encoded = String::new();
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+// 查询参数的值，/ 也要转义
+pub fn encode_component(value: &str) -> String {
+    encode_path(value).replace('/', "%2F")
+}
+
+fn is_commit_hash(rev: &str) -> bool {
+    rev.len() >= 7 && rev.len() <= 40 && rev.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+impl RemoteWebRepo {
+    pub fn url_for(&self, target: &RemoteWebTarget) -> String {
+        match target {
+            RemoteWebTarget::Commit { hash } => self.commit_url(hash),
+            RemoteWebTarget::File { rev, path, line, end_line } => self.file_url(rev, path, *line, *end_line),
+            RemoteWebTarget::Branch { name } => self.branch_url(name),
+            RemoteWebTarget::Compare { base, head } => self.compare_url(base, head),
+            RemoteWebTarget::PullRequest { base, head } => self.pull_request_url(base, head),
+            RemoteWebTarget::Issue { number } => match self.provider {
+                RemoteProvider::GitLab => format!("{}/-/issues/{}", self.base_url, number),
+                _ => format!("{}/issues/{}", self.base_url, number),
+            },
+            RemoteWebTarget::MergeRequest { number } => match self.provider {
+                RemoteProvider::GitHub => format!("{}/pull/{}", self.base_url, number),
+                RemoteProvider::GitLab => format!("{}/-/merge_requests/{}", self.base_url, number),
+                RemoteProvider::Bitbucket => format!("{}/pull-requests/{}", self.base_url, number),
+                RemoteProvider::Gitea => format!("{}/pulls/{}", self.base_url, number),
+            },
+        }
+    }
+
+    fn commit_url(&self, hash: &str) -> String {
+        match self.provider {
+            RemoteProvider::GitHub | RemoteProvider::Gitea => format!("{}/commit/{}", self.base_url, hash),
+            RemoteProvider::GitLab => format!("{}/-/commit/{}", self.base_url, hash),
+            RemoteProvider::Bitbucket => format!("{}/commits/{}", self.base_url, hash),
+        }
+    }
+
+    fn file_url(&self, rev: &str, path: &str, line: Option<u32>, end_line: Option<u32>) -> String {
+        let rev = encode_path(rev);
+        let path = encode_path(path.trim_start_matches('/'));
+        let end_line = end_line.filter(|end| line.is_some_and(|start| *end > start));
+        match self.provider {
+            RemoteProvider::GitHub => {
+                let anchor = match (line, end_line) {
+                    (Some(start), Some(end)) => format!("#L{}-L{}", start, end),
+                    (Some(start), None) => format!("#L{}", start),
+                    _ => String::new(),
+                };
+                format!("{}/blob/{}/{}{}", self.base_url, rev, path, anchor)
+            }
+            RemoteProvider::GitLab => {
+                let anchor = match (line, end_line) {
+                    (Some(start), Some(end)) => format!("#L{}-{}", start, end),
+                    (Some(start), None) => format!("#L{}", start),
+                    _ => String::new(),
+                };
+                format!("{}/-/blob/{}/{}{}", self.base_url, rev, path, anchor)
+            }
+            RemoteProvider::Bitbucket => {
+                let anchor = match (line, end_line) {
+                    (Some(start), Some(end)) => format!("#lines-{}:{}", start, end),
+                    (Some(start), None) => format!("#lines-{}", start),
+                    _ => String::new(),
+                };
+                format!("{}/src/{}/{}{}", self.base_url, rev, path, anchor)
+            }
+            RemoteProvider::Gitea => {
+                let anchor = match (line, end_line) {
+                    (Some(start), Some(end)) => format!("#L{}-L{}", start, end),
+                    (Some(start), None) => format!("#L{}", start),
+                    _ => String::new(),
+                };
+                let kind = if is_commit_hash(&rev) { "commit" } else { "branch" };
+                format!("{}/src/{}/{}/{}{}", self.base_url, kind, rev, path, anchor)
+            }
+        }
+    }
+
+    fn branch_url(&self, name: &str) -> String {
+        let name = encode_path(name);
+        match self.provider {
+            RemoteProvider::GitHub => format!("{}/tree/{}", self.base_url, name),
+            RemoteProvider::GitLab => format!("{}/-/tree/{}", self.base_url, name),
+            RemoteProvider::Bitbucket => format!("{}/src/{}", self.base_url, name),
+            RemoteProvider::Gitea => format!("{}/src/branch/{}", self.base_url, name),
+        }
+    }
+
+    fn compare_url(&self, base: &str, head: &str) -> String {
+        let (base, head) = (encode_path(base), encode_path(head));
+        match self.provider {
+            RemoteProvider::GitHub | RemoteProvider::Gitea => format!("{}/compare/{}...{}", self.base_url, base, head),
+            RemoteProvider::GitLab => format!("{}/-/compare/{}...{}", self.base_url, base, head),
+            // Bitbucket 的比较页面是 源%0D目标
+            RemoteProvider::Bitbucket => format!("{}/branches/compare/{}%0D{}", self.base_url, head, base),
+        }
+    }
+
+    fn pull_request_url(&self, base: &str, head: &str) -> String {
+        match self.provider {
+            // expand=1 直接展开填写标题和说明的表单；Gitea 的比较页面本身就带有新建表单
+            RemoteProvider::GitHub => format!("{}?expand=1", self.compare_url(base, head)),
+            RemoteProvider::Gitea => self.compare_url(base, head),
+            RemoteProvider::GitLab => format!(
+                "{}/-/merge_requests/new?merge_request%5Bsource_branch%5D={}&merge_request%5Btarget_branch%5D={}",
+                self.base_url,
+                encode_component(head),
+                encode_component(base)
+            ),
+            RemoteProvider::Bitbucket => format!("{}/pull-requests/new?source={}&dest={}", self.base_url, encode_component(head), encode_component(base)),
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, TryLockError};
+use std::time::SystemTime;
+
+use git2::{ErrorCode, Repository};
+
+use crate::error::{GitError, GitResult};
+
+// 缓存已打开的仓库句柄，避免每个命令都重新 discover 并解析配置和引用，由 Tauri 托管为全局状态
+#[derive(Clone, Default)]
+pub struct RepoCache {
+    entries: Arc<Mutex<HashMap<PathBuf, CachedRepo>>>,
+}
+
+struct CachedRepo {
+    repo: Arc<Mutex<Repository>>,
+    git_dir: PathBuf,
+    common_dir: PathBuf,
+    stamp: RepoStamp,
+}
+
+// 仓库外部（命令行、其它客户端）改动这些文件后，缓存的句柄视为过期
+#[derive(PartialEq)]
+struct RepoStamp {
+    config: Option<SystemTime>,
+    head: Option<SystemTime>,
+    // libgit2 缓存读入的索引，不会自己重新读取；命令行暂存后要重新打开，否则写回索引时会覆盖这些改动
+    index: Option<SystemTime>,
+    packed_refs: Option<SystemTime>,
+}
+
+impl RepoStamp {
+    fn read(git_dir: &Path, common_dir: &Path) -> Self {
+        let modified = |path: PathBuf| fs::metadata(path).and_then(|m| m.modified()).ok();
+        RepoStamp {
+            config: modified(common_dir.join("config")),
+            head: modified(git_dir.join("HEAD")),
+            index: modified(git_dir.join("index")),
+            packed_refs: modified(common_dir.join("packed-refs")),
+        }
+    }
+}
+
+pub fn open_repo(repo_path: &str) -> GitResult<Repository> {
+    let path = Path::new(repo_path);
+    let discover_path = if path.is_file() {
+        path.parent().unwrap_or(path)
+    } else {
+        path
+    };
+    match Repository::discover(discover_path) {
+        Ok(repo) => Ok(repo),
+        Err(e) if e.code() == ErrorCode::NotFound => Err(GitError::NotARepository {
+            path: repo_path.to_string(),
+        }),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// 链接工作树的 .git/worktrees/<name> 中记录了共享的主 .git 目录
+pub fn common_dir(git_dir: &Path) -> PathBuf {
+    match fs::read_to_string(git_dir.join("commondir")) {
+        Ok(content) => git_dir.join(content.trim()),
+        Err(_) => git_dir.to_path_buf(),
+    }
+}
+
+fn cache_key(repo_path: &str) -> PathBuf {
+    fs::canonicalize(repo_path).unwrap_or_else(|_| PathBuf::from(repo_path))
+}
+
+impl RepoCache {
+    pub fn with_repo<T>(&self, repo_path: &str, task: impl FnOnce(&Repository) -> GitResult<T>) -> GitResult<T> {
+        let handle = self.handle(repo_path)?;
+        // 同一仓库上已有命令在执行（例如统计仓库大小）时不排队，临时打开一个独立的句柄
+        let result = match handle.try_lock() {
+            Ok(repo) => task(&repo),
+            Err(TryLockError::WouldBlock) => task(&open_repo(repo_path)?),
+            Err(TryLockError::Poisoned(poisoned)) => task(&poisoned.into_inner()),
+        };
+        result
+    }
+
+    fn handle(&self, repo_path: &str) -> GitResult<Arc<Mutex<Repository>>> {
+        let key = cache_key(repo_path);
+        {
+            let mut entries = self.entries.lock().unwrap();
+            if let Some(entry) = entries.get(&key) {
+                if entry.stamp == RepoStamp::read(&entry.git_dir, &entry.common_dir) {
+                    return Ok(entry.repo.clone());
+                }
+                entries.remove(&key);
+            }
+        }
+
+        let repo = open_repo(repo_path)?;
+        let git_dir = repo.path().to_path_buf();
+        let common_dir = common_dir(&git_dir);
+        let stamp = RepoStamp::read(&git_dir, &common_dir);
+        let repo = Arc::new(Mutex::new(repo));
+        self.entries.lock().unwrap().insert(
+            key,
+            CachedRepo {
+                repo: repo.clone(),
+                git_dir,
+                common_dir,
+                stamp,
+            },
+        );
+        Ok(repo)
+    }
+
+    // 写操作之后调用：同一仓库（包括共享 .git 的其它工作树）的句柄都丢弃，下次命令重新打开
+    pub fn invalidate(&self, repo_path: &str) {
+        let key = cache_key(repo_path);
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(common_dir) = entries.get(&key).map(|entry| entry.common_dir.clone()) {
+            entries.retain(|_, entry| entry.common_dir != common_dir);
+        }
+    }
+}
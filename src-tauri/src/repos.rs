@@ -0,0 +1,163 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::{GitError, GitResult};
+use crate::repo_cache::open_repo;
+
+const RECENT_REPOS_FILE: &str = "recent_repos.json";
+// 超出数量时淘汰最久未打开的，固定的仓库不计入也不会被淘汰
+const MAX_RECENT_REPOS: usize = 20;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RecentRepo {
+    pub path: String,
+    pub display_name: String,
+    // Unix 时间戳（秒）
+    pub last_opened: i64,
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+// 返回给前端的条目，附带加载时检查的结果：仓库被移动或删除后 isValid 为 false
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RecentRepoEntry {
+    #[serde(flatten)]
+    pub repo: RecentRepo,
+    pub is_valid: bool,
+}
+
+// 最近打开的仓库列表，保存在应用数据目录下，由 Tauri 托管为全局状态
+#[derive(Clone)]
+pub struct RecentRepos {
+    file: PathBuf,
+    entries: Arc<Mutex<Vec<RecentRepo>>>,
+}
+
+fn now_seconds() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+fn is_git_repo(path: &str) -> bool {
+    Path::new(path).is_dir() && open_repo(path).is_ok()
+}
+
+impl RecentRepos {
+    // 文件不存在或内容损坏时从空列表开始，不影响应用启动
+    pub fn load(data_dir: &Path) -> Self {
+        let file = data_dir.join(RECENT_REPOS_FILE);
+        let entries = match fs::read_to_string(&file) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                log::warn!("ignoring corrupt {}: {}", file.display(), e);
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+        RecentRepos {
+            file,
+            entries: Arc::new(Mutex::new(entries)),
+        }
+    }
+
+    // 固定的在前，其余按最近打开时间倒序
+    pub fn list(&self) -> Vec<RecentRepoEntry> {
+        let mut entries = self.entries.lock().unwrap().clone();
+        entries.sort_by(|a, b| b.pinned.cmp(&a.pinned).then(b.last_opened.cmp(&a.last_opened)));
+        entries
+            .into_iter()
+            .map(|repo| RecentRepoEntry {
+                is_valid: is_git_repo(&repo.path),
+                repo,
+            })
+            .collect()
+    }
+
+    // 已存在的条目只更新打开时间（和显式传入的名称），保留固定状态
+    pub fn add(&self, repo_path: &str, display_name: Option<&str>) -> GitResult<RecentRepo> {
+        let repo = open_repo(repo_path)?;
+        let root = repo.workdir().unwrap_or_else(|| repo.path());
+        let path = root.to_string_lossy().trim_end_matches(['/', '\\']).to_string();
+        let default_name = root
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.clone());
+
+        let mut entries = self.entries.lock().unwrap();
+        let entry = match entries.iter().position(|entry| entry.path == path) {
+            Some(index) => {
+                let entry = &mut entries[index];
+                entry.last_opened = now_seconds();
+                if let Some(name) = display_name {
+                    entry.display_name = name.to_string();
+                }
+                entry.clone()
+            }
+            None => {
+                let entry = RecentRepo {
+                    path,
+                    display_name: display_name.map(str::to_string).unwrap_or(default_name),
+                    last_opened: now_seconds(),
+                    pinned: false,
+                };
+                entries.push(entry.clone());
+                entry
+            }
+        };
+
+        let unpinned = entries.iter().filter(|entry| !entry.pinned).count();
+        if unpinned > MAX_RECENT_REPOS {
+            entries.sort_by_key(|entry| std::cmp::Reverse(entry.last_opened));
+            let mut kept = 0;
+            entries.retain(|entry| {
+                if entry.pinned {
+                    return true;
+                }
+                kept += 1;
+                kept <= MAX_RECENT_REPOS
+            });
+        }
+
+        self.save(&entries)?;
+        Ok(entry)
+    }
+
+    pub fn remove(&self, repo_path: &str) -> GitResult<bool> {
+        let mut entries = self.entries.lock().unwrap();
+        let before = entries.len();
+        entries.retain(|entry| entry.path != repo_path);
+        if entries.len() == before {
+            return Ok(false);
+        }
+        self.save(&entries)?;
+        Ok(true)
+    }
+
+    pub fn set_pinned(&self, repo_path: &str, pinned: bool) -> GitResult<()> {
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries
+            .iter_mut()
+            .find(|entry| entry.path == repo_path)
+            .ok_or_else(|| GitError::not_found(format!("repository is not in the recent list: {}", repo_path)))?;
+        entry.pinned = pinned;
+        self.save(&entries)
+    }
+
+    // 先写临时文件再改名，避免写到一半退出时留下损坏的列表
+    fn save(&self, entries: &[RecentRepo]) -> GitResult<()> {
+        if let Some(dir) = self.file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let content = serde_json::to_string_pretty(entries).map_err(|e| GitError::Internal { message: e.to_string() })?;
+        let temp_file = self.file.with_extension("json.tmp");
+        fs::write(&temp_file, content)?;
+        fs::rename(&temp_file, &self.file)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+// 重用记录的冲突解决（git rerere）。记录保存在 git 自己的 rr-cache 和 MERGE_RR 中，
+// 命令行中的 git merge / rebase 会照常使用它们
+use std::fs;
+
+use git2::{ErrorCode, Repository};
+use serde::{Deserialize, Serialize};
+
+use crate::cancel::CancellationToken;
+use crate::error::{GitError, GitResult};
+use crate::git;
+use crate::git_cli;
+use crate::repo_cache::common_dir;
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitRerereStatus {
+    pub enabled: bool,
+    // rerere.autoUpdate：自动解决的文件同时加入暂存区
+    pub auto_update: bool,
+    // rr-cache 中已经记录了解决结果的冲突数
+    pub recorded_resolutions: usize,
+    // 当前索引中有冲突的文件
+    pub conflicted: Vec<String>,
+    // 当前冲突中 rerere 已经记录了冲突内容、解决后会被记录下来的文件
+    pub tracked: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitRerereResult {
+    // 用记录的解决结果自动解决的文件
+    pub auto_resolved: Vec<String>,
+    // 仍需手动解决的文件
+    pub remaining: Vec<String>,
+    // 本次新记录了解决结果的文件
+    pub recorded: Vec<String>,
+    pub status: GitRerereStatus,
+}
+
+fn config_bool(repo: &Repository, key: &str) -> GitResult<Option<bool>> {
+    match repo.config()?.get_bool(key) {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if e.code() == ErrorCode::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// 和 git 一样，没有配置 rerere.enabled 时只要 rr-cache 目录存在就视为开启
+pub fn is_rerere_enabled(repo: &Repository) -> GitResult<bool> {
+    match config_bool(repo, "rerere.enabled")? {
+        Some(enabled) => Ok(enabled),
+        None => Ok(common_dir(repo.path()).join("rr-cache").is_dir()),
+    }
+}
+
+fn rerere_git(repo: &Repository, args: &[&str]) -> GitResult<Vec<String>> {
+    let workdir = repo.workdir().ok_or(GitError::BareRepository)?;
+    let mut command_args = vec!["rerere"];
+    command_args.extend_from_slice(args);
+    let output = git_cli::run_git_with_progress(repo.path(), Some(workdir), &command_args, &CancellationToken::default(), |_| {})?;
+    Ok(output.stdout.lines().filter(|line| !line.is_empty()).map(|line| line.to_string()).collect())
+}
+
+fn recorded_resolutions(repo: &Repository) -> usize {
+    let Ok(entries) = fs::read_dir(common_dir(repo.path()).join("rr-cache")) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .filter(|entry| {
+            fs::read_dir(entry.path()).is_ok_and(|files| {
+                files.flatten().any(|file| file.file_name().to_string_lossy().starts_with("postimage"))
+            })
+        })
+        .count()
+}
+
+pub fn get_rerere_status(repo: &Repository) -> GitResult<GitRerereStatus> {
+    let enabled = is_rerere_enabled(repo)?;
+    let tracked = if enabled && repo.path().join("MERGE_RR").is_file() {
+        rerere_git(repo, &["status"])?
+    } else {
+        Vec::new()
+    };
+    Ok(GitRerereStatus {
+        enabled,
+        auto_update: config_bool(repo, "rerere.autoUpdate")?.unwrap_or(false),
+        recorded_resolutions: recorded_resolutions(repo),
+        conflicted: git::conflict_paths(&repo.index()?)?,
+        tracked,
+    })
+}
+
+// git rerere 在 stderr 中报告每个文件的处理结果，例如 "Resolved 'a.txt' using previous resolution."
+fn reported_paths(output: &str, prefix: &str, suffix: &str) -> Vec<String> {
+    let mut paths: Vec<String> = output
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix(prefix)?.strip_suffix(suffix))
+        .map(|path| path.to_string())
+        .collect();
+    paths.sort();
+    paths.dedup();
+    paths
+}
+
+// 相当于执行一次 git rerere：记录当前冲突的内容，把已记录的解决结果应用到相同的冲突上，
+// 并记录已经手动解决的文件的结果。rerere 未开启时什么也不做。
+// 在应用内合并之外（例如命令行的 git merge）产生的冲突，git 当时已经应用过记录的结果，这里不会再报告
+pub fn rerere_apply(repo: &Repository) -> GitResult<GitRerereResult> {
+    if !is_rerere_enabled(repo)? {
+        return Ok(GitRerereResult {
+            auto_resolved: Vec::new(),
+            remaining: git::conflict_paths(&repo.index()?)?,
+            recorded: Vec::new(),
+            status: get_rerere_status(repo)?,
+        });
+    }
+    let workdir = repo.workdir().ok_or(GitError::BareRepository)?;
+    let output = git_cli::run_git_with_progress(repo.path(), Some(workdir), &["rerere"], &CancellationToken::default(), |_| {})?;
+    // rerere.autoUpdate 时报告为 "Staged '...' using previous resolution."
+    let mut auto_resolved = reported_paths(&output.stderr, "Resolved '", "' using previous resolution.");
+    auto_resolved.extend(reported_paths(&output.stderr, "Staged '", "' using previous resolution."));
+    auto_resolved.sort();
+    let recorded = reported_paths(&output.stderr, "Recorded resolution for '", "'.");
+
+    let status = get_rerere_status(repo)?;
+    // rerere remaining 不包含已经解决（自动或手动）的文件
+    let remaining = rerere_git(repo, &["remaining"])?;
+    Ok(GitRerereResult {
+        auto_resolved,
+        remaining,
+        recorded,
+        status,
+    })
+}
+
+// 和 git commit 一样，提交合并结果前记录已解决文件的结果，供以后相同的冲突重用
+pub fn record_resolutions(repo: &Repository) -> GitResult<()> {
+    if repo.workdir().is_some() && repo.path().join("MERGE_RR").is_file() && is_rerere_enabled(repo)? {
+        rerere_git(repo, &[])?;
+    }
+    Ok(())
+}
+
+// 删除某个文件当前冲突的记录结果（git rerere forget），用于纠正记录错的解决方式
+pub fn rerere_forget(repo: &Repository, path: &str) -> GitResult<GitRerereStatus> {
+    if !git::conflict_paths(&repo.index()?)?.iter().any(|conflicted| conflicted == path) {
+        return Err(GitError::invalid(format!("'{}' has no conflict to forget a resolution for", path)));
+    }
+    rerere_git(repo, &["forget", "--", path])?;
+    get_rerere_status(repo)
+}
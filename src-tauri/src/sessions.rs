@@ -0,0 +1,137 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use git2::Repository;
+
+use crate::error::{GitError, GitResult};
+use crate::git;
+use crate::repo_cache::{open_repo, RepoCache};
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RepoSession {
+    pub id: String,
+    // 仓库根目录（工作区，裸仓库则为 .git 目录），不是打开时传入的子目录
+    pub path: String,
+    pub name: String,
+    pub is_bare: bool,
+    // 在链接工作树中打开时为 true，命令都作用于这个工作树的 HEAD 和 index；main_repo_path 为主仓库的根目录
+    pub is_worktree: bool,
+    pub main_repo_path: Option<String>,
+}
+
+// 前端先用 open_repo 换取一个句柄，之后的命令都只传句柄；
+// 按句柄路由到同一个仓库，缓存、监听、操作锁都挂在这里，由 Tauri 托管为全局状态
+#[derive(Clone, Default)]
+pub struct RepoSessions {
+    sessions: Arc<Mutex<HashMap<String, RepoSession>>>,
+    next_id: Arc<AtomicU64>,
+    cache: RepoCache,
+    // 每个仓库正在执行的写操作名称
+    write_locks: Arc<Mutex<HashMap<String, &'static str>>>,
+}
+
+// 写操作结束（guard 被丢弃）时释放仓库的写锁
+pub struct WriteGuard {
+    repo_id: String,
+    write_locks: Arc<Mutex<HashMap<String, &'static str>>>,
+}
+
+impl Drop for WriteGuard {
+    fn drop(&mut self) {
+        self.write_locks.lock().unwrap().remove(&self.repo_id);
+    }
+}
+
+impl RepoSessions {
+    // 同一个仓库重复打开时返回已有的句柄
+    pub fn open(&self, repo_path: &str) -> GitResult<RepoSession> {
+        let repo = open_repo(repo_path)?;
+        let root = repo.workdir().unwrap_or_else(|| repo.path());
+        let root = fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+        let path = root.to_string_lossy().to_string();
+        let main_repo_path = git::main_repository(&repo)?.map(|main| {
+            let main_root = git::repo_root(&main);
+            fs::canonicalize(main_root).unwrap_or_else(|_| main_root.to_path_buf()).to_string_lossy().to_string()
+        });
+
+        let mut sessions = self.sessions.lock().unwrap();
+        if let Some(session) = sessions.values().find(|session| session.path == path) {
+            return Ok(session.clone());
+        }
+        let id = format!("repo-{}", self.next_id.fetch_add(1, Ordering::Relaxed) + 1);
+        let session = RepoSession {
+            id: id.clone(),
+            name: root
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| path.clone()),
+            path,
+            is_bare: repo.is_bare(),
+            is_worktree: main_repo_path.is_some(),
+            main_repo_path,
+        };
+        sessions.insert(id, session.clone());
+        Ok(session)
+    }
+
+    // 关闭后句柄失效，缓存的仓库也一并释放
+    pub fn close(&self, repo_id: &str) -> bool {
+        match self.sessions.lock().unwrap().remove(repo_id) {
+            Some(session) => {
+                self.cache.invalidate(&session.path);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn list(&self) -> Vec<RepoSession> {
+        let mut sessions: Vec<RepoSession> = self.sessions.lock().unwrap().values().cloned().collect();
+        sessions.sort_by(|a, b| a.path.cmp(&b.path));
+        sessions
+    }
+
+    pub fn get(&self, repo_id: &str) -> GitResult<RepoSession> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .get(repo_id)
+            .cloned()
+            .ok_or_else(|| GitError::not_found(format!("repository is not open: {}", repo_id)))
+    }
+
+    pub fn cache(&self) -> &RepoCache {
+        &self.cache
+    }
+
+    pub fn with_repo<T>(&self, repo_id: &str, task: impl FnOnce(&Repository) -> GitResult<T>) -> GitResult<T> {
+        let session = self.get(repo_id)?;
+        self.cache.with_repo(&session.path, task)
+    }
+
+    // 同一仓库同时只允许一个写操作，用户连续点击时直接拒绝后来的操作，避免 index 被并发改坏
+    pub fn lock_for_write(&self, repo_id: &str, operation: &'static str) -> GitResult<WriteGuard> {
+        self.get(repo_id)?;
+        let mut write_locks = self.write_locks.lock().unwrap();
+        if let Some(current) = write_locks.get(repo_id) {
+            return Err(GitError::OperationInProgress {
+                operation: current.to_string(),
+            });
+        }
+        write_locks.insert(repo_id.to_string(), operation);
+        Ok(WriteGuard {
+            repo_id: repo_id.to_string(),
+            write_locks: self.write_locks.clone(),
+        })
+    }
+
+    pub fn invalidate(&self, repo_id: &str) {
+        if let Ok(session) = self.get(repo_id) {
+            self.cache.invalidate(&session.path);
+        }
+    }
+}
@@ -0,0 +1,481 @@
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use git2::{Config, ErrorCode, Signature};
+use serde::Serialize;
+
+use crate::error::{GitError, GitResult};
+
+// 对应 gpg.format：openpgp（默认）/ x509 / ssh
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SigningFormat {
+    OpenPgp,
+    X509,
+    Ssh,
+}
+
+impl SigningFormat {
+    // 按签名内容的开头判断格式，验证时不依赖当前的 gpg.format 配置
+    pub fn of_signature(signature: &[u8]) -> Self {
+        if signature.starts_with(b"-----BEGIN SSH SIGNATURE-----") {
+            SigningFormat::Ssh
+        } else if signature.starts_with(b"-----BEGIN SIGNED MESSAGE-----") {
+            SigningFormat::X509
+        } else {
+            SigningFormat::OpenPgp
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SigningFormat::OpenPgp => "gpg",
+            SigningFormat::X509 => "x509",
+            SigningFormat::Ssh => "ssh",
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum SignatureStatus {
+    // 签名有效且签名者的密钥可信（gpg 钥匙环中有该公钥 / 在 ssh allowedSigners 中）
+    Good,
+    // 签名与内容不匹配
+    Bad,
+    // 无法确认：缺少公钥、没有配置 allowedSignersFile、密钥过期或被吊销
+    Unknown,
+}
+
+pub struct SignatureCheck {
+    pub status: SignatureStatus,
+    pub signer: Option<String>,
+    pub key_id: Option<String>,
+}
+
+// 和 git 一样调用外部的 gpg / gpgsm / ssh-keygen 生成签名，私钥和口令交给它们及其 agent 处理
+pub struct Signer {
+    format: SigningFormat,
+    program: String,
+    key: String,
+}
+
+fn config_value(config: &Config, key: &str) -> GitResult<Option<String>> {
+    match config.get_string(key) {
+        Ok(value) if !value.trim().is_empty() => Ok(Some(value.trim().to_string())),
+        Ok(_) => Ok(None),
+        Err(e) if e.code() == ErrorCode::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn signing_format(config: &Config) -> GitResult<SigningFormat> {
+    match config_value(config, "gpg.format")?.as_deref() {
+        None | Some("openpgp") => Ok(SigningFormat::OpenPgp),
+        Some("x509") => Ok(SigningFormat::X509),
+        Some("ssh") => Ok(SigningFormat::Ssh),
+        Some(other) => Err(GitError::invalid(format!("unsupported gpg.format: {}", other))),
+    }
+}
+
+pub fn program_for(config: &Config, format: SigningFormat) -> GitResult<String> {
+    let (key, default) = match format {
+        SigningFormat::OpenPgp => ("gpg.openpgp.program", "gpg"),
+        SigningFormat::X509 => ("gpg.x509.program", "gpgsm"),
+        SigningFormat::Ssh => ("gpg.ssh.program", "ssh-keygen"),
+    };
+    let program = match config_value(config, key)? {
+        Some(program) => program,
+        // gpg.program 是 gpg.openpgp.program 的旧名字
+        None if format == SigningFormat::OpenPgp => config_value(config, "gpg.program")?.unwrap_or_else(|| default.to_string()),
+        None => default.to_string(),
+    };
+    Ok(program)
+}
+
+pub fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => match env::var_os("HOME").or_else(|| env::var_os("USERPROFILE")) {
+            Some(home) => PathBuf::from(home).join(rest),
+            None => PathBuf::from(path),
+        },
+        None => PathBuf::from(path),
+    }
+}
+
+// 外部程序只接受文件参数的内容（签名、公钥）写到临时目录，用完由调用方删除
+fn write_temp_file(prefix: &str, content: &[u8]) -> GitResult<PathBuf> {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.subsec_nanos()).unwrap_or(0);
+    let path = env::temp_dir().join(format!(".git_{}_{}_{}", prefix, std::process::id(), nanos));
+    fs::write(&path, content)?;
+    Ok(path)
+}
+
+// 把命令的 stdin 写入数据后收集输出；程序不存在时给出明确的提示
+pub fn run_with_input(program: &str, args: &[&str], input: &[u8]) -> GitResult<std::process::Output> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| GitError::Io {
+            message: format!("failed to run {}: {}", program, e),
+        })?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input)?;
+    }
+    Ok(child.wait_with_output()?)
+}
+
+impl Signer {
+    // 未设置 user.signingkey 时 gpg 按提交者身份查找密钥，ssh 则必须显式配置
+    pub fn from_config(config: &Config, committer: &Signature) -> GitResult<Signer> {
+        let format = signing_format(config)?;
+        let program = program_for(config, format)?;
+        let key = match config_value(config, "user.signingkey")? {
+            Some(key) => key,
+            None if format == SigningFormat::Ssh => {
+                return Err(GitError::invalid("user.signingkey must be set to sign with ssh"));
+            }
+            None => format!("{} <{}>", committer.name().unwrap_or(""), committer.email().unwrap_or("")),
+        };
+        Ok(Signer { format, program, key })
+    }
+
+    pub fn sign(&self, payload: &[u8]) -> GitResult<String> {
+        let output = match self.format {
+            SigningFormat::OpenPgp | SigningFormat::X509 => {
+                run_with_input(&self.program, &["--status-fd=2", "-bsau", &self.key], payload)?
+            }
+            SigningFormat::Ssh => self.sign_ssh(payload)?,
+        };
+
+        let signature = String::from_utf8_lossy(&output.stdout).to_string();
+        if !output.status.success() || signature.trim().is_empty() {
+            return Err(GitError::SigningFailed {
+                message: format!("{} failed: {}", self.program, String::from_utf8_lossy(&output.stderr).trim()),
+            });
+        }
+        Ok(signature)
+    }
+
+    // user.signingkey 可以是密钥文件路径，也可以是 "key::ssh-ed25519 AAAA..." 形式的公钥字面量，
+    // 后者写入临时文件后交给 ssh-keygen（私钥由 ssh-agent 提供）
+    fn sign_ssh(&self, payload: &[u8]) -> GitResult<std::process::Output> {
+        let literal = self
+            .key
+            .strip_prefix("key::")
+            .or_else(|| self.key.starts_with("ssh-").then_some(self.key.as_str()));
+        let (key_file, temporary) = match literal {
+            Some(public_key) => (write_temp_file("signing_key", format!("{}\n", public_key).as_bytes())?, true),
+            None => (expand_home(&self.key), false),
+        };
+        let key_file_arg = key_file.to_string_lossy().to_string();
+        let output = run_with_input(&self.program, &["-Y", "sign", "-n", "git", "-f", &key_file_arg], payload);
+        if temporary {
+            let _ = fs::remove_file(&key_file);
+        }
+        output
+    }
+}
+
+// ssh-keygen 输出中的 "SHA256:..." 指纹
+pub fn ssh_fingerprint(output: &str) -> Option<String> {
+    output
+        .split_whitespace()
+        .find(|word| word.starts_with("SHA256:"))
+        .map(|word| word.trim_end_matches('.').to_string())
+}
+
+// 解析 gpg --status-fd 的输出，参见 gnupg 的 doc/DETAILS
+fn parse_gpg_status(status: &str) -> SignatureCheck {
+    let mut check = SignatureCheck {
+        status: SignatureStatus::Unknown,
+        signer: None,
+        key_id: None,
+    };
+    for line in status.lines() {
+        let Some(line) = line.strip_prefix("[GNUPG:] ") else {
+            continue;
+        };
+        let mut parts = line.splitn(3, ' ');
+        let keyword = parts.next().unwrap_or("");
+        let key_id = parts.next().map(str::to_string);
+        let rest = parts.next().map(str::to_string);
+        match keyword {
+            "GOODSIG" => {
+                check.status = SignatureStatus::Good;
+                check.key_id = check.key_id.or(key_id);
+                check.signer = rest;
+            }
+            "BADSIG" => {
+                check.status = SignatureStatus::Bad;
+                check.key_id = check.key_id.or(key_id);
+                check.signer = rest;
+            }
+            "EXPSIG" | "EXPKEYSIG" | "REVKEYSIG" => {
+                check.status = SignatureStatus::Unknown;
+                check.key_id = check.key_id.or(key_id);
+                check.signer = rest;
+            }
+            "ERRSIG" => check.key_id = check.key_id.or(key_id),
+            // 完整指纹比长 key id 更有用
+            "VALIDSIG" => check.key_id = key_id,
+            _ => {}
+        }
+    }
+    check
+}
+
+fn verify_gpg(program: &str, signature_file: &str, payload: &[u8]) -> GitResult<SignatureCheck> {
+    // 验证失败时 gpg 返回非零，结果以状态输出为准
+    let output = run_with_input(program, &["--status-fd=1", "--verify", signature_file, "-"], payload)?;
+    Ok(parse_gpg_status(&String::from_utf8_lossy(&output.stdout)))
+}
+
+// 配置了 gpg.ssh.allowedSignersFile 时按其中的身份验证；否则只能确认签名本身完整，签名者未知
+fn verify_ssh(config: &Config, program: &str, signature_file: &str, payload: &[u8]) -> GitResult<SignatureCheck> {
+    let allowed_signers = config_value(config, "gpg.ssh.allowedSignersFile")?
+        .map(|path| expand_home(&path))
+        .filter(|path| path.is_file());
+
+    if let Some(allowed_signers) = allowed_signers {
+        let allowed_signers = allowed_signers.to_string_lossy().to_string();
+        let principals = run_with_input(
+            program,
+            &["-Y", "find-principals", "-f", &allowed_signers, "-s", signature_file],
+            &[],
+        )?;
+        let principal = String::from_utf8_lossy(&principals.stdout).lines().next().map(str::to_string);
+        if let Some(principal) = principal.filter(|_| principals.status.success()) {
+            let output = run_with_input(
+                program,
+                &["-Y", "verify", "-f", &allowed_signers, "-I", &principal, "-n", "git", "-s", signature_file],
+                payload,
+            )?;
+            let text = format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+            return Ok(SignatureCheck {
+                status: if output.status.success() { SignatureStatus::Good } else { SignatureStatus::Bad },
+                signer: Some(principal),
+                key_id: ssh_fingerprint(&text),
+            });
+        }
+    }
+
+    let output = run_with_input(program, &["-Y", "check-novalidate", "-n", "git", "-s", signature_file], payload)?;
+    let text = format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+    Ok(SignatureCheck {
+        status: if output.status.success() { SignatureStatus::Unknown } else { SignatureStatus::Bad },
+        signer: None,
+        key_id: ssh_fingerprint(&text),
+    })
+}
+
+pub fn verify(config: &Config, signature: &[u8], payload: &[u8]) -> GitResult<SignatureCheck> {
+    let format = SigningFormat::of_signature(signature);
+    let program = program_for(config, format)?;
+    let signature_path = write_temp_file("signature", signature)?;
+    let signature_file = signature_path.to_string_lossy().to_string();
+    let result = match format {
+        SigningFormat::OpenPgp | SigningFormat::X509 => verify_gpg(&program, &signature_file, payload),
+        SigningFormat::Ssh => verify_ssh(config, &program, &signature_file, payload),
+    };
+    let _ = fs::remove_file(&signature_path);
+    result
+}
+
+// 可以用来签名的一个密钥；key 是填入 user.signingkey 的值
+pub struct SigningKeyInfo {
+    pub format: SigningFormat,
+    pub key: String,
+    pub fingerprint: Option<String>,
+    // gpg 密钥的用户 id，或 ssh 公钥的注释
+    pub user_ids: Vec<String>,
+    // gpg 密钥的过期时间（Unix 时间戳）
+    pub expires_at: Option<i64>,
+    pub path: Option<String>,
+    // ssh 公钥（"类型 base64"，不含注释）
+    pub public_key: Option<String>,
+    pub in_agent: bool,
+}
+
+fn home_dir() -> Option<PathBuf> {
+    env::var_os("HOME").or_else(|| env::var_os("USERPROFILE")).map(PathBuf::from)
+}
+
+// 解析 gpg --with-colons 的输出（字段说明参见 gnupg 的 doc/DETAILS），只保留可用于签名、
+// 没有过期、吊销或禁用的主密钥；能力字段中的大写字母表示整个密钥（含子密钥）具有该能力
+fn parse_gpg_secret_keys(output: &str) -> Vec<SigningKeyInfo> {
+    let mut keys = Vec::new();
+    let mut current: Option<SigningKeyInfo> = None;
+    for line in output.lines() {
+        let fields: Vec<&str> = line.split(':').collect();
+        let field = |i: usize| fields.get(i).copied().unwrap_or("");
+        match field(0) {
+            "sec" => {
+                keys.extend(current.take());
+                let validity = field(1);
+                let capabilities = field(11);
+                if !matches!(validity, "r" | "e" | "i" | "d" | "n") && capabilities.contains('S') && !capabilities.contains('D') {
+                    current = Some(SigningKeyInfo {
+                        format: SigningFormat::OpenPgp,
+                        key: field(4).to_string(),
+                        fingerprint: None,
+                        user_ids: Vec::new(),
+                        expires_at: field(6).parse().ok(),
+                        path: None,
+                        public_key: None,
+                        in_agent: false,
+                    });
+                }
+            }
+            // 紧跟在 sec 之后的 fpr 是主密钥的指纹，子密钥的指纹跟在各自的 ssb 之后
+            "fpr" => {
+                if let Some(key) = current.as_mut().filter(|key| key.fingerprint.is_none()) {
+                    key.fingerprint = Some(field(9).to_string());
+                    key.key = field(9).to_string();
+                }
+            }
+            "uid" if field(1) != "r" => {
+                if let Some(key) = current.as_mut() {
+                    key.user_ids.push(field(9).replace("\\x3a", ":"));
+                }
+            }
+            "pub" | "crt" | "crs" => keys.extend(current.take()),
+            _ => {}
+        }
+    }
+    keys.extend(current);
+    keys
+}
+
+fn list_gpg_keys(config: &Config) -> GitResult<Vec<SigningKeyInfo>> {
+    let program = program_for(config, SigningFormat::OpenPgp)?;
+    let output = run_with_input(&program, &["--list-secret-keys", "--with-colons", "--fixed-list-mode"], &[])?;
+    if !output.status.success() {
+        return Err(GitError::Io {
+            message: format!("{} failed: {}", program, String::from_utf8_lossy(&output.stderr).trim()),
+        });
+    }
+    Ok(parse_gpg_secret_keys(&String::from_utf8_lossy(&output.stdout)))
+}
+
+// 公钥行形如 "ssh-ed25519 AAAA... comment"，比较时只看类型和密钥本身
+fn ssh_public_key(line: &str) -> Option<(String, Option<String>)> {
+    let mut parts = line.split_whitespace();
+    let key_type = parts.next()?;
+    let blob = parts.next()?;
+    let comment = parts.collect::<Vec<_>>().join(" ");
+    Some((format!("{} {}", key_type, blob), Some(comment).filter(|comment| !comment.is_empty())))
+}
+
+// ssh-agent 中的密钥：ssh-add -L 列出公钥，ssh-add -l 按同样的顺序列出指纹。
+// 没有运行 agent 或 agent 中没有密钥时返回空
+fn list_ssh_agent_keys() -> Vec<(String, Option<String>, Option<String>)> {
+    let public_keys = match run_with_input("ssh-add", &["-L"], &[]) {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout).to_string(),
+        _ => return Vec::new(),
+    };
+    let fingerprints = run_with_input("ssh-add", &["-l", "-E", "sha256"], &[])
+        .map(|output| String::from_utf8_lossy(&output.stdout).to_string())
+        .unwrap_or_default();
+    let mut fingerprints = fingerprints.lines().map(ssh_fingerprint);
+    public_keys
+        .lines()
+        .filter_map(|line| {
+            let fingerprint = fingerprints.next().flatten();
+            let (public_key, comment) = ssh_public_key(line)?;
+            Some((public_key, comment, fingerprint))
+        })
+        .collect()
+}
+
+// ~/.ssh 中有对应私钥的 .pub 文件，以及 ssh-agent 中的密钥；同一个密钥只列出一次，优先使用文件路径
+fn list_ssh_keys(config: &Config) -> GitResult<Vec<SigningKeyInfo>> {
+    let program = program_for(config, SigningFormat::Ssh)?;
+    let mut agent_keys = list_ssh_agent_keys();
+    let mut keys = Vec::new();
+
+    let ssh_dir = home_dir().map(|home| home.join(".ssh"));
+    let mut files: Vec<PathBuf> = ssh_dir
+        .and_then(|dir| fs::read_dir(dir).ok())
+        .map(|entries| entries.flatten().map(|entry| entry.path()).collect())
+        .unwrap_or_default();
+    files.sort();
+    for path in files {
+        if path.extension().and_then(|extension| extension.to_str()) != Some("pub") || !path.with_extension("").is_file() {
+            continue;
+        }
+        let Some((public_key, comment)) = fs::read_to_string(&path).ok().and_then(|content| content.lines().next().and_then(ssh_public_key)) else {
+            continue;
+        };
+        let path_arg = path.to_string_lossy().to_string();
+        let fingerprint = run_with_input(&program, &["-l", "-E", "sha256", "-f", &path_arg], &[])
+            .ok()
+            .filter(|output| output.status.success())
+            .and_then(|output| ssh_fingerprint(&String::from_utf8_lossy(&output.stdout)));
+        let agent_position = agent_keys.iter().position(|(agent_key, _, _)| *agent_key == public_key);
+        keys.push(SigningKeyInfo {
+            format: SigningFormat::Ssh,
+            key: path_arg.clone(),
+            fingerprint,
+            user_ids: comment.into_iter().collect(),
+            expires_at: None,
+            path: Some(path_arg),
+            public_key: Some(public_key),
+            in_agent: agent_position.map(|i| agent_keys.remove(i)).is_some(),
+        });
+    }
+    // 只在 agent 中的密钥（例如硬件密钥或密码管理器提供的密钥）以公钥字面量的形式配置
+    keys.extend(agent_keys.into_iter().map(|(public_key, comment, fingerprint)| SigningKeyInfo {
+        format: SigningFormat::Ssh,
+        key: format!("key::{}", public_key),
+        fingerprint,
+        user_ids: comment.into_iter().collect(),
+        expires_at: None,
+        path: None,
+        public_key: Some(public_key),
+        in_agent: true,
+    }));
+    Ok(keys)
+}
+
+// 列出 gpg 钥匙环中的私钥和可用的 ssh 密钥；某一类工具不可用时记录日志并跳过，不影响另一类
+pub fn list_signing_keys(config: &Config) -> Vec<SigningKeyInfo> {
+    let mut keys = Vec::new();
+    for (label, result) in [("gpg", list_gpg_keys(config)), ("ssh", list_ssh_keys(config))] {
+        match result {
+            Ok(found) => keys.extend(found),
+            Err(e) => log::warn!("failed to list {} signing keys: {}", label, e),
+        }
+    }
+    keys
+}
+
+// 判断候选密钥是否就是 user.signingkey 配置的密钥：gpg 可以配置指纹或（短/长）key id，
+// ssh 可以配置公钥或私钥文件的路径，也可以配置公钥字面量
+pub fn is_configured_key(configured: &str, key: &SigningKeyInfo) -> bool {
+    let configured = configured.trim();
+    match key.format {
+        SigningFormat::Ssh => {
+            // 和 Signer::sign_ssh 相同的规则区分公钥字面量和文件路径
+            let literal = configured.strip_prefix("key::").or_else(|| configured.starts_with("ssh-").then_some(configured));
+            if let Some(literal) = literal {
+                return ssh_public_key(literal).map(|(public_key, _)| public_key) == key.public_key;
+            }
+            let configured_path = expand_home(configured);
+            key.path.as_ref().is_some_and(|path| {
+                let path = PathBuf::from(path);
+                configured_path == path || configured_path == path.with_extension("")
+            })
+        }
+        _ => {
+            let id = configured.trim_start_matches("0x").trim_end_matches('!').to_uppercase();
+            !id.is_empty() && key.fingerprint.as_ref().is_some_and(|fingerprint| fingerprint.to_uppercase().ends_with(&id))
+        }
+    }
+}
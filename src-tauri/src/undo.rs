@@ -0,0 +1,294 @@
+// 根据 reflog 撤销最近一次移动 HEAD 的操作（提交、合并、重置、切换分支、变基），包括在命令行中执行的操作。
+// 撤销本身也是一次重置或切换，会写入 reflog，再撤销一次即可恢复。
+// uncommit 只撤销最近一次提交，不依赖 reflog
+use git2::{BranchType, Oid, Repository, Sort};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{GitError, GitResult};
+use crate::git::{self, GitHeadKind, GitHeadState, GitOperationCommit, GitStatus, ResetMode};
+
+// 预览中最多列出的被移走的提交
+const REMOVED_COMMITS_LIMIT: usize = 50;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum UndoKind {
+    Commit,
+    Amend,
+    Merge,
+    Reset,
+    Checkout,
+    Rebase,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitUndoPreview {
+    pub kind: UndoKind,
+    // 被撤销的 reflog 记录的原文，例如 "commit: fix typo"
+    pub reflog_message: String,
+    pub description: String,
+    // 要移动的分支，分离 HEAD 时为空
+    pub branch: Option<String>,
+    pub from: String,
+    pub to: String,
+    // 切换回原来的分支；为空且 kind 为 Checkout 时以分离 HEAD 的方式检出 to
+    pub checkout_branch: Option<String>,
+    // 移动分支时使用的重置方式，切换分支时为空
+    pub reset_mode: Option<ResetMode>,
+    // 撤销后不再在分支上的提交（仍可以从 reflog 找回）
+    pub removed_commits: Vec<GitOperationCommit>,
+    pub removed_commits_truncated: bool,
+    // 未提交的改动；不为空时硬重置和切换分支会被拒绝
+    pub uncommitted_changes: Vec<GitStatus>,
+    pub blocked_reason: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitUncommitResult {
+    // 被撤销的提交
+    pub commit: GitOperationCommit,
+    pub performed: bool,
+    pub is_merge: bool,
+    // 已包含该提交的远程跟踪分支（以上次 fetch 的结果为准），不为空时撤销后需要强制推送
+    pub pushed_to: Vec<String>,
+    pub head: GitHeadState,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitUndoResult {
+    pub preview: GitUndoPreview,
+    pub head: GitHeadState,
+}
+
+fn short(oid: Oid) -> String {
+    oid.to_string()[..7].to_string()
+}
+
+fn commits_between(repo: &Repository, from: Oid, to: Oid) -> GitResult<(Vec<GitOperationCommit>, bool)> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(Sort::TOPOLOGICAL)?;
+    revwalk.push(from)?;
+    revwalk.hide(to)?;
+    let mut commits = Vec::new();
+    for oid in revwalk {
+        if commits.len() == REMOVED_COMMITS_LIMIT {
+            return Ok((commits, true));
+        }
+        let commit = repo.find_commit(oid?)?;
+        commits.push(GitOperationCommit {
+            hash: commit.id().to_string(),
+            summary: commit.summary().unwrap_or("").to_string(),
+        });
+    }
+    Ok((commits, false))
+}
+
+// 按 reflog 说明的前缀归类，和 git 自己写入的说明一致
+fn classify(message: &str) -> Option<UndoKind> {
+    let action = message.split(':').next().unwrap_or("");
+    if action == "commit (amend)" {
+        Some(UndoKind::Amend)
+    } else if (action.starts_with("rebase") || action.starts_with("pull --rebase")) && action.ends_with("(finish)") {
+        Some(UndoKind::Rebase)
+    } else if action == "commit (merge)" || action.starts_with("merge ") || action.starts_with("pull") {
+        Some(UndoKind::Merge)
+    } else if matches!(action, "commit" | "cherry-pick" | "revert") {
+        Some(UndoKind::Commit)
+    } else if action == "reset" {
+        Some(UndoKind::Reset)
+    } else if action == "checkout" {
+        Some(UndoKind::Checkout)
+    } else {
+        None
+    }
+}
+
+// 描述撤销最近一次操作会做什么，不修改仓库。无法撤销时 blocked_reason 说明原因
+pub fn get_undo_preview(repo: &Repository) -> GitResult<GitUndoPreview> {
+    if git::get_operation_status(repo)?.kind.is_some() {
+        return Err(GitError::invalid("another operation is in progress"));
+    }
+    let reflog = repo.reflog("HEAD")?;
+    let entry = reflog.get(0).ok_or_else(|| GitError::not_found("HEAD has no reflog entries to undo"))?;
+    let reflog_message = entry.message().unwrap_or("").to_string();
+    let kind = classify(&reflog_message).ok_or_else(|| GitError::Unsupported {
+        message: format!("cannot undo \"{}\"", reflog_message),
+    })?;
+    let head = git::get_head_state(repo)?;
+    let branch = match head.kind {
+        GitHeadKind::Branch => head.branch,
+        _ => None,
+    };
+    let (mut from, mut to) = (entry.id_new(), entry.id_old());
+
+    // 变基结束时 HEAD 的记录只是回到分支，变基前的位置在分支自己的 reflog 中
+    if kind == UndoKind::Rebase {
+        let name = branch.as_deref().ok_or(GitError::DetachedHead)?;
+        let branch_reflog = repo.reflog(&format!("refs/heads/{}", name))?;
+        let branch_entry = branch_reflog
+            .get(0)
+            .filter(|entry| entry.message().is_some_and(|message| message.starts_with("rebase") || message.starts_with("pull --rebase")))
+            .ok_or_else(|| GitError::not_found(format!("no rebase found in the reflog of {}", name)))?;
+        from = branch_entry.id_new();
+        to = branch_entry.id_old();
+    }
+
+    let uncommitted_changes = git::uncommitted_tracked_changes(repo)?;
+    let mut preview = GitUndoPreview {
+        kind,
+        reflog_message: reflog_message.clone(),
+        description: String::new(),
+        branch: branch.clone(),
+        from: from.to_string(),
+        to: to.to_string(),
+        checkout_branch: None,
+        reset_mode: None,
+        removed_commits: Vec::new(),
+        removed_commits_truncated: false,
+        uncommitted_changes,
+        blocked_reason: None,
+    };
+    if to.is_zero() {
+        preview.blocked_reason = Some("the first commit of a branch cannot be undone".to_string());
+        return Ok(preview);
+    }
+    let target = branch.as_deref().unwrap_or("HEAD");
+    // 关闭了 reflog 或引用在没有记录的情况下被移动过时，最近的记录已经不是当前状态
+    let current = repo.head()?.peel_to_commit()?.id();
+    if current != from {
+        preview.blocked_reason = Some(format!("{} is at {}, not {} as recorded in the reflog", target, short(current), short(from)));
+        return Ok(preview);
+    }
+    let dirty = !preview.uncommitted_changes.is_empty();
+
+    match kind {
+        UndoKind::Checkout => {
+            // "checkout: moving from <原分支或提交> to <新分支或提交>"
+            let previous = reflog_message
+                .strip_prefix("checkout: moving from ")
+                .and_then(|rest| rest.rsplit_once(" to "))
+                .map(|(previous, _)| previous.to_string());
+            // 原分支之后又移动过时，回到当时的提交而不是分支
+            let previous_branch = previous.filter(|name| {
+                repo.find_branch(name, BranchType::Local)
+                    .is_ok_and(|branch| branch.get().target() == Some(to))
+            });
+            preview.description = match &previous_branch {
+                Some(name) => format!("Switch back to branch {}", name),
+                None => format!("Check out {} as a detached HEAD", short(to)),
+            };
+            preview.checkout_branch = previous_branch;
+            if dirty {
+                preview.blocked_reason = Some("commit or stash your changes before switching back".to_string());
+            }
+            return Ok(preview);
+        }
+        UndoKind::Commit | UndoKind::Amend => {
+            preview.reset_mode = Some(ResetMode::Soft);
+            preview.description = match kind {
+                UndoKind::Amend => format!("Move {} back to {}, the commit before it was amended; the amended changes stay staged", target, short(to)),
+                _ => format!("Move {} back to {}; the changes of the undone commit stay staged", target, short(to)),
+            };
+        }
+        UndoKind::Merge | UndoKind::Rebase => {
+            preview.reset_mode = Some(ResetMode::Hard);
+            let operation = if kind == UndoKind::Merge { "merge" } else { "rebase" };
+            preview.description = format!("Move {} back to {}, where it was before the {}", target, short(to), operation);
+            if dirty {
+                preview.blocked_reason = Some(format!("commit or stash your changes before undoing the {}", operation));
+            }
+        }
+        // 没有未提交的改动时硬重置回去即可恢复原来的文件。有改动时无法区分是软/混合重置留下的，
+        // 还是硬重置之后新做的修改，保留它们可能在下次提交时悄悄还原被恢复的提交，因此不猜测
+        UndoKind::Reset => {
+            preview.reset_mode = Some(ResetMode::Hard);
+            preview.description = format!("Move {} back to {}, where it was before the reset", target, short(to));
+            if dirty {
+                preview.blocked_reason = Some("commit or stash your changes before undoing the reset".to_string());
+            }
+        }
+    }
+    let (removed_commits, truncated) = commits_between(repo, from, to)?;
+    preview.removed_commits = removed_commits;
+    preview.removed_commits_truncated = truncated;
+    Ok(preview)
+}
+
+// 重新计算预览后执行，无法撤销时返回错误
+pub fn undo_last_operation(repo: &Repository) -> GitResult<GitUndoResult> {
+    let preview = get_undo_preview(repo)?;
+    if let Some(reason) = &preview.blocked_reason {
+        return Err(GitError::invalid(format!("cannot undo \"{}\": {}", preview.reflog_message, reason)));
+    }
+    match (&preview.checkout_branch, preview.reset_mode) {
+        (Some(branch), _) => git::checkout_branch(repo, branch)?,
+        (None, Some(mode)) => {
+            let result = git::reset(repo, &preview.to, mode, false)?;
+            if !result.performed {
+                return Err(GitError::DirtyWorkdir {
+                    files: result.files_at_risk.into_iter().map(|status| status.file_path).collect(),
+                });
+            }
+        }
+        (None, None) => {
+            git::checkout_commit(repo, &preview.to)?;
+        }
+    }
+    Ok(GitUndoResult {
+        preview,
+        head: git::get_head_state(repo)?,
+    })
+}
+
+fn remote_branches_containing(repo: &Repository, oid: Oid) -> GitResult<Vec<String>> {
+    let mut names = Vec::new();
+    for branch in repo.branches(Some(BranchType::Remote))? {
+        let (branch, _) = branch?;
+        // origin/HEAD 只是指向其他远程分支的符号引用
+        if branch.get().symbolic_target().is_some() {
+            continue;
+        }
+        let (Some(name), Some(tip)) = (branch.name()?, branch.get().target()) else {
+            continue;
+        };
+        if tip == oid || repo.graph_descendant_of(tip, oid)? {
+            names.push(name.to_string());
+        }
+    }
+    Ok(names)
+}
+
+// 把当前分支重置到 HEAD~1，提交的改动保留在暂存区。
+// HEAD 是合并提交（撤销后只剩第一个父提交，合并进来的改动混在暂存区）或已经推送时不执行，
+// performed 为 false，由前端提示后带 force 重试
+pub fn uncommit(repo: &Repository, force: bool) -> GitResult<GitUncommitResult> {
+    if git::get_operation_status(repo)?.kind.is_some() {
+        return Err(GitError::invalid("another operation is in progress"));
+    }
+    let head = repo.head()?.peel_to_commit()?;
+    let parent = head
+        .parents()
+        .next()
+        .ok_or_else(|| GitError::invalid("the first commit of a branch cannot be uncommitted"))?;
+    let is_merge = head.parent_count() > 1;
+    let pushed_to = remote_branches_containing(repo, head.id())?;
+    let commit = GitOperationCommit {
+        hash: head.id().to_string(),
+        summary: head.summary().unwrap_or("").to_string(),
+    };
+
+    let performed = force || (!is_merge && pushed_to.is_empty());
+    if performed {
+        git::reset(repo, &parent.id().to_string(), ResetMode::Soft, false)?;
+    }
+    Ok(GitUncommitResult {
+        commit,
+        performed,
+        is_merge,
+        pushed_to,
+        head: git::get_head_state(repo)?,
+    })
+}
@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use git2::Repository;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+use crate::error::GitResult;
+use crate::repo_cache::{common_dir, open_repo, RepoCache};
+use crate::sessions::RepoSession;
+
+pub const REPO_CHANGED_EVENT: &str = "repo-changed";
+
+// 编辑器保存、git 命令等往往连续写多个文件，安静一段时间后再合并通知前端
+const DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(300);
+// 持续有写入（例如构建）时最多攒这么久也要通知一次
+const MAX_BATCH_DURATION: Duration = Duration::from_secs(2);
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum RepoChangeKind {
+    Workdir,
+    Index,
+    Refs,
+    Head,
+}
+
+// 前端据此决定刷新什么：workdir/index 刷新状态，refs/head 刷新分支和历史
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RepoChangedEvent {
+    pub repo_id: String,
+    pub repo_path: String,
+    pub kinds: Vec<RepoChangeKind>,
+}
+
+// 每个打开的仓库一个监听器，以仓库句柄为键，由 Tauri 托管为全局状态
+#[derive(Clone, Default)]
+pub struct RepoWatchers {
+    watchers: Arc<Mutex<HashMap<String, RecommendedWatcher>>>,
+}
+
+struct RepoLayout {
+    workdir: Option<PathBuf>,
+    git_dir: PathBuf,
+    common_dir: PathBuf,
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+impl RepoLayout {
+    fn of(repo: &Repository) -> Self {
+        let git_dir = canonical(repo.path());
+        let common_dir = canonical(&common_dir(&git_dir));
+        RepoLayout {
+            workdir: repo.workdir().map(canonical),
+            git_dir,
+            common_dir,
+        }
+    }
+
+    // 需要监听的根目录：工作区已包含 .git 时不再重复监听
+    fn roots(&self) -> Vec<&Path> {
+        let mut roots: Vec<&Path> = Vec::new();
+        for root in [self.workdir.as_deref(), Some(self.git_dir.as_path()), Some(self.common_dir.as_path())]
+            .into_iter()
+            .flatten()
+        {
+            if !roots.iter().any(|existing| root.starts_with(existing)) {
+                roots.push(root);
+            }
+        }
+        roots
+    }
+
+    fn classify(&self, repo: &Repository, path: &Path) -> Option<RepoChangeKind> {
+        if path.extension().is_some_and(|e| e == "lock") {
+            return None;
+        }
+        for git_dir in [&self.git_dir, &self.common_dir] {
+            if let Ok(relative) = path.strip_prefix(git_dir) {
+                return match relative.components().next() {
+                    Some(Component::Normal(name)) if name == "index" => Some(RepoChangeKind::Index),
+                    Some(Component::Normal(name)) if name == "HEAD" => Some(RepoChangeKind::Head),
+                    Some(Component::Normal(name)) if name == "refs" || name == "packed-refs" => Some(RepoChangeKind::Refs),
+                    // objects、logs 等内部文件的变化总伴随着上面几类，忽略
+                    _ => None,
+                };
+            }
+        }
+        let relative = path.strip_prefix(self.workdir.as_deref()?).ok()?;
+        if relative.as_os_str().is_empty() {
+            return None;
+        }
+        // 目录要带上结尾的 / 才能匹配 "build/" 这类只忽略目录的规则
+        let ignored = if path.is_dir() {
+            repo.is_path_ignored(relative.join(""))
+        } else {
+            repo.is_path_ignored(relative)
+        };
+        if ignored.unwrap_or(false) {
+            return None;
+        }
+        Some(RepoChangeKind::Workdir)
+    }
+
+    // 只关心内容变化；读取文件（包括本程序自己读取状态）产生的访问事件不算
+    fn collect(&self, repo: &Repository, event: &Event, kinds: &mut Vec<RepoChangeKind>) {
+        if matches!(event.kind, EventKind::Access(_)) {
+            return;
+        }
+        for path in &event.paths {
+            if let Some(kind) = self.classify(repo, path) {
+                if !kinds.contains(&kind) {
+                    kinds.push(kind);
+                }
+            }
+        }
+    }
+}
+
+impl RepoWatchers {
+    // 重复监听同一仓库时直接返回，不会创建第二个监听器
+    pub fn watch(&self, app: AppHandle, repos: RepoCache, session: &RepoSession) -> GitResult<()> {
+        if self.watchers.lock().unwrap().contains_key(&session.id) {
+            return Ok(());
+        }
+
+        // 事件线程自己持有一个句柄，用来判断变化的文件是否被忽略
+        let repo = open_repo(&session.path)?;
+        let layout = RepoLayout::of(&repo);
+        let (sender, receiver) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        for root in layout.roots() {
+            watcher.watch(root, RecursiveMode::Recursive)?;
+        }
+
+        let repo_id = session.id.clone();
+        let repo_path = session.path.clone();
+        // 监听器被丢弃后通道关闭，线程随之退出
+        thread::spawn(move || {
+            while let Ok(first) = receiver.recv() {
+                let started = Instant::now();
+                let mut kinds = Vec::new();
+                let mut next = Some(first);
+                while let Some(result) = next {
+                    match result {
+                        Ok(event) => layout.collect(&repo, &event, &mut kinds),
+                        Err(e) => log::warn!("watcher error for {}: {}", repo_path, e),
+                    }
+                    next = if started.elapsed() < MAX_BATCH_DURATION {
+                        receiver.recv_timeout(DEBOUNCE_TIMEOUT).ok()
+                    } else {
+                        None
+                    };
+                }
+                if kinds.is_empty() {
+                    continue;
+                }
+                if kinds.iter().any(|kind| matches!(kind, RepoChangeKind::Refs | RepoChangeKind::Head)) {
+                    repos.invalidate(&repo_path);
+                }
+                let payload = RepoChangedEvent {
+                    repo_id: repo_id.clone(),
+                    repo_path: repo_path.clone(),
+                    kinds,
+                };
+                if let Err(e) = app.emit(REPO_CHANGED_EVENT, payload) {
+                    log::warn!("failed to emit {}: {}", REPO_CHANGED_EVENT, e);
+                }
+            }
+        });
+
+        self.watchers.lock().unwrap().insert(session.id.clone(), watcher);
+        Ok(())
+    }
+
+    // 丢弃监听器即停止监听；返回该仓库之前是否在监听
+    pub fn unwatch(&self, repo_id: &str) -> bool {
+        self.watchers.lock().unwrap().remove(repo_id).is_some()
+    }
+}
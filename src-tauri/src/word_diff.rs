@@ -0,0 +1,114 @@
+// 行内（单词级）差异：对一对删除/新增行按单词做 LCS，找出真正变化的片段
+// 返回的区间以 UTF-16 码元计数，前端可以直接用 String.prototype.slice 截取
+
+// 超过这个单词数的行不做行内比较，避免 O(n*m) 的表过大
+const MAX_TOKENS: usize = 400;
+
+// UTF-16 偏移的左闭右开区间
+pub type Span = (usize, usize);
+
+struct Token<'a> {
+    text: &'a str,
+    start: usize,
+    end: usize,
+}
+
+fn token_class(c: char) -> u8 {
+    if c.is_alphanumeric() || c == '_' {
+        0
+    } else if c.is_whitespace() {
+        1
+    } else {
+        2
+    }
+}
+
+// 连续的字母数字、连续的空白各成一个单词，标点符号单独成词
+fn tokenize(line: &str) -> Vec<Token<'_>> {
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut utf16_offset = 0;
+    let mut byte_start = 0;
+    let mut utf16_start = 0;
+    let mut current_class = None;
+
+    for (byte_index, c) in line.char_indices() {
+        let class = token_class(c);
+        let continues = current_class == Some(class) && class != 2;
+        if !continues {
+            if current_class.is_some() {
+                tokens.push(Token {
+                    text: &line[byte_start..byte_index],
+                    start: utf16_start,
+                    end: utf16_offset,
+                });
+            }
+            byte_start = byte_index;
+            utf16_start = utf16_offset;
+            current_class = Some(class);
+        }
+        utf16_offset += c.len_utf16();
+    }
+    if current_class.is_some() {
+        tokens.push(Token {
+            text: &line[byte_start..],
+            start: utf16_start,
+            end: utf16_offset,
+        });
+    }
+    tokens
+}
+
+fn push_range(ranges: &mut Vec<Span>, token: &Token) {
+    match ranges.last_mut() {
+        Some(last) if last.1 == token.start => last.1 = token.end,
+        _ => ranges.push((token.start, token.end)),
+    }
+}
+
+// 返回 (旧行中变化的区间, 新行中变化的区间)；行过长时返回 None
+pub fn changed_ranges(old_line: &str, new_line: &str) -> Option<(Vec<Span>, Vec<Span>)> {
+    let old_tokens = tokenize(old_line.trim_end_matches(['\r', '\n']));
+    let new_tokens = tokenize(new_line.trim_end_matches(['\r', '\n']));
+    if old_tokens.len() > MAX_TOKENS || new_tokens.len() > MAX_TOKENS {
+        return None;
+    }
+
+    let rows = old_tokens.len();
+    let cols = new_tokens.len();
+    // lcs[i][j] 为 old[i..] 与 new[j..] 的最长公共子序列长度
+    let mut lcs = vec![0_u16; (rows + 1) * (cols + 1)];
+    let at = |i: usize, j: usize| i * (cols + 1) + j;
+    for i in (0..rows).rev() {
+        for j in (0..cols).rev() {
+            lcs[at(i, j)] = if old_tokens[i].text == new_tokens[j].text {
+                lcs[at(i + 1, j + 1)] + 1
+            } else {
+                lcs[at(i + 1, j)].max(lcs[at(i, j + 1)])
+            };
+        }
+    }
+
+    let mut old_ranges = Vec::new();
+    let mut new_ranges = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < rows && j < cols {
+        if old_tokens[i].text == new_tokens[j].text {
+            i += 1;
+            j += 1;
+        } else if lcs[at(i + 1, j)] >= lcs[at(i, j + 1)] {
+            push_range(&mut old_ranges, &old_tokens[i]);
+            i += 1;
+        } else {
+            push_range(&mut new_ranges, &new_tokens[j]);
+            j += 1;
+        }
+    }
+    for token in &old_tokens[i..] {
+        push_range(&mut old_ranges, token);
+    }
+    for token in &new_tokens[j..] {
+        push_range(&mut new_ranges, token);
+    }
+
+    Some((old_ranges, new_ranges))
+}
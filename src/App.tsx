@@ -4,6 +4,7 @@ import { getCurrentWindow } from '@tauri-apps/api/window';
 import Sidebar from './components/Sidebar';
 import MainContent from './components/MainContent';
 import { WorkspaceService } from './services/workspaceService';
+import { getErrorMessage } from './services/gitService';
 import type { Workspace } from './types';
 import './App.css';
 
@@ -112,7 +113,7 @@ function App() {
         })
         .catch((error) => {
           console.error('Failed to open native folder dialog:', error);
-          alert(`打开文件夹选择器失败: ${String(error)}`);
+          alert(`打开文件夹选择器失败: ${getErrorMessage(error)}`);
         });
       return;
     }
@@ -1,6 +1,6 @@
 import React, { useEffect, useMemo, useRef, useState } from 'react';
-import { GitService } from '../services/gitService';
-import type { GitBranch, GitRemote, GitCommit, GitCommitChange, GitRepoInfo, GitStatus, Worktree } from '../types';
+import { GitService, getErrorMessage } from '../services/gitService';
+import type { GitBranch, GitRemote, GitCommit, GitCommitChange, GitIdentity, GitRepoInfo, GitStatus, Worktree } from '../types';
 import { DiffEditor } from '@monaco-editor/react';
 
 interface MainContentProps {
@@ -71,29 +71,12 @@ const MainContent: React.FC<MainContentProps> = ({ workspacePath }) => {
     return `${value.toFixed(2)} ${unit}`;
   };
 
-  const formatCommitDate = (rawDate: string) => {
-    const value = rawDate.trim();
-    if (!value) {
+  const formatCommitDate = (identity: GitIdentity) => {
+    const parsed = new Date(identity.time * 1000);
+    if (Number.isNaN(parsed.getTime())) {
       return '-';
     }
-
-    // Handle numeric Unix timestamps from backend (seconds or milliseconds).
-    if (/^-?\d+$/.test(value)) {
-      const numeric = Number(value);
-      if (Number.isFinite(numeric)) {
-        const timestamp = value.length <= 10 ? numeric * 1000 : numeric;
-        const parsed = new Date(timestamp);
-        if (!Number.isNaN(parsed.getTime())) {
-          return parsed.toLocaleString();
-        }
-      }
-    }
-
-    const parsed = new Date(value);
-    if (!Number.isNaN(parsed.getTime())) {
-      return parsed.toLocaleString();
-    }
-    return value;
+    return parsed.toLocaleString();
   };
 
   useEffect(() => {
@@ -120,9 +103,9 @@ const MainContent: React.FC<MainContentProps> = ({ workspacePath }) => {
         setRemotes(remotesData);
         
         console.log('Calling getCommits');
-        const commitsData = await gitService.getCommits();
-        console.log('Got commits:', commitsData);
-        setCommits(commitsData);
+        const commitsPage = await gitService.getCommits();
+        console.log('Got commits:', commitsPage);
+        setCommits(commitsPage.commits);
         
         console.log('Calling getStatus');
         const statusData = await gitService.getStatus();
@@ -140,7 +123,7 @@ const MainContent: React.FC<MainContentProps> = ({ workspacePath }) => {
         setRepoInfo(repoInfoData);
       } catch (error) {
         console.error('Failed to load Git data:', error);
-        setError(error instanceof Error ? error.message : String(error));
+        setError(getErrorMessage(error));
       } finally {
         setLoading(false);
       }
@@ -209,8 +192,8 @@ const MainContent: React.FC<MainContentProps> = ({ workspacePath }) => {
       const gitService = new GitService(workspacePath);
       const diffData = await gitService.getDiff(filePath);
       setSelectedFile(filePath);
-      setDiffOriginal('');
-      setDiffModified(diffData);
+      setDiffOriginal(diffData.original);
+      setDiffModified(diffData.modified);
     } catch (error) {
       console.error('Failed to get diff:', error);
     }
@@ -449,9 +432,16 @@ const MainContent: React.FC<MainContentProps> = ({ workspacePath }) => {
                 </div>
                 <div className="commit-hash">{commit.hash.substring(0, 7)}</div>
                 <div className="commit-info">
-                  <div className="commit-message">{commit.message}</div>
+                  <div className="commit-message">
+                    {commit.refs.map(ref => (
+                      <span key={`${ref.kind}:${ref.name}`} className={`commit-ref-badge commit-ref-${ref.kind}`}>
+                        {ref.name}
+                      </span>
+                    ))}
+                    {commit.message}
+                  </div>
                   <div className="commit-meta">
-                    {commit.author} • {formatCommitDate(commit.date)}
+                    {commit.author.name} • {formatCommitDate(commit.author)}
                     {commit.parents.length > 1 && (
                       <span className="commit-merge-badge">Merge ({commit.parents.length} parents)</span>
                     )}
@@ -499,7 +489,11 @@ const MainContent: React.FC<MainContentProps> = ({ workspacePath }) => {
         {worktrees.map((worktree, index) => (
           <div key={index} className="worktree-item">
             <div className="worktree-path">{worktree.path}</div>
-            <div className="worktree-branch">{worktree.branch}</div>
+            <div className="worktree-branch">
+              {worktree.head?.kind === 'detached'
+                ? `HEAD detached at ${worktree.head.target?.slice(0, 7) ?? ''}`
+                : worktree.branch}
+            </div>
           </div>
         ))}
       </div>
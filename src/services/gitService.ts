@@ -1,4 +1,4 @@
-import type { GitBranch, GitRemote, GitCommit, GitCommitChange, GitCommitFileDiff, GitRepoInfo, GitStatus, Worktree } from '../types';
+import type { GitBranch, GitRemote, GitCommitPage, GitCommitFilter, GitCommitChange, GitCommitFileDiff, GitError, GitRepoInfo, GitStatus, RepoSession, Worktree } from '../types';
 import { invoke as tauriInvoke } from '@tauri-apps/api/core';
 
 const isTauriRuntime = typeof window !== 'undefined' && '__TAURI_INTERNALS__' in window;
@@ -6,6 +6,9 @@ const isTauriRuntime = typeof window !== 'undefined' && '__TAURI_INTERNALS__' in
 const mockInvoke = async (command: string, params: any) => {
   console.log('Mock invoke called with command:', command, 'params:', params);
   switch (command) {
+    case 'open_repo':
+      // The mock uses the path itself as the handle
+      return { id: params.repoPath, path: params.repoPath, name: params.repoPath.split('/').pop(), isBare: false, isWorktree: false, mainRepoPath: null };
     case 'get_branches':
       return [
         { name: 'main', is_current: true, is_remote: false },
@@ -15,25 +18,36 @@ const mockInvoke = async (command: string, params: any) => {
     case 'get_remotes':
       return [{ name: 'origin', url: 'https://github.com/example/repo.git' }];
     case 'get_commits':
-      return [
-        {
-          hash: '1234567890abcdef1234567890abcdef12345678',
-          author: 'John Doe',
-          date: new Date().toISOString(),
-          message: 'Initial commit',
-          parents: []
-        }
-      ];
+      return {
+        commits: [
+          {
+            hash: '1234567890abcdef1234567890abcdef12345678',
+            author: { name: 'John Doe', email: 'john@example.com', time: Math.floor(Date.now() / 1000), offsetMinutes: 0 },
+            committer: { name: 'John Doe', email: 'john@example.com', time: Math.floor(Date.now() / 1000), offsetMinutes: 0 },
+            rawAuthor: null,
+            rawCommitter: null,
+            message: 'Initial commit',
+            parents: [],
+            refs: [
+              { name: 'HEAD', kind: 'head' },
+              { name: 'main', kind: 'localBranch' }
+            ]
+          }
+        ],
+        nextCursor: null,
+        shallowBoundary: false
+      };
     case 'get_status':
       return [];
     case 'get_worktrees':
-      return [{ path: params.repoPath, branch: 'main' }];
+      return [{ name: '', path: params.repoId, branch: 'main', head: { kind: 'branch', branch: 'main', target: '1234567890abcdef1234567890abcdef12345678' } }];
     case 'get_commit_changes':
       return [
-        { path: 'src/main.tsx', status: 'modified' },
-        { path: 'README.md', status: 'added' }
+        { path: 'src/main.tsx', status: 'modified', submodule: null },
+        { path: 'README.md', status: 'added', submodule: null }
       ];
     case 'get_commit_file_diff':
+    case 'get_workdir_file_diff':
       return {
         original: [
           'import React from \'react\';',
@@ -50,13 +64,19 @@ const mockInvoke = async (command: string, params: any) => {
           '  return <div className="app">Hello</div>;',
           '}',
         ].join('\n'),
+        isBinary: false,
+        originalSize: 78,
+        modifiedSize: 113,
+        truncated: false,
       };
     case 'get_repo_info':
       return {
-        repoPath: params.repoPath,
-        gitDirPath: `${params.repoPath}/.git`,
-        worktreePath: params.repoPath,
+        repoPath: params.repoId,
+        gitDirPath: `${params.repoId}/.git`,
+        worktreePath: params.repoId,
         isBare: false,
+        isWorktree: false,
+        mainRepoPath: null,
         totalSizeBytes: 1024 * 1024 * 245,
         worktreeSizeBytes: 1024 * 1024 * 190,
         gitMetadataSizeBytes: 1024 * 1024 * 55,
@@ -65,12 +85,23 @@ const mockInvoke = async (command: string, params: any) => {
         gitRefsSizeBytes: 1024 * 18,
         lfsEnabled: false,
         lfsObjectsSizeBytes: 0,
+        identityWarning: null,
       };
     default:
       return [];
   }
 };
 
+export const isGitError = (error: unknown): error is GitError =>
+  typeof error === 'object' && error !== null && 'code' in error && 'message' in error;
+
+export const getErrorMessage = (error: unknown): string => {
+  if (isGitError(error) || error instanceof Error) {
+    return error.message;
+  }
+  return String(error);
+};
+
 const invoke = async <T>(command: string, params: Record<string, unknown>): Promise<T> => {
   if (isTauriRuntime) {
     return tauriInvoke<T>(command, params);
@@ -80,15 +111,30 @@ const invoke = async <T>(command: string, params: Record<string, unknown>): Prom
 
 export class GitService {
   private workdir: string;
+  private repoIdPromise: Promise<string> | null = null;
 
   constructor(workdir: string) {
     this.workdir = workdir;
   }
 
+  // Backend commands address repositories by the handle returned from open_repo.
+  // Opening the same repository again returns the existing handle.
+  private repoId(): Promise<string> {
+    if (!this.repoIdPromise) {
+      this.repoIdPromise = invoke<RepoSession>('open_repo', { repoPath: this.workdir })
+        .then((session) => session.id)
+        .catch((error) => {
+          this.repoIdPromise = null;
+          throw error;
+        });
+    }
+    return this.repoIdPromise;
+  }
+
   async getBranches(): Promise<GitBranch[]> {
     try {
       const branches = await invoke<GitBranch[]>('get_branches', {
-        repoPath: this.workdir
+        repoId: await this.repoId()
       });
       return branches;
     } catch (error) {
@@ -100,7 +146,7 @@ export class GitService {
   async getRemotes(): Promise<GitRemote[]> {
     try {
       const remotes = await invoke<GitRemote[]>('get_remotes', {
-        repoPath: this.workdir
+        repoId: await this.repoId()
       });
       return remotes;
     } catch (error) {
@@ -112,7 +158,7 @@ export class GitService {
   async checkoutBranch(branchName: string): Promise<void> {
     try {
       await invoke<void>('checkout_branch', {
-        repoPath: this.workdir,
+        repoId: await this.repoId(),
         branchName
       });
     } catch (error) {
@@ -121,26 +167,31 @@ export class GitService {
     }
   }
 
-  async createWorktree(_branchName: string, _worktreePath: string): Promise<void> {
+  async createWorktree(branchName: string, worktreePath: string, createBranch = false): Promise<Worktree> {
     try {
-      // 注意：后端尚未实现 create_worktree 函数
-      console.log('Worktree creation is not yet implemented in the backend');
-      // await invoke('create_worktree', {
-      //   repoPath: this.workdir,
-      //   branchName: _branchName,
-      //   worktreePath: _worktreePath
-      // });
+      const worktree = await invoke<Worktree>('add_worktree', {
+        repoId: await this.repoId(),
+        worktreePath,
+        branchName,
+        createBranch
+      });
+      return worktree;
     } catch (error) {
       console.error('Error creating worktree:', error);
+      throw error;
     }
   }
 
-  async getCommits(): Promise<GitCommit[]> {
+  async getCommits(rev?: string | null, cursor?: string | null, limit?: number, filter?: GitCommitFilter): Promise<GitCommitPage> {
     try {
-      const commits = await invoke<GitCommit[]>('get_commits', {
-        repoPath: this.workdir
+      const page = await invoke<GitCommitPage>('get_commits', {
+        repoId: await this.repoId(),
+        rev: rev ?? null,
+        cursor: cursor ?? null,
+        limit,
+        filter
       });
-      return commits;
+      return page;
     } catch (error) {
       console.error('Error getting commits:', error);
       throw error;
@@ -150,7 +201,7 @@ export class GitService {
   async getStatus(): Promise<GitStatus[]> {
     try {
       const status = await invoke<GitStatus[]>('get_status', {
-        repoPath: this.workdir
+        repoId: await this.repoId()
       });
       return status;
     } catch (error) {
@@ -159,26 +210,24 @@ export class GitService {
     }
   }
 
-  async getDiff(_filePath: string): Promise<string> {
+  async getDiff(filePath: string, staged = false): Promise<GitCommitFileDiff> {
     try {
-      // 注意：后端尚未实现 get_diff 函数
-      console.log('Diff functionality is not yet implemented in the backend');
-      // const diff = await invoke('get_diff', {
-      //   repoPath: this.workdir,
-      //   filePath: _filePath
-      // });
-      // return diff;
-      return '';
+      const diff = await invoke<GitCommitFileDiff>('get_workdir_file_diff', {
+        repoId: await this.repoId(),
+        filePath,
+        staged
+      });
+      return diff;
     } catch (error) {
       console.error('Error getting diff:', error);
-      return '';
+      throw error;
     }
   }
 
   async getWorktrees(): Promise<Worktree[]> {
     try {
       const worktrees = await invoke<Worktree[]>('get_worktrees', {
-        repoPath: this.workdir
+        repoId: await this.repoId()
       });
       return worktrees;
     } catch (error) {
@@ -190,7 +239,7 @@ export class GitService {
   async getCommitChanges(commitHash: string): Promise<GitCommitChange[]> {
     try {
       const changes = await invoke<GitCommitChange[]>('get_commit_changes', {
-        repoPath: this.workdir,
+        repoId: await this.repoId(),
         commitHash
       });
       return changes;
@@ -203,7 +252,7 @@ export class GitService {
   async getCommitFileDiff(commitHash: string, filePath: string): Promise<GitCommitFileDiff> {
     try {
       const diff = await invoke<GitCommitFileDiff>('get_commit_file_diff', {
-        repoPath: this.workdir,
+        repoId: await this.repoId(),
         commitHash,
         filePath
       });
@@ -217,7 +266,7 @@ export class GitService {
   async getRepoInfo(): Promise<GitRepoInfo> {
     try {
       const repoInfo = await invoke<GitRepoInfo>('get_repo_info', {
-        repoPath: this.workdir
+        repoId: await this.repoId()
       });
       return repoInfo;
     } catch (error) {
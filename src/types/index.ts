@@ -5,6 +5,12 @@ export interface Workspace {
   lastOpened: number;
 }
 
+export interface GitError {
+  code: string;
+  message: string;
+  context: Record<string, unknown> | null;
+}
+
 export interface GitBranch {
   name: string;
   isCurrent: boolean;
@@ -15,34 +21,106 @@ export interface GitBranch {
 export interface GitRemote {
   name: string;
   url: string;
+  pushUrl?: string | null;
+}
+
+export interface GitIdentity {
+  name: string;
+  email: string;
+  // Unix timestamp in seconds
+  time: number;
+  // Offset from UTC of the original timezone, in minutes
+  offsetMinutes: number;
+}
+
+export type GitRefKind = 'head' | 'localBranch' | 'remoteBranch' | 'tag';
+
+export interface GitRefDecoration {
+  name: string;
+  kind: GitRefKind;
 }
 
+// 'mapped' (default) consolidates identities via .mailmap; 'both' also returns the raw identity
+export type MailmapMode = 'off' | 'mapped' | 'both';
+
 export interface GitCommit {
   hash: string;
-  author: string;
-  date: string;
+  author: GitIdentity;
+  committer: GitIdentity;
+  // Only set when requested with MailmapMode 'both'
+  rawAuthor: GitIdentity | null;
+  rawCommitter: GitIdentity | null;
   message: string;
   parents: string[];
+  refs: GitRefDecoration[];
+}
+
+export interface GitCommitPage {
+  commits: GitCommit[];
+  nextCursor: string | null;
+  shallowBoundary: boolean;
+}
+
+export interface GitCommitFilter {
+  author?: string;
+  path?: string;
+  since?: number;
+  until?: number;
+  message?: string;
+}
+
+export interface GitSubmoduleCommit {
+  hash: string;
+  summary: string;
+  // Only on the old side, i.e. the submodule was rewound
+  removed: boolean;
+}
+
+export interface GitSubmoduleChange {
+  // null when the submodule was added or removed
+  oldCommit: string | null;
+  newCommit: string | null;
+  commits: GitSubmoduleCommit[];
+  commitsTruncated: boolean;
 }
 
 export interface GitCommitChange {
   path: string;
   status: string;
+  submodule: GitSubmoduleChange | null;
 }
 
 export interface GitCommitFileDiff {
   original: string;
   modified: string;
+  isBinary: boolean;
+  originalSize: number;
+  modifiedSize: number;
+  truncated: boolean;
 }
 
 export interface GitStatus {
   filePath: string;
   status: string;
+  submodule: GitSubmoduleChange | null;
+}
+
+export type GitHeadKind = 'branch' | 'detached' | 'unborn';
+
+export interface GitHeadState {
+  kind: GitHeadKind;
+  // Short branch name; null when HEAD is detached
+  branch: string | null;
+  // Commit HEAD points at; null on an unborn branch
+  target: string | null;
 }
 
 export interface Worktree {
+  name: string;
   path: string;
+  // Empty when HEAD is detached
   branch: string;
+  head: GitHeadState | null;
 }
 
 export interface GitRepoInfo {
@@ -50,6 +128,9 @@ export interface GitRepoInfo {
   gitDirPath: string;
   worktreePath: string;
   isBare: boolean;
+  // True when opened inside a linked worktree; mainRepoPath is the main repository root
+  isWorktree: boolean;
+  mainRepoPath: string | null;
   totalSizeBytes: number;
   worktreeSizeBytes: number;
   gitMetadataSizeBytes: number;
@@ -58,4 +139,19 @@ export interface GitRepoInfo {
   gitRefsSizeBytes: number;
   lfsEnabled: boolean;
   lfsObjectsSizeBytes: number;
+  identityWarning: GitIdentityWarning | null;
+}
+
+export interface GitIdentityWarning {
+  missingName: boolean;
+  missingEmail: boolean;
+}
+
+export interface RepoSession {
+  id: string;
+  path: string;
+  name: string;
+  isBare: boolean;
+  isWorktree: boolean;
+  mainRepoPath: string | null;
 }